@@ -0,0 +1,747 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+use core_protobuf_data_access::chariott::service_discovery::core::v1::service_registry_client::ServiceRegistryClient;
+use core_protobuf_data_access::chariott::service_discovery::core::v1::{
+    DiscoverByNamespaceRequest, RegisterRequest, ServiceMetadata, UnregisterRequest,
+};
+use log::{debug, error, info, warn};
+use serde_derive::Deserialize;
+use std::fmt;
+use strum_macros::Display;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio::time::Duration;
+use tonic::transport::Channel;
+use tonic::{Code, Status};
+use tracing::{info_span, Instrument};
+
+use crate::tls_settings::{connect_channel, TlsSettings};
+use crate::trace_context::traced_request;
+
+// This module provides a client for Chariott's service registry, and a registrar that keeps a
+// service registered with Chariott in the background, for as long as the service runs.
+
+/// The default delay before the first retry of a failed registration.
+pub const DEFAULT_INITIAL_RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The default upper bound of the delay between retries of a failed registration.
+pub const DEFAULT_MAX_RETRY_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The default interval at which a successful registration is re-asserted, so that the service
+/// is registered again when Chariott restarts.
+pub const DEFAULT_REASSERT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// A client for Chariott's service registry.
+#[derive(Clone, Debug)]
+pub struct ChariottClient {
+    /// Chariott's URI.
+    chariott_uri: String,
+    /// The TLS settings used to connect to Chariott, if TLS is used.
+    tls_settings: Option<TlsSettings>,
+}
+
+impl ChariottClient {
+    /// Creates a new ChariottClient.
+    ///
+    /// # Arguments
+    /// * `chariott_uri` - Chariott's URI.
+    /// * `tls_settings` - The TLS settings used to connect to Chariott, if TLS is used.
+    pub fn new(chariott_uri: &str, tls_settings: Option<TlsSettings>) -> Self {
+        Self { chariott_uri: chariott_uri.to_string(), tls_settings }
+    }
+
+    /// Gets Chariott's URI.
+    pub fn chariott_uri(&self) -> &str {
+        &self.chariott_uri
+    }
+
+    /// Connects to Chariott's service registry.
+    async fn connect(&self) -> Result<ServiceRegistryClient<Channel>, Status> {
+        let channel = connect_channel(&self.chariott_uri, self.tls_settings.as_ref())
+            .await
+            .map_err(|error| Status::unavailable(error.to_string()))?;
+
+        Ok(ServiceRegistryClient::new(channel))
+    }
+
+    /// Registers a service with Chariott. Registering a service that is already registered
+    /// replaces its registration.
+    ///
+    /// # Arguments
+    /// * `service` - The service's metadata.
+    pub async fn register(&self, service: &ServiceMetadata) -> Result<(), Status> {
+        let mut client = self.connect().await?;
+
+        let request = traced_request(RegisterRequest { service: Some(service.clone()) });
+
+        client.register(request).await?;
+
+        Ok(())
+    }
+
+    /// Unregisters a service from Chariott.
+    ///
+    /// # Arguments
+    /// * `namespace` - The service's namespace.
+    /// * `name` - The service's name.
+    /// * `version` - The service's version.
+    pub async fn unregister(
+        &self,
+        namespace: &str,
+        name: &str,
+        version: &str,
+    ) -> Result<(), Status> {
+        let mut client = self.connect().await?;
+
+        let request = traced_request(UnregisterRequest {
+            namespace: namespace.to_string(),
+            name: name.to_string(),
+            version: version.to_string(),
+        });
+
+        client.unregister(request).await?;
+
+        Ok(())
+    }
+
+    /// Gets all of the services in Chariott with a namespace and name, whatever their version.
+    ///
+    /// # Arguments
+    /// * `namespace` - The services' namespace.
+    /// * `name` - The services' name.
+    pub async fn discover_candidates(
+        &self,
+        namespace: &str,
+        name: &str,
+    ) -> Result<Vec<ServiceMetadata>, DiscoveryError> {
+        let mut client = self.connect().await.map_err(DiscoveryError::Chariott)?;
+
+        let request =
+            traced_request(DiscoverByNamespaceRequest { namespace: namespace.to_string() });
+
+        // Chariott answers with not found when it has no service in the namespace.
+        let services = match client.discover_by_namespace(request).await {
+            Ok(response) => response.into_inner().services,
+            Err(status) if status.code() == Code::NotFound => Vec::new(),
+            Err(status) => return Err(DiscoveryError::Chariott(status)),
+        };
+
+        Ok(services.into_iter().filter(|service| service.name == name).collect())
+    }
+
+    /// Discovers the services that match a query, best match first.
+    ///
+    /// # Arguments
+    /// * `query` - The requested service and how the candidates are matched against it.
+    pub async fn discover_services(
+        &self,
+        query: &ServiceQuery,
+    ) -> Result<Vec<ServiceMetadata>, DiscoveryError> {
+        let candidates = self.discover_candidates(&query.namespace, &query.name).await?;
+
+        if candidates.is_empty() {
+            return Err(DiscoveryError::NotFound {
+                namespace: query.namespace.clone(),
+                name: query.name.clone(),
+            });
+        }
+
+        let services = query.filter(&candidates)?;
+
+        if services.is_empty() {
+            return Err(DiscoveryError::NoMatchingService { query: query.clone(), candidates });
+        }
+
+        Ok(services)
+    }
+
+    /// Discovers the URI of the service that best matches a query.
+    ///
+    /// # Arguments
+    /// * `query` - The requested service and how the candidates are matched against it.
+    pub async fn discover(&self, query: &ServiceQuery) -> Result<String, DiscoveryError> {
+        let service = self.discover_services(query).await?.remove(0);
+
+        Ok(service.uri)
+    }
+}
+
+/// How the services in Chariott are matched against a query.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DiscoveryPolicy {
+    /// The version, communication kind and communication reference must all match.
+    #[default]
+    Exact,
+    /// The version and communication kind must match. The communication reference may differ.
+    KindOnly,
+    /// The communication kind and reference must match, and the version must be compatible with
+    /// the requested version, as in semver: it has the same major version and is not older. A 0
+    /// major version also requires the same minor version. The newest version is preferred.
+    CompatibleVersion,
+}
+
+/// A request for a service in Chariott.
+#[derive(Clone, Debug)]
+pub struct ServiceQuery {
+    /// The service's namespace.
+    pub namespace: String,
+    /// The service's name.
+    pub name: String,
+    /// The service's version.
+    pub version: String,
+    /// The service's expected communication kind.
+    pub communication_kind: String,
+    /// The service's expected communication reference.
+    pub communication_reference: String,
+    /// How the candidates are matched against the query.
+    pub policy: DiscoveryPolicy,
+}
+
+impl ServiceQuery {
+    /// Gets the candidates that match the query, best match first.
+    ///
+    /// # Arguments
+    /// * `candidates` - The services with the query's namespace and name.
+    pub fn filter(
+        &self,
+        candidates: &[ServiceMetadata],
+    ) -> Result<Vec<ServiceMetadata>, DiscoveryError> {
+        let has_kind =
+            |service: &ServiceMetadata| service.communication_kind == self.communication_kind;
+        let has_reference = |service: &ServiceMetadata| {
+            service.communication_reference == self.communication_reference
+        };
+
+        let services = match self.policy {
+            DiscoveryPolicy::Exact => candidates
+                .iter()
+                .filter(|service| {
+                    service.version == self.version && has_kind(service) && has_reference(service)
+                })
+                .cloned()
+                .collect(),
+            DiscoveryPolicy::KindOnly => candidates
+                .iter()
+                .filter(|service| service.version == self.version && has_kind(service))
+                .cloned()
+                .collect(),
+            DiscoveryPolicy::CompatibleVersion => {
+                let requested_version = parse_version(&self.version)
+                    .ok_or_else(|| DiscoveryError::InvalidVersion(self.version.clone()))?;
+
+                let mut services: Vec<(Version, ServiceMetadata)> = candidates
+                    .iter()
+                    .filter(|service| has_kind(service) && has_reference(service))
+                    .filter_map(|service| {
+                        parse_version(&service.version)
+                            .filter(|version| is_compatible_version(requested_version, *version))
+                            .map(|version| (version, service.clone()))
+                    })
+                    .collect();
+
+                // The newest version is the best match.
+                services.sort_by(|(a, _), (b, _)| b.cmp(a));
+                services.into_iter().map(|(_, service)| service).collect()
+            }
+        };
+
+        Ok(services)
+    }
+}
+
+impl fmt::Display for ServiceQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "namespace '{}', name '{}', version '{}', communication kind '{}' and communication reference '{}' (policy: {:?})",
+            self.namespace,
+            self.name,
+            self.version,
+            self.communication_kind,
+            self.communication_reference,
+            self.policy
+        )
+    }
+}
+
+/// Why a service could not be discovered.
+#[derive(Debug)]
+pub enum DiscoveryError {
+    /// Chariott could not be reached, or it failed the request.
+    Chariott(Status),
+    /// Chariott has no service with the namespace and name.
+    NotFound { namespace: String, name: String },
+    /// Chariott has services with the namespace and name, but none of them match the query.
+    NoMatchingService { query: ServiceQuery, candidates: Vec<ServiceMetadata> },
+    /// The query's version is not a valid version.
+    InvalidVersion(String),
+}
+
+impl fmt::Display for DiscoveryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DiscoveryError::Chariott(status) => {
+                write!(f, "The discovery request to Chariott failed: {status}")
+            }
+            DiscoveryError::NotFound { namespace, name } => write!(
+                f,
+                "Did not find a service in Chariott with namespace '{namespace}' and name '{name}'"
+            ),
+            DiscoveryError::NoMatchingService { query, candidates } => {
+                let versions: Vec<&str> =
+                    candidates.iter().map(|service| service.version.as_str()).collect();
+                write!(
+                    f,
+                    "Did not find a service in Chariott with {query}. The candidates have versions {versions:?}"
+                )
+            }
+            DiscoveryError::InvalidVersion(version) => {
+                write!(f, "The version '{version}' is not a valid version")
+            }
+        }
+    }
+}
+
+impl std::error::Error for DiscoveryError {}
+
+impl From<DiscoveryError> for Status {
+    fn from(error: DiscoveryError) -> Self {
+        match error {
+            DiscoveryError::Chariott(status) => status,
+            DiscoveryError::NotFound { .. } | DiscoveryError::NoMatchingService { .. } => {
+                Status::not_found(error.to_string())
+            }
+            DiscoveryError::InvalidVersion(_) => Status::invalid_argument(error.to_string()),
+        }
+    }
+}
+
+/// A version's major, minor and patch numbers.
+type Version = (u64, u64, u64);
+
+/// Parses a version such as "1", "1.2" or "v1.2.3". The missing numbers are 0.
+///
+/// # Arguments
+/// * `version` - The version to parse.
+fn parse_version(version: &str) -> Option<Version> {
+    let mut numbers = version.trim().trim_start_matches('v').split('.');
+
+    let major = numbers.next()?.parse().ok()?;
+    let minor = numbers.next().map_or(Some(0), |number| number.parse().ok())?;
+    let patch = numbers.next().map_or(Some(0), |number| number.parse().ok())?;
+
+    if numbers.next().is_some() {
+        return None;
+    }
+
+    Some((major, minor, patch))
+}
+
+/// Whether a candidate's version is compatible with the requested version, as in semver.
+///
+/// # Arguments
+/// * `requested_version` - The requested version.
+/// * `candidate_version` - The candidate's version.
+fn is_compatible_version(requested_version: Version, candidate_version: Version) -> bool {
+    if requested_version.0 != candidate_version.0 {
+        return false;
+    }
+
+    // Before 1.0, a minor version may break compatibility.
+    if requested_version.0 == 0 && requested_version.1 != candidate_version.1 {
+        return false;
+    }
+
+    candidate_version >= requested_version
+}
+
+/// The state of a service's registration with Chariott.
+#[derive(Clone, Copy, Debug, Display, Eq, PartialEq)]
+pub enum ChariottRegistrationState {
+    /// The service is not registered yet, or the last attempt to register it failed.
+    Registering,
+    /// The last attempt to register the service succeeded.
+    Registered,
+    /// The registrar has stopped, and the service has been unregistered.
+    Unregistered,
+}
+
+/// How a ChariottRegistrar retries and re-asserts the registration.
+#[derive(Clone, Debug)]
+pub struct ChariottRegistrationSettings {
+    /// The delay before the first retry of a failed registration. It doubles after each failed
+    /// attempt, up to `max_retry_interval`.
+    pub initial_retry_interval: Duration,
+    /// The upper bound of the delay between retries of a failed registration.
+    pub max_retry_interval: Duration,
+    /// The interval at which a successful registration is re-asserted.
+    pub reassert_interval: Duration,
+}
+
+impl Default for ChariottRegistrationSettings {
+    fn default() -> Self {
+        Self {
+            initial_retry_interval: DEFAULT_INITIAL_RETRY_INTERVAL,
+            max_retry_interval: DEFAULT_MAX_RETRY_INTERVAL,
+            reassert_interval: DEFAULT_REASSERT_INTERVAL,
+        }
+    }
+}
+
+/// Gets the delay before the next retry of a failed registration, by doubling the current delay
+/// up to the maximum.
+///
+/// # Arguments
+/// * `retry_interval` - The current delay.
+/// * `max_retry_interval` - The upper bound of the delay.
+fn next_retry_interval(retry_interval: Duration, max_retry_interval: Duration) -> Duration {
+    retry_interval.saturating_mul(2).min(max_retry_interval)
+}
+
+/// Keeps a service registered with Chariott in a background task. It registers the service with
+/// exponential backoff, re-asserts the registration periodically and unregisters the service
+/// when it is shut down.
+pub struct ChariottRegistrar {
+    client: ChariottClient,
+    service: ServiceMetadata,
+    state_receiver: watch::Receiver<ChariottRegistrationState>,
+    stop_sender: watch::Sender<bool>,
+    task: JoinHandle<watch::Sender<ChariottRegistrationState>>,
+}
+
+impl ChariottRegistrar {
+    /// Starts registering a service with Chariott in the background.
+    ///
+    /// # Arguments
+    /// * `client` - The client for Chariott's service registry.
+    /// * `service` - The metadata of the service to register.
+    /// * `settings` - How the registration is retried and re-asserted.
+    pub fn start(
+        client: ChariottClient,
+        service: ServiceMetadata,
+        settings: ChariottRegistrationSettings,
+    ) -> Self {
+        let (state_sender, state_receiver) = watch::channel(ChariottRegistrationState::Registering);
+        let (stop_sender, stop_receiver) = watch::channel(false);
+
+        let task = tokio::spawn(Self::run(
+            client.clone(),
+            service.clone(),
+            settings,
+            state_sender,
+            stop_receiver,
+        ));
+
+        Self { client, service, state_receiver, stop_sender, task }
+    }
+
+    /// Registers the service until the registrar is stopped. The state's sender is handed back,
+    /// so that the state can be updated once the service has been unregistered.
+    ///
+    /// # Arguments
+    /// * `client` - The client for Chariott's service registry.
+    /// * `service` - The metadata of the service to register.
+    /// * `settings` - How the registration is retried and re-asserted.
+    /// * `state_sender` - Publishes the state of the registration.
+    /// * `stop_receiver` - Signals that the registrar has been stopped.
+    async fn run(
+        client: ChariottClient,
+        service: ServiceMetadata,
+        settings: ChariottRegistrationSettings,
+        state_sender: watch::Sender<ChariottRegistrationState>,
+        mut stop_receiver: watch::Receiver<bool>,
+    ) -> watch::Sender<ChariottRegistrationState> {
+        let mut retry_interval = settings.initial_retry_interval;
+
+        loop {
+            let result =
+                client.register(&service).instrument(info_span!("register_with_chariott")).await;
+
+            let delay = match result {
+                Ok(()) => {
+                    if *state_sender.borrow() != ChariottRegistrationState::Registered {
+                        info!(
+                            "The service '{}' is now registered with Chariott at '{}'.",
+                            service.name,
+                            client.chariott_uri()
+                        );
+                    }
+                    state_sender.send_replace(ChariottRegistrationState::Registered);
+                    retry_interval = settings.initial_retry_interval;
+                    settings.reassert_interval
+                }
+                Err(status) => {
+                    if *state_sender.borrow() == ChariottRegistrationState::Registered {
+                        warn!(
+                            "The service '{}' is no longer registered with Chariott: '{status}'",
+                            service.name
+                        );
+                    } else {
+                        debug!(
+                            "Failed to register the service '{}' with Chariott, retrying in {retry_interval:?}: '{status}'",
+                            service.name
+                        );
+                    }
+                    state_sender.send_replace(ChariottRegistrationState::Registering);
+                    let delay = retry_interval;
+                    retry_interval =
+                        next_retry_interval(retry_interval, settings.max_retry_interval);
+                    delay
+                }
+            };
+
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = stop_receiver.changed() => return state_sender,
+            }
+        }
+    }
+
+    /// Gets a receiver that is notified of the changes to the registration's state.
+    pub fn state(&self) -> watch::Receiver<ChariottRegistrationState> {
+        self.state_receiver.clone()
+    }
+
+    /// Stops the background task and unregisters the service from Chariott.
+    pub async fn shutdown(self) -> Result<(), Status> {
+        // The task may have already stopped, in which case there is no one to notify.
+        let _ = self.stop_sender.send(true);
+        let state_sender = self.task.await.map_err(|error| {
+            error!("The Chariott registration task failed: '{error}'");
+            Status::internal(error.to_string())
+        })?;
+
+        self.client
+            .unregister(&self.service.namespace, &self.service.name, &self.service.version)
+            .instrument(info_span!("unregister_from_chariott"))
+            .await?;
+
+        state_sender.send_replace(ChariottRegistrationState::Unregistered);
+        info!("The service '{}' is now unregistered from Chariott.", self.service.name);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod chariott_client_tests {
+    use super::*;
+
+    use test_support::mock_chariott::MockServiceRegistry;
+
+    const NAMESPACE: &str = "sdv.test";
+    const NAME: &str = "test_service";
+    const KIND: &str = "grpc+proto";
+    const REFERENCE: &str = "test.v1.test.proto";
+
+    /// Serves a MockServiceRegistry with services on an ephemeral port and returns its URI.
+    ///
+    /// # Arguments
+    /// * `services` - The services that the registry serves.
+    async fn start_mock_service_registry(services: Vec<ServiceMetadata>) -> String {
+        let service_registry = MockServiceRegistry::new();
+        for service in services {
+            service_registry.add_service(service);
+        }
+
+        service_registry.serve().await
+    }
+
+    fn create_service(version: &str, kind: &str, reference: &str, uri: &str) -> ServiceMetadata {
+        ServiceMetadata {
+            namespace: NAMESPACE.to_string(),
+            name: NAME.to_string(),
+            version: version.to_string(),
+            uri: uri.to_string(),
+            communication_kind: kind.to_string(),
+            communication_reference: reference.to_string(),
+        }
+    }
+
+    fn create_query(version: &str, policy: DiscoveryPolicy) -> ServiceQuery {
+        ServiceQuery {
+            namespace: NAMESPACE.to_string(),
+            name: NAME.to_string(),
+            version: version.to_string(),
+            communication_kind: KIND.to_string(),
+            communication_reference: REFERENCE.to_string(),
+            policy,
+        }
+    }
+
+    #[test]
+    fn parse_version_test() {
+        assert_eq!(parse_version("1"), Some((1, 0, 0)));
+        assert_eq!(parse_version("1.2"), Some((1, 2, 0)));
+        assert_eq!(parse_version("v1.2.3"), Some((1, 2, 3)));
+        assert_eq!(parse_version("1.2.3.4"), None);
+        assert_eq!(parse_version("1.x"), None);
+        assert_eq!(parse_version(""), None);
+    }
+
+    #[test]
+    fn is_compatible_version_test() {
+        assert!(is_compatible_version((1, 1, 0), (1, 1, 0)));
+        assert!(is_compatible_version((1, 1, 0), (1, 3, 2)));
+        assert!(!is_compatible_version((1, 1, 0), (1, 0, 9)));
+        assert!(!is_compatible_version((1, 1, 0), (2, 0, 0)));
+        assert!(is_compatible_version((0, 2, 0), (0, 2, 5)));
+        assert!(!is_compatible_version((0, 2, 0), (0, 3, 0)));
+    }
+
+    #[tokio::test]
+    async fn exact_policy_requires_kind_and_reference_test() {
+        let service_registry = MockServiceRegistry::new();
+        service_registry.add_service(create_service(
+            "1.0",
+            KIND,
+            "other.v1.other.proto",
+            "http://other-reference",
+        ));
+        service_registry.add_service(create_service(
+            "2.0",
+            "mqtt_v5",
+            REFERENCE,
+            "http://other-kind",
+        ));
+        let chariott_uri = service_registry.serve().await;
+        let client = ChariottClient::new(&chariott_uri, None);
+
+        for version in ["1.0", "2.0"] {
+            let result = client.discover(&create_query(version, DiscoveryPolicy::Exact)).await;
+            match result {
+                Err(DiscoveryError::NoMatchingService { candidates, .. }) => {
+                    assert_eq!(candidates.len(), 2)
+                }
+                other => panic!("Unexpected result: {other:?}"),
+            }
+        }
+
+        // The kind only policy accepts a service with a different communication reference.
+        let uri = client.discover(&create_query("1.0", DiscoveryPolicy::KindOnly)).await.unwrap();
+        assert_eq!(uri, "http://other-reference");
+        let result = client.discover(&create_query("2.0", DiscoveryPolicy::KindOnly)).await;
+        assert!(matches!(result, Err(DiscoveryError::NoMatchingService { .. })));
+
+        service_registry.add_service(create_service("1.0", KIND, REFERENCE, "http://exact"));
+        let uri = client.discover(&create_query("1.0", DiscoveryPolicy::Exact)).await.unwrap();
+        assert_eq!(uri, "http://exact");
+    }
+
+    #[tokio::test]
+    async fn compatible_version_policy_prefers_newest_test() {
+        let chariott_uri = start_mock_service_registry(vec![
+            create_service("1.0", KIND, REFERENCE, "http://v1-0"),
+            create_service("1.2", KIND, REFERENCE, "http://v1-2"),
+            create_service("1.4", "mqtt_v5", REFERENCE, "http://v1-4"),
+            create_service("2.0", KIND, REFERENCE, "http://v2-0"),
+        ])
+        .await;
+        let client = ChariottClient::new(&chariott_uri, None);
+
+        let services = client
+            .discover_services(&create_query("1.1", DiscoveryPolicy::CompatibleVersion))
+            .await
+            .unwrap();
+        let uris: Vec<&str> = services.iter().map(|service| service.uri.as_str()).collect();
+        assert_eq!(uris, vec!["http://v1-2"]);
+
+        let uri = client
+            .discover(&create_query("1.0", DiscoveryPolicy::CompatibleVersion))
+            .await
+            .unwrap();
+        assert_eq!(uri, "http://v1-2");
+
+        let result =
+            client.discover(&create_query("one", DiscoveryPolicy::CompatibleVersion)).await;
+        assert!(matches!(result, Err(DiscoveryError::InvalidVersion(_))));
+    }
+
+    #[tokio::test]
+    async fn discover_not_found_test() {
+        let chariott_uri = start_mock_service_registry(Vec::new()).await;
+        let client = ChariottClient::new(&chariott_uri, None);
+
+        let error =
+            client.discover(&create_query("1.0", DiscoveryPolicy::Exact)).await.unwrap_err();
+        assert!(matches!(error, DiscoveryError::NotFound { .. }));
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "Did not find a service in Chariott with namespace '{NAMESPACE}' and name '{NAME}'"
+            )
+        );
+        assert_eq!(Status::from(error).code(), Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn discover_unreachable_chariott_test() {
+        // Nothing listens on this port.
+        let client = ChariottClient::new("http://127.0.0.1:1", None);
+
+        let error =
+            client.discover(&create_query("1.0", DiscoveryPolicy::Exact)).await.unwrap_err();
+        assert!(matches!(error, DiscoveryError::Chariott(_)));
+        assert_eq!(Status::from(error).code(), Code::Unavailable);
+    }
+
+    #[test]
+    fn next_retry_interval_test() {
+        let max_retry_interval = Duration::from_secs(10);
+
+        assert_eq!(
+            next_retry_interval(Duration::from_secs(1), max_retry_interval),
+            Duration::from_secs(2)
+        );
+        assert_eq!(
+            next_retry_interval(Duration::from_secs(4), max_retry_interval),
+            Duration::from_secs(8)
+        );
+        assert_eq!(
+            next_retry_interval(Duration::from_secs(8), max_retry_interval),
+            max_retry_interval
+        );
+        assert_eq!(next_retry_interval(Duration::MAX, max_retry_interval), max_retry_interval);
+    }
+
+    #[tokio::test]
+    async fn registrar_registers_and_unregisters_test() {
+        let service_registry = MockServiceRegistry::new();
+        let client = ChariottClient::new(&service_registry.serve().await, None);
+        let service = create_service("1.0", KIND, REFERENCE, "http://registered");
+
+        let registrar = ChariottRegistrar::start(
+            client,
+            service.clone(),
+            ChariottRegistrationSettings::default(),
+        );
+        let mut state = registrar.state();
+        state.wait_for(|state| *state == ChariottRegistrationState::Registered).await.unwrap();
+        assert_eq!(service_registry.services(), vec![service]);
+
+        registrar.shutdown().await.unwrap();
+        assert_eq!(*state.borrow(), ChariottRegistrationState::Unregistered);
+        assert!(service_registry.services().is_empty());
+    }
+
+    #[tokio::test]
+    async fn registrar_retries_until_stopped_test() {
+        // Nothing listens on this port, so every attempt to register fails.
+        let client = ChariottClient::new("http://127.0.0.1:1", None);
+        let settings = ChariottRegistrationSettings {
+            initial_retry_interval: Duration::from_millis(10),
+            max_retry_interval: Duration::from_millis(20),
+            reassert_interval: Duration::from_secs(1),
+        };
+
+        let registrar = ChariottRegistrar::start(client, ServiceMetadata::default(), settings);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(*registrar.state().borrow(), ChariottRegistrationState::Registering);
+
+        // Chariott is unreachable, so the service cannot be unregistered either.
+        assert!(registrar.shutdown().await.is_err());
+    }
+}
@@ -0,0 +1,257 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use http::HeaderMap;
+use http_body::Body;
+use std::error::Error;
+use std::io::{Read, Write};
+use tonic::Status;
+
+// This module provides the framing of gRPC messages in an http body. Each message is prefixed
+// by a Compressed-Flag byte and a four byte Message-Length.
+
+/// The gRPC header represents the gRPC call's Compress-Flag and Message-Length.
+pub const GRPC_HEADER_LENGTH: usize = 5;
+
+/// The header that holds the encoding of the compressed messages.
+const GRPC_ENCODING_HEADER: &str = "grpc-encoding";
+
+/// The only message encoding that is supported.
+const GZIP_ENCODING: &str = "gzip";
+
+/// The largest message that is read by default, in bytes. This is tonic's default limit for the
+/// messages that a service decodes.
+pub const DEFAULT_MAX_MESSAGE_SIZE: usize = 4 * 1024 * 1024;
+
+/// A length-prefixed gRPC message.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GrpcFrame {
+    /// Whether the message is compressed.
+    pub compressed: bool,
+    /// The message, as it appears in the body.
+    pub message: Bytes,
+}
+
+impl GrpcFrame {
+    /// Get the frame's protobuf message, decompressing it when it is compressed.
+    ///
+    /// # Arguments
+    /// * `headers` - The headers of the request or response, which hold the message encoding.
+    pub fn decode_message(&self, headers: &HeaderMap) -> Result<Bytes, Status> {
+        if !self.compressed {
+            return Ok(self.message.clone());
+        }
+
+        check_encoding(headers)?;
+
+        let mut message = Vec::new();
+        GzDecoder::new(&self.message[..]).read_to_end(&mut message).map_err(|error| {
+            Status::internal(format!("Unable to decompress the gRPC message: {error}"))
+        })?;
+
+        Ok(Bytes::from(message))
+    }
+
+    /// Create a frame for a protobuf message and encode it as it appears in the body.
+    ///
+    /// # Arguments
+    /// * `message` - The protobuf message.
+    /// * `compressed` - Whether to compress the message.
+    pub fn encode_message(message: Bytes, compressed: bool) -> Result<Bytes, Status> {
+        let message = if compressed {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(&message).and_then(|_| encoder.finish()).map(Bytes::from).map_err(
+                |error| Status::internal(format!("Unable to compress the gRPC message: {error}")),
+            )?
+        } else {
+            message
+        };
+
+        let message_length = u32::try_from(message.len())
+            .map_err(|_| Status::resource_exhausted("The gRPC message is too large"))?;
+
+        let mut frame = BytesMut::with_capacity(GRPC_HEADER_LENGTH + message.len());
+        frame.put_u8(u8::from(compressed));
+        frame.put_u32(message_length);
+        frame.put(message);
+
+        Ok(frame.freeze())
+    }
+}
+
+/// Check that compressed messages use an encoding that is supported.
+///
+/// # Arguments
+/// * `headers` - The headers of the request or response, which hold the message encoding.
+fn check_encoding(headers: &HeaderMap) -> Result<(), Status> {
+    match headers.get(GRPC_ENCODING_HEADER).map(|encoding| encoding.to_str()) {
+        Some(Ok(GZIP_ENCODING)) => Ok(()),
+        Some(Ok(encoding)) => Err(Status::unimplemented(format!(
+            "The gRPC message encoding '{encoding}' is not supported"
+        ))),
+        _ => Err(Status::internal("A compressed gRPC message does not have an encoding")),
+    }
+}
+
+/// Reads the length-prefixed gRPC messages from an http body, however they are split into chunks.
+/// A message that is larger than the maximum message size is rejected before it is buffered.
+pub struct GrpcFrameDecoder<B> {
+    body: B,
+    buffer: BytesMut,
+    max_message_size: usize,
+}
+
+impl<B> GrpcFrameDecoder<B>
+where
+    B: Body<Data = Bytes> + Unpin,
+    B::Error: Into<Box<dyn Error + Send + Sync>>,
+{
+    /// Create a new GrpcFrameDecoder.
+    ///
+    /// # Arguments
+    /// * `body` - The http body that holds the messages.
+    pub fn new(body: B) -> Self {
+        GrpcFrameDecoder {
+            body,
+            buffer: BytesMut::new(),
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+        }
+    }
+
+    /// Read messages of up to a maximum size, rather than `DEFAULT_MAX_MESSAGE_SIZE`.
+    ///
+    /// # Arguments
+    /// * `max_message_size` - The largest message that is read, in bytes.
+    pub fn with_max_message_size(mut self, max_message_size: usize) -> Self {
+        self.max_message_size = max_message_size;
+        self
+    }
+
+    /// Read the next frame. Returns `None` when the body has ended. A message that is larger than
+    /// the maximum message size is an error, which is a RESOURCE_EXHAUSTED `Status`.
+    pub async fn next_frame(&mut self) -> Result<Option<GrpcFrame>, Box<dyn Error + Send + Sync>> {
+        loop {
+            if self.buffer.len() >= GRPC_HEADER_LENGTH {
+                let compressed = self.buffer[0] == 1;
+                let message_length = u32::from_be_bytes([
+                    self.buffer[1],
+                    self.buffer[2],
+                    self.buffer[3],
+                    self.buffer[4],
+                ]) as usize;
+
+                if message_length > self.max_message_size {
+                    return Err(Box::new(Status::resource_exhausted(format!(
+                        "The gRPC message is {message_length} bytes, which is larger than the maximum of {} bytes",
+                        self.max_message_size
+                    ))));
+                }
+
+                if self.buffer.len() >= GRPC_HEADER_LENGTH + message_length {
+                    self.buffer.advance(GRPC_HEADER_LENGTH);
+                    let message = self.buffer.split_to(message_length).freeze();
+                    return Ok(Some(GrpcFrame { compressed, message }));
+                }
+            }
+
+            match self.body.data().await {
+                Some(Ok(chunk)) => self.buffer.extend_from_slice(&chunk),
+                Some(Err(error)) => return Err(error.into()),
+                None if self.buffer.is_empty() => return Ok(None),
+                None => return Err("The body ended part way through a gRPC message".into()),
+            }
+        }
+    }
+
+    /// Read the body's trailers. This must only be called after the last frame has been read.
+    pub async fn trailers(&mut self) -> Result<Option<HeaderMap>, Box<dyn Error + Send + Sync>> {
+        self.body.trailers().await.map_err(|error| error.into())
+    }
+}
+
+#[cfg(test)]
+mod grpc_frame_tests {
+    use super::*;
+
+    #[test]
+    fn encode_and_decode_message_test() {
+        let mut headers = HeaderMap::new();
+        headers.insert(GRPC_ENCODING_HEADER, GZIP_ENCODING.parse().unwrap());
+
+        let message = Bytes::from_static(b"ambient air temperature");
+
+        let frame_bytes = GrpcFrame::encode_message(message.clone(), false).unwrap();
+        assert_eq!(frame_bytes.len(), GRPC_HEADER_LENGTH + message.len());
+        let frame =
+            GrpcFrame { compressed: false, message: frame_bytes.slice(GRPC_HEADER_LENGTH..) };
+        assert_eq!(frame.decode_message(&HeaderMap::new()).unwrap(), message);
+
+        let frame_bytes = GrpcFrame::encode_message(message.clone(), true).unwrap();
+        assert_eq!(frame_bytes[0], 1);
+        let frame =
+            GrpcFrame { compressed: true, message: frame_bytes.slice(GRPC_HEADER_LENGTH..) };
+        assert_eq!(frame.decode_message(&headers).unwrap(), message);
+
+        // A compressed message cannot be decoded with an unsupported encoding.
+        headers.insert(GRPC_ENCODING_HEADER, "deflate".parse().unwrap());
+        assert_eq!(frame.decode_message(&headers).unwrap_err().code(), tonic::Code::Unimplemented);
+    }
+
+    #[tokio::test]
+    async fn next_frame_test() {
+        let first_frame = GrpcFrame::encode_message(Bytes::from_static(&[1, 2, 3]), false).unwrap();
+        let second_frame = GrpcFrame::encode_message(Bytes::new(), false).unwrap();
+
+        // The frames are split across chunks differently from how they are framed.
+        let (mut sender, body) = hyper::Body::channel();
+        let body_bytes = [&first_frame[..], &second_frame[..]].concat();
+        tokio::spawn(async move {
+            sender.send_data(Bytes::copy_from_slice(&body_bytes[..3])).await.unwrap();
+            sender.send_data(Bytes::copy_from_slice(&body_bytes[3..9])).await.unwrap();
+            sender.send_data(Bytes::copy_from_slice(&body_bytes[9..])).await.unwrap();
+        });
+
+        let mut decoder = GrpcFrameDecoder::new(body);
+        assert_eq!(
+            decoder.next_frame().await.unwrap(),
+            Some(GrpcFrame { compressed: false, message: Bytes::from_static(&[1, 2, 3]) })
+        );
+        assert_eq!(
+            decoder.next_frame().await.unwrap(),
+            Some(GrpcFrame { compressed: false, message: Bytes::new() })
+        );
+        assert_eq!(decoder.next_frame().await.unwrap(), None);
+
+        // A body that ends part way through a message is an error.
+        let mut decoder = GrpcFrameDecoder::new(hyper::Body::from(first_frame.slice(..6)));
+        assert!(decoder.next_frame().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn next_frame_max_message_size_test() {
+        let frame = GrpcFrame::encode_message(Bytes::from_static(&[1, 2, 3]), false).unwrap();
+
+        // A message of the maximum size is read.
+        let mut decoder =
+            GrpcFrameDecoder::new(hyper::Body::from(frame.clone())).with_max_message_size(3);
+        assert!(decoder.next_frame().await.unwrap().is_some());
+
+        // A larger one is rejected as soon as its length has been read, before the rest of it.
+        let body = hyper::Body::from(frame.slice(..GRPC_HEADER_LENGTH));
+        let mut decoder = GrpcFrameDecoder::new(body).with_max_message_size(2);
+        let error = decoder.next_frame().await.unwrap_err();
+        assert_eq!(error.downcast::<Status>().unwrap().code(), tonic::Code::ResourceExhausted);
+
+        // The default maximum is tonic's.
+        let mut header = vec![0];
+        header.extend_from_slice(&(DEFAULT_MAX_MESSAGE_SIZE as u32 + 1).to_be_bytes());
+        let mut decoder = GrpcFrameDecoder::new(hyper::Body::from(header));
+        let error = decoder.next_frame().await.unwrap_err();
+        assert_eq!(error.downcast::<Status>().unwrap().code(), tonic::Code::ResourceExhausted);
+    }
+}
@@ -7,21 +7,27 @@ use core::future::Future;
 use dyn_clone::DynClone;
 use futures_core::task::{Context, Poll};
 use http::uri::Uri;
+use http::HeaderMap;
 use http_body::Body;
 use hyper::Method;
 use log::warn;
 use regex::Regex;
-use std::error::Error;
 use std::pin::Pin;
+use std::sync::Arc;
+use tonic::transport::server::{TcpConnectInfo, TlsConnectInfo};
+use tonic::Status;
+use tower::util::BoxCloneService;
 use tower::{Layer, Service};
 
+use crate::grpc_frame::{GrpcFrame, GrpcFrameDecoder, DEFAULT_MAX_MESSAGE_SIZE};
+
 // This module provides the gRPC Interceptor construct. It can be used to
 // intercept gRPC calls, and examine/modify their requests and responses.
 
-/// The gRPC header represents the gRPC call's Compress-Flag and Message-Length.
-const GRPC_HEADER_LENGTH: usize = 5;
-
 /// This is the trait that a gRPC Interceptor needs to imnplement.
+/// The request and response handlers are asynchronous, so that they can make network calls
+/// without blocking the runtime.
+#[tonic::async_trait]
 pub trait GrpcInterceptor: Sync + DynClone {
     /// Is this interceptor applicable?
     ///
@@ -37,48 +43,252 @@ pub trait GrpcInterceptor: Sync + DynClone {
     fn must_handle_response(&self) -> bool;
 
     /// Handle request. Return the new new request.
+    /// This is called for each of the request's messages. The service is called once the first
+    /// message has been handled, so changes to the headers only take effect for the first message.
+    /// Returning a status for the first message answers the call with that status, without
+    /// invoking the service. Any metadata that is added to the status is sent to the caller with
+    /// it. Returning a status for a later message aborts the request.
     ///
     /// # Arguments
     /// * `service_name` - The gRPC call's service name.
     /// * `method_name` - The gRPC call's method name.
-    /// * `protobuf_message_bytes` - The request's protobuf messages as bytes.
-    fn handle_request(
+    /// * `request_parts` - The request's headers, which can be modified, and its extensions, such
+    ///                     as the peer's TLS certificates.
+    /// * `protobuf_message` - The request's protobuf messages as bytes.
+    async fn handle_request(
         &self,
         service_name: &str,
         method_name: &str,
+        request_parts: &mut http::request::Parts,
         protobuf_message: Bytes,
-    ) -> Result<Bytes, Box<dyn Error + Send + Sync>>;
+    ) -> Result<Bytes, Status>;
 
     /// Handle response. Return the new response.
+    /// This is called for each of the response's messages. The response is returned once the
+    /// first message has been handled, so changes to the headers only take effect for the first
+    /// message. Returning a status answers the call with that status instead of the rest of the
+    /// service's response.
     ///
     /// # Arguments
     /// * `service_name` - The gRPC call's service name.
     /// * `method_name` - The gRPC call's method name.
-    /// * `protobuf_message_bytes` - The response's protobuf messages as bytes.
-    fn handle_response(
+    /// * `response_parts` - The response's headers, which can be modified, and its extensions,
+    ///                      which hold the call's request as `InterceptedRequestParts`.
+    /// * `protobuf_message` - The response's protobuf messages as bytes.
+    async fn handle_response(
         &self,
         service_name: &str,
         method_name: &str,
+        response_parts: &mut http::response::Parts,
         protobuf_message: Bytes,
-    ) -> Result<Bytes, Box<dyn Error + Send + Sync>>;
+    ) -> Result<Bytes, Status>;
 }
 
 // Macro that allows for clonable dynamic traits.
 dyn_clone::clone_trait_object!(GrpcInterceptor);
 
+/// Marks a response with which an interceptor answered a call, rather than the service.
+#[derive(Clone, Copy, Debug)]
+pub struct InterceptedResponse;
+
+/// The request of an intercepted call, with its headers and connection information. It is added
+/// to the response's extensions before the response is handled, so that the response handlers can
+/// tell who made the call.
+#[derive(Clone)]
+pub struct InterceptedRequestParts(pub Arc<http::request::Parts>);
+
+/// The first message of an intercepted call's request, as the interceptor returned it. It is added
+/// to the extensions of the `InterceptedRequestParts` when the interceptor handles both the
+/// requests and the responses, so that the response handlers can tell what was asked for.
+#[derive(Clone)]
+pub struct InterceptedRequestMessage(pub Bytes);
+
+/// The first message of an intercepted call's request, as the caller sent it. It is added along
+/// with the `InterceptedRequestMessage`, so that the response handlers can also tell what the
+/// caller asked for when the request handler changed the message.
+#[derive(Clone)]
+pub struct ReceivedRequestMessage(pub Bytes);
+
+/// A handler for the typed messages of a gRPC method.
+pub type TypedMessageHandler<M> = Arc<dyn Fn(M) -> Result<M, Status> + Send + Sync>;
+
+/// A gRPC Interceptor for one gRPC method, whose handlers are given the method's decoded prost
+/// messages rather than their bytes. A message that cannot be decoded is answered with an
+/// INVALID_ARGUMENT status, so that a malformed message does not reach the handlers.
+pub struct TypedGrpcInterceptor<Req, Resp> {
+    service_name: String,
+    method_name: String,
+    request_handler: Option<TypedMessageHandler<Req>>,
+    response_handler: Option<TypedMessageHandler<Resp>>,
+}
+
+impl<Req, Resp> TypedGrpcInterceptor<Req, Resp> {
+    /// Create a TypedGrpcInterceptor for a gRPC method, which does not handle its requests or
+    /// responses until handlers are added.
+    ///
+    /// # Arguments
+    /// * `service_name` - The gRPC service name, such as "InvehicleDigitalTwin".
+    /// * `method_name` - The gRPC method name, such as "Register".
+    pub fn new(service_name: &str, method_name: &str) -> Self {
+        Self {
+            service_name: service_name.to_string(),
+            method_name: method_name.to_string(),
+            request_handler: None,
+            response_handler: None,
+        }
+    }
+
+    /// Handle the method's requests. The handler returns the new request.
+    ///
+    /// # Arguments
+    /// * `handler` - The request handler.
+    pub fn with_request_handler(
+        mut self,
+        handler: impl Fn(Req) -> Result<Req, Status> + Send + Sync + 'static,
+    ) -> Self {
+        self.request_handler = Some(Arc::new(handler));
+        self
+    }
+
+    /// Handle the method's responses. The handler returns the new response.
+    ///
+    /// # Arguments
+    /// * `handler` - The response handler.
+    pub fn with_response_handler(
+        mut self,
+        handler: impl Fn(Resp) -> Result<Resp, Status> + Send + Sync + 'static,
+    ) -> Self {
+        self.response_handler = Some(Arc::new(handler));
+        self
+    }
+}
+
+impl<Req, Resp> Clone for TypedGrpcInterceptor<Req, Resp> {
+    fn clone(&self) -> Self {
+        Self {
+            service_name: self.service_name.clone(),
+            method_name: self.method_name.clone(),
+            request_handler: self.request_handler.clone(),
+            response_handler: self.response_handler.clone(),
+        }
+    }
+}
+
+/// Decode a protobuf message, handle it and encode the new message.
+///
+/// # Arguments
+/// * `handler` - The message handler, if any.
+/// * `description` - Describes the message in the status for a message that cannot be decoded.
+/// * `protobuf_message` - The protobuf message as bytes.
+fn handle_typed_message<M: prost::Message + Default>(
+    handler: &Option<TypedMessageHandler<M>>,
+    description: &str,
+    protobuf_message: Bytes,
+) -> Result<Bytes, Status> {
+    let Some(handler) = handler else {
+        return Ok(protobuf_message);
+    };
+
+    let message = M::decode(protobuf_message).map_err(|error| {
+        Status::invalid_argument(format!("Unable to decode the {description}: {error}"))
+    })?;
+
+    Ok(Bytes::from(handler(message)?.encode_to_vec()))
+}
+
+#[tonic::async_trait]
+impl<Req, Resp> GrpcInterceptor for TypedGrpcInterceptor<Req, Resp>
+where
+    Req: prost::Message + Default + 'static,
+    Resp: prost::Message + Default + 'static,
+{
+    /// Is this interceptor applicable? It is applicable to its gRPC method.
+    ///
+    /// # Arguments
+    /// * `service_name` - The gRPC call's service name.
+    /// * `method_name` - The gRPC call's method name.
+    fn is_applicable(&self, service_name: &str, method_name: &str) -> bool {
+        service_name == self.service_name && method_name == self.method_name
+    }
+
+    /// Indicates that the request must be handled, when there is a request handler.
+    fn must_handle_request(&self) -> bool {
+        self.request_handler.is_some()
+    }
+
+    /// Indicates that the response must be handled, when there is a response handler.
+    fn must_handle_response(&self) -> bool {
+        self.response_handler.is_some()
+    }
+
+    /// Handle request. Return the new request.
+    ///
+    /// # Arguments
+    /// * `service_name` - The gRPC call's service name.
+    /// * `method_name` - The gRPC call's method name.
+    /// * `request_parts` - The request's headers and extensions.
+    /// * `protobuf_message` - The request's protobuf messages as bytes.
+    async fn handle_request(
+        &self,
+        _service_name: &str,
+        method_name: &str,
+        _request_parts: &mut http::request::Parts,
+        protobuf_message: Bytes,
+    ) -> Result<Bytes, Status> {
+        handle_typed_message(
+            &self.request_handler,
+            &format!("{method_name} request"),
+            protobuf_message,
+        )
+    }
+
+    /// Handle response. Return the new response.
+    ///
+    /// # Arguments
+    /// * `service_name` - The gRPC call's service name.
+    /// * `method_name` - The gRPC call's method name.
+    /// * `response_parts` - The response's headers.
+    /// * `protobuf_message` - The response's protobuf messages as bytes.
+    async fn handle_response(
+        &self,
+        _service_name: &str,
+        method_name: &str,
+        _response_parts: &mut http::response::Parts,
+        protobuf_message: Bytes,
+    ) -> Result<Bytes, Status> {
+        handle_typed_message(
+            &self.response_handler,
+            &format!("{method_name} response"),
+            protobuf_message,
+        )
+    }
+}
+
 /// The tower layer that hosts a service that hosts a gRPC Interceptor.
 #[derive(Clone)]
 pub struct GrpcInterceptorLayer {
     interceptor: Box<dyn GrpcInterceptor + Send>,
+    max_message_size: usize,
 }
 
 impl GrpcInterceptorLayer {
-    /// Create the tower layer for a gRPC Interceptor.
+    /// Create the tower layer for a gRPC Interceptor, which handles messages of up to
+    /// `DEFAULT_MAX_MESSAGE_SIZE` bytes.
     ///
     /// # Arguments
     /// * `interceptor` - The boxed gRPC Interceptor.
     pub fn new(interceptor: Box<dyn GrpcInterceptor + Send>) -> Self {
-        Self { interceptor }
+        Self { interceptor, max_message_size: DEFAULT_MAX_MESSAGE_SIZE }
+    }
+
+    /// Handle messages of up to a maximum size. A call with a larger message is answered with a
+    /// RESOURCE_EXHAUSTED status.
+    ///
+    /// # Arguments
+    /// * `max_message_size` - The largest message that is handled, in bytes.
+    pub fn with_max_message_size(mut self, max_message_size: usize) -> Self {
+        self.max_message_size = max_message_size;
+        self
     }
 }
 
@@ -86,53 +296,89 @@ impl<S> Layer<S> for GrpcInterceptorLayer {
     type Service = GrpcInterceptorService<S>;
 
     fn layer(&self, service: S) -> Self::Service {
-        GrpcInterceptorService { service, interceptor: self.interceptor.clone() }
+        GrpcInterceptorService {
+            service,
+            interceptor: self.interceptor.clone(),
+            max_message_size: self.max_message_size,
+        }
     }
 }
 
-#[derive(Clone)]
-/// The tower service that hosts a gRPC Interceptor.
-pub struct GrpcInterceptorService<S> {
-    service: S,
-    interceptor: Box<dyn GrpcInterceptor + Send>,
+/// The tower layer that applies a list of gRPC Interceptors, which can be decided at runtime.
+/// The first interceptor handles each request first and each response last, as if each
+/// interceptor had been added to the middleware with its own `GrpcInterceptorLayer`.
+#[derive(Clone, Default)]
+pub struct GrpcInterceptorChainLayer {
+    layers: Vec<GrpcInterceptorLayer>,
 }
 
-impl<S> GrpcInterceptorService<S> {
-    /// Retrieve the gRPC service name and method name from a URI.
-    /// If it cannot succesfully be parsed, then an empty service name and/or method name will be returned.
+impl GrpcInterceptorChainLayer {
+    /// Create the tower layer for a list of gRPC Interceptors.
     ///
-    /// * `uri` - The uri used for the gRPC call.
-    fn retrieve_grpc_names_from_uri(uri: &Uri) -> (String, String) {
-        let mut service_name = String::new();
-        let mut method_name = String::new();
-        // A gRPC URI path looks like this "/invehicle_digital_twin.InvehicleDigitalTwin/FindById".
-        match Regex::new(r"^/[^/\.]+\.([^/]+)/(.+)$") {
-            Ok(regex_pattern) => {
-                if let Some(caps) = regex_pattern.captures(uri.path()) {
-                    // Note: caps.get(0) represents the entire string that matched.
-                    //       In the earlier gRPC URI path example it would be
-                    //       "/invehicle_digital_twin.InvehicleDigitalTwin/FindById".
-                    //       caps.get(1) and caps.get(2) represent the sub-parts that matched.
-                    if caps.len() == 3 {
-                        service_name = caps.get(1).unwrap().as_str().to_string();
-                        method_name = caps.get(2).unwrap().as_str().to_string();
-                    }
-                }
-            }
-            Err(err) => warn!("Regex pattern for gRPC names is not valid: {err}"),
+    /// # Arguments
+    /// * `interceptors` - The gRPC Interceptors, in the order that they handle requests.
+    pub fn new(interceptors: Vec<Box<dyn GrpcInterceptor + Send>>) -> Self {
+        Self { layers: interceptors.into_iter().map(GrpcInterceptorLayer::new).collect() }
+    }
+
+    /// Handle messages of up to a maximum size with each of the interceptors. A call with a
+    /// larger message is answered with a RESOURCE_EXHAUSTED status.
+    ///
+    /// # Arguments
+    /// * `max_message_size` - The largest message that is handled, in bytes.
+    pub fn with_max_message_size(self, max_message_size: usize) -> Self {
+        Self {
+            layers: self
+                .layers
+                .into_iter()
+                .map(|layer| layer.with_max_message_size(max_message_size))
+                .collect(),
         }
+    }
+}
+
+impl<S> Layer<S> for GrpcInterceptorChainLayer
+where
+    S: Service<
+            http::request::Request<tonic::transport::Body>,
+            Response = http::response::Response<tonic::body::BoxBody>,
+            Error = Box<dyn std::error::Error + Sync + Send>,
+        > + Clone
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+{
+    type Service = BoxCloneService<
+        http::request::Request<tonic::transport::Body>,
+        http::response::Response<tonic::body::BoxBody>,
+        Box<dyn std::error::Error + Sync + Send>,
+    >;
 
-        (service_name, method_name)
+    fn layer(&self, service: S) -> Self::Service {
+        // The last interceptor is the innermost one, so the layers are applied in reverse.
+        self.layers.iter().rev().fold(BoxCloneService::new(service), |service, layer| {
+            BoxCloneService::new(layer.layer(service))
+        })
     }
 }
 
+#[derive(Clone)]
+/// The tower service that hosts a gRPC Interceptor.
+pub struct GrpcInterceptorService<S> {
+    service: S,
+    interceptor: Box<dyn GrpcInterceptor + Send>,
+    max_message_size: usize,
+}
+
 impl<S> Service<http::request::Request<tonic::transport::Body>> for GrpcInterceptorService<S>
 where
     S: Service<
             http::request::Request<tonic::transport::Body>,
             Response = http::response::Response<tonic::body::BoxBody>,
             Error = Box<dyn std::error::Error + Sync + Send>,
-        > + Send,
+        > + Clone
+        + Send
+        + 'static,
     S::Future: Send + 'static,
 {
     type Response = S::Response;
@@ -153,81 +399,223 @@ where
         mut request: http::request::Request<tonic::transport::Body>,
     ) -> Self::Future {
         let interceptor = self.interceptor.clone();
+        let max_message_size = self.max_message_size;
 
-        let (service_name, method_name) = Self::retrieve_grpc_names_from_uri(request.uri());
+        let (service_name, method_name) = retrieve_grpc_names_from_uri(request.uri());
         let is_applicable = interceptor.is_applicable(&service_name, &method_name)
             && (request.method() == Method::POST);
 
-        if is_applicable && interceptor.must_handle_request() {
-            let (parts, body) = request.into_parts();
-
-            // There is a known issue where hyper::body::to_bytes sometimes hangs in the code below.
-            // We will use a timeout to break out when this happens.  This fix is a bandaid.  We will
-            // implement a better fix after we have upgraded to the latest major version of the hyper crate.
-            let mut body_bytes: Bytes = match futures::executor::block_on(async {
-                async_std::future::timeout(
-                    core::time::Duration::from_secs(5),
-                    hyper::body::to_bytes(body),
-                )
-                .await
-            }) {
-                Ok(Ok(bytes)) => bytes,
-                Ok(Err(err)) => {
-                    return Box::pin(async move {
-                        Err(Box::new(err) as Box<dyn std::error::Error + Sync + Send>)
-                    });
-                }
-                Err(err) => {
-                    return Box::pin(async move {
-                        Err(Box::new(err) as Box<dyn std::error::Error + Sync + Send>)
-                    });
+        // The inner service has been driven to readiness, so it is the one that must be called.
+        // A clone takes its place for the next call.
+        let clone = self.service.clone();
+        let mut service = std::mem::replace(&mut self.service, clone);
+
+        Box::pin(async move {
+            let mut intercepted_request_messages = None;
+
+            if is_applicable && interceptor.must_handle_request() {
+                let (mut parts, body) = request.into_parts();
+                let mut decoder =
+                    GrpcFrameDecoder::new(body).with_max_message_size(max_message_size);
+
+                // The first message is handled before the service is called, so that the
+                // interceptor can modify the request's headers or answer the call with a status.
+                let first_frame = match decoder.next_frame().await {
+                    Ok(first_frame) => first_frame,
+                    Err(error) => return create_intercepted_error_response(error),
+                };
+                let first_frame_bytes = match first_frame {
+                    Some(frame) => match handle_request_frame(
+                        interceptor.as_ref(),
+                        &service_name,
+                        &method_name,
+                        &mut parts,
+                        frame,
+                    )
+                    .await
+                    {
+                        Ok((received_message, message, bytes)) => {
+                            intercepted_request_messages = Some((
+                                ReceivedRequestMessage(received_message),
+                                InterceptedRequestMessage(message),
+                            ));
+                            Some(bytes)
+                        }
+                        // Answer the call with the status, without invoking the service.
+                        Err(status) => return Ok(create_intercepted_response(status)),
+                    },
+                    None => None,
+                };
+
+                // The later messages of a streaming call are handled as they arrive.
+                let (mut sender, new_body) = tonic::transport::Body::channel();
+                let mut later_parts = copy_request_parts(&parts);
+                let interceptor = interceptor.clone();
+                let (service_name, method_name) = (service_name.clone(), method_name.clone());
+                tokio::spawn(async move {
+                    if let Some(bytes) = first_frame_bytes {
+                        if sender.send_data(bytes).await.is_err() {
+                            return;
+                        }
+                    }
+
+                    loop {
+                        let frame = match decoder.next_frame().await {
+                            Ok(Some(frame)) => frame,
+                            Ok(None) => break,
+                            Err(err) => {
+                                warn!("Unable to read the request's messages: {err}");
+                                sender.abort();
+                                return;
+                            }
+                        };
+
+                        match handle_request_frame(
+                            interceptor.as_ref(),
+                            &service_name,
+                            &method_name,
+                            &mut later_parts,
+                            frame,
+                        )
+                        .await
+                        {
+                            Ok((_, _, bytes)) => {
+                                if sender.send_data(bytes).await.is_err() {
+                                    return;
+                                }
+                            }
+                            Err(status) => {
+                                warn!("Aborting the request, as its interceptor failed: {status}");
+                                sender.abort();
+                                return;
+                            }
+                        }
+                    }
+
+                    if let Ok(Some(trailers)) = decoder.trailers().await {
+                        let _ = sender.send_trailers(trailers).await;
+                    }
+                });
+
+                request = http::request::Request::from_parts(parts, new_body);
+            }
+
+            // The response handlers are given the request as the interceptor last saw it.
+            let intercepted_request_parts = if is_applicable && interceptor.must_handle_response() {
+                let (parts, body) = request.into_parts();
+                let mut intercepted_parts = copy_request_parts(&parts);
+                if let Some((received_request_message, intercepted_request_message)) =
+                    intercepted_request_messages
+                {
+                    intercepted_parts.extensions.insert(received_request_message);
+                    intercepted_parts.extensions.insert(intercepted_request_message);
                 }
+                request = http::request::Request::from_parts(parts, body);
+                Some(InterceptedRequestParts(Arc::new(intercepted_parts)))
+            } else {
+                None
             };
 
-            let protobuf_message_bytes: Bytes = body_bytes.split_off(GRPC_HEADER_LENGTH);
-            let grpc_header_bytes = body_bytes;
-            let new_protobuf_message_bytes: Bytes = match interceptor.handle_request(
-                &service_name,
-                &method_name,
-                protobuf_message_bytes,
-            ) {
-                Ok(bytes) => bytes,
-                Err(err) => return Box::pin(async move { Err(err) }),
-            };
-            let new_body_chunks: Vec<Result<_, std::io::Error>> =
-                vec![Ok(grpc_header_bytes), Ok(new_protobuf_message_bytes)];
-            let stream = futures_util::stream::iter(new_body_chunks);
-            let new_body = tonic::transport::Body::wrap_stream(stream);
-            request = http::request::Request::from_parts(parts, new_body);
-        }
+            let mut response = service.call(request).await?;
 
-        let fut = self.service.call(request);
+            if let Some(intercepted_request_parts) = intercepted_request_parts {
+                let (mut parts, body) = response.into_parts();
+                parts.extensions.insert(intercepted_request_parts);
+                let mut decoder =
+                    GrpcFrameDecoder::new(body).with_max_message_size(max_message_size);
 
-        Box::pin(async move {
-            let mut response = fut.await?;
-
-            if is_applicable && interceptor.must_handle_response() {
-                let (parts, body) = response.into_parts();
-                let mut body_bytes = match hyper::body::to_bytes(body).await {
-                    Ok(bytes) => bytes,
-                    Err(err) => {
-                        return Err(Box::new(err) as Box<dyn std::error::Error + Sync + Send>)
-                    }
+                // The first message is handled before the response is returned, so that the
+                // interceptor can modify the response's headers or replace it with a status.
+                let first_frame = match decoder.next_frame().await {
+                    Ok(first_frame) => first_frame,
+                    Err(error) => return create_intercepted_error_response(error),
                 };
-                let protobuf_message_bytes = body_bytes.split_off(GRPC_HEADER_LENGTH);
-                let grpc_header_bytes = body_bytes;
-                let new_protobuf_message_bytes = match interceptor.handle_response(
-                    &service_name,
-                    &method_name,
-                    protobuf_message_bytes,
-                ) {
-                    Ok(bytes) => bytes,
-                    Err(err) => return Err(err),
+                let first_frame_bytes = match first_frame {
+                    Some(frame) => match handle_response_frame(
+                        interceptor.as_ref(),
+                        &service_name,
+                        &method_name,
+                        &mut parts,
+                        frame,
+                    )
+                    .await
+                    {
+                        Ok(bytes) => Some(bytes),
+                        Err(status) => return Ok(create_intercepted_response(status)),
+                    },
+                    None => None,
                 };
-                let new_body_chunks: Vec<Result<_, std::io::Error>> =
-                    vec![Ok(grpc_header_bytes), Ok(new_protobuf_message_bytes)];
-                let stream = futures_util::stream::iter(new_body_chunks);
-                let new_body = tonic::transport::Body::wrap_stream(stream);
+
+                // The later messages of a streaming call are handled as they are produced.
+                let (mut sender, new_body) = tonic::transport::Body::channel();
+                let mut later_parts = copy_response_parts(&parts);
+                tokio::spawn(async move {
+                    if let Some(bytes) = first_frame_bytes {
+                        if sender.send_data(bytes).await.is_err() {
+                            return;
+                        }
+                    }
+
+                    loop {
+                        let frame = match decoder.next_frame().await {
+                            Ok(Some(frame)) => frame,
+                            Ok(None) => break,
+                            Err(err) => {
+                                warn!("Unable to read the response's messages: {err}");
+
+                                // A message that is too large ends the call with its status.
+                                let mut trailers = HeaderMap::new();
+                                match err.downcast::<Status>() {
+                                    Ok(status) if status.add_header(&mut trailers).is_ok() => {
+                                        let _ = sender.send_trailers(trailers).await;
+                                    }
+                                    _ => sender.abort(),
+                                }
+                                return;
+                            }
+                        };
+
+                        match handle_response_frame(
+                            interceptor.as_ref(),
+                            &service_name,
+                            &method_name,
+                            &mut later_parts,
+                            frame,
+                        )
+                        .await
+                        {
+                            Ok(bytes) => {
+                                if sender.send_data(bytes).await.is_err() {
+                                    return;
+                                }
+                            }
+                            Err(status) => {
+                                // The headers have already been sent, so the call ends with the
+                                // status in its trailers.
+                                let mut trailers = HeaderMap::new();
+                                if status.add_header(&mut trailers).is_ok() {
+                                    let _ = sender.send_trailers(trailers).await;
+                                } else {
+                                    sender.abort();
+                                }
+                                return;
+                            }
+                        }
+                    }
+
+                    // The trailers hold the call's status.
+                    match decoder.trailers().await {
+                        Ok(Some(trailers)) => {
+                            let _ = sender.send_trailers(trailers).await;
+                        }
+                        Ok(None) => {}
+                        Err(err) => {
+                            warn!("Unable to read the response's trailers: {err}");
+                            sender.abort();
+                        }
+                    }
+                });
+
                 let new_box_body =
                     new_body.map_err(|e| tonic::Status::from_error(Box::new(e))).boxed_unsync();
                 response = http::response::Response::from_parts(parts, new_box_body);
@@ -237,3 +625,407 @@ where
         })
     }
 }
+
+/// Retrieve the gRPC service name and method name from a URI.
+/// If it cannot succesfully be parsed, then an empty service name and/or method name will be returned.
+///
+/// * `uri` - The uri used for the gRPC call.
+pub(crate) fn retrieve_grpc_names_from_uri(uri: &Uri) -> (String, String) {
+    let mut service_name = String::new();
+    let mut method_name = String::new();
+    // A gRPC URI path looks like this "/invehicle_digital_twin.InvehicleDigitalTwin/FindById".
+    match Regex::new(r"^/[^/\.]+\.([^/]+)/(.+)$") {
+        Ok(regex_pattern) => {
+            if let Some(caps) = regex_pattern.captures(uri.path()) {
+                // Note: caps.get(0) represents the entire string that matched.
+                //       In the earlier gRPC URI path example it would be
+                //       "/invehicle_digital_twin.InvehicleDigitalTwin/FindById".
+                //       caps.get(1) and caps.get(2) represent the sub-parts that matched.
+                if caps.len() == 3 {
+                    service_name = caps.get(1).unwrap().as_str().to_string();
+                    method_name = caps.get(2).unwrap().as_str().to_string();
+                }
+            }
+        }
+        Err(err) => warn!("Regex pattern for gRPC names is not valid: {err}"),
+    }
+
+    (service_name, method_name)
+}
+
+/// Answer a call with a status on behalf of an interceptor. The response is marked with the
+/// `InterceptedResponse` extension.
+///
+/// # Arguments
+/// * `status` - The interceptor's status.
+fn create_intercepted_response(status: Status) -> http::response::Response<tonic::body::BoxBody> {
+    let mut response = status.to_http();
+    response.extensions_mut().insert(InterceptedResponse);
+    response
+}
+
+/// Answer a call whose messages could not be read on behalf of an interceptor. An error that is
+/// a status, such as the RESOURCE_EXHAUSTED status of a message that is too large, answers the call
+/// with that status. Other errors are returned as they are.
+///
+/// # Arguments
+/// * `error` - The error that reading the messages failed with.
+fn create_intercepted_error_response(
+    error: Box<dyn std::error::Error + Sync + Send>,
+) -> Result<http::response::Response<tonic::body::BoxBody>, Box<dyn std::error::Error + Sync + Send>>
+{
+    let status = error.downcast::<Status>()?;
+    warn!("Unable to read the call's messages: {status}");
+    Ok(create_intercepted_response(*status))
+}
+
+/// Handle one of a request's messages with an interceptor. Returns the message that was received,
+/// the new message, and the new message's frame.
+///
+/// # Arguments
+/// * `interceptor` - The gRPC Interceptor.
+/// * `service_name` - The gRPC call's service name.
+/// * `method_name` - The gRPC call's method name.
+/// * `request_parts` - The request's headers and extensions.
+/// * `frame` - The message's frame.
+async fn handle_request_frame(
+    interceptor: &(dyn GrpcInterceptor + Send),
+    service_name: &str,
+    method_name: &str,
+    request_parts: &mut http::request::Parts,
+    frame: GrpcFrame,
+) -> Result<(Bytes, Bytes, Bytes), Status> {
+    let protobuf_message_bytes = frame.decode_message(&request_parts.headers)?;
+    let new_protobuf_message_bytes = interceptor
+        .handle_request(service_name, method_name, request_parts, protobuf_message_bytes.clone())
+        .await?;
+    let new_frame_bytes =
+        GrpcFrame::encode_message(new_protobuf_message_bytes.clone(), frame.compressed)?;
+    Ok((protobuf_message_bytes, new_protobuf_message_bytes, new_frame_bytes))
+}
+
+/// Handle one of a response's messages with an interceptor. Returns the new message's frame.
+///
+/// # Arguments
+/// * `interceptor` - The gRPC Interceptor.
+/// * `service_name` - The gRPC call's service name.
+/// * `method_name` - The gRPC call's method name.
+/// * `response_parts` - The response's headers.
+/// * `frame` - The message's frame.
+async fn handle_response_frame(
+    interceptor: &(dyn GrpcInterceptor + Send),
+    service_name: &str,
+    method_name: &str,
+    response_parts: &mut http::response::Parts,
+    frame: GrpcFrame,
+) -> Result<Bytes, Status> {
+    let protobuf_message_bytes = frame.decode_message(&response_parts.headers)?;
+    let new_protobuf_message_bytes = interceptor
+        .handle_response(service_name, method_name, response_parts, protobuf_message_bytes)
+        .await?;
+    GrpcFrame::encode_message(new_protobuf_message_bytes, frame.compressed)
+}
+
+/// Copy a request's parts for handling the messages after the first one. The copy has the
+/// request's headers and connection information.
+///
+/// # Arguments
+/// * `parts` - The request's parts.
+fn copy_request_parts(parts: &http::request::Parts) -> http::request::Parts {
+    let (mut copy, _) = http::request::Request::new(()).into_parts();
+    copy.method = parts.method.clone();
+    copy.uri = parts.uri.clone();
+    copy.version = parts.version;
+    copy.headers = parts.headers.clone();
+
+    if let Some(tcp_connect_info) = parts.extensions.get::<TcpConnectInfo>() {
+        copy.extensions.insert(tcp_connect_info.clone());
+    }
+    if let Some(tls_connect_info) = parts.extensions.get::<TlsConnectInfo<TcpConnectInfo>>() {
+        copy.extensions.insert(tls_connect_info.clone());
+    }
+
+    copy
+}
+
+/// Copy a response's parts for handling the messages after the first one. The copy has the
+/// response's status and headers, and the call's request.
+///
+/// # Arguments
+/// * `parts` - The response's parts.
+fn copy_response_parts(parts: &http::response::Parts) -> http::response::Parts {
+    let (mut copy, _) = http::response::Response::new(()).into_parts();
+    copy.status = parts.status;
+    copy.version = parts.version;
+    copy.headers = parts.headers.clone();
+
+    if let Some(intercepted_request_parts) = parts.extensions.get::<InterceptedRequestParts>() {
+        copy.extensions.insert(intercepted_request_parts.clone());
+    }
+
+    copy
+}
+
+#[cfg(test)]
+mod grpc_interceptor_tests {
+    use super::*;
+    use prost::Message;
+    use std::error::Error;
+    use std::time::Duration;
+    use tonic::metadata::MetadataValue;
+    use tower::ServiceExt;
+
+    const DENY_METHOD_NAME: &str = "Deny";
+
+    /// Interceptor that increments each byte of a request's message, after awaiting a timer.
+    /// It marks the requests that it has handled with a header, and copies that header and the
+    /// length of the request's first message to the responses that it has handled. It denies
+    /// calls to the Deny method.
+    #[derive(Clone)]
+    struct IncrementingGrpcInterceptor {}
+
+    #[tonic::async_trait]
+    impl GrpcInterceptor for IncrementingGrpcInterceptor {
+        fn is_applicable(&self, _service_name: &str, _method_name: &str) -> bool {
+            true
+        }
+
+        fn must_handle_request(&self) -> bool {
+            true
+        }
+
+        fn must_handle_response(&self) -> bool {
+            true
+        }
+
+        async fn handle_request(
+            &self,
+            _service_name: &str,
+            method_name: &str,
+            request_parts: &mut http::request::Parts,
+            protobuf_message: Bytes,
+        ) -> Result<Bytes, Status> {
+            if method_name == DENY_METHOD_NAME {
+                let mut status = Status::permission_denied("The call is denied");
+                status.metadata_mut().insert("x-denied-by", MetadataValue::from_static("test"));
+                return Err(status);
+            }
+
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            request_parts.headers.insert("x-request-intercepted", "true".parse().unwrap());
+            Ok(protobuf_message.iter().map(|byte| byte + 1).collect())
+        }
+
+        async fn handle_response(
+            &self,
+            _service_name: &str,
+            _method_name: &str,
+            response_parts: &mut http::response::Parts,
+            protobuf_message: Bytes,
+        ) -> Result<Bytes, Status> {
+            // The request is given to the response handler as the request handler left it.
+            let InterceptedRequestParts(request_parts) =
+                response_parts.extensions.get::<InterceptedRequestParts>().unwrap();
+            let request_intercepted = request_parts.headers["x-request-intercepted"].clone();
+            response_parts.headers.insert("x-response-intercepted", request_intercepted);
+
+            // So is the request's first message.
+            let InterceptedRequestMessage(request_message) =
+                request_parts.extensions.get::<InterceptedRequestMessage>().unwrap();
+            response_parts.headers.insert("x-request-message-length", request_message.len().into());
+
+            // The request's first message is also given as the caller sent it.
+            let ReceivedRequestMessage(received_message) =
+                request_parts.extensions.get::<ReceivedRequestMessage>().unwrap();
+            let incremented_message: Vec<u8> =
+                received_message.iter().map(|byte| byte + 1).collect();
+            assert_eq!(request_message.as_ref(), incremented_message.as_slice());
+            Ok(protobuf_message)
+        }
+    }
+
+    /// Create a service that echoes the request's body and its x-request-intercepted and
+    /// grpc-encoding headers, wrapped by the interceptor.
+    fn create_service() -> GrpcInterceptorService<
+        impl Service<
+                http::request::Request<tonic::transport::Body>,
+                Response = http::response::Response<tonic::body::BoxBody>,
+                Error = Box<dyn Error + Send + Sync>,
+                Future = impl Send,
+            > + Clone
+            + Send,
+    > {
+        let echo_service = tower::service_fn(
+            |request: http::request::Request<tonic::transport::Body>| async move {
+                assert_ne!(request.uri().path(), "/test.Test/Deny");
+
+                let (parts, body) = request.into_parts();
+                let body_bytes = hyper::body::to_bytes(body).await?;
+                let body = tonic::transport::Body::from(body_bytes)
+                    .map_err(|e| Status::from_error(Box::new(e)))
+                    .boxed_unsync();
+
+                let mut response = http::response::Response::new(body);
+                for header in ["x-request-intercepted", "grpc-encoding"] {
+                    if let Some(value) = parts.headers.get(header) {
+                        response.headers_mut().insert(header, value.clone());
+                    }
+                }
+                Ok::<_, Box<dyn Error + Send + Sync>>(response)
+            },
+        );
+
+        GrpcInterceptorLayer::new(Box::new(IncrementingGrpcInterceptor {})).layer(echo_service)
+    }
+
+    #[tokio::test]
+    async fn call_test() {
+        // The body is sent by another task, as it is when it is received over a connection.
+        let (mut sender, body) = tonic::transport::Body::channel();
+        tokio::spawn(async move {
+            sender.send_data(Bytes::from_static(&[0, 0, 0, 0, 3])).await.unwrap();
+            sender.send_data(Bytes::from_static(&[1, 2, 3])).await.unwrap();
+        });
+
+        let request = http::request::Request::builder()
+            .method(Method::POST)
+            .uri("/test.Test/Echo")
+            .body(body)
+            .unwrap();
+
+        let response = create_service().oneshot(request).await.unwrap();
+        assert_eq!(response.headers()["x-request-intercepted"], "true");
+        assert_eq!(response.headers()["x-response-intercepted"], "true");
+        assert_eq!(response.headers()["x-request-message-length"], "3");
+
+        let response_bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(&response_bytes[..], &[0, 0, 0, 0, 3, 2, 3, 4]);
+    }
+
+    #[tokio::test]
+    async fn call_with_status_test() {
+        let request = http::request::Request::builder()
+            .method(Method::POST)
+            .uri("/test.Test/Deny")
+            .body(tonic::transport::Body::from(vec![0, 0, 0, 0, 0]))
+            .unwrap();
+
+        let response = create_service().oneshot(request).await.unwrap();
+        let status = Status::from_header_map(response.headers()).unwrap();
+        assert_eq!(status.code(), tonic::Code::PermissionDenied);
+        assert_eq!(response.headers()["x-denied-by"], "test");
+    }
+
+    #[tokio::test]
+    async fn call_with_too_large_message_test() {
+        let echo_service = create_service().service;
+        let service = GrpcInterceptorLayer::new(Box::new(IncrementingGrpcInterceptor {}))
+            .with_max_message_size(2)
+            .layer(echo_service);
+
+        let request = http::request::Request::builder()
+            .method(Method::POST)
+            .uri("/test.Test/Echo")
+            .body(tonic::transport::Body::from(vec![0, 0, 0, 0, 3, 1, 2, 3]))
+            .unwrap();
+
+        // The call is answered without invoking the service.
+        let response = service.oneshot(request).await.unwrap();
+        let status = Status::from_header_map(response.headers()).unwrap();
+        assert_eq!(status.code(), tonic::Code::ResourceExhausted);
+        assert!(response.headers().get("x-request-intercepted").is_none());
+    }
+
+    #[tokio::test]
+    async fn call_with_multiple_messages_test() {
+        // The second message is compressed and split across chunks.
+        let first_frame = GrpcFrame::encode_message(Bytes::from_static(&[1, 2]), false).unwrap();
+        let second_frame = GrpcFrame::encode_message(Bytes::from_static(&[5]), true).unwrap();
+        let (mut sender, body) = tonic::transport::Body::channel();
+        tokio::spawn(async move {
+            sender.send_data(first_frame).await.unwrap();
+            sender.send_data(second_frame.slice(..3)).await.unwrap();
+            sender.send_data(second_frame.slice(3..)).await.unwrap();
+        });
+
+        let request = http::request::Request::builder()
+            .method(Method::POST)
+            .uri("/test.Test/Echo")
+            .header("grpc-encoding", "gzip")
+            .body(body)
+            .unwrap();
+
+        let response = create_service().oneshot(request).await.unwrap();
+        let (parts, body) = response.into_parts();
+        let mut decoder = GrpcFrameDecoder::new(body);
+
+        let frame = decoder.next_frame().await.unwrap().unwrap();
+        assert_eq!(&frame.decode_message(&parts.headers).unwrap()[..], &[2, 3]);
+
+        let frame = decoder.next_frame().await.unwrap().unwrap();
+        assert!(frame.compressed);
+        assert_eq!(&frame.decode_message(&parts.headers).unwrap()[..], &[6]);
+
+        assert!(decoder.next_frame().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn chain_layer_test() {
+        // The echo service, without the interceptor that wraps it.
+        let echo_service = create_service().service;
+        let chain_layer = GrpcInterceptorChainLayer::new(vec![
+            Box::new(IncrementingGrpcInterceptor {}),
+            Box::new(IncrementingGrpcInterceptor {}),
+        ]);
+
+        let request = http::request::Request::builder()
+            .method(Method::POST)
+            .uri("/test.Test/Echo")
+            .body(tonic::transport::Body::from(vec![0, 0, 0, 0, 2, 1, 2]))
+            .unwrap();
+
+        // Each interceptor in the chain increments each byte of the request.
+        let response = chain_layer.layer(echo_service).oneshot(request).await.unwrap();
+        let response_bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(&response_bytes[..], &[0, 0, 0, 0, 2, 3, 4]);
+    }
+
+    /// A prost message for the typed interceptor tests.
+    #[derive(Clone, PartialEq, prost::Message)]
+    struct TestMessage {
+        #[prost(string, tag = "1")]
+        value: String,
+    }
+
+    #[tokio::test]
+    async fn typed_grpc_interceptor_test() {
+        let interceptor = TypedGrpcInterceptor::<TestMessage, TestMessage>::new("Test", "Echo")
+            .with_request_handler(|message| {
+                if message.value.is_empty() {
+                    return Err(Status::failed_precondition("The value is empty"));
+                }
+                Ok(TestMessage { value: message.value.to_uppercase() })
+            });
+
+        assert!(interceptor.is_applicable("Test", "Echo"));
+        assert!(!interceptor.is_applicable("Test", "Other"));
+        assert!(interceptor.must_handle_request());
+        assert!(!interceptor.must_handle_response());
+
+        let (mut parts, _) = http::request::Request::new(()).into_parts();
+        let message = Bytes::from(TestMessage { value: String::from("seat") }.encode_to_vec());
+        let new_message =
+            interceptor.handle_request("Test", "Echo", &mut parts, message).await.unwrap();
+        assert_eq!(TestMessage::decode(new_message).unwrap().value, "SEAT");
+
+        // The handler's status is returned.
+        let message = Bytes::from(TestMessage::default().encode_to_vec());
+        let status = interceptor.handle_request("Test", "Echo", &mut parts, message).await;
+        assert_eq!(status.unwrap_err().code(), tonic::Code::FailedPrecondition);
+
+        // A malformed message is answered with INVALID_ARGUMENT, rather than causing a panic.
+        let message = Bytes::from_static(&[0xff, 0xff]);
+        let status = interceptor.handle_request("Test", "Echo", &mut parts, message).await;
+        assert_eq!(status.unwrap_err().code(), tonic::Code::InvalidArgument);
+    }
+}
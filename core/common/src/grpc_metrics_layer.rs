@@ -0,0 +1,214 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+use core::future::Future;
+use futures_core::task::{Context, Poll};
+use std::collections::HashSet;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Instant;
+use tonic::{Code, Status};
+use tower::{Layer, Service};
+
+use crate::grpc_interceptor::{retrieve_grpc_names_from_uri, InterceptedResponse};
+use crate::metrics::MetricsRegistry;
+
+// This module provides a tower layer that collects the metrics of each gRPC call. It should be
+// the outermost layer, so that it also sees the calls that interceptors answer with a status.
+
+const REQUESTS_TOTAL_NAME: &str = "ibeji_grpc_requests_total";
+const REQUESTS_TOTAL_HELP: &str = "The number of gRPC calls, by service, method and status code.";
+const REQUEST_DURATION_NAME: &str = "ibeji_grpc_request_duration_seconds";
+const REQUEST_DURATION_HELP: &str =
+    "How long the gRPC calls took to respond, in seconds, by service and method.";
+const INTERCEPTOR_ERRORS_TOTAL_NAME: &str = "ibeji_grpc_interceptor_errors_total";
+const INTERCEPTOR_ERRORS_TOTAL_HELP: &str =
+    "The number of gRPC calls that an interceptor answered with a status, by service, method and status code.";
+
+/// The label value of a service or method that is not hosted. Callers choose the names in a
+/// call's path, so they are only used as labels when they are hosted.
+const UNKNOWN_LABEL_VALUE: &str = "unknown";
+
+/// The tower layer that collects the metrics of each gRPC call.
+#[derive(Clone)]
+pub struct GrpcMetricsLayer {
+    metrics_registry: Arc<MetricsRegistry>,
+    hosted_service_names: Arc<HashSet<String>>,
+}
+
+impl GrpcMetricsLayer {
+    /// Create the tower layer that collects the metrics of each gRPC call.
+    ///
+    /// # Arguments
+    /// * `metrics_registry` - The registry that the metrics are collected in.
+    /// * `hosted_service_names` - The full names of the hosted services, such as
+    ///                            "invehicle_digital_twin.InvehicleDigitalTwin". Calls to any
+    ///                            other service are labelled with an unknown service and method.
+    pub fn new(metrics_registry: Arc<MetricsRegistry>, hosted_service_names: &[&str]) -> Self {
+        let hosted_service_names =
+            hosted_service_names.iter().map(|service_name| service_name.to_string()).collect();
+
+        Self { metrics_registry, hosted_service_names: Arc::new(hosted_service_names) }
+    }
+}
+
+impl<S> Layer<S> for GrpcMetricsLayer {
+    type Service = GrpcMetricsService<S>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        GrpcMetricsService {
+            service,
+            metrics_registry: self.metrics_registry.clone(),
+            hosted_service_names: self.hosted_service_names.clone(),
+        }
+    }
+}
+
+/// The tower service that collects the metrics of each gRPC call.
+#[derive(Clone)]
+pub struct GrpcMetricsService<S> {
+    service: S,
+    metrics_registry: Arc<MetricsRegistry>,
+    hosted_service_names: Arc<HashSet<String>>,
+}
+
+impl<S, ReqBody, ResBody> Service<http::request::Request<ReqBody>> for GrpcMetricsService<S>
+where
+    S: Service<http::request::Request<ReqBody>, Response = http::response::Response<ResBody>>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future =
+        Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send + 'static>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: http::request::Request<ReqBody>) -> Self::Future {
+        // A gRPC URI path looks like this "/invehicle_digital_twin.InvehicleDigitalTwin/FindById".
+        let is_hosted = request.uri().path().trim_start_matches('/').split_once('/').is_some_and(
+            |(full_service_name, _)| self.hosted_service_names.contains(full_service_name),
+        );
+        let (service_name, method_name) = if is_hosted {
+            retrieve_grpc_names_from_uri(request.uri())
+        } else {
+            (UNKNOWN_LABEL_VALUE.to_string(), UNKNOWN_LABEL_VALUE.to_string())
+        };
+        let metrics_registry = self.metrics_registry.clone();
+        let start = Instant::now();
+
+        let future = self.service.call(request);
+
+        Box::pin(async move {
+            let result = future.await;
+            let elapsed = start.elapsed();
+
+            // A call that fails is answered with its status in the headers. Otherwise, the status
+            // is sent in the trailers once the response has been streamed, and it is counted as OK.
+            let (code, is_intercepted) = match &result {
+                Ok(response) => (
+                    Status::from_header_map(response.headers())
+                        .map_or(Code::Ok, |status| status.code()),
+                    response.extensions().get::<InterceptedResponse>().is_some(),
+                ),
+                Err(_) => (Code::Unknown, false),
+            };
+
+            // A hosted service answers a method that it does not have as unimplemented.
+            let method_name = if code == Code::Unimplemented && !is_intercepted {
+                UNKNOWN_LABEL_VALUE
+            } else {
+                method_name.as_str()
+            };
+            let code = format!("{code:?}");
+
+            let labels =
+                [("service", service_name.as_str()), ("method", method_name), ("code", &code)];
+            metrics_registry.increment_counter(REQUESTS_TOTAL_NAME, REQUESTS_TOTAL_HELP, &labels);
+            metrics_registry.observe_latency(
+                REQUEST_DURATION_NAME,
+                REQUEST_DURATION_HELP,
+                &labels[..2],
+                elapsed.as_secs_f64(),
+            );
+
+            if is_intercepted {
+                metrics_registry.increment_counter(
+                    INTERCEPTOR_ERRORS_TOTAL_NAME,
+                    INTERCEPTOR_ERRORS_TOTAL_HELP,
+                    &labels,
+                );
+            }
+
+            result
+        })
+    }
+}
+
+#[cfg(test)]
+mod grpc_metrics_layer_tests {
+    use super::*;
+    use std::convert::Infallible;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn call_test() {
+        let metrics_registry = Arc::new(MetricsRegistry::new());
+
+        // The service fails the Deny method, as an interceptor would, and does not have the
+        // Missing method.
+        let service = tower::service_fn(|request: http::request::Request<()>| async move {
+            if request.uri().path().ends_with("/Deny") {
+                let mut response = Status::permission_denied("Denied").to_http();
+                response.extensions_mut().insert(InterceptedResponse);
+                return Ok::<_, Infallible>(response);
+            }
+            if request.uri().path().ends_with("/Missing") {
+                return Ok(Status::unimplemented("Missing").to_http());
+            }
+            Ok(http::response::Response::new(tonic::body::empty_body()))
+        });
+        let service = GrpcMetricsLayer::new(
+            metrics_registry.clone(),
+            &["invehicle_digital_twin.InvehicleDigitalTwin"],
+        )
+        .layer(service);
+
+        for path in [
+            "/invehicle_digital_twin.InvehicleDigitalTwin/Find",
+            "/invehicle_digital_twin.InvehicleDigitalTwin/Find",
+            "/invehicle_digital_twin.InvehicleDigitalTwin/Deny",
+            "/invehicle_digital_twin.InvehicleDigitalTwin/Missing",
+            "/unknown_package.Random/Find",
+            "/not_a_grpc_path",
+        ] {
+            let request = http::request::Request::builder().uri(path).body(()).unwrap();
+            service.clone().oneshot(request).await.unwrap();
+        }
+
+        let text = metrics_registry.render();
+        assert!(text.contains(
+            "ibeji_grpc_requests_total{service=\"InvehicleDigitalTwin\",method=\"Find\",code=\"Ok\"} 2\n"
+        ));
+        assert!(text.contains(
+            "ibeji_grpc_requests_total{service=\"InvehicleDigitalTwin\",method=\"Deny\",code=\"PermissionDenied\"} 1\n"
+        ));
+        assert!(text.contains(
+            "ibeji_grpc_interceptor_errors_total{service=\"InvehicleDigitalTwin\",method=\"Deny\",code=\"PermissionDenied\"} 1\n"
+        ));
+        assert!(text.contains(
+            "ibeji_grpc_request_duration_seconds_count{service=\"InvehicleDigitalTwin\",method=\"Find\"} 2\n"
+        ));
+        assert!(text.contains(
+            "ibeji_grpc_requests_total{service=\"InvehicleDigitalTwin\",method=\"unknown\",code=\"Unimplemented\"} 1\n"
+        ));
+        assert!(text.contains(
+            "ibeji_grpc_requests_total{service=\"unknown\",method=\"unknown\",code=\"Ok\"} 2\n"
+        ));
+        assert!(!text.contains("Random"));
+        assert!(!text.contains("Missing"));
+    }
+}
@@ -3,13 +3,70 @@
 // SPDX-License-Identifier: MIT
 
 use tonic::transport::server::RoutesBuilder;
+use tonic::Status;
+
+use crate::grpc_interceptor::GrpcInterceptor;
+use crate::metrics::MetricsRegistry;
 
 /// Trait that must be implemented for a module to add one or more grpc services to the hosted
 /// server. A GrpcModule may also implement one or more GrpcInterceptor objects and share state.
-pub trait GrpcModule {
+/// The GrpcServer drives the module's lifecycle hooks: `start` before the server serves calls,
+/// `on_ready` once it is listening, and `shutdown` after it has stopped. While it serves calls,
+/// `on_entity_removed` is called for each entity that is removed from the registry.
+#[tonic::async_trait]
+pub trait GrpcModule: Send + Sync {
     /// Function to add necessary services to the server builder.
     ///
     /// # Arguments
     /// * `builder` - A tonic::RoutesBuilder that contains the grpc services to build.
     fn add_grpc_services(&self, builder: &mut RoutesBuilder);
+
+    /// Creates the module's interceptors, in the order that they handle requests.
+    /// The default is no interceptors.
+    fn create_interceptors(&self) -> Vec<Box<dyn GrpcInterceptor + Send>> {
+        Vec::new()
+    }
+
+    /// Gets the encoded file descriptor sets of the module's services, so that they can be
+    /// discovered with reflection. The default is none.
+    fn file_descriptor_sets(&self) -> Vec<&'static [u8]> {
+        Vec::new()
+    }
+
+    /// Gets the fully qualified names of the module's services, such as
+    /// "managed_subscribe.ManagedSubscribe", so that their serving status can be reported.
+    /// The default is none.
+    fn service_names(&self) -> Vec<&'static str> {
+        Vec::new()
+    }
+
+    /// Registers the module's gauges, when metrics are collected. The default registers none.
+    ///
+    /// # Arguments
+    /// * `metrics_registry` - The registry of the service's metrics.
+    fn register_metrics(&self, _metrics_registry: &MetricsRegistry) {}
+
+    /// Starts the module, before the server serves calls. A module can start its background
+    /// tasks here. An error stops the server from starting, and the modules are then shut down,
+    /// including those that have not started. The default does nothing.
+    async fn start(&self) -> Result<(), Status> {
+        Ok(())
+    }
+
+    /// Called once the server is listening for calls. The default does nothing.
+    async fn on_ready(&self) {}
+
+    /// Called when an entity has been removed from the registry, however it was removed. This
+    /// includes the entities that are evicted when their provider's lease expires, which the
+    /// module's interceptors do not see. The default does nothing.
+    ///
+    /// # Arguments
+    /// * `entity_key` - The entity's key in the registry.
+    async fn on_entity_removed(&self, _entity_key: &str) {}
+
+    /// Shuts the module down, after the server has stopped serving calls. A module can stop its
+    /// background tasks and release external resources here. The default does nothing.
+    async fn shutdown(&self) -> Result<(), Status> {
+        Ok(())
+    }
 }
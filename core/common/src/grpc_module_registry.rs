@@ -0,0 +1,189 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+use core::future::Future;
+use log::info;
+use std::collections::HashMap;
+use std::pin::Pin;
+use tonic::Status;
+
+use crate::grpc_module::GrpcModule;
+
+/// The future that a module factory returns.
+pub type GrpcModuleFuture =
+    Pin<Box<dyn Future<Output = Result<Box<dyn GrpcModule>, Status>> + Send + 'static>>;
+
+/// A factory that creates a module.
+pub type GrpcModuleFactory = Box<dyn Fn() -> GrpcModuleFuture + Send + Sync>;
+
+/// The priority of a module, which decides when its interceptors handle calls relative to the
+/// interceptors of the other modules.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum GrpcModulePriority {
+    /// The module's interceptors handle calls before those of the other modules, wherever the
+    /// module is listed. Modules that authorize calls use this priority, so that a call that they
+    /// deny never reaches another module's interceptors.
+    Security,
+    /// The module's interceptors handle calls in the order that the module is listed.
+    Default,
+}
+
+/// A module's factory and priority.
+struct RegisteredModule {
+    priority: GrpcModulePriority,
+    factory: GrpcModuleFactory,
+}
+
+/// Registry of the modules that can be enabled at runtime. Each module registers a factory under
+/// its name, and the modules that are listed in the settings are created with their factories.
+#[derive(Default)]
+pub struct GrpcModuleRegistry {
+    modules: HashMap<String, RegisteredModule>,
+}
+
+impl GrpcModuleRegistry {
+    /// Creates a new empty GrpcModuleRegistry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a module's factory under the module's name. A factory that is registered under
+    /// the same name replaces the previous one.
+    ///
+    /// # Arguments
+    /// * `name` - The module's name.
+    /// * `priority` - The module's priority.
+    /// * `factory` - The function that creates the module.
+    pub fn register<F, Fut>(&mut self, name: &str, priority: GrpcModulePriority, factory: F)
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Box<dyn GrpcModule>, Status>> + Send + 'static,
+    {
+        self.modules.insert(
+            name.to_string(),
+            RegisteredModule { priority, factory: Box::new(move || Box::pin(factory())) },
+        );
+    }
+
+    /// Gets the names of the registered modules, in alphabetical order.
+    pub fn registered_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.modules.keys().map(|name| name.as_str()).collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Creates the named modules, ordered by their priority and then in the order that they are
+    /// listed. The modules' interceptors are applied in the same order, so the interceptors of
+    /// security modules always handle calls first.
+    ///
+    /// # Arguments
+    /// * `names` - The names of the modules to create.
+    pub async fn create_modules(
+        &self,
+        names: &[String],
+    ) -> Result<Vec<Box<dyn GrpcModule>>, Status> {
+        let mut registered_modules = Vec::new();
+
+        for (index, name) in names.iter().enumerate() {
+            if names[..index].contains(name) {
+                return Err(Status::invalid_argument(format!(
+                    "The module '{name}' is listed more than once."
+                )));
+            }
+
+            let registered_module = self.modules.get(name).ok_or_else(|| {
+                Status::invalid_argument(format!(
+                    "The module '{name}' is not registered. The registered modules are: {:?}.",
+                    self.registered_names()
+                ))
+            })?;
+
+            registered_modules.push((name, registered_module));
+        }
+
+        // A stable sort keeps the modules of the same priority in the order that they are listed.
+        registered_modules.sort_by_key(|(_, registered_module)| registered_module.priority);
+
+        let mut modules = Vec::new();
+
+        for (name, registered_module) in registered_modules {
+            modules.push((registered_module.factory)().await?);
+
+            info!("Initialized the '{name}' module.");
+        }
+
+        Ok(modules)
+    }
+}
+
+#[cfg(test)]
+mod grpc_module_registry_tests {
+    use super::*;
+    use tonic::transport::server::RoutesBuilder;
+
+    struct TestModule {
+        name: &'static str,
+    }
+
+    impl GrpcModule for TestModule {
+        fn add_grpc_services(&self, _builder: &mut RoutesBuilder) {}
+
+        fn service_names(&self) -> Vec<&'static str> {
+            vec![self.name]
+        }
+    }
+
+    fn create_registry() -> GrpcModuleRegistry {
+        let mut registry = GrpcModuleRegistry::new();
+        registry.register("first", GrpcModulePriority::Default, || async {
+            Ok(Box::new(TestModule { name: "first" }) as Box<dyn GrpcModule>)
+        });
+        registry.register("second", GrpcModulePriority::Default, || async {
+            Ok(Box::new(TestModule { name: "second" }) as Box<dyn GrpcModule>)
+        });
+        registry.register("security", GrpcModulePriority::Security, || async {
+            Ok(Box::new(TestModule { name: "security" }) as Box<dyn GrpcModule>)
+        });
+        registry.register("failing", GrpcModulePriority::Default, || async {
+            Err(Status::unavailable("Unavailable"))
+        });
+        registry
+    }
+
+    #[tokio::test]
+    async fn create_modules_test() {
+        let registry = create_registry();
+        assert_eq!(registry.registered_names(), vec!["failing", "first", "second", "security"]);
+
+        let names = vec![String::from("second"), String::from("first")];
+        let modules = registry.create_modules(&names).await.unwrap();
+        let service_names: Vec<&str> =
+            modules.iter().flat_map(|module| module.service_names()).collect();
+        assert_eq!(service_names, vec!["second", "first"]);
+
+        // The security module is created first, wherever it is listed.
+        let names = vec![String::from("second"), String::from("security"), String::from("first")];
+        let modules = registry.create_modules(&names).await.unwrap();
+        let service_names: Vec<&str> =
+            modules.iter().flat_map(|module| module.service_names()).collect();
+        assert_eq!(service_names, vec!["security", "second", "first"]);
+
+        assert!(registry.create_modules(&[]).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn create_modules_error_test() {
+        let registry = create_registry();
+
+        let result = registry.create_modules(&[String::from("unknown")]).await;
+        assert_eq!(result.err().unwrap().code(), tonic::Code::InvalidArgument);
+
+        let names = vec![String::from("first"), String::from("first")];
+        let result = registry.create_modules(&names).await;
+        assert_eq!(result.err().unwrap().code(), tonic::Code::InvalidArgument);
+
+        let result = registry.create_modules(&[String::from("failing")]).await;
+        assert_eq!(result.err().unwrap().code(), tonic::Code::Unavailable);
+    }
+}
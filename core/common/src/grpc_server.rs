@@ -2,13 +2,18 @@
 // Licensed under the MIT license.
 // SPDX-License-Identifier: MIT
 
+use log::{info, warn};
 use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
 
 use tonic::transport::server::Router;
-use tonic::transport::{server::RoutesBuilder, Server};
+use tonic::transport::{server::RoutesBuilder, Server, ServerTlsConfig};
+use tonic::Status;
 use tower::layer::util::{Identity, Stack};
 use tower::ServiceBuilder;
 
+use crate::grpc_interceptor::GrpcInterceptorChainLayer;
 use crate::grpc_module::GrpcModule;
 
 /// Grpc Server struct that builds multiple services and layers.
@@ -16,6 +21,8 @@ pub struct GrpcServer<L> {
     address: SocketAddr,
     pub modules: RoutesBuilder,
     pub middleware: ServiceBuilder<L>,
+    tls_config: Option<ServerTlsConfig>,
+    grpc_modules: Vec<Arc<dyn GrpcModule>>,
 }
 
 impl GrpcServer<Identity> {
@@ -24,11 +31,25 @@ impl GrpcServer<Identity> {
     /// # Arguments
     /// * `address` - The address the server will be hosted on.
     pub fn new(address: SocketAddr) -> Self {
-        GrpcServer { address, modules: RoutesBuilder::default(), middleware: ServiceBuilder::new() }
+        GrpcServer {
+            address,
+            modules: RoutesBuilder::default(),
+            middleware: ServiceBuilder::new(),
+            tls_config: None,
+            grpc_modules: Vec::new(),
+        }
     }
 }
 
 impl<L> GrpcServer<L> {
+    /// Serve over TLS with the given configuration.
+    ///
+    /// # Arguments
+    /// * `tls_config` - The server's TLS configuration.
+    pub fn set_tls_config(&mut self, tls_config: ServerTlsConfig) {
+        self.tls_config = Some(tls_config);
+    }
+
     /// Adds a module (collection of grpc services and grpc interceptors) for the server to host.
     /// Returns a newly decorated GrpcServer with the added module.
     ///
@@ -42,18 +63,234 @@ impl<L> GrpcServer<L> {
         module: Box<dyn GrpcModule>,
     ) -> GrpcServer<S> {
         module.add_grpc_services(&mut self.modules);
+        self.grpc_modules.push(Arc::from(module));
 
-        GrpcServer { address: self.address, modules: self.modules.clone(), middleware }
+        GrpcServer {
+            address: self.address,
+            modules: self.modules.clone(),
+            middleware,
+            tls_config: self.tls_config.clone(),
+            grpc_modules: self.grpc_modules.clone(),
+        }
+    }
+
+    /// Adds a tower layer to the server's middleware. The layer is outside of the interceptors of
+    /// the modules that are added afterwards, so it sees the calls before they do.
+    /// Returns a newly decorated GrpcServer with the added layer.
+    ///
+    /// # Arguments
+    /// * `layer` - The tower layer to be added to the server.
+    pub fn add_layer<T>(&self, layer: T) -> GrpcServer<Stack<T, L>>
+    where
+        L: Clone,
+    {
+        GrpcServer {
+            address: self.address,
+            modules: self.modules.clone(),
+            middleware: self.middleware.clone().layer(layer),
+            tls_config: self.tls_config.clone(),
+            grpc_modules: self.grpc_modules.clone(),
+        }
+    }
+
+    /// Adds modules for the server to host, along with their interceptors, which handle requests
+    /// in the order of the modules. This is used for modules that are enabled at runtime.
+    /// Returns a newly decorated GrpcServer with the added modules.
+    ///
+    /// # Arguments
+    /// * `modules` - The modules to be added to the server.
+    pub fn add_modules(
+        &mut self,
+        modules: Vec<Box<dyn GrpcModule>>,
+    ) -> GrpcServer<Stack<GrpcInterceptorChainLayer, L>>
+    where
+        L: Clone,
+    {
+        let interceptors = modules.iter().flat_map(|module| module.create_interceptors()).collect();
+
+        for module in modules {
+            module.add_grpc_services(&mut self.modules);
+            self.grpc_modules.push(Arc::from(module));
+        }
+
+        GrpcServer {
+            address: self.address,
+            modules: self.modules.clone(),
+            middleware: self.middleware.clone().layer(GrpcInterceptorChainLayer::new(interceptors)),
+            tls_config: self.tls_config.clone(),
+            grpc_modules: self.grpc_modules.clone(),
+        }
     }
 
     /// Constructs the added modules and layers into a server to host.
-    pub fn construct_server(&self) -> Router<Stack<L, Identity>>
+    /// The server uses TLS when a TLS configuration has been set.
+    pub fn construct_server(&self) -> Result<Router<Stack<L, Identity>>, tonic::transport::Error>
     where
         L: Clone,
     {
+        let mut server_builder = Server::builder();
+        if let Some(tls_config) = &self.tls_config {
+            server_builder = server_builder.tls_config(tls_config.clone())?;
+        }
+
         // Construct the server.
-        Server::builder()
+        Ok(server_builder
             .layer(self.middleware.clone().into_inner())
-            .add_routes(self.modules.clone().routes())
+            .add_routes(self.modules.clone().routes()))
+    }
+
+    /// Starts the added modules, in the order that they were added. This must be called before
+    /// the server serves calls. When a module fails to start, the modules after it are not
+    /// started, and the caller shuts the modules down with `shutdown_modules`.
+    pub async fn start_modules(&self) -> Result<(), Status> {
+        for module in &self.grpc_modules {
+            module.start().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Notifies the added modules, in the order that they were added, that the server is
+    /// listening for calls.
+    pub async fn notify_modules_ready(&self) {
+        for module in &self.grpc_modules {
+            module.on_ready().await;
+        }
+    }
+
+    /// Notifies the added modules, in the order that they were added, that an entity has been
+    /// removed from the registry.
+    ///
+    /// # Arguments
+    /// * `entity_key` - The entity's key in the registry.
+    pub async fn notify_modules_entity_removed(&self, entity_key: &str) {
+        for module in &self.grpc_modules {
+            module.on_entity_removed(entity_key).await;
+        }
+    }
+
+    /// Shuts the added modules down, in the reverse order that they were added. This must be
+    /// called after the server has stopped serving calls. Each module is given the timeout, so a
+    /// module's error or a module that does not shut down in time does not stop the others from
+    /// shutting down.
+    ///
+    /// # Arguments
+    /// * `timeout` - How long to wait for each module to shut down.
+    pub async fn shutdown_modules(&self, timeout: Duration) {
+        for module in self.grpc_modules.iter().rev() {
+            match tokio::time::timeout(timeout, module.shutdown()).await {
+                Ok(Ok(())) => (),
+                Ok(Err(status)) => warn!("A module failed to shut down: '{status}'"),
+                Err(_) => warn!("A module did not shut down within {timeout:?}."),
+            }
+        }
+
+        info!("The modules have shut down.");
+    }
+}
+
+#[cfg(test)]
+mod grpc_server_tests {
+    use super::*;
+    use parking_lot::Mutex;
+    use std::net::{IpAddr, Ipv4Addr};
+    use tonic::transport::server::RoutesBuilder;
+
+    /// Module that records the lifecycle hooks that are called, and optionally fails to start or
+    /// takes longer to shut down than the test allows.
+    struct RecordingModule {
+        name: &'static str,
+        hooks: Arc<Mutex<Vec<String>>>,
+        fails_to_start: bool,
+        shutdown_delay: Duration,
+    }
+
+    #[tonic::async_trait]
+    impl GrpcModule for RecordingModule {
+        fn add_grpc_services(&self, _builder: &mut RoutesBuilder) {}
+
+        async fn start(&self) -> Result<(), Status> {
+            self.hooks.lock().push(format!("start {}", self.name));
+            if self.fails_to_start {
+                return Err(Status::internal("The module failed to start"));
+            }
+            Ok(())
+        }
+
+        async fn on_ready(&self) {
+            self.hooks.lock().push(format!("on_ready {}", self.name));
+        }
+
+        async fn on_entity_removed(&self, entity_key: &str) {
+            self.hooks.lock().push(format!("on_entity_removed {} {entity_key}", self.name));
+        }
+
+        async fn shutdown(&self) -> Result<(), Status> {
+            tokio::time::sleep(self.shutdown_delay).await;
+            self.hooks.lock().push(format!("shutdown {}", self.name));
+            Err(Status::internal("A shutdown error does not stop the other modules"))
+        }
+    }
+
+    #[tokio::test]
+    async fn module_lifecycle_test() {
+        let hooks = Arc::new(Mutex::new(Vec::new()));
+        let create_module = |name, fails_to_start, shutdown_delay| -> Box<dyn GrpcModule> {
+            Box::new(RecordingModule { name, hooks: hooks.clone(), fails_to_start, shutdown_delay })
+        };
+
+        let mut server = GrpcServer::new(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0));
+        let server = server.add_modules(vec![
+            create_module("first", false, Duration::ZERO),
+            create_module("second", false, Duration::ZERO),
+            create_module("slow", false, Duration::from_secs(60)),
+        ]);
+
+        server.start_modules().await.unwrap();
+        server.notify_modules_ready().await;
+        server.notify_modules_entity_removed("hvac").await;
+
+        // The slow module is shut down first, and it does not finish within the timeout, but the
+        // other modules are still shut down.
+        server.shutdown_modules(Duration::from_millis(50)).await;
+        assert_eq!(
+            *hooks.lock(),
+            vec![
+                "start first",
+                "start second",
+                "start slow",
+                "on_ready first",
+                "on_ready second",
+                "on_ready slow",
+                "on_entity_removed first hvac",
+                "on_entity_removed second hvac",
+                "on_entity_removed slow hvac",
+                "shutdown second",
+                "shutdown first",
+            ]
+        );
+
+        // A module that fails to start stops the modules after it from starting. The modules are
+        // then shut down, including those that had already started.
+        hooks.lock().clear();
+        let mut server = GrpcServer::new(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0));
+        let server = server.add_modules(vec![
+            create_module("first", false, Duration::ZERO),
+            create_module("broken", true, Duration::ZERO),
+            create_module("last", false, Duration::ZERO),
+        ]);
+
+        assert!(server.start_modules().await.is_err());
+        server.shutdown_modules(Duration::from_millis(50)).await;
+        assert_eq!(
+            *hooks.lock(),
+            vec![
+                "start first",
+                "start broken",
+                "shutdown last",
+                "shutdown broken",
+                "shutdown first"
+            ]
+        );
     }
 }
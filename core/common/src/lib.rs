@@ -2,8 +2,16 @@
 // Licensed under the MIT license.
 // SPDX-License-Identifier: MIT
 
+pub mod chariott_client;
+pub mod grpc_frame;
 pub mod grpc_interceptor;
+pub mod grpc_metrics_layer;
 pub mod grpc_module;
+pub mod grpc_module_registry;
 pub mod grpc_server;
+pub mod metrics;
 pub mod sample_grpc_interceptor;
+pub mod tls_settings;
+pub mod trace_context;
+pub mod tracing_settings;
 pub mod utils;
@@ -0,0 +1,317 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use parking_lot::Mutex;
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::fmt::Write;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+// This module provides a metrics registry that is rendered in the Prometheus text exposition
+// format, and an http endpoint that serves it for scraping.
+
+/// The path that the metrics are served on.
+pub const METRICS_PATH: &str = "/metrics";
+
+/// The content type of the Prometheus text exposition format.
+const METRICS_CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+/// The upper bounds, in seconds, of the latency histograms' buckets.
+const LATENCY_BUCKETS: [f64; 11] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// A metric's labels, as name and value pairs.
+type Labels = Vec<(String, String)>;
+
+/// A function that reads a gauge's current value.
+type GaugeFn = Box<dyn Fn() -> f64 + Send + Sync>;
+
+/// A counter metric, with a value for each set of labels.
+struct Counter {
+    help: String,
+    values: BTreeMap<Labels, u64>,
+}
+
+/// The observations of a histogram for one set of labels.
+#[derive(Default)]
+struct HistogramValue {
+    bucket_counts: [u64; LATENCY_BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+/// A latency histogram metric, with observations for each set of labels.
+struct Histogram {
+    help: String,
+    values: BTreeMap<Labels, HistogramValue>,
+}
+
+/// A gauge metric, whose value is read when the metrics are rendered.
+struct Gauge {
+    help: String,
+    read_value: GaugeFn,
+}
+
+/// Registry of the service's metrics.
+#[derive(Default)]
+pub struct MetricsRegistry {
+    counters: Mutex<BTreeMap<String, Counter>>,
+    histograms: Mutex<BTreeMap<String, Histogram>>,
+    gauges: Mutex<BTreeMap<String, Gauge>>,
+}
+
+impl MetricsRegistry {
+    /// Creates a new empty MetricsRegistry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Increments a counter. The counter is created the first time that it is incremented.
+    ///
+    /// # Arguments
+    /// * `name` - The counter's name.
+    /// * `help` - Describes the counter.
+    /// * `labels` - The labels of the value to increment.
+    pub fn increment_counter(&self, name: &str, help: &str, labels: &[(&str, &str)]) {
+        let mut counters = self.counters.lock();
+        let counter = counters
+            .entry(name.to_string())
+            .or_insert_with(|| Counter { help: help.to_string(), values: BTreeMap::new() });
+        *counter.values.entry(to_labels(labels)).or_default() += 1;
+    }
+
+    /// Observes a latency in a histogram. The histogram is created the first time that a latency
+    /// is observed.
+    ///
+    /// # Arguments
+    /// * `name` - The histogram's name.
+    /// * `help` - Describes the histogram.
+    /// * `labels` - The labels of the observation.
+    /// * `seconds` - The observed latency, in seconds.
+    pub fn observe_latency(&self, name: &str, help: &str, labels: &[(&str, &str)], seconds: f64) {
+        let mut histograms = self.histograms.lock();
+        let histogram = histograms
+            .entry(name.to_string())
+            .or_insert_with(|| Histogram { help: help.to_string(), values: BTreeMap::new() });
+        let value = histogram.values.entry(to_labels(labels)).or_default();
+
+        for (bucket_count, upper_bound) in value.bucket_counts.iter_mut().zip(LATENCY_BUCKETS) {
+            if seconds <= upper_bound {
+                *bucket_count += 1;
+            }
+        }
+        value.sum += seconds;
+        value.count += 1;
+    }
+
+    /// Registers a gauge, whose value is read each time that the metrics are rendered.
+    /// A gauge that is registered under the same name replaces the previous one.
+    ///
+    /// # Arguments
+    /// * `name` - The gauge's name.
+    /// * `help` - Describes the gauge.
+    /// * `read_value` - Reads the gauge's current value.
+    pub fn register_gauge(
+        &self,
+        name: &str,
+        help: &str,
+        read_value: impl Fn() -> f64 + Send + Sync + 'static,
+    ) {
+        self.gauges.lock().insert(
+            name.to_string(),
+            Gauge { help: help.to_string(), read_value: Box::new(read_value) },
+        );
+    }
+
+    /// Renders the metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let mut text = String::new();
+
+        for (name, counter) in self.counters.lock().iter() {
+            write_header(&mut text, name, &counter.help, "counter");
+            for (labels, value) in &counter.values {
+                let _ = writeln!(text, "{name}{} {value}", format_labels(labels, None));
+            }
+        }
+
+        for (name, histogram) in self.histograms.lock().iter() {
+            write_header(&mut text, name, &histogram.help, "histogram");
+            for (labels, value) in &histogram.values {
+                for (bucket_count, upper_bound) in value.bucket_counts.iter().zip(LATENCY_BUCKETS) {
+                    let le = upper_bound.to_string();
+                    let _ = writeln!(
+                        text,
+                        "{name}_bucket{} {bucket_count}",
+                        format_labels(labels, Some(&le))
+                    );
+                }
+                let _ = writeln!(
+                    text,
+                    "{name}_bucket{} {}",
+                    format_labels(labels, Some("+Inf")),
+                    value.count
+                );
+                let _ = writeln!(text, "{name}_sum{} {}", format_labels(labels, None), value.sum);
+                let _ =
+                    writeln!(text, "{name}_count{} {}", format_labels(labels, None), value.count);
+            }
+        }
+
+        for (name, gauge) in self.gauges.lock().iter() {
+            write_header(&mut text, name, &gauge.help, "gauge");
+            let _ = writeln!(text, "{name} {}", (gauge.read_value)());
+        }
+
+        text
+    }
+}
+
+/// Convert label name and value pairs to owned labels.
+///
+/// # Arguments
+/// * `labels` - The label name and value pairs.
+fn to_labels(labels: &[(&str, &str)]) -> Labels {
+    labels.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+}
+
+/// Write the HELP and TYPE lines of a metric.
+///
+/// # Arguments
+/// * `text` - The text to write to.
+/// * `name` - The metric's name.
+/// * `help` - Describes the metric.
+/// * `metric_type` - The metric's type.
+fn write_header(text: &mut String, name: &str, help: &str, metric_type: &str) {
+    let help = help.replace('\\', "\\\\").replace('\n', "\\n");
+    let _ = writeln!(text, "# HELP {name} {help}");
+    let _ = writeln!(text, "# TYPE {name} {metric_type}");
+}
+
+/// Format a metric's labels, such as `{method="Register",code="Ok"}`. Returns an empty string
+/// when there are no labels.
+///
+/// # Arguments
+/// * `labels` - The labels.
+/// * `le` - The upper bound of a histogram bucket, which is added as the "le" label.
+fn format_labels(labels: &Labels, le: Option<&str>) -> String {
+    let mut formatted_labels: Vec<String> = labels
+        .iter()
+        .map(|(name, value)| {
+            let value = value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n");
+            format!("{name}=\"{value}\"")
+        })
+        .collect();
+
+    if let Some(le) = le {
+        formatted_labels.push(format!("le=\"{le}\""));
+    }
+
+    if formatted_labels.is_empty() {
+        String::new()
+    } else {
+        format!("{{{}}}", formatted_labels.join(","))
+    }
+}
+
+/// Serve the metrics over http, so that they can be scraped from the metrics path.
+///
+/// # Arguments
+/// * `addr` - The address that the metrics are served on.
+/// * `metrics_registry` - The registry of the metrics.
+pub async fn serve_metrics(
+    addr: SocketAddr,
+    metrics_registry: Arc<MetricsRegistry>,
+) -> Result<(), hyper::Error> {
+    let make_service = make_service_fn(move |_| {
+        let metrics_registry = metrics_registry.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request: Request<Body>| {
+                let response = create_metrics_response(&request, &metrics_registry);
+                async move { Ok::<_, Infallible>(response) }
+            }))
+        }
+    });
+
+    Server::try_bind(&addr)?.serve(make_service).await
+}
+
+/// Create the response to a request for the metrics.
+///
+/// # Arguments
+/// * `request` - The http request.
+/// * `metrics_registry` - The registry of the metrics.
+fn create_metrics_response(
+    request: &Request<Body>,
+    metrics_registry: &MetricsRegistry,
+) -> Response<Body> {
+    let mut response = Response::new(Body::empty());
+
+    if request.uri().path() != METRICS_PATH {
+        *response.status_mut() = StatusCode::NOT_FOUND;
+    } else if request.method() != Method::GET {
+        *response.status_mut() = StatusCode::METHOD_NOT_ALLOWED;
+    } else {
+        response
+            .headers_mut()
+            .insert(hyper::header::CONTENT_TYPE, METRICS_CONTENT_TYPE.parse().unwrap());
+        *response.body_mut() = Body::from(metrics_registry.render());
+    }
+
+    response
+}
+
+#[cfg(test)]
+mod metrics_tests {
+    use super::*;
+
+    #[test]
+    fn render_test() {
+        let metrics_registry = MetricsRegistry::new();
+        metrics_registry.increment_counter("calls_total", "The calls.", &[("method", "Find")]);
+        metrics_registry.increment_counter("calls_total", "The calls.", &[("method", "Find")]);
+        metrics_registry.increment_counter(
+            "calls_total",
+            "The calls.",
+            &[("method", "Reg\"ister")],
+        );
+        metrics_registry.observe_latency("latency_seconds", "The latency.", &[], 0.02);
+        metrics_registry.observe_latency("latency_seconds", "The latency.", &[], 20.0);
+        metrics_registry.register_gauge("entities", "The entities.", || 3.0);
+
+        let text = metrics_registry.render();
+
+        assert!(text.contains("# HELP calls_total The calls.\n# TYPE calls_total counter\n"));
+        assert!(text.contains("calls_total{method=\"Find\"} 2\n"));
+        assert!(text.contains("calls_total{method=\"Reg\\\"ister\"} 1\n"));
+        assert!(text.contains("# TYPE latency_seconds histogram\n"));
+        assert!(text.contains("latency_seconds_bucket{le=\"0.01\"} 0\n"));
+        assert!(text.contains("latency_seconds_bucket{le=\"0.025\"} 1\n"));
+        assert!(text.contains("latency_seconds_bucket{le=\"10\"} 1\n"));
+        assert!(text.contains("latency_seconds_bucket{le=\"+Inf\"} 2\n"));
+        assert!(text.contains("latency_seconds_sum 20.02\n"));
+        assert!(text.contains("latency_seconds_count 2\n"));
+        assert!(text.contains("# TYPE entities gauge\nentities 3\n"));
+    }
+
+    #[test]
+    fn create_metrics_response_test() {
+        let metrics_registry = MetricsRegistry::new();
+        metrics_registry.register_gauge("entities", "The entities.", || 1.0);
+
+        let request = Request::get(METRICS_PATH).body(Body::empty()).unwrap();
+        let response = create_metrics_response(&request, &metrics_registry);
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[hyper::header::CONTENT_TYPE], METRICS_CONTENT_TYPE);
+
+        let request = Request::get("/other").body(Body::empty()).unwrap();
+        let response = create_metrics_response(&request, &metrics_registry);
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let request = Request::post(METRICS_PATH).body(Body::empty()).unwrap();
+        let response = create_metrics_response(&request, &metrics_registry);
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+    }
+}
@@ -0,0 +1,174 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+use serde_derive::Deserialize;
+use std::fs;
+use tonic::transport::{
+    Certificate, Channel, ClientTlsConfig, Endpoint, Identity, ServerTlsConfig,
+};
+
+/// TLS settings for a gRPC server and the clients that it uses. All of the files are PEM encoded.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct TlsSettings {
+    /// The certificate that the server presents, and that clients present when mutual TLS is used.
+    pub cert_path: Option<String>,
+    /// The private key for the certificate.
+    pub key_path: Option<String>,
+    /// The CA certificate that peer certificates are verified against.
+    pub ca_cert_path: Option<String>,
+    /// Whether clients must present a certificate that is signed by the CA to connect to the server.
+    pub require_client_certificate: Option<bool>,
+    /// The domain name that server certificates are verified against, when it differs from the
+    /// host in the URI.
+    pub domain_name: Option<String>,
+}
+
+impl TlsSettings {
+    /// Read the certificate and private key into an identity.
+    /// Returns `None` when either path is not set.
+    fn read_identity(&self) -> Result<Option<Identity>, Box<dyn std::error::Error + Send + Sync>> {
+        match (&self.cert_path, &self.key_path) {
+            (Some(cert_path), Some(key_path)) => {
+                let cert = fs::read_to_string(cert_path)
+                    .map_err(|error| format!("Unable to read '{cert_path}': {error}"))?;
+                let key = fs::read_to_string(key_path)
+                    .map_err(|error| format!("Unable to read '{key_path}': {error}"))?;
+                Ok(Some(Identity::from_pem(cert, key)))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Read the CA certificate. Returns `None` when its path is not set.
+    fn read_ca_certificate(
+        &self,
+    ) -> Result<Option<Certificate>, Box<dyn std::error::Error + Send + Sync>> {
+        match &self.ca_cert_path {
+            Some(ca_cert_path) => {
+                let ca_cert = fs::read_to_string(ca_cert_path)
+                    .map_err(|error| format!("Unable to read '{ca_cert_path}': {error}"))?;
+                Ok(Some(Certificate::from_pem(ca_cert)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Create the TLS configuration for a server. When a CA certificate is set, client
+    /// certificates are verified against it, and they are required if `require_client_certificate`
+    /// is set.
+    pub fn create_server_tls_config(
+        &self,
+    ) -> Result<ServerTlsConfig, Box<dyn std::error::Error + Send + Sync>> {
+        let require_client_certificate = self.require_client_certificate.unwrap_or(false);
+        if require_client_certificate && self.ca_cert_path.is_none() {
+            return Err("The ca_cert_path TLS setting is required to verify client certificates")?;
+        }
+
+        let identity = self
+            .read_identity()?
+            .ok_or("The cert_path and key_path TLS settings are required for a server")?;
+
+        let mut server_tls_config = ServerTlsConfig::new().identity(identity);
+
+        if let Some(ca_cert) = self.read_ca_certificate()? {
+            server_tls_config = server_tls_config
+                .client_ca_root(ca_cert)
+                .client_auth_optional(!require_client_certificate);
+        }
+
+        Ok(server_tls_config)
+    }
+
+    /// Create the TLS configuration for a client. The client presents its certificate when one is
+    /// set, so that it can connect to servers that require client certificates.
+    pub fn create_client_tls_config(
+        &self,
+    ) -> Result<ClientTlsConfig, Box<dyn std::error::Error + Send + Sync>> {
+        let mut client_tls_config = ClientTlsConfig::new();
+
+        if let Some(ca_cert) = self.read_ca_certificate()? {
+            client_tls_config = client_tls_config.ca_certificate(ca_cert);
+        }
+
+        if let Some(identity) = self.read_identity()? {
+            client_tls_config = client_tls_config.identity(identity);
+        }
+
+        if let Some(domain_name) = &self.domain_name {
+            client_tls_config = client_tls_config.domain_name(domain_name);
+        }
+
+        Ok(client_tls_config)
+    }
+}
+
+/// Get the URI scheme for a gRPC service: "https" when TLS is used and "http" otherwise.
+///
+/// # Arguments
+/// * `tls_settings` - The TLS settings, if TLS is used.
+pub fn get_uri_scheme(tls_settings: Option<&TlsSettings>) -> &'static str {
+    match tls_settings {
+        Some(_) => "https",
+        None => "http",
+    }
+}
+
+/// Connect to a gRPC service. TLS is used for "https" URIs when TLS settings are provided.
+///
+/// # Arguments
+/// * `uri` - The service's URI.
+/// * `tls_settings` - The TLS settings, if TLS is used.
+pub async fn connect_channel(
+    uri: &str,
+    tls_settings: Option<&TlsSettings>,
+) -> Result<Channel, Box<dyn std::error::Error + Send + Sync>> {
+    let mut endpoint = Endpoint::from_shared(uri.to_string())?;
+
+    if let Some(tls_settings) = tls_settings {
+        endpoint = endpoint.tls_config(tls_settings.create_client_tls_config()?)?;
+    }
+
+    Ok(endpoint.connect().await?)
+}
+
+#[cfg(test)]
+mod tls_settings_tests {
+    use super::*;
+
+    #[test]
+    fn get_uri_scheme_test() {
+        assert_eq!(get_uri_scheme(None), "http");
+        assert_eq!(get_uri_scheme(Some(&TlsSettings::default())), "https");
+    }
+
+    #[test]
+    fn create_server_tls_config_test() {
+        // A server cannot use TLS without a certificate.
+        assert!(TlsSettings::default().create_server_tls_config().is_err());
+
+        let tls_settings = TlsSettings {
+            cert_path: Some(String::from("missing_cert.pem")),
+            key_path: Some(String::from("missing_key.pem")),
+            ..Default::default()
+        };
+        assert!(tls_settings.create_server_tls_config().is_err());
+
+        // Client certificates cannot be verified without a CA certificate.
+        let tls_settings =
+            TlsSettings { require_client_certificate: Some(true), ..Default::default() };
+        let error = tls_settings.create_server_tls_config().unwrap_err();
+        assert!(error.to_string().contains("ca_cert_path"));
+    }
+
+    #[test]
+    fn create_client_tls_config_test() {
+        assert!(TlsSettings::default().create_client_tls_config().is_ok());
+
+        let tls_settings = TlsSettings {
+            ca_cert_path: Some(String::from("missing_ca_cert.pem")),
+            ..Default::default()
+        };
+        assert!(tls_settings.create_client_tls_config().is_err());
+    }
+}
@@ -0,0 +1,208 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+use core::future::Future;
+use futures_core::task::{Context, Poll};
+use opentelemetry::global;
+use opentelemetry::propagation::{Extractor, Injector};
+use std::pin::Pin;
+use tonic::metadata::{MetadataKey, MetadataMap, MetadataValue};
+use tonic::{Code, Status};
+use tower::{Layer, Service};
+use tracing::{info_span, Instrument, Span};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+use crate::grpc_interceptor::retrieve_grpc_names_from_uri;
+
+// This module propagates the W3C trace context (the "traceparent" and "tracestate" headers)
+// through gRPC metadata, so that the spans of a call can be correlated across the services that
+// it crosses. The server side is a tower layer, and the client side injects the current span's
+// context into each outgoing request.
+
+/// Reads the trace context from an http request's headers.
+struct HeaderExtractor<'a>(&'a http::HeaderMap);
+
+impl<'a> Extractor for HeaderExtractor<'a> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|key| key.as_str()).collect()
+    }
+}
+
+/// Writes the trace context to an outgoing request's gRPC metadata.
+struct MetadataInjector<'a>(&'a mut MetadataMap);
+
+impl<'a> Injector for MetadataInjector<'a> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(key), Ok(value)) =
+            (MetadataKey::from_bytes(key.as_bytes()), MetadataValue::try_from(&value))
+        {
+            self.0.insert(key, value);
+        }
+    }
+}
+
+/// Inject the current span's trace context into an outgoing request's gRPC metadata.
+///
+/// # Arguments
+/// * `metadata` - The outgoing request's gRPC metadata.
+pub fn inject_trace_context(metadata: &mut MetadataMap) {
+    let context = Span::current().context();
+    global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&context, &mut MetadataInjector(metadata))
+    });
+}
+
+/// Create an outgoing gRPC request that carries the current span's trace context. Use this in
+/// place of `tonic::Request::new` for the calls that should be correlated with the current call.
+///
+/// # Arguments
+/// * `message` - The request's message.
+pub fn traced_request<T>(message: T) -> tonic::Request<T> {
+    let mut request = tonic::Request::new(message);
+    inject_trace_context(request.metadata_mut());
+    request
+}
+
+/// The tower layer that creates a span for each gRPC call, as a child of the caller's span when
+/// the call carries a trace context.
+#[derive(Clone, Default)]
+pub struct TraceContextLayer;
+
+impl TraceContextLayer {
+    /// Create the tower layer that creates a span for each gRPC call.
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl<S> Layer<S> for TraceContextLayer {
+    type Service = TraceContextService<S>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        TraceContextService { service }
+    }
+}
+
+/// The tower service that creates a span for each gRPC call.
+#[derive(Clone)]
+pub struct TraceContextService<S> {
+    service: S,
+}
+
+impl<S, ReqBody, ResBody> Service<http::request::Request<ReqBody>> for TraceContextService<S>
+where
+    S: Service<http::request::Request<ReqBody>, Response = http::response::Response<ResBody>>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future =
+        Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send + 'static>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: http::request::Request<ReqBody>) -> Self::Future {
+        let (service_name, method_name) = retrieve_grpc_names_from_uri(request.uri());
+
+        let parent_context = global::get_text_map_propagator(|propagator| {
+            propagator.extract(&HeaderExtractor(request.headers()))
+        });
+
+        let span = info_span!(
+            "grpc_request",
+            otel.name = format!("{service_name}/{method_name}"),
+            otel.kind = "server",
+            rpc.system = "grpc",
+            rpc.service = service_name,
+            rpc.method = method_name,
+            rpc.grpc.status_code = tracing::field::Empty,
+            otel.status_code = tracing::field::Empty,
+        );
+        span.set_parent(parent_context);
+
+        // The call is made within the span, so that the interceptors' logs are recorded in it.
+        let future = span.in_scope(|| self.service.call(request));
+
+        let response_span = span.clone();
+        Box::pin(
+            async move {
+                let result = future.await;
+
+                // A call that fails is answered with its status in the headers. Otherwise, the
+                // status is sent in the trailers once the response has been streamed.
+                let code = match &result {
+                    Ok(response) => Status::from_header_map(response.headers())
+                        .map_or(Code::Ok, |status| status.code()),
+                    Err(_) => Code::Unknown,
+                };
+                response_span.record("rpc.grpc.status_code", code as i32);
+                if code != Code::Ok {
+                    response_span.record("otel.status_code", "ERROR");
+                }
+
+                result
+            }
+            .instrument(span),
+        )
+    }
+}
+
+#[cfg(test)]
+mod trace_context_tests {
+    use super::*;
+    use opentelemetry::trace::{TraceContextExt, TracerProvider as _};
+    use opentelemetry_sdk::propagation::TraceContextPropagator;
+    use opentelemetry_sdk::trace::TracerProvider;
+    use std::convert::Infallible;
+    use std::sync::{Arc, Mutex};
+    use tower::ServiceExt;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+    const TRACEPARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    #[tokio::test]
+    async fn call_and_traced_request_test() {
+        global::set_text_map_propagator(TraceContextPropagator::new());
+        let provider = TracerProvider::builder().build();
+        let tracer = provider.tracer("trace_context_tests");
+        let subscriber =
+            tracing_subscriber::registry().with(tracing_opentelemetry::layer().with_tracer(tracer));
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        // The service makes an outgoing call, whose request carries the trace context.
+        let outgoing_metadata = Arc::new(Mutex::new(MetadataMap::new()));
+        let service_metadata = outgoing_metadata.clone();
+        let service = tower::service_fn(move |_request: http::request::Request<()>| {
+            let outgoing_request = traced_request(());
+            *service_metadata.lock().unwrap() = outgoing_request.metadata().clone();
+            async move { Ok::<_, Infallible>(http::response::Response::new(())) }
+        });
+        let service = TraceContextLayer::new().layer(service);
+
+        let request = http::request::Request::builder()
+            .uri("/invehicle_digital_twin.InvehicleDigitalTwin/FindById")
+            .header("traceparent", TRACEPARENT)
+            .body(())
+            .unwrap();
+        service.oneshot(request).await.unwrap();
+
+        // The outgoing call belongs to the caller's trace, with a span of its own.
+        let outgoing_metadata = outgoing_metadata.lock().unwrap();
+        let traceparent = outgoing_metadata.get("traceparent").unwrap().to_str().unwrap();
+        assert!(traceparent.contains(TRACE_ID));
+        assert_ne!(traceparent, TRACEPARENT);
+
+        // Outside of a span, a request carries no trace context.
+        let request = traced_request(());
+        assert!(!Span::current().context().span().span_context().is_valid());
+        assert!(request.metadata().get("traceparent").is_none());
+    }
+}
@@ -0,0 +1,261 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+use futures_util::future::BoxFuture;
+use opentelemetry::trace::{Status as SpanStatus, TraceError, TracerProvider as _};
+use opentelemetry::{global, KeyValue};
+use opentelemetry_otlp::{SpanExporterBuilder, WithExportConfig};
+use opentelemetry_sdk::export::trace::{ExportResult, SpanData, SpanExporter};
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{config, TracerProvider};
+use opentelemetry_sdk::{runtime, Resource};
+use serde_derive::Deserialize;
+use serde_json::{json, Map, Value};
+use std::fs::{File, OpenOptions};
+use std::io::{IsTerminal, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::{Layer, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::Registry;
+
+/// Tracing settings for a service. The spans are exported when either setting is provided, and
+/// the trace context is always propagated, so that the services that a call crosses can be
+/// correlated.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct TracingSettings {
+    /// The endpoint of the OTLP collector that the spans are exported to over gRPC, such as
+    /// "http://0.0.0.0:4317".
+    pub otlp_endpoint: Option<String>,
+    /// The path of the file that the spans are appended to, one JSON object per line.
+    pub json_file_path: Option<String>,
+}
+
+/// Initialize the logging and tracing of a service. The spans and log lines are written to stdout,
+/// and the spans are exported as the tracing settings describe. Log lines from the `log` crate are
+/// recorded as events within the current span.
+///
+/// # Arguments
+/// * `service_name` - The name that the service's spans are exported under.
+/// * `log_level` - The most verbose level that is logged.
+/// * `tracing_settings` - The tracing settings, if tracing is configured.
+/// * `additional_layer` - Another layer to add to the subscriber, such as the tokio console's.
+///   It is not filtered by the log level.
+pub fn init_tracing(
+    service_name: &str,
+    log_level: log::LevelFilter,
+    tracing_settings: Option<&TracingSettings>,
+    additional_layer: Option<Box<dyn Layer<Registry> + Send + Sync>>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    global::set_text_map_propagator(TraceContextPropagator::new());
+
+    let tracing_settings = tracing_settings.cloned().unwrap_or_default();
+
+    let mut provider_builder =
+        TracerProvider::builder().with_config(config().with_resource(Resource::new(vec![
+            KeyValue::new("service.name", service_name.to_string()),
+        ])));
+
+    if let Some(otlp_endpoint) = &tracing_settings.otlp_endpoint {
+        let exporter = SpanExporterBuilder::from(
+            opentelemetry_otlp::new_exporter().tonic().with_endpoint(otlp_endpoint),
+        )
+        .build_span_exporter()?;
+        provider_builder = provider_builder.with_batch_exporter(exporter, runtime::Tokio);
+    }
+
+    if let Some(json_file_path) = &tracing_settings.json_file_path {
+        provider_builder =
+            provider_builder.with_simple_exporter(JsonFileSpanExporter::new(json_file_path)?);
+    }
+
+    let provider = provider_builder.build();
+    let tracer = provider.tracer(service_name.to_string());
+    global::set_tracer_provider(provider);
+
+    let level_filter = to_tracing_level_filter(log_level);
+
+    tracing_subscriber::registry()
+        .with(additional_layer)
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_writer(std::io::stdout)
+                .with_ansi(std::io::stdout().is_terminal())
+                .with_filter(level_filter),
+        )
+        .with(tracing_opentelemetry::layer().with_tracer(tracer).with_filter(level_filter))
+        .try_init()?;
+
+    Ok(())
+}
+
+/// Export the spans that have not been exported yet, before the service stops.
+pub fn shutdown_tracing() {
+    global::shutdown_tracer_provider();
+}
+
+/// Convert a log level filter to the equivalent tracing level filter.
+///
+/// # Arguments
+/// * `log_level` - The log level filter.
+fn to_tracing_level_filter(log_level: log::LevelFilter) -> LevelFilter {
+    match log_level {
+        log::LevelFilter::Off => LevelFilter::OFF,
+        log::LevelFilter::Error => LevelFilter::ERROR,
+        log::LevelFilter::Warn => LevelFilter::WARN,
+        log::LevelFilter::Info => LevelFilter::INFO,
+        log::LevelFilter::Debug => LevelFilter::DEBUG,
+        log::LevelFilter::Trace => LevelFilter::TRACE,
+    }
+}
+
+/// Exports spans to a file, one JSON object per line, for when there is no collector.
+#[derive(Debug)]
+pub struct JsonFileSpanExporter {
+    file: File,
+}
+
+impl JsonFileSpanExporter {
+    /// Create a new JsonFileSpanExporter that appends to a file, which is created if it does not
+    /// exist.
+    ///
+    /// # Arguments
+    /// * `path` - The path of the file.
+    pub fn new(path: &str) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|error| format!("Unable to open '{path}': {error}"))?;
+
+        Ok(JsonFileSpanExporter { file })
+    }
+}
+
+impl SpanExporter for JsonFileSpanExporter {
+    fn export(&mut self, batch: Vec<SpanData>) -> BoxFuture<'static, ExportResult> {
+        let result = batch.iter().try_for_each(|span_data| {
+            writeln!(self.file, "{}", span_to_json(span_data))
+                .map_err(|error| TraceError::from(error.to_string()))
+        });
+
+        Box::pin(async move { result })
+    }
+}
+
+/// Convert a span to the JSON object that it is exported as.
+///
+/// # Arguments
+/// * `span_data` - The span.
+fn span_to_json(span_data: &SpanData) -> Value {
+    let (status, status_description) = match &span_data.status {
+        SpanStatus::Unset => ("Unset", None),
+        SpanStatus::Ok => ("Ok", None),
+        SpanStatus::Error { description } => ("Error", Some(description.to_string())),
+    };
+
+    let events: Vec<Value> = span_data
+        .events
+        .iter()
+        .map(|event| {
+            json!({
+                "name": event.name,
+                "time_unix_nano": to_unix_nanos(event.timestamp),
+                "attributes": attributes_to_json(&event.attributes),
+            })
+        })
+        .collect();
+
+    let parent_span_id = if span_data.parent_span_id == opentelemetry::trace::SpanId::INVALID {
+        None
+    } else {
+        Some(span_data.parent_span_id.to_string())
+    };
+
+    json!({
+        "trace_id": span_data.span_context.trace_id().to_string(),
+        "span_id": span_data.span_context.span_id().to_string(),
+        "parent_span_id": parent_span_id,
+        "name": span_data.name,
+        "kind": format!("{:?}", span_data.span_kind),
+        "start_time_unix_nano": to_unix_nanos(span_data.start_time),
+        "end_time_unix_nano": to_unix_nanos(span_data.end_time),
+        "attributes": attributes_to_json(&span_data.attributes),
+        "events": events,
+        "status": status,
+        "status_description": status_description,
+        "resource": attributes_to_json(
+            &span_data
+                .resource
+                .iter()
+                .map(|(key, value)| KeyValue::new(key.clone(), value.clone()))
+                .collect::<Vec<_>>()
+        ),
+    })
+}
+
+/// Convert attributes to a JSON object of their string values.
+///
+/// # Arguments
+/// * `attributes` - The attributes.
+fn attributes_to_json(attributes: &[KeyValue]) -> Value {
+    let attributes: Map<String, Value> = attributes
+        .iter()
+        .map(|key_value| (key_value.key.to_string(), Value::from(key_value.value.to_string())))
+        .collect();
+
+    Value::Object(attributes)
+}
+
+/// Convert a time to the number of nanoseconds since the Unix epoch.
+///
+/// # Arguments
+/// * `time` - The time.
+fn to_unix_nanos(time: SystemTime) -> u128 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |duration| duration.as_nanos())
+}
+
+#[cfg(test)]
+mod tracing_settings_tests {
+    use super::*;
+    use opentelemetry::trace::{Span, Tracer};
+    use std::fs;
+
+    #[test]
+    fn json_file_span_exporter_test() {
+        let path = std::env::temp_dir()
+            .join(format!("tracing_settings_tests_{}.json", std::process::id()))
+            .to_string_lossy()
+            .to_string();
+        let _ = fs::remove_file(&path);
+
+        let provider = TracerProvider::builder()
+            .with_simple_exporter(JsonFileSpanExporter::new(&path).unwrap())
+            .build();
+        let tracer = provider.tracer("tracing_settings_tests");
+
+        let mut span = tracer.start("grpc_request");
+        span.set_attribute(KeyValue::new("rpc.method", "FindById"));
+        span.add_event("Found the entity", vec![KeyValue::new("id", "dtmi:sdv:hvac;1")]);
+        let trace_id = span.span_context().trace_id().to_string();
+        span.end();
+
+        // The spans are exported on a background thread.
+        provider.force_flush();
+
+        let contents = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines.len(), 1);
+
+        let span_json: Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(span_json["trace_id"], trace_id);
+        assert_eq!(span_json["name"], "grpc_request");
+        assert_eq!(span_json["parent_span_id"], Value::Null);
+        assert_eq!(span_json["attributes"]["rpc.method"], "FindById");
+        assert_eq!(span_json["events"][0]["name"], "Found the entity");
+        assert_eq!(span_json["events"][0]["attributes"]["id"], "dtmi:sdv:hvac;1");
+    }
+}
@@ -5,16 +5,16 @@
 #![allow(unused_imports)]
 
 use config::{Config, ConfigError, File, FileFormat};
-use core_protobuf_data_access::chariott::service_discovery::core::v1::{
-    service_registry_client::ServiceRegistryClient, DiscoverRequest,
-};
 use log::{debug, info};
 use serde_derive::Deserialize;
 use std::env;
 use std::future::Future;
 use strum_macros::Display;
 use tokio::time::{sleep, Duration};
-use tonic::{Request, Status};
+use tonic::Status;
+
+use crate::chariott_client::{ChariottClient, DiscoveryPolicy, ServiceQuery};
+use crate::tls_settings::TlsSettings;
 
 const IBEJI_HOME_VAR_NAME: &str = "IBEJI_HOME";
 
@@ -35,7 +35,13 @@ pub enum ServiceUriSource {
     /// Use the local configuration settings to find the service's URI.
     Local { service_uri: String },
     /// Use Chariott to discover the service's URI.
-    Chariott { chariott_uri: String, service_identifier: ServiceIdentifier },
+    Chariott {
+        chariott_uri: String,
+        service_identifier: ServiceIdentifier,
+        /// How the services in Chariott are matched against the service identifier.
+        #[serde(default)]
+        discovery_policy: DiscoveryPolicy,
+    },
 }
 
 /// Load the settings.
@@ -99,45 +105,17 @@ where
     last_error
 }
 
-/// Use Chariott to discover a service.
+/// Get the key that identifies an entity in the registry, which is its instance id, or its id
+/// when it does not have an instance id.
 ///
 /// # Arguments
-/// * `chariott_uri` - Chariott's URI.
-/// * `namespace` - The service's namespace.
-/// * `name` - The service's name.
-/// * `version` - The service's version.
-/// # `expected_communication_kind` - The service's expected communication kind.
-/// # `expected_communication_reference` - The service's expected communication reference.
-pub async fn discover_service_using_chariott(
-    chariott_uri: &str,
-    namespace: &str,
-    name: &str,
-    version: &str,
-    expected_communication_kind: &str,
-    expected_communication_reference: &str,
-) -> Result<String, Status> {
-    let mut client = ServiceRegistryClient::connect(chariott_uri.to_owned())
-        .await
-        .map_err(|e| Status::internal(e.to_string()))?;
-
-    let request = Request::new(DiscoverRequest {
-        namespace: namespace.to_string(),
-        name: name.to_string(),
-        version: version.to_string(),
-    });
-
-    let response = client.discover(request).await?;
-
-    let service = response.into_inner().service.ok_or_else(|| Status::not_found("Did not find a service in Chariott with namespace '{namespace}', name '{name}' and version {version}"))?;
-
-    if service.communication_kind != expected_communication_kind
-        && service.communication_reference != expected_communication_reference
-    {
-        Err(Status::not_found(
-            "Did not find a service in Chariott with namespace '{namespace}', name '{name}' and version {version} that has communication kind '{communication_kind} and communication_reference '{communication_reference}''",
-        ))
+/// * `entity_id` - The id of the entity's model.
+/// * `instance_id` - The entity's instance id, which is empty when it does not have one.
+pub fn get_entity_key<'a>(entity_id: &'a str, instance_id: &'a str) -> &'a str {
+    if instance_id.is_empty() {
+        entity_id
     } else {
-        Ok(service.uri)
+        instance_id
     }
 }
 
@@ -147,36 +125,36 @@ pub async fn discover_service_using_chariott(
 /// * `service_uri_source` - Enum providing information on how to get the service URI.
 /// # `expected_communication_kind` - The service's expected communication kind.
 /// # `expected_communication_reference` - The service's expected communication reference.
+/// * `tls_settings` - The TLS settings used to connect to Chariott, if TLS is used.
 pub async fn get_service_uri(
     service_uri_source: ServiceUriSource,
     expected_communication_kind: &str,
     expected_communication_reference: &str,
+    tls_settings: Option<&TlsSettings>,
 ) -> Result<String, Status> {
     let result = match service_uri_source {
         ServiceUriSource::Local { service_uri } => {
             info!("URI set in settings.");
             service_uri
         }
-        ServiceUriSource::Chariott { chariott_uri, service_identifier } => {
+        ServiceUriSource::Chariott { chariott_uri, service_identifier, discovery_policy } => {
             info!("Retrieving URI from Chariott.");
 
+            let chariott_client = ChariottClient::new(&chariott_uri, tls_settings.cloned());
+            let query = ServiceQuery {
+                namespace: service_identifier.namespace,
+                name: service_identifier.name,
+                version: service_identifier.version,
+                communication_kind: expected_communication_kind.to_string(),
+                communication_reference: expected_communication_reference.to_string(),
+                policy: discovery_policy,
+            };
+
             execute_with_retry(
                 30,
                 Duration::from_secs(1),
-                || {
-                    discover_service_using_chariott(
-                        &chariott_uri,
-                        &service_identifier.namespace,
-                        &service_identifier.name,
-                        &service_identifier.version,
-                        expected_communication_kind,
-                        expected_communication_reference,
-                    )
-                },
-                Some(format!(
-                    "Attempting to discover service '{}' with chariott.",
-                    service_identifier.name
-                )),
+                || chariott_client.discover(&query),
+                Some(format!("Attempting to discover service '{}' with chariott.", query.name)),
             )
             .await?
         }
@@ -228,4 +206,13 @@ mod tests {
         .await;
         assert!(result.is_err());
     }
+
+    #[test]
+    fn get_entity_key_test() {
+        assert_eq!(get_entity_key("dtmi:sdv:seat_massager;1", ""), "dtmi:sdv:seat_massager;1");
+        assert_eq!(
+            get_entity_key("dtmi:sdv:seat_massager;1", "front_left_seat"),
+            "front_left_seat"
+        );
+    }
 }
@@ -0,0 +1,329 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+use core_protobuf_data_access::invehicle_digital_twin::v1::EntityAccessInfo;
+use log::{debug, info};
+use serde_derive::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// The operations that can be performed on an Interface, which are those of all of its contents.
+const INTERFACE_OPERATIONS: &[&str] =
+    &["Get", "Set", "Subscribe", "Unsubscribe", "ManagedSubscribe", "Stream", "Invoke"];
+/// The operations that can be performed on a Property.
+const PROPERTY_OPERATIONS: &[&str] =
+    &["Get", "Set", "Subscribe", "Unsubscribe", "ManagedSubscribe", "Stream"];
+/// The operations that can be performed on a Command.
+const COMMAND_OPERATIONS: &[&str] = &["Invoke"];
+/// The operations that can be performed on Telemetry.
+const TELEMETRY_OPERATIONS: &[&str] = &["Subscribe", "Unsubscribe", "ManagedSubscribe", "Stream"];
+
+/// How registrations are validated against the DTDL models.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DtdlValidationMode {
+    /// Registrations are not validated.
+    #[default]
+    Off,
+    /// Invalid registrations are accepted, but a warning is logged.
+    Warn,
+    /// Invalid registrations are rejected.
+    Enforce,
+}
+
+/// The kinds of DTDL element that an entity can be.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ContentType {
+    /// An entity that implements a whole interface, such as one instance of a seat massager.
+    Interface,
+    /// A property of an interface, which can be read, written and subscribed to.
+    Property,
+    /// A command of an interface, which can be invoked.
+    Command,
+    /// Telemetry of an interface, which can be subscribed to or streamed.
+    Telemetry,
+}
+
+impl ContentType {
+    /// The operations that can be performed on this kind of content.
+    fn operations(&self) -> &'static [&'static str] {
+        match self {
+            ContentType::Interface => INTERFACE_OPERATIONS,
+            ContentType::Property => PROPERTY_OPERATIONS,
+            ContentType::Command => COMMAND_OPERATIONS,
+            ContentType::Telemetry => TELEMETRY_OPERATIONS,
+        }
+    }
+}
+
+/// Validates registered entities against the Interfaces, and their Properties, Commands and
+/// Telemetry, that are declared in a set of DTDL files.
+#[derive(Clone, Debug, Default)]
+pub struct DtdlValidator {
+    pub mode: DtdlValidationMode,
+    content_type_map: HashMap<String, ContentType>,
+    /// The id of the interface that declares each relationship, keyed by the relationship's id.
+    relationship_source_map: HashMap<String, String>,
+}
+
+impl DtdlValidator {
+    /// Create a new DtdlValidator without any DTDL interfaces.
+    ///
+    /// # Arguments
+    /// * `mode` - How registrations are validated.
+    pub fn new(mode: DtdlValidationMode) -> Self {
+        DtdlValidator {
+            mode,
+            content_type_map: HashMap::new(),
+            relationship_source_map: HashMap::new(),
+        }
+    }
+
+    /// Load every DTDL interface in a directory tree.
+    ///
+    /// # Arguments
+    /// * `dtdl_path` - The directory that contains the DTDL files.
+    pub fn load_directory(
+        &mut self,
+        dtdl_path: &Path,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        for dir_entry in fs::read_dir(dtdl_path)? {
+            let path = dir_entry?.path();
+            if path.is_dir() {
+                self.load_directory(&path)?;
+            } else if path.extension().is_some_and(|extension| extension == "json") {
+                debug!("Loading the DTDL file '{}'.", path.display());
+                let contents = fs::read_to_string(&path)?;
+                let document: Value = serde_json::from_str(&contents)
+                    .map_err(|error| format!("Unable to parse '{}': {error}", path.display()))?;
+                self.add_document(&document);
+            }
+        }
+
+        info!(
+            "Loaded {} DTDL contents from '{}'.",
+            self.content_type_map.len(),
+            dtdl_path.display()
+        );
+
+        Ok(())
+    }
+
+    /// Add the interfaces in a DTDL document. A document holds either a single interface or an
+    /// array of interfaces.
+    ///
+    /// # Arguments
+    /// * `document` - The DTDL document.
+    pub fn add_document(&mut self, document: &Value) {
+        let interfaces = match document {
+            Value::Array(interfaces) => interfaces.iter().collect(),
+            interface => vec![interface],
+        };
+
+        for interface in interfaces {
+            let interface_id = interface.get("@id").and_then(Value::as_str).unwrap_or_default();
+            if !interface_id.is_empty() {
+                self.content_type_map.insert(interface_id.to_string(), ContentType::Interface);
+            }
+
+            let Some(contents) = interface.get("contents").and_then(Value::as_array) else {
+                continue;
+            };
+
+            for content in contents {
+                let Some(id) = content.get("@id").and_then(Value::as_str) else {
+                    continue;
+                };
+
+                if content.get("@type").and_then(Value::as_str) == Some("Relationship") {
+                    self.relationship_source_map.insert(id.to_string(), interface_id.to_string());
+                } else if let Some(content_type) = Self::get_content_type(content) {
+                    self.content_type_map.insert(id.to_string(), content_type);
+                }
+            }
+        }
+    }
+
+    /// Get the content type from a content's `@type`, which is either a string or an array that
+    /// also holds semantic types.
+    ///
+    /// # Arguments
+    /// * `content` - The DTDL content.
+    fn get_content_type(content: &Value) -> Option<ContentType> {
+        let types: Vec<&str> = match content.get("@type")? {
+            Value::String(content_type) => vec![content_type.as_str()],
+            Value::Array(content_types) => content_types.iter().filter_map(Value::as_str).collect(),
+            _ => return None,
+        };
+
+        types.into_iter().find_map(|content_type| match content_type {
+            "Property" => Some(ContentType::Property),
+            "Command" => Some(ContentType::Command),
+            "Telemetry" => Some(ContentType::Telemetry),
+            _ => None,
+        })
+    }
+
+    /// Validate an entity, including that its relationships are declared by its model.
+    /// Operations that are not known to this validator are not checked.
+    ///
+    /// # Arguments
+    /// * `entity_access_info` - The entity to validate.
+    pub fn validate(&self, entity_access_info: &EntityAccessInfo) -> Result<(), String> {
+        let content_type = self.content_type_map.get(&entity_access_info.id).ok_or_else(|| {
+            format!(
+                "The entity id '{}' is not an Interface, Property, Command or Telemetry in the DTDL models",
+                entity_access_info.id
+            )
+        })?;
+
+        let all_operations: Vec<&str> = PROPERTY_OPERATIONS
+            .iter()
+            .chain(COMMAND_OPERATIONS)
+            .chain(TELEMETRY_OPERATIONS)
+            .copied()
+            .collect();
+
+        for endpoint_info in &entity_access_info.endpoint_info_list {
+            for operation in &endpoint_info.operations {
+                if all_operations.contains(&operation.as_str())
+                    && !content_type.operations().contains(&operation.as_str())
+                {
+                    return Err(format!(
+                        "The operation '{operation}' is not supported by the {content_type:?} '{}'",
+                        entity_access_info.id
+                    ));
+                }
+            }
+        }
+
+        for relationship_info in &entity_access_info.relationship_list {
+            match self.relationship_source_map.get(&relationship_info.relationship_id) {
+                Some(source_id) if *source_id == entity_access_info.id => {}
+                Some(source_id) => {
+                    return Err(format!(
+                        "The relationship '{}' is declared by '{source_id}', not by '{}'",
+                        relationship_info.relationship_id, entity_access_info.id
+                    ));
+                }
+                None => {
+                    return Err(format!(
+                        "The relationship '{}' is not a Relationship in the DTDL models",
+                        relationship_info.relationship_id
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod dtdl_validator_tests {
+    use super::*;
+    use core_protobuf_data_access::invehicle_digital_twin::v1::{EndpointInfo, RelationshipInfo};
+    use std::path::PathBuf;
+
+    fn create_entity(id: &str, operations: &[&str]) -> EntityAccessInfo {
+        let endpoint_info = EndpointInfo {
+            protocol: String::from("grpc"),
+            uri: String::from("http://[::1]:40010"), // Devskim: ignore DS137138
+            context: id.to_string(),
+            operations: operations.iter().map(|operation| operation.to_string()).collect(),
+            provider_id: String::from("test_provider"),
+        };
+
+        EntityAccessInfo {
+            name: String::new(),
+            id: id.to_string(),
+            description: String::new(),
+            endpoint_info_list: vec![endpoint_info],
+            instance_id: String::new(),
+            relationship_list: vec![],
+        }
+    }
+
+    #[test]
+    fn validate_test() {
+        let document: Value = serde_json::from_str(
+            r#"{
+                "@context": ["dtmi:dtdl:context;3"],
+                "@type": "Interface",
+                "@id": "dtmi:test:hvac;1",
+                "contents": [
+                    {
+                        "@type": ["Property", "Temperature"],
+                        "@id": "dtmi:test:hvac:ambient_air_temperature;1",
+                        "name": "ambient_air_temperature",
+                        "schema": "integer"
+                    },
+                    {
+                        "@type": "Command",
+                        "@id": "dtmi:test:hvac:reset;1",
+                        "name": "reset"
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let mut dtdl_validator = DtdlValidator::new(DtdlValidationMode::Enforce);
+        dtdl_validator.add_document(&document);
+
+        let property_id = "dtmi:test:hvac:ambient_air_temperature;1";
+        let command_id = "dtmi:test:hvac:reset;1";
+
+        assert!(dtdl_validator
+            .validate(&create_entity(property_id, &["Get", "Subscribe"]))
+            .is_ok());
+        assert!(dtdl_validator.validate(&create_entity(property_id, &["Invoke"])).is_err());
+        assert!(dtdl_validator.validate(&create_entity(command_id, &["Invoke"])).is_ok());
+        assert!(dtdl_validator.validate(&create_entity(command_id, &["Set"])).is_err());
+        assert!(dtdl_validator.validate(&create_entity(command_id, &["Custom"])).is_ok());
+        assert!(dtdl_validator.validate(&create_entity("dtmi:test:hvac;1", &["Get"])).is_ok());
+        assert!(dtdl_validator.validate(&create_entity("dtmi:test:cabin;1", &["Get"])).is_err());
+        assert!(dtdl_validator
+            .validate(&create_entity("dtmi:test:hvac:ambient_air_temperature;2", &["Get"]))
+            .is_err());
+    }
+
+    #[test]
+    fn load_directory_test() {
+        let dtdl_path =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../digital-twin-model/dtdl");
+
+        let mut dtdl_validator = DtdlValidator::new(DtdlValidationMode::Enforce);
+        assert!(dtdl_validator.load_directory(&dtdl_path).is_ok());
+
+        assert!(dtdl_validator
+            .validate(&create_entity("dtmi:sdv:hvac:ambient_air_temperature;1", &["Get"]))
+            .is_ok());
+        assert!(dtdl_validator
+            .validate(&create_entity("dtmi:sdv:seat_massager:load_sequence;1", &["Invoke"]))
+            .is_ok());
+        assert!(dtdl_validator
+            .validate(&create_entity("dtmi:sdv:camera:feed;1", &["Subscribe", "Stream"]))
+            .is_ok());
+
+        let create_relationship = |relationship_id: &str| RelationshipInfo {
+            name: String::new(),
+            relationship_id: relationship_id.to_string(),
+            target_id: String::from("dtmi:sdv:cabin;1"),
+            provider_id: String::new(),
+        };
+
+        let mut vehicle = create_entity("dtmi:sdv:vehicle;1", &[]);
+        vehicle.relationship_list = vec![create_relationship("dtmi:sdv:vehicle:has_cabin;1")];
+        assert!(dtdl_validator.validate(&vehicle).is_ok());
+
+        vehicle.relationship_list = vec![create_relationship("dtmi:sdv:cabin:has_seat;1")];
+        assert!(dtdl_validator.validate(&vehicle).is_err());
+
+        vehicle.relationship_list = vec![create_relationship("dtmi:sdv:vehicle:has_trailer;1")];
+        assert!(dtdl_validator.validate(&vehicle).is_err());
+    }
+}
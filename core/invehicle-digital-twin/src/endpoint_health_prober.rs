@@ -0,0 +1,154 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+use core_protobuf_data_access::invehicle_digital_twin::v1::EntityAccessInfo;
+use futures::future::join_all;
+use log::{debug, info, warn};
+use parking_lot::{RwLockReadGuard, RwLockWriteGuard};
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use url::{Host, Url};
+
+use crate::invehicle_digital_twin_impl::{EndpointHealth, InvehicleDigitalTwinImpl};
+
+/// The default port for an MQTT broker.
+const DEFAULT_MQTT_PORT: u16 = 1883;
+/// The default port for an MQTT broker that uses TLS.
+const DEFAULT_MQTTS_PORT: u16 = 8883;
+
+/// Get the address to connect to for an endpoint's URI.
+/// Returns `None` when the URI does not identify a host and port.
+///
+/// # Arguments
+/// * `uri` - The endpoint's URI.
+fn get_probe_address(uri: &str) -> Option<String> {
+    let url = Url::parse(uri).ok()?;
+
+    let port = url.port_or_known_default().or(match url.scheme() {
+        "mqtt" | "tcp" => Some(DEFAULT_MQTT_PORT),
+        "mqtts" | "ssl" => Some(DEFAULT_MQTTS_PORT),
+        _ => None,
+    })?;
+
+    match url.host()? {
+        Host::Domain(domain) => Some(format!("{domain}:{port}")),
+        Host::Ipv4(address) => Some(SocketAddr::new(address.into(), port).to_string()),
+        Host::Ipv6(address) => Some(SocketAddr::new(address.into(), port).to_string()),
+    }
+}
+
+/// Probe an endpoint by connecting to it. This works for both gRPC endpoints and MQTT brokers.
+///
+/// # Arguments
+/// * `uri` - The endpoint's URI.
+/// * `timeout` - How long to wait for the connection to be established.
+pub async fn probe_endpoint(uri: &str, timeout: Duration) -> EndpointHealth {
+    let Some(address) = get_probe_address(uri) else {
+        debug!("Unable to probe the endpoint '{uri}', as its URI does not have a host and port.");
+        return EndpointHealth::Unknown;
+    };
+
+    match tokio::time::timeout(timeout, TcpStream::connect(&address)).await {
+        Ok(Ok(_)) => EndpointHealth::Healthy,
+        Ok(Err(error)) => {
+            debug!("Unable to connect to the endpoint '{uri}': '{error}'");
+            EndpointHealth::Unhealthy
+        }
+        Err(_) => {
+            debug!("Timed out connecting to the endpoint '{uri}'.");
+            EndpointHealth::Unhealthy
+        }
+    }
+}
+
+/// Probe every registered endpoint and record its health.
+///
+/// # Arguments
+/// * `invehicle_digital_twin_impl` - The in-vehicle digital twin service implementation.
+/// * `timeout` - How long to wait for each endpoint to respond.
+pub async fn probe_registered_endpoints(
+    invehicle_digital_twin_impl: &InvehicleDigitalTwinImpl,
+    timeout: Duration,
+) {
+    let uris: HashSet<String>;
+
+    // This block controls the lifetime of the lock.
+    {
+        let lock: RwLockReadGuard<HashMap<String, EntityAccessInfo>> =
+            invehicle_digital_twin_impl.entity_access_info_map.read();
+        uris = lock
+            .values()
+            .flat_map(|entity_access_info| {
+                entity_access_info
+                    .endpoint_info_list
+                    .iter()
+                    .map(|endpoint_info| endpoint_info.uri.clone())
+            })
+            .collect();
+    }
+
+    let probes = uris.into_iter().map(|uri| async move {
+        let health = probe_endpoint(&uri, timeout).await;
+        (uri, health)
+    });
+    let endpoint_health_map: HashMap<String, EndpointHealth> =
+        join_all(probes).await.into_iter().collect();
+
+    // This block controls the lifetime of the lock.
+    {
+        let mut lock: RwLockWriteGuard<HashMap<String, EndpointHealth>> =
+            invehicle_digital_twin_impl.endpoint_health_map.write();
+
+        for (uri, health) in &endpoint_health_map {
+            let previous_health = lock.get(uri).copied().unwrap_or(EndpointHealth::Unknown);
+            if *health == EndpointHealth::Unhealthy && previous_health != EndpointHealth::Unhealthy
+            {
+                warn!("The endpoint '{uri}' has become unhealthy.");
+            } else if *health == EndpointHealth::Healthy
+                && previous_health != EndpointHealth::Healthy
+            {
+                info!("The endpoint '{uri}' is healthy.");
+            }
+        }
+
+        // Replacing the map also drops the health of endpoints that are no longer registered.
+        *lock = endpoint_health_map;
+    }
+}
+
+#[cfg(test)]
+mod endpoint_health_prober_tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn get_probe_address_test() {
+        assert_eq!(get_probe_address("http://[::1]:40010"), Some(String::from("[::1]:40010"))); // Devskim: ignore DS137138
+        assert_eq!(
+            get_probe_address("http://0.0.0.0"), // Devskim: ignore DS137138
+            Some(String::from("0.0.0.0:80"))
+        );
+        assert_eq!(get_probe_address("tcp://localhost"), Some(String::from("localhost:1883")));
+        assert_eq!(get_probe_address("not a uri"), None);
+    }
+
+    #[tokio::test]
+    async fn probe_endpoint_test() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let uri = format!("http://{}", listener.local_addr().unwrap()); // Devskim: ignore DS137138
+
+        assert_eq!(probe_endpoint(&uri, Duration::from_secs(1)).await, EndpointHealth::Healthy);
+
+        drop(listener);
+
+        assert_eq!(probe_endpoint(&uri, Duration::from_secs(1)).await, EndpointHealth::Unhealthy);
+
+        assert_eq!(
+            probe_endpoint("unknown:endpoint", Duration::from_secs(1)).await,
+            EndpointHealth::Unknown
+        );
+    }
+}
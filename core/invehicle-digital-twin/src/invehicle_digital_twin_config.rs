@@ -2,15 +2,33 @@
 // Licensed under the MIT license.
 // SPDX-License-Identifier: MIT
 
+use common::tls_settings::TlsSettings;
+use common::tracing_settings::TracingSettings;
 use common::utils;
 use serde_derive::Deserialize;
 
+use crate::dtdl_validator::DtdlValidationMode;
+
 const CONFIG_FILENAME: &str = "invehicle_digital_twin_settings";
 
 #[derive(Debug, Deserialize)]
 pub struct Settings {
     pub invehicle_digital_twin_authority: String,
     pub chariott_uri: Option<String>,
+    pub chariott_reassert_interval_secs: Option<u64>,
+    pub lease_eviction_interval_secs: Option<u64>,
+    pub registry_snapshot_path: Option<String>,
+    pub registry_snapshot_interval_secs: Option<u64>,
+    pub endpoint_health_probe_interval_secs: Option<u64>,
+    pub endpoint_health_probe_timeout_ms: Option<u64>,
+    pub filter_unhealthy_endpoints: Option<bool>,
+    pub dtdl_validation_mode: Option<DtdlValidationMode>,
+    pub dtdl_path: Option<String>,
+    pub tls: Option<TlsSettings>,
+    pub modules: Option<Vec<String>>,
+    pub shutdown_timeout_secs: Option<u64>,
+    pub metrics_authority: Option<String>,
+    pub tracing: Option<TracingSettings>,
 }
 
 /// Load the settings.
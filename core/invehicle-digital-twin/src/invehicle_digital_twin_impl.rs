@@ -4,23 +4,98 @@
 
 extern crate iref;
 
+use common::metrics::MetricsRegistry;
+use common::utils::get_entity_key;
 use core_protobuf_data_access::invehicle_digital_twin::v1::invehicle_digital_twin_server::InvehicleDigitalTwin;
 use core_protobuf_data_access::invehicle_digital_twin::v1::{
-    EntityAccessInfo, FindByIdRequest, FindByIdResponse, RegisterRequest, RegisterResponse,
+    EndpointInfo, EntityAccessInfo, FindByIdRequest, FindByIdResponse, FindRequest, FindResponse,
+    GetChildrenRequest, GetChildrenResponse, GetParentRequest, GetParentResponse, RegisterRequest,
+    RegisterResponse, RelationshipInfo, RenewLeaseRequest, RenewLeaseResponse, UnregisterRequest,
+    UnregisterResponse, WatchRequest, WatchResponse,
 };
-use log::{debug, info};
+use log::{debug, info, warn};
 use parking_lot::{RwLock, RwLockReadGuard, RwLockWriteGuard};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::pin::Pin;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::{Stream, StreamExt};
 use tonic::{Request, Response, Status};
 
-#[derive(Debug, Default)]
+use crate::dtdl_validator::{DtdlValidationMode, DtdlValidator};
+use crate::registry_store::RegistrySnapshot;
+
+type ChangeType = core_protobuf_data_access::invehicle_digital_twin::v1::watch_response::ChangeType;
+
+/// The number of entities returned by a find request that does not specify a page size.
+const DEFAULT_FIND_PAGE_SIZE: usize = 100;
+/// The maximum number of entities returned by a find request.
+const MAX_FIND_PAGE_SIZE: usize = 1000;
+/// The number of entity changes that are buffered for each watcher.
+const ENTITY_CHANGE_CHANNEL_CAPACITY: usize = 100;
+
+/// A provider's lease on the endpoints that it has registered.
+#[derive(Clone, Debug)]
+pub struct ProviderLease {
+    /// The duration of the lease, in seconds.
+    pub duration_seconds: u32,
+    /// When the lease expires, unless it is renewed.
+    pub expiry: Instant,
+}
+
+impl ProviderLease {
+    /// Creates a new lease that expires after the given duration.
+    ///
+    /// # Arguments
+    /// * `duration_seconds` - The duration of the lease, in seconds.
+    pub fn new(duration_seconds: u32) -> Self {
+        ProviderLease {
+            duration_seconds,
+            expiry: Instant::now() + Duration::from_secs(duration_seconds.into()),
+        }
+    }
+}
+
+/// The health of an endpoint, as determined by the endpoint health prober.
+/// The variants are ordered from most to least preferred.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum EndpointHealth {
+    /// The endpoint responded to the last probe.
+    Healthy,
+    /// The endpoint has not been probed, or it cannot be probed.
+    Unknown,
+    /// The endpoint did not respond to the last probe.
+    Unhealthy,
+}
+
+#[derive(Clone, Debug)]
 pub struct InvehicleDigitalTwinImpl {
     pub entity_access_info_map: Arc<RwLock<HashMap<String, EntityAccessInfo>>>,
+    pub provider_lease_map: Arc<RwLock<HashMap<String, ProviderLease>>>,
+    pub entity_change_sender: broadcast::Sender<WatchResponse>,
+    /// The health of each probed endpoint, keyed by the endpoint's uri.
+    pub endpoint_health_map: Arc<RwLock<HashMap<String, EndpointHealth>>>,
+    /// When set, unhealthy endpoints are left out of find results.
+    pub filter_unhealthy_endpoints: bool,
+    /// Validates the registered entities against the DTDL models.
+    pub dtdl_validator: Arc<DtdlValidator>,
+}
+
+impl Default for InvehicleDigitalTwinImpl {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[tonic::async_trait]
 impl InvehicleDigitalTwin for InvehicleDigitalTwinImpl {
+    // Note: The name "WatchStream" is generated by gRPC by concatenating the rpc method name with
+    //       "Stream".
+    type WatchStream = Pin<Box<dyn Stream<Item = Result<WatchResponse, Status>> + Send>>;
+
     /// Find-by-id implementation.
     ///
     /// # Arguments
@@ -29,32 +104,130 @@ impl InvehicleDigitalTwin for InvehicleDigitalTwinImpl {
         &self,
         request: Request<FindByIdRequest>,
     ) -> Result<Response<FindByIdResponse>, Status> {
-        let entity_id = request.into_inner().id;
+        let request_inner = request.into_inner();
+        let entity_id = request_inner.id;
+        let instance_id = request_inner.instance_id;
 
-        info!("Received a find_by_id request for entity id {entity_id}");
+        info!("Received a find_by_id request for entity id {entity_id} and instance id '{instance_id}'");
 
-        let entity_access_info;
+        let mut entity_access_info_list: Vec<EntityAccessInfo>;
 
         // This block controls the lifetime of the lock.
         {
             let lock: RwLockReadGuard<HashMap<String, EntityAccessInfo>> =
                 self.entity_access_info_map.read();
-            entity_access_info = lock.get(&entity_id).cloned();
+            if instance_id.is_empty() {
+                // The id identifies either a model, which matches every instance that implements
+                // it, or a single instance.
+                entity_access_info_list = lock
+                    .values()
+                    .filter(|entity_access_info| {
+                        entity_access_info.id == entity_id
+                            || entity_access_info.instance_id == entity_id
+                    })
+                    .cloned()
+                    .collect();
+            } else {
+                entity_access_info_list = lock
+                    .get(&instance_id)
+                    .filter(|entity_access_info| {
+                        entity_id.is_empty() || entity_access_info.id == entity_id
+                    })
+                    .cloned()
+                    .into_iter()
+                    .collect();
+            }
         }
 
-        info!("{entity_access_info:?}");
+        // Order the entities by their key, which puts an entity without an instance id first.
+        entity_access_info_list.sort_by(|a, b| {
+            (!a.instance_id.is_empty(), Self::get_entity_key(a))
+                .cmp(&(!b.instance_id.is_empty(), Self::get_entity_key(b)))
+        });
+
+        let entity_access_info_list: Vec<EntityAccessInfo> = entity_access_info_list
+            .into_iter()
+            .filter_map(|entity_access_info| self.apply_endpoint_health(entity_access_info))
+            .collect();
+
+        info!("{entity_access_info_list:?}");
 
-        if entity_access_info.is_none() {
-            return Err(Status::not_found("Unable to find the entity with id {entity_id}"));
+        if entity_access_info_list.is_empty() {
+            return Err(Status::not_found(format!(
+                "Unable to find the entity with id {entity_id} and instance id '{instance_id}'"
+            )));
         }
 
-        let response = FindByIdResponse { entity_access_info };
+        let response = FindByIdResponse {
+            entity_access_info: entity_access_info_list.first().cloned(),
+            entity_access_info_list,
+        };
 
         debug!("Responded to the find_by_id request.");
 
         Ok(Response::new(response))
     }
 
+    /// Find implementation.
+    ///
+    /// # Arguments
+    /// * `request` - Find request.
+    async fn find(&self, request: Request<FindRequest>) -> Result<Response<FindResponse>, Status> {
+        let request_inner = request.into_inner();
+
+        info!("Received a find request: {request_inner:?}");
+
+        let page_size = match request_inner.page_size as usize {
+            0 => DEFAULT_FIND_PAGE_SIZE,
+            page_size => page_size.min(MAX_FIND_PAGE_SIZE),
+        };
+
+        let mut entity_access_info_list: Vec<EntityAccessInfo>;
+
+        // This block controls the lifetime of the lock.
+        {
+            let lock: RwLockReadGuard<HashMap<String, EntityAccessInfo>> =
+                self.entity_access_info_map.read();
+            entity_access_info_list = lock
+                .values()
+                .filter(|entity_access_info| {
+                    request_inner.page_token.is_empty()
+                        || Self::get_entity_key(entity_access_info)
+                            > request_inner.page_token.as_str()
+                })
+                .filter_map(|entity_access_info| {
+                    Self::filter_entity(entity_access_info, &request_inner)
+                })
+                .collect();
+        }
+
+        entity_access_info_list = entity_access_info_list
+            .into_iter()
+            .filter_map(|entity_access_info| self.apply_endpoint_health(entity_access_info))
+            .collect();
+
+        entity_access_info_list
+            .sort_by(|a, b| Self::get_entity_key(a).cmp(Self::get_entity_key(b)));
+
+        let next_page_token = if entity_access_info_list.len() > page_size {
+            entity_access_info_list.truncate(page_size);
+            entity_access_info_list
+                .last()
+                .map(|entity_access_info| Self::get_entity_key(entity_access_info).to_string())
+        } else {
+            None
+        };
+
+        let response = FindResponse {
+            entity_access_info_list,
+            next_page_token: next_page_token.unwrap_or_default(),
+        };
+
+        debug!("Responded to the find request.");
+
+        Ok(Response::new(response))
+    }
+
     /// Register implementation.
     ///
     /// # Arguments
@@ -65,10 +238,30 @@ impl InvehicleDigitalTwin for InvehicleDigitalTwinImpl {
     ) -> Result<Response<RegisterResponse>, Status> {
         let request_inner = request.into_inner();
 
-        for entity_access_info in &request_inner.entity_access_info_list {
-            info!("Received a register request for the the entity:\n{}", entity_access_info.id);
+        // This block controls the lifetime of the locks. They are held from the validation through
+        // to the registration, so that either all of the entities are registered or none are.
+        {
+            let mut lease_lock: RwLockWriteGuard<HashMap<String, ProviderLease>> =
+                self.provider_lease_map.write();
+            let mut entity_lock: RwLockWriteGuard<HashMap<String, EntityAccessInfo>> =
+                self.entity_access_info_map.write();
+
+            // All of the entities are validated before any of them are registered.
+            self.validate_entities(&entity_lock, &request_inner.entity_access_info_list)?;
+
+            // The leases are updated first, so that the new endpoints cannot be evicted due to a
+            // previous lease that has expired.
+            Self::update_provider_leases(
+                &mut lease_lock,
+                &request_inner.entity_access_info_list,
+                request_inner.lease_duration_seconds,
+            );
+
+            for entity_access_info in &request_inner.entity_access_info_list {
+                info!("Received a register request for the the entity:\n{}", entity_access_info.id);
 
-            self.register_entity(entity_access_info.clone())?;
+                self.register_entity(&mut entity_lock, entity_access_info.clone());
+            }
         }
 
         let response = RegisterResponse {};
@@ -77,126 +270,1804 @@ impl InvehicleDigitalTwin for InvehicleDigitalTwinImpl {
 
         Ok(Response::new(response))
     }
-}
 
-impl InvehicleDigitalTwinImpl {
-    /// Register the entity.
+    /// Unregister implementation.
     ///
     /// # Arguments
-    /// * `entity` - The entity.
-    fn register_entity(&self, entity_access_info: EntityAccessInfo) -> Result<(), Status> {
+    /// * `request` - Unregister request.
+    async fn unregister(
+        &self,
+        request: Request<UnregisterRequest>,
+    ) -> Result<Response<UnregisterResponse>, Status> {
+        let request_inner = request.into_inner();
+
+        info!(
+            "Received an unregister request for the entity:\n{} '{}'",
+            request_inner.id, request_inner.instance_id
+        );
+
+        let (endpoint_info_list, entity_removed) = self.unregister_entity(
+            &request_inner.id,
+            &request_inner.instance_id,
+            &request_inner.endpoint_uri_list,
+        )?;
+
+        let response = UnregisterResponse { endpoint_info_list, entity_removed };
+
+        debug!("Completed the unregister request.");
+
+        Ok(Response::new(response))
+    }
+
+    /// Renew lease implementation.
+    ///
+    /// # Arguments
+    /// * `request` - Renew lease request.
+    async fn renew_lease(
+        &self,
+        request: Request<RenewLeaseRequest>,
+    ) -> Result<Response<RenewLeaseResponse>, Status> {
+        let provider_id = request.into_inner().provider_id;
+
+        debug!("Received a renew_lease request for the provider {provider_id}");
+
+        let lease_duration_seconds;
+
         // This block controls the lifetime of the lock.
         {
-            let mut lock: RwLockWriteGuard<HashMap<String, EntityAccessInfo>> =
-                self.entity_access_info_map.write();
-            let get_result = lock.get(&entity_access_info.id);
-            match get_result {
-                Some(_) => {
-                    return Err(Status::unimplemented("The in-vehicle digital twin service does not yet support multiple registrations of the same entity."));
-                }
-                None => {
-                    lock.insert(entity_access_info.id.clone(), entity_access_info.clone());
-                }
-            };
+            let mut lock: RwLockWriteGuard<HashMap<String, ProviderLease>> =
+                self.provider_lease_map.write();
+            let lease = lock.get_mut(&provider_id).ok_or_else(|| {
+                Status::not_found(format!("Unable to find a lease for the provider {provider_id}"))
+            })?;
+            *lease = ProviderLease::new(lease.duration_seconds);
+            lease_duration_seconds = lease.duration_seconds;
         }
 
-        debug!("Registered entity {}", &entity_access_info.id);
+        let response = RenewLeaseResponse { lease_duration_seconds };
 
-        Ok(())
+        debug!("Completed the renew_lease request.");
+
+        Ok(Response::new(response))
     }
-}
 
-#[cfg(test)]
-mod invehicle_digital_twin_impl_tests {
-    use super::*;
-    use core_protobuf_data_access::invehicle_digital_twin::v1::EndpointInfo;
+    /// Watch implementation.
+    ///
+    /// # Arguments
+    /// * `request` - Watch request.
+    async fn watch(
+        &self,
+        request: Request<WatchRequest>,
+    ) -> Result<Response<Self::WatchStream>, Status> {
+        let request_inner = request.into_inner();
 
-    #[tokio::test]
-    async fn find_by_id_test() {
-        let operations = vec![String::from("Subscribe"), String::from("Unsubscribe")];
+        info!("Received a watch request: {request_inner:?}");
 
-        let endpoint_info = EndpointInfo {
-            protocol: String::from("grpc"),
-            uri: String::from("http://[::1]:40010"), // Devskim: ignore DS137138
-            context: String::from("dtmi:sdv:Vehicle:Cabin:HVAC:AmbientAirTemperature;1"),
-            operations,
-        };
+        // Subscribe before looking up the existing entities, so that no change can be missed.
+        // An entity that changes in between may be sent twice.
+        let entity_change_receiver = self.entity_change_sender.subscribe();
 
-        let entity_access_info = EntityAccessInfo {
-            name: String::from("AmbientAirTemperature"),
-            id: String::from("dtmi:sdv:Vehicle:Cabin:HVAC:AmbientAirTemperature;1"),
-            description: String::from("Ambient air temperature"),
-            endpoint_info_list: vec![endpoint_info],
-        };
+        let mut existing_entity_changes: Vec<Result<WatchResponse, Status>> = Vec::new();
+        if request_inner.include_existing {
+            // This block controls the lifetime of the lock.
+            {
+                let lock: RwLockReadGuard<HashMap<String, EntityAccessInfo>> =
+                    self.entity_access_info_map.read();
+                existing_entity_changes = lock
+                    .values()
+                    .filter(|entity_access_info| {
+                        Self::is_watched(entity_access_info, &request_inner)
+                    })
+                    .map(|entity_access_info| {
+                        Ok(WatchResponse {
+                            change_type: ChangeType::Added.into(),
+                            entity_access_info: Some(entity_access_info.clone()),
+                        })
+                    })
+                    .collect();
+            }
+        }
 
-        let entity_access_info_map = Arc::new(RwLock::new(HashMap::new()));
+        let entity_change_stream =
+            BroadcastStream::new(entity_change_receiver).filter_map(move |result| match result {
+                Ok(entity_change) => entity_change
+                    .entity_access_info
+                    .as_ref()
+                    .filter(|entity_access_info| {
+                        Self::is_watched(entity_access_info, &request_inner)
+                    })
+                    .map(|_| Ok(entity_change.clone())),
+                Err(BroadcastStreamRecvError::Lagged(missed_change_count)) => {
+                    warn!("A watcher fell behind and missed {missed_change_count} entity changes.");
+                    Some(Err(Status::data_loss(format!(
+                        "Missed {missed_change_count} entity changes. Watch again to resynchronize."
+                    ))))
+                }
+            });
+
+        let output_stream = tokio_stream::iter(existing_entity_changes).chain(entity_change_stream);
+
+        debug!("Responded to the watch request.");
+
+        Ok(Response::new(Box::pin(output_stream) as Self::WatchStream))
+    }
+
+    /// Get children implementation.
+    ///
+    /// # Arguments
+    /// * `request` - Get children request.
+    async fn get_children(
+        &self,
+        request: Request<GetChildrenRequest>,
+    ) -> Result<Response<GetChildrenResponse>, Status> {
+        let request_inner = request.into_inner();
+
+        info!("Received a get_children request: {request_inner:?}");
 
-        let invehicle_digital_twin_impl =
-            InvehicleDigitalTwinImpl { entity_access_info_map: entity_access_info_map.clone() };
+        let entity_access_info_list: Vec<EntityAccessInfo>;
 
         // This block controls the lifetime of the lock.
         {
-            let mut lock: RwLockWriteGuard<HashMap<String, EntityAccessInfo>> =
-                entity_access_info_map.write();
-            lock.insert(entity_access_info.id.clone(), entity_access_info.clone());
+            let lock: RwLockReadGuard<HashMap<String, EntityAccessInfo>> =
+                self.entity_access_info_map.read();
+
+            // The id identifies either a model, which matches every instance that implements it, or
+            // a single instance.
+            let mut parent_entity_access_info_list: Vec<&EntityAccessInfo> = lock
+                .values()
+                .filter(|entity_access_info| {
+                    entity_access_info.id == request_inner.id
+                        || entity_access_info.instance_id == request_inner.id
+                })
+                .collect();
+
+            if parent_entity_access_info_list.is_empty() {
+                return Err(Status::not_found(format!(
+                    "Unable to find the entity with id {}",
+                    request_inner.id
+                )));
+            }
+
+            parent_entity_access_info_list
+                .sort_by(|a, b| Self::get_entity_key(a).cmp(Self::get_entity_key(b)));
+
+            // Targets that are not registered are skipped, and a target that is claimed more than
+            // once is only returned once.
+            let mut target_ids: HashSet<&str> = HashSet::new();
+            entity_access_info_list = parent_entity_access_info_list
+                .into_iter()
+                .flat_map(|parent_entity_access_info| &parent_entity_access_info.relationship_list)
+                .filter(|relationship_info| {
+                    request_inner.relationship_name.is_empty()
+                        || relationship_info.name == request_inner.relationship_name
+                })
+                .filter(|relationship_info| target_ids.insert(relationship_info.target_id.as_str()))
+                .filter_map(|relationship_info| lock.get(&relationship_info.target_id).cloned())
+                .collect();
         }
 
-        let request = tonic::Request::new(FindByIdRequest {
-            id: String::from("dtmi:sdv:Vehicle:Cabin:HVAC:AmbientAirTemperature;1"),
-        });
-        let result = invehicle_digital_twin_impl.find_by_id(request).await;
-        assert!(result.is_ok());
-        let response = result.unwrap();
-        let response_inner = response.into_inner();
+        let response = GetChildrenResponse { entity_access_info_list };
 
-        assert!(response_inner.entity_access_info.is_some());
+        debug!("Responded to the get_children request.");
 
-        let response_entity_access_info = response_inner.entity_access_info.unwrap();
+        Ok(Response::new(response))
+    }
 
-        assert_eq!(
-            response_entity_access_info.id,
-            "dtmi:sdv:Vehicle:Cabin:HVAC:AmbientAirTemperature;1"
+    /// Get parent implementation.
+    ///
+    /// # Arguments
+    /// * `request` - Get parent request.
+    async fn get_parent(
+        &self,
+        request: Request<GetParentRequest>,
+    ) -> Result<Response<GetParentResponse>, Status> {
+        let entity_id = request.into_inner().id;
+
+        info!("Received a get_parent request for entity id {entity_id}");
+
+        let parent: Option<(EntityAccessInfo, RelationshipInfo)>;
+
+        // This block controls the lifetime of the lock.
+        {
+            let lock: RwLockReadGuard<HashMap<String, EntityAccessInfo>> =
+                self.entity_access_info_map.read();
+
+            // When more than one entity claims the child, the one with the lowest key is used, so
+            // that the result is stable.
+            parent = lock
+                .values()
+                .flat_map(|entity_access_info| {
+                    entity_access_info
+                        .relationship_list
+                        .iter()
+                        .filter(|relationship_info| relationship_info.target_id == entity_id)
+                        .map(move |relationship_info| (entity_access_info, relationship_info))
+                })
+                .min_by(|(a, _), (b, _)| Self::get_entity_key(a).cmp(Self::get_entity_key(b)))
+                .map(|(entity_access_info, relationship_info)| {
+                    (entity_access_info.clone(), relationship_info.clone())
+                });
+        }
+
+        let (entity_access_info, relationship_info) = parent.ok_or_else(|| {
+            Status::not_found(format!("Unable to find a parent of the entity with id {entity_id}"))
+        })?;
+
+        let response = GetParentResponse {
+            entity_access_info: Some(entity_access_info),
+            relationship_info: Some(relationship_info),
+        };
+
+        debug!("Responded to the get_parent request.");
+
+        Ok(Response::new(response))
+    }
+}
+
+impl InvehicleDigitalTwinImpl {
+    /// Creates a new instance of the in-vehicle digital twin service implementation.
+    pub fn new() -> Self {
+        let (entity_change_sender, _) = broadcast::channel(ENTITY_CHANGE_CHANNEL_CAPACITY);
+
+        InvehicleDigitalTwinImpl {
+            entity_access_info_map: Arc::new(RwLock::new(HashMap::new())),
+            provider_lease_map: Arc::new(RwLock::new(HashMap::new())),
+            entity_change_sender,
+            endpoint_health_map: Arc::new(RwLock::new(HashMap::new())),
+            filter_unhealthy_endpoints: false,
+            dtdl_validator: Arc::new(DtdlValidator::default()),
+        }
+    }
+
+    /// Registers the gauges for the entities, endpoints and provider leases in the registry.
+    ///
+    /// # Arguments
+    /// * `metrics_registry` - The registry of the service's metrics.
+    pub fn register_metrics(&self, metrics_registry: &MetricsRegistry) {
+        let entity_access_info_map = self.entity_access_info_map.clone();
+        metrics_registry.register_gauge(
+            "ibeji_registered_entities",
+            "The number of entities in the registry.",
+            move || entity_access_info_map.read().len() as f64,
         );
-        assert_eq!(response_entity_access_info.endpoint_info_list.len(), 1);
-        assert_eq!(
-            response_entity_access_info.endpoint_info_list[0].uri,
-            "http://[::1]:40010" // Devskim: ignore DS137138
+
+        let entity_access_info_map = self.entity_access_info_map.clone();
+        metrics_registry.register_gauge(
+            "ibeji_registered_endpoints",
+            "The number of endpoints of the entities in the registry.",
+            move || {
+                entity_access_info_map
+                    .read()
+                    .values()
+                    .map(|entity_access_info| entity_access_info.endpoint_info_list.len())
+                    .sum::<usize>() as f64
+            },
+        );
+
+        let provider_lease_map = self.provider_lease_map.clone();
+        metrics_registry.register_gauge(
+            "ibeji_provider_leases",
+            "The number of providers that hold a lease on their registrations.",
+            move || provider_lease_map.read().len() as f64,
+        );
+
+        let endpoint_health_map = self.endpoint_health_map.clone();
+        metrics_registry.register_gauge(
+            "ibeji_unhealthy_endpoints",
+            "The number of endpoints that did not respond to the last health probe.",
+            move || {
+                endpoint_health_map
+                    .read()
+                    .values()
+                    .filter(|health| **health == EndpointHealth::Unhealthy)
+                    .count() as f64
+            },
         );
     }
 
-    #[tokio::test]
-    async fn register_test() {
-        let endpoint_info = EndpointInfo {
-            protocol: String::from("grpc"),
-            uri: String::from("http://[::1]:40010"), // Devskim: ignore DS137138
-            context: String::from("dtmi:sdv:Vehicle:Cabin:HVAC:AmbientAirTemperature;1"),
-            operations: vec![String::from("Subscribe"), String::from("Unsubscribe")],
+    /// Publish an entity change to the watchers.
+    ///
+    /// # Arguments
+    /// * `change_type` - The type of change.
+    /// * `entity_access_info` - The entity's access information.
+    fn publish_entity_change(&self, change_type: ChangeType, entity_access_info: EntityAccessInfo) {
+        let entity_change = WatchResponse {
+            change_type: change_type.into(),
+            entity_access_info: Some(entity_access_info),
         };
 
-        let entity_access_info = EntityAccessInfo {
-            name: String::from("AmbientAirTemperature"),
-            id: String::from("dtmi:sdv:Vehicle:Cabin:HVAC:AmbientAirTemperature;1"),
-            description: String::from("Ambient air temperature"),
-            endpoint_info_list: vec![endpoint_info],
-        };
+        // Sending only fails when there are no watchers, in which case the change can be dropped.
+        let _ = self.entity_change_sender.send(entity_change);
+    }
 
-        let entity_access_info_map = Arc::new(RwLock::new(HashMap::new()));
+    /// Does the entity match the watch request's filters?
+    ///
+    /// # Arguments
+    /// * `entity_access_info` - The entity.
+    /// * `watch_request` - The watch request.
+    fn is_watched(entity_access_info: &EntityAccessInfo, watch_request: &WatchRequest) -> bool {
+        (watch_request.name.is_empty() || entity_access_info.name == watch_request.name)
+            && entity_access_info.id.starts_with(&watch_request.id_prefix)
+    }
 
-        let invehicle_digital_twin_impl =
-            InvehicleDigitalTwinImpl { entity_access_info_map: entity_access_info_map.clone() };
+    /// Take a snapshot of the registry, so that it can be persisted.
+    pub fn snapshot(&self) -> RegistrySnapshot {
+        let entity_access_info_list: Vec<EntityAccessInfo>;
+        let provider_lease_durations: HashMap<String, u32>;
 
-        let request = tonic::Request::new(RegisterRequest {
-            entity_access_info_list: vec![entity_access_info],
-        });
-        let result = invehicle_digital_twin_impl.register(request).await;
-        assert!(result.is_ok(), "register result is not okay: {result:?}");
+        // This block controls the lifetime of the locks.
+        {
+            let lease_lock: RwLockReadGuard<HashMap<String, ProviderLease>> =
+                self.provider_lease_map.read();
+            let entity_lock: RwLockReadGuard<HashMap<String, EntityAccessInfo>> =
+                self.entity_access_info_map.read();
+
+            entity_access_info_list = entity_lock.values().cloned().collect();
+            provider_lease_durations = lease_lock
+                .iter()
+                .map(|(provider_id, lease)| (provider_id.clone(), lease.duration_seconds))
+                .collect();
+        }
+
+        RegistrySnapshot { entity_access_info_list, provider_lease_durations }
+    }
+
+    /// Restore the registry from a snapshot, replacing its current contents.
+    /// Leases are restarted with their full duration, which gives the providers a chance to renew
+    /// them before their endpoints are evicted.
+    ///
+    /// # Arguments
+    /// * `snapshot` - The snapshot to restore.
+    pub fn restore(&self, snapshot: RegistrySnapshot) {
+        // This block controls the lifetime of the locks.
+        {
+            let mut lease_lock: RwLockWriteGuard<HashMap<String, ProviderLease>> =
+                self.provider_lease_map.write();
+            let mut entity_lock: RwLockWriteGuard<HashMap<String, EntityAccessInfo>> =
+                self.entity_access_info_map.write();
+
+            *lease_lock = snapshot
+                .provider_lease_durations
+                .into_iter()
+                .map(|(provider_id, duration_seconds)| {
+                    (provider_id, ProviderLease::new(duration_seconds))
+                })
+                .collect();
+            *entity_lock = snapshot
+                .entity_access_info_list
+                .into_iter()
+                .map(|entity_access_info| {
+                    (Self::get_entity_key(&entity_access_info).to_string(), entity_access_info)
+                })
+                .collect();
+
+            info!("Restored {} entities from the registry snapshot.", entity_lock.len());
+        }
+    }
+
+    /// Get the key that identifies the entity in the registry, which is its instance id, or its id
+    /// when it does not have an instance id.
+    ///
+    /// # Arguments
+    /// * `entity_access_info` - The entity.
+    fn get_entity_key(entity_access_info: &EntityAccessInfo) -> &str {
+        get_entity_key(&entity_access_info.id, &entity_access_info.instance_id)
+    }
+
+    /// Get the identity of the provider that registered an endpoint.
+    /// When the endpoint does not specify a provider id, its uri is used as the identity.
+    ///
+    /// # Arguments
+    /// * `endpoint_info` - The endpoint.
+    fn get_provider_id(endpoint_info: &EndpointInfo) -> &str {
+        if endpoint_info.provider_id.is_empty() {
+            &endpoint_info.uri
+        } else {
+            &endpoint_info.provider_id
+        }
+    }
+
+    /// Is the provided subset a subset of the provided superset?
+    ///
+    /// # Arguments
+    /// * `subset` - The provided subset.
+    /// * `superset` - The provided superset.
+    fn is_subset(subset: &[String], superset: &[String]) -> bool {
+        subset.iter().all(|subset_member| {
+            superset.iter().any(|supserset_member| subset_member == supserset_member)
+        })
+    }
+
+    /// Apply a find request's filters to an entity. Returns the entity with only its matching
+    /// endpoints, or None when the entity does not match.
+    ///
+    /// # Arguments
+    /// * `entity_access_info` - The entity.
+    /// * `find_request` - The find request.
+    fn filter_entity(
+        entity_access_info: &EntityAccessInfo,
+        find_request: &FindRequest,
+    ) -> Option<EntityAccessInfo> {
+        if !find_request.name.is_empty() && entity_access_info.name != find_request.name {
+            return None;
+        }
+
+        if !entity_access_info.id.starts_with(&find_request.id_prefix) {
+            return None;
+        }
+
+        let endpoint_info_list: Vec<EndpointInfo> = entity_access_info
+            .endpoint_info_list
+            .iter()
+            .filter(|endpoint_info| {
+                (find_request.protocol.is_empty()
+                    || endpoint_info.protocol == find_request.protocol)
+                    && Self::is_subset(&find_request.operations, &endpoint_info.operations)
+            })
+            .cloned()
+            .collect();
+
+        if endpoint_info_list.is_empty() {
+            return None;
+        }
+
+        Some(EntityAccessInfo { endpoint_info_list, ..entity_access_info.clone() })
+    }
 
+    /// Order the entity's endpoints so that the healthy ones come first. When unhealthy endpoints
+    /// are filtered out, an entity that is left without endpoints is not returned.
+    ///
+    /// # Arguments
+    /// * `entity_access_info` - The entity.
+    fn apply_endpoint_health(
+        &self,
+        mut entity_access_info: EntityAccessInfo,
+    ) -> Option<EntityAccessInfo> {
         // This block controls the lifetime of the lock.
         {
-            let lock: RwLockReadGuard<HashMap<String, EntityAccessInfo>> =
-                entity_access_info_map.read();
-            // Make sure that we populated the entity map from the contents of the DTDL.
-            assert_eq!(lock.len(), 1, "expected length was 1, actual length is {}", lock.len());
+            let lock: RwLockReadGuard<HashMap<String, EndpointHealth>> =
+                self.endpoint_health_map.read();
+            let get_health = |endpoint_info: &EndpointInfo| {
+                lock.get(&endpoint_info.uri).copied().unwrap_or(EndpointHealth::Unknown)
+            };
+
+            if self.filter_unhealthy_endpoints {
+                entity_access_info
+                    .endpoint_info_list
+                    .retain(|endpoint_info| get_health(endpoint_info) != EndpointHealth::Unhealthy);
+            }
+
+            // The sort is stable, so endpoints with the same health keep their registration order.
+            entity_access_info.endpoint_info_list.sort_by_key(get_health);
+        }
+
+        if entity_access_info.endpoint_info_list.is_empty() {
+            return None;
         }
+
+        Some(entity_access_info)
+    }
+
+    /// Validate the entities before they are registered. An entity may not reuse an instance id that
+    /// is already registered, or requested, for a different entity. The entities are also validated
+    /// against the DTDL models, according to the validation mode.
+    ///
+    /// # Arguments
+    /// * `entity_access_info_map` - The registered entities.
+    /// * `entity_access_info_list` - The entities to validate.
+    fn validate_entities(
+        &self,
+        entity_access_info_map: &HashMap<String, EntityAccessInfo>,
+        entity_access_info_list: &[EntityAccessInfo],
+    ) -> Result<(), Status> {
+        let mut requested_entity_ids: HashMap<&str, &str> = HashMap::new();
+        for entity_access_info in entity_access_info_list {
+            let entity_key = Self::get_entity_key(entity_access_info);
+            let existing_entity_id = entity_access_info_map
+                .get(entity_key)
+                .map(|existing_entity_access_info| existing_entity_access_info.id.as_str())
+                .or_else(|| requested_entity_ids.get(entity_key).copied());
+
+            if let Some(existing_entity_id) = existing_entity_id {
+                if existing_entity_id != entity_access_info.id {
+                    return Err(Status::already_exists(format!(
+                        "The instance id {} is already registered for the entity {existing_entity_id}",
+                        entity_access_info.instance_id
+                    )));
+                }
+            }
+
+            requested_entity_ids.insert(entity_key, &entity_access_info.id);
+        }
+
+        if self.dtdl_validator.mode == DtdlValidationMode::Off {
+            return Ok(());
+        }
+
+        for entity_access_info in entity_access_info_list {
+            if let Err(error) = self.dtdl_validator.validate(entity_access_info) {
+                if self.dtdl_validator.mode == DtdlValidationMode::Enforce {
+                    return Err(Status::invalid_argument(error));
+                }
+
+                warn!("{error}");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Update the leases for the providers of the registered endpoints.
+    ///
+    /// # Arguments
+    /// * `provider_lease_map` - The providers' leases.
+    /// * `entity_access_info_list` - The registered entities.
+    /// * `lease_duration_seconds` - The duration of the lease, in seconds. When zero, the providers'
+    ///                              endpoints never expire.
+    fn update_provider_leases(
+        provider_lease_map: &mut HashMap<String, ProviderLease>,
+        entity_access_info_list: &[EntityAccessInfo],
+        lease_duration_seconds: u32,
+    ) {
+        let provider_ids: HashSet<&str> = entity_access_info_list
+            .iter()
+            .flat_map(|entity_access_info| {
+                entity_access_info.endpoint_info_list.iter().map(Self::get_provider_id)
+            })
+            .collect();
+
+        for provider_id in provider_ids {
+            if lease_duration_seconds == 0 {
+                provider_lease_map.remove(provider_id);
+            } else {
+                provider_lease_map
+                    .insert(provider_id.to_string(), ProviderLease::new(lease_duration_seconds));
+            }
+        }
+    }
+
+    /// Evict the endpoints and relationships of every provider whose lease has expired. Entities
+    /// that no longer have any endpoints are removed.
+    ///
+    /// # Arguments
+    /// * `now` - The point in time that the leases are checked against.
+    pub fn evict_expired_endpoints(&self, now: Instant) {
+        // This block controls the lifetime of the locks. The lease lock is held while the entities
+        // are updated, so that a provider cannot renew its lease part way through its eviction.
+        {
+            let mut lease_lock: RwLockWriteGuard<HashMap<String, ProviderLease>> =
+                self.provider_lease_map.write();
+
+            let expired_provider_ids: HashSet<String> = lease_lock
+                .iter()
+                .filter(|(_, lease)| lease.expiry <= now)
+                .map(|(provider_id, _)| provider_id.clone())
+                .collect();
+
+            if expired_provider_ids.is_empty() {
+                return;
+            }
+
+            lease_lock.retain(|provider_id, _| !expired_provider_ids.contains(provider_id));
+
+            let mut entity_lock: RwLockWriteGuard<HashMap<String, EntityAccessInfo>> =
+                self.entity_access_info_map.write();
+
+            entity_lock.retain(|entity_id, entity_access_info| {
+                let (evicted, retained): (Vec<EndpointInfo>, Vec<EndpointInfo>) =
+                    entity_access_info.endpoint_info_list.drain(..).partition(|endpoint_info| {
+                        expired_provider_ids.contains(Self::get_provider_id(endpoint_info))
+                    });
+
+                for endpoint_info in &evicted {
+                    info!(
+                        "Evicted the endpoint '{}' for entity {entity_id}, as the lease for provider '{}' expired without being renewed.",
+                        endpoint_info.uri,
+                        Self::get_provider_id(endpoint_info)
+                    );
+                }
+
+                if retained.is_empty() {
+                    info!("Evicted the entity {entity_id}, as it no longer has any endpoints.");
+                    entity_access_info.endpoint_info_list = evicted;
+                    self.publish_entity_change(ChangeType::Removed, entity_access_info.clone());
+                    false
+                } else {
+                    entity_access_info.endpoint_info_list = retained;
+
+                    let relationship_count = entity_access_info.relationship_list.len();
+                    entity_access_info.relationship_list.retain(|relationship_info| {
+                        !expired_provider_ids.contains(&relationship_info.provider_id)
+                    });
+
+                    if !evicted.is_empty()
+                        || entity_access_info.relationship_list.len() != relationship_count
+                    {
+                        self.publish_entity_change(ChangeType::Updated, entity_access_info.clone());
+                    }
+                    true
+                }
+            });
+        }
+    }
+
+    /// Register the entity. If the entity is already registered, then the endpoints and
+    /// relationships are merged with the existing ones. Any existing endpoints and relationships
+    /// that belong to a provider in the new registration are replaced by that provider's new ones.
+    ///
+    /// The entity's instance id must already have been checked by `validate_entities`.
+    ///
+    /// # Arguments
+    /// * `entity_access_info_map` - The registered entities.
+    /// * `entity` - The entity.
+    fn register_entity(
+        &self,
+        entity_access_info_map: &mut HashMap<String, EntityAccessInfo>,
+        mut entity_access_info: EntityAccessInfo,
+    ) {
+        // A relationship without a provider id belongs to the provider of the first endpoint.
+        if let Some(endpoint_info) = entity_access_info.endpoint_info_list.first() {
+            let provider_id = Self::get_provider_id(endpoint_info).to_string();
+            for relationship_info in &mut entity_access_info.relationship_list {
+                if relationship_info.provider_id.is_empty() {
+                    relationship_info.provider_id = provider_id.clone();
+                }
+            }
+        }
+
+        match entity_access_info_map.get_mut(Self::get_entity_key(&entity_access_info)) {
+            Some(existing_entity_access_info) => {
+                let provider_ids: HashSet<&str> = entity_access_info
+                    .endpoint_info_list
+                    .iter()
+                    .map(Self::get_provider_id)
+                    .chain(
+                        entity_access_info
+                            .relationship_list
+                            .iter()
+                            .map(|relationship_info| relationship_info.provider_id.as_str()),
+                    )
+                    .collect();
+
+                existing_entity_access_info.endpoint_info_list.retain(|endpoint_info| {
+                    !provider_ids.contains(Self::get_provider_id(endpoint_info))
+                });
+                existing_entity_access_info
+                    .endpoint_info_list
+                    .extend(entity_access_info.endpoint_info_list.iter().cloned());
+
+                existing_entity_access_info.name = entity_access_info.name.clone();
+                existing_entity_access_info.description = entity_access_info.description.clone();
+
+                existing_entity_access_info.relationship_list.retain(|relationship_info| {
+                    !provider_ids.contains(relationship_info.provider_id.as_str())
+                });
+                existing_entity_access_info
+                    .relationship_list
+                    .extend(entity_access_info.relationship_list.iter().cloned());
+
+                self.publish_entity_change(
+                    ChangeType::Updated,
+                    existing_entity_access_info.clone(),
+                );
+            }
+            None => {
+                entity_access_info_map.insert(
+                    Self::get_entity_key(&entity_access_info).to_string(),
+                    entity_access_info.clone(),
+                );
+
+                self.publish_entity_change(ChangeType::Added, entity_access_info.clone());
+            }
+        };
+
+        debug!("Registered entity {}", Self::get_entity_key(&entity_access_info));
+    }
+
+    /// Unregister the entity's endpoints. The entity is removed once it no longer has any endpoints.
+    /// Returns the endpoints that were removed and whether the entity was removed.
+    ///
+    /// # Arguments
+    /// * `entity_id` - The entity's id.
+    /// * `instance_id` - The entity's instance id. When empty, the entity without an instance id is
+    ///                   unregistered.
+    /// * `endpoint_uri_list` - The URIs of the endpoints to remove. When empty, all of the entity's
+    ///                         endpoints are removed.
+    fn unregister_entity(
+        &self,
+        entity_id: &str,
+        instance_id: &str,
+        endpoint_uri_list: &[String],
+    ) -> Result<(Vec<EndpointInfo>, bool), Status> {
+        let removed_endpoint_info_list: Vec<EndpointInfo>;
+        let entity_removed: bool;
+        let entity_key = get_entity_key(entity_id, instance_id);
+
+        // This block controls the lifetime of the lock.
+        {
+            let mut lock: RwLockWriteGuard<HashMap<String, EntityAccessInfo>> =
+                self.entity_access_info_map.write();
+            let entity_access_info = lock
+                .get_mut(entity_key)
+                .filter(|entity_access_info| entity_access_info.id == entity_id)
+                .ok_or_else(|| {
+                    Status::not_found(format!(
+                        "Unable to find the entity with id {entity_id} and instance id '{instance_id}'"
+                    ))
+                })?;
+
+            let (removed, retained): (Vec<EndpointInfo>, Vec<EndpointInfo>) =
+                entity_access_info.endpoint_info_list.drain(..).partition(|endpoint_info| {
+                    endpoint_uri_list.is_empty() || endpoint_uri_list.contains(&endpoint_info.uri)
+                });
+
+            entity_removed = retained.is_empty();
+            if entity_removed {
+                entity_access_info.endpoint_info_list = removed.clone();
+                let removed_entity_access_info = entity_access_info.clone();
+                lock.remove(entity_key);
+
+                self.publish_entity_change(ChangeType::Removed, removed_entity_access_info);
+            } else {
+                entity_access_info.endpoint_info_list = retained;
+
+                if !removed.is_empty() {
+                    self.publish_entity_change(ChangeType::Updated, entity_access_info.clone());
+                }
+            }
+
+            removed_endpoint_info_list = removed;
+        }
+
+        debug!(
+            "Unregistered {} endpoint(s) from entity {entity_key}",
+            removed_endpoint_info_list.len()
+        );
+
+        Ok((removed_endpoint_info_list, entity_removed))
+    }
+}
+
+#[cfg(test)]
+mod invehicle_digital_twin_impl_tests {
+    use super::*;
+    use crate::registry_store::{InMemoryRegistryStore, RegistryStore};
+
+    #[tokio::test]
+    async fn find_by_id_test() {
+        let operations = vec![String::from("Subscribe"), String::from("Unsubscribe")];
+
+        let endpoint_info = EndpointInfo {
+            protocol: String::from("grpc"),
+            uri: String::from("http://[::1]:40010"), // Devskim: ignore DS137138
+            context: String::from("dtmi:sdv:Vehicle:Cabin:HVAC:AmbientAirTemperature;1"),
+            operations,
+            provider_id: String::from("test_provider"),
+        };
+
+        let entity_access_info = EntityAccessInfo {
+            name: String::from("AmbientAirTemperature"),
+            id: String::from("dtmi:sdv:Vehicle:Cabin:HVAC:AmbientAirTemperature;1"),
+            description: String::from("Ambient air temperature"),
+            endpoint_info_list: vec![endpoint_info],
+            instance_id: String::new(),
+            relationship_list: vec![],
+        };
+
+        let entity_access_info_map = Arc::new(RwLock::new(HashMap::new()));
+
+        let invehicle_digital_twin_impl = InvehicleDigitalTwinImpl {
+            entity_access_info_map: entity_access_info_map.clone(),
+            ..Default::default()
+        };
+
+        // This block controls the lifetime of the lock.
+        {
+            let mut lock: RwLockWriteGuard<HashMap<String, EntityAccessInfo>> =
+                entity_access_info_map.write();
+            lock.insert(entity_access_info.id.clone(), entity_access_info.clone());
+        }
+
+        let request = tonic::Request::new(FindByIdRequest {
+            id: String::from("dtmi:sdv:Vehicle:Cabin:HVAC:AmbientAirTemperature;1"),
+            instance_id: String::new(),
+        });
+        let result = invehicle_digital_twin_impl.find_by_id(request).await;
+        assert!(result.is_ok());
+        let response = result.unwrap();
+        let response_inner = response.into_inner();
+
+        assert!(response_inner.entity_access_info.is_some());
+
+        let response_entity_access_info = response_inner.entity_access_info.unwrap();
+
+        assert_eq!(
+            response_entity_access_info.id,
+            "dtmi:sdv:Vehicle:Cabin:HVAC:AmbientAirTemperature;1"
+        );
+        assert_eq!(response_entity_access_info.endpoint_info_list.len(), 1);
+        assert_eq!(
+            response_entity_access_info.endpoint_info_list[0].uri,
+            "http://[::1]:40010" // Devskim: ignore DS137138
+        );
+    }
+
+    #[tokio::test]
+    async fn register_test() {
+        let endpoint_info = EndpointInfo {
+            protocol: String::from("grpc"),
+            uri: String::from("http://[::1]:40010"), // Devskim: ignore DS137138
+            context: String::from("dtmi:sdv:Vehicle:Cabin:HVAC:AmbientAirTemperature;1"),
+            operations: vec![String::from("Subscribe"), String::from("Unsubscribe")],
+            provider_id: String::from("test_provider"),
+        };
+
+        let entity_access_info = EntityAccessInfo {
+            name: String::from("AmbientAirTemperature"),
+            id: String::from("dtmi:sdv:Vehicle:Cabin:HVAC:AmbientAirTemperature;1"),
+            description: String::from("Ambient air temperature"),
+            endpoint_info_list: vec![endpoint_info],
+            instance_id: String::new(),
+            relationship_list: vec![],
+        };
+
+        let entity_access_info_map = Arc::new(RwLock::new(HashMap::new()));
+
+        let invehicle_digital_twin_impl = InvehicleDigitalTwinImpl {
+            entity_access_info_map: entity_access_info_map.clone(),
+            ..Default::default()
+        };
+
+        let request = tonic::Request::new(RegisterRequest {
+            entity_access_info_list: vec![entity_access_info],
+            lease_duration_seconds: 0,
+        });
+        let result = invehicle_digital_twin_impl.register(request).await;
+        assert!(result.is_ok(), "register result is not okay: {result:?}");
+
+        // This block controls the lifetime of the lock.
+        {
+            let lock: RwLockReadGuard<HashMap<String, EntityAccessInfo>> =
+                entity_access_info_map.read();
+            // Make sure that we populated the entity map from the contents of the DTDL.
+            assert_eq!(lock.len(), 1, "expected length was 1, actual length is {}", lock.len());
+        }
+    }
+
+    #[tokio::test]
+    async fn register_multiple_providers_test() {
+        let first_provider_endpoint_info = EndpointInfo {
+            protocol: String::from("grpc"),
+            uri: String::from("http://[::1]:40010"), // Devskim: ignore DS137138
+            context: String::from("dtmi:sdv:Vehicle:Cabin:HVAC:AmbientAirTemperature;1"),
+            operations: vec![String::from("Subscribe"), String::from("Unsubscribe")],
+            provider_id: String::from("first_provider"),
+        };
+
+        let second_provider_endpoint_info = EndpointInfo {
+            protocol: String::from("grpc"),
+            uri: String::from("http://[::1]:40020"), // Devskim: ignore DS137138
+            context: String::from("dtmi:sdv:Vehicle:Cabin:HVAC:AmbientAirTemperature;1"),
+            operations: vec![String::from("Get")],
+            provider_id: String::from("second_provider"),
+        };
+
+        let entity_access_info = EntityAccessInfo {
+            name: String::from("AmbientAirTemperature"),
+            id: String::from("dtmi:sdv:Vehicle:Cabin:HVAC:AmbientAirTemperature;1"),
+            description: String::from("Ambient air temperature"),
+            endpoint_info_list: vec![first_provider_endpoint_info.clone()],
+            instance_id: String::new(),
+            relationship_list: vec![],
+        };
+
+        let entity_access_info_map = Arc::new(RwLock::new(HashMap::new()));
+
+        let invehicle_digital_twin_impl = InvehicleDigitalTwinImpl {
+            entity_access_info_map: entity_access_info_map.clone(),
+            ..Default::default()
+        };
+
+        // The first provider registers the entity.
+        let request = tonic::Request::new(RegisterRequest {
+            entity_access_info_list: vec![entity_access_info.clone()],
+            lease_duration_seconds: 0,
+        });
+        let result = invehicle_digital_twin_impl.register(request).await;
+        assert!(result.is_ok(), "register result is not okay: {result:?}");
+
+        // The second provider registers the same entity.
+        let request = tonic::Request::new(RegisterRequest {
+            entity_access_info_list: vec![EntityAccessInfo {
+                endpoint_info_list: vec![second_provider_endpoint_info],
+                ..entity_access_info.clone()
+            }],
+            lease_duration_seconds: 0,
+        });
+        let result = invehicle_digital_twin_impl.register(request).await;
+        assert!(result.is_ok(), "register result is not okay: {result:?}");
+
+        // The first provider restarts on a new uri and registers again.
+        let restarted_endpoint_info = EndpointInfo {
+            uri: String::from("http://[::1]:40030"), // Devskim: ignore DS137138
+            ..first_provider_endpoint_info
+        };
+        let request = tonic::Request::new(RegisterRequest {
+            entity_access_info_list: vec![EntityAccessInfo {
+                endpoint_info_list: vec![restarted_endpoint_info],
+                ..entity_access_info
+            }],
+            lease_duration_seconds: 0,
+        });
+        let result = invehicle_digital_twin_impl.register(request).await;
+        assert!(result.is_ok(), "register result is not okay: {result:?}");
+
+        // This block controls the lifetime of the lock.
+        {
+            let lock: RwLockReadGuard<HashMap<String, EntityAccessInfo>> =
+                entity_access_info_map.read();
+            assert_eq!(lock.len(), 1, "expected length was 1, actual length is {}", lock.len());
+
+            let endpoint_info_list = &lock
+                .get("dtmi:sdv:Vehicle:Cabin:HVAC:AmbientAirTemperature;1")
+                .unwrap()
+                .endpoint_info_list;
+            assert_eq!(endpoint_info_list.len(), 2);
+            assert_eq!(endpoint_info_list[0].provider_id, "second_provider");
+            assert_eq!(endpoint_info_list[1].provider_id, "first_provider");
+            assert_eq!(
+                endpoint_info_list[1].uri,
+                "http://[::1]:40030" // Devskim: ignore DS137138
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn unregister_test() {
+        let subscribe_endpoint_info = EndpointInfo {
+            protocol: String::from("grpc"),
+            uri: String::from("http://[::1]:40010"), // Devskim: ignore DS137138
+            context: String::from("dtmi:sdv:Vehicle:Cabin:HVAC:AmbientAirTemperature;1"),
+            operations: vec![String::from("Subscribe"), String::from("Unsubscribe")],
+            provider_id: String::from("test_provider"),
+        };
+
+        let get_endpoint_info = EndpointInfo {
+            protocol: String::from("grpc"),
+            uri: String::from("http://[::1]:40020"), // Devskim: ignore DS137138
+            context: String::from("dtmi:sdv:Vehicle:Cabin:HVAC:AmbientAirTemperature;1"),
+            operations: vec![String::from("Get")],
+            provider_id: String::from("test_provider"),
+        };
+
+        let entity_access_info = EntityAccessInfo {
+            name: String::from("AmbientAirTemperature"),
+            id: String::from("dtmi:sdv:Vehicle:Cabin:HVAC:AmbientAirTemperature;1"),
+            description: String::from("Ambient air temperature"),
+            endpoint_info_list: vec![subscribe_endpoint_info, get_endpoint_info],
+            instance_id: String::new(),
+            relationship_list: vec![],
+        };
+
+        let entity_access_info_map = Arc::new(RwLock::new(HashMap::new()));
+
+        let invehicle_digital_twin_impl = InvehicleDigitalTwinImpl {
+            entity_access_info_map: entity_access_info_map.clone(),
+            ..Default::default()
+        };
+
+        // This block controls the lifetime of the lock.
+        {
+            let mut lock: RwLockWriteGuard<HashMap<String, EntityAccessInfo>> =
+                entity_access_info_map.write();
+            lock.insert(entity_access_info.id.clone(), entity_access_info.clone());
+        }
+
+        // Remove a single endpoint.
+        let request = tonic::Request::new(UnregisterRequest {
+            id: String::from("dtmi:sdv:Vehicle:Cabin:HVAC:AmbientAirTemperature;1"),
+            endpoint_uri_list: vec![String::from("http://[::1]:40020")], // Devskim: ignore DS137138
+            instance_id: String::new(),
+        });
+        let result = invehicle_digital_twin_impl.unregister(request).await;
+        assert!(result.is_ok(), "unregister result is not okay: {result:?}");
+        let response_inner = result.unwrap().into_inner();
+        assert!(!response_inner.entity_removed);
+        assert_eq!(response_inner.endpoint_info_list.len(), 1);
+        assert_eq!(
+            response_inner.endpoint_info_list[0].uri,
+            "http://[::1]:40020" // Devskim: ignore DS137138
+        );
+
+        // This block controls the lifetime of the lock.
+        {
+            let lock: RwLockReadGuard<HashMap<String, EntityAccessInfo>> =
+                entity_access_info_map.read();
+            let entity_access_info =
+                lock.get("dtmi:sdv:Vehicle:Cabin:HVAC:AmbientAirTemperature;1").unwrap();
+            assert_eq!(entity_access_info.endpoint_info_list.len(), 1);
+        }
+
+        // Remove the rest of the entity.
+        let request = tonic::Request::new(UnregisterRequest {
+            id: String::from("dtmi:sdv:Vehicle:Cabin:HVAC:AmbientAirTemperature;1"),
+            endpoint_uri_list: vec![],
+            instance_id: String::new(),
+        });
+        let result = invehicle_digital_twin_impl.unregister(request).await;
+        assert!(result.is_ok(), "unregister result is not okay: {result:?}");
+        let response_inner = result.unwrap().into_inner();
+        assert!(response_inner.entity_removed);
+        assert_eq!(response_inner.endpoint_info_list.len(), 1);
+
+        // This block controls the lifetime of the lock.
+        {
+            let lock: RwLockReadGuard<HashMap<String, EntityAccessInfo>> =
+                entity_access_info_map.read();
+            assert!(lock.is_empty());
+        }
+
+        // Unregistering an unknown entity fails.
+        let request = tonic::Request::new(UnregisterRequest {
+            id: String::from("dtmi:sdv:Vehicle:Cabin:HVAC:AmbientAirTemperature;1"),
+            endpoint_uri_list: vec![],
+            instance_id: String::new(),
+        });
+        let result = invehicle_digital_twin_impl.unregister(request).await;
+        assert_eq!(result.unwrap_err().code(), tonic::Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn lease_test() {
+        let endpoint_info = EndpointInfo {
+            protocol: String::from("grpc"),
+            uri: String::from("http://[::1]:40010"), // Devskim: ignore DS137138
+            context: String::from("dtmi:sdv:Vehicle:Cabin:HVAC:AmbientAirTemperature;1"),
+            operations: vec![String::from("Subscribe"), String::from("Unsubscribe")],
+            provider_id: String::from("test_provider"),
+        };
+
+        let entity_access_info = EntityAccessInfo {
+            name: String::from("AmbientAirTemperature"),
+            id: String::from("dtmi:sdv:Vehicle:Cabin:HVAC:AmbientAirTemperature;1"),
+            description: String::from("Ambient air temperature"),
+            endpoint_info_list: vec![endpoint_info],
+            instance_id: String::new(),
+            relationship_list: vec![],
+        };
+
+        let invehicle_digital_twin_impl = InvehicleDigitalTwinImpl::default();
+
+        let request = tonic::Request::new(RegisterRequest {
+            entity_access_info_list: vec![entity_access_info],
+            lease_duration_seconds: 10,
+        });
+        let result = invehicle_digital_twin_impl.register(request).await;
+        assert!(result.is_ok(), "register result is not okay: {result:?}");
+
+        // The lease has not expired yet.
+        invehicle_digital_twin_impl.evict_expired_endpoints(Instant::now());
+        assert_eq!(invehicle_digital_twin_impl.entity_access_info_map.read().len(), 1);
+
+        let request =
+            tonic::Request::new(RenewLeaseRequest { provider_id: String::from("test_provider") });
+        let result = invehicle_digital_twin_impl.renew_lease(request).await;
+        assert!(result.is_ok(), "renew_lease result is not okay: {result:?}");
+        assert_eq!(result.unwrap().into_inner().lease_duration_seconds, 10);
+
+        // The lease has expired.
+        invehicle_digital_twin_impl
+            .evict_expired_endpoints(Instant::now() + Duration::from_secs(11));
+        assert!(invehicle_digital_twin_impl.entity_access_info_map.read().is_empty());
+        assert!(invehicle_digital_twin_impl.provider_lease_map.read().is_empty());
+
+        // The lease can no longer be renewed.
+        let request =
+            tonic::Request::new(RenewLeaseRequest { provider_id: String::from("test_provider") });
+        let result = invehicle_digital_twin_impl.renew_lease(request).await;
+        assert_eq!(result.unwrap_err().code(), tonic::Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn watch_test() {
+        let endpoint_info = EndpointInfo {
+            protocol: String::from("grpc"),
+            uri: String::from("http://[::1]:40010"), // Devskim: ignore DS137138
+            context: String::from("dtmi:sdv:Vehicle:Cabin:HVAC:AmbientAirTemperature;1"),
+            operations: vec![String::from("Subscribe"), String::from("Unsubscribe")],
+            provider_id: String::from("test_provider"),
+        };
+
+        let existing_entity_access_info = EntityAccessInfo {
+            name: String::from("AmbientAirTemperature"),
+            id: String::from("dtmi:sdv:Vehicle:Cabin:HVAC:AmbientAirTemperature;1"),
+            description: String::from("Ambient air temperature"),
+            endpoint_info_list: vec![endpoint_info.clone()],
+            instance_id: String::new(),
+            relationship_list: vec![],
+        };
+
+        let new_entity_access_info = EntityAccessInfo {
+            name: String::from("IsActive"),
+            id: String::from("dtmi:sdv:Vehicle:Cabin:HVAC:IsActive;1"),
+            description: String::from("Is HVAC active"),
+            endpoint_info_list: vec![endpoint_info.clone()],
+            instance_id: String::new(),
+            relationship_list: vec![],
+        };
+
+        let unwatched_entity_access_info = EntityAccessInfo {
+            name: String::from("Speed"),
+            id: String::from("dtmi:sdv:Vehicle:Speed;1"),
+            description: String::from("Vehicle speed"),
+            endpoint_info_list: vec![endpoint_info],
+            instance_id: String::new(),
+            relationship_list: vec![],
+        };
+
+        let invehicle_digital_twin_impl = InvehicleDigitalTwinImpl::new();
+
+        let request = tonic::Request::new(RegisterRequest {
+            entity_access_info_list: vec![existing_entity_access_info.clone()],
+            lease_duration_seconds: 0,
+        });
+        assert!(invehicle_digital_twin_impl.register(request).await.is_ok());
+
+        let request = tonic::Request::new(WatchRequest {
+            name: String::new(),
+            id_prefix: String::from("dtmi:sdv:Vehicle:Cabin:HVAC:"),
+            include_existing: true,
+        });
+        let result = invehicle_digital_twin_impl.watch(request).await;
+        assert!(result.is_ok(), "watch result is not okay");
+        let mut stream = result.unwrap().into_inner();
+
+        let request = tonic::Request::new(RegisterRequest {
+            entity_access_info_list: vec![
+                unwatched_entity_access_info,
+                new_entity_access_info.clone(),
+            ],
+            lease_duration_seconds: 0,
+        });
+        assert!(invehicle_digital_twin_impl.register(request).await.is_ok());
+
+        let request = tonic::Request::new(UnregisterRequest {
+            id: new_entity_access_info.id.clone(),
+            endpoint_uri_list: vec![],
+            instance_id: String::new(),
+        });
+        assert!(invehicle_digital_twin_impl.unregister(request).await.is_ok());
+
+        let entity_change = stream.next().await.unwrap().unwrap();
+        assert_eq!(entity_change.change_type(), ChangeType::Added);
+        assert_eq!(entity_change.entity_access_info.unwrap().id, existing_entity_access_info.id);
+
+        let entity_change = stream.next().await.unwrap().unwrap();
+        assert_eq!(entity_change.change_type(), ChangeType::Added);
+        assert_eq!(entity_change.entity_access_info.unwrap().id, new_entity_access_info.id);
+
+        let entity_change = stream.next().await.unwrap().unwrap();
+        assert_eq!(entity_change.change_type(), ChangeType::Removed);
+        let removed_entity_access_info = entity_change.entity_access_info.unwrap();
+        assert_eq!(removed_entity_access_info.id, new_entity_access_info.id);
+        assert_eq!(removed_entity_access_info.endpoint_info_list.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn snapshot_and_restore_test() {
+        let endpoint_info = EndpointInfo {
+            protocol: String::from("grpc"),
+            uri: String::from("http://[::1]:40010"), // Devskim: ignore DS137138
+            context: String::from("dtmi:sdv:Vehicle:Cabin:HVAC:AmbientAirTemperature;1"),
+            operations: vec![String::from("Subscribe"), String::from("Unsubscribe")],
+            provider_id: String::from("test_provider"),
+        };
+
+        let entity_access_info = EntityAccessInfo {
+            name: String::from("AmbientAirTemperature"),
+            id: String::from("dtmi:sdv:Vehicle:Cabin:HVAC:AmbientAirTemperature;1"),
+            description: String::from("Ambient air temperature"),
+            endpoint_info_list: vec![endpoint_info],
+            instance_id: String::new(),
+            relationship_list: vec![],
+        };
+
+        let registry_store = InMemoryRegistryStore::default();
+
+        let invehicle_digital_twin_impl = InvehicleDigitalTwinImpl::new();
+        let request = tonic::Request::new(RegisterRequest {
+            entity_access_info_list: vec![entity_access_info.clone()],
+            lease_duration_seconds: 30,
+        });
+        assert!(invehicle_digital_twin_impl.register(request).await.is_ok());
+        assert!(registry_store.save(&invehicle_digital_twin_impl.snapshot()).is_ok());
+
+        // Simulate a restart with a new instance.
+        let invehicle_digital_twin_impl = InvehicleDigitalTwinImpl::new();
+        invehicle_digital_twin_impl.restore(registry_store.load().unwrap().unwrap());
+
+        let request = tonic::Request::new(FindByIdRequest {
+            id: entity_access_info.id.clone(),
+            instance_id: String::new(),
+        });
+        let result = invehicle_digital_twin_impl.find_by_id(request).await;
+        assert!(result.is_ok(), "find_by_id result is not okay: {result:?}");
+        assert_eq!(result.unwrap().into_inner().entity_access_info, Some(entity_access_info));
+
+        // The restored lease is restarted, so it has not expired yet.
+        invehicle_digital_twin_impl.evict_expired_endpoints(Instant::now());
+        assert_eq!(invehicle_digital_twin_impl.entity_access_info_map.read().len(), 1);
+        let lock: RwLockReadGuard<HashMap<String, ProviderLease>> =
+            invehicle_digital_twin_impl.provider_lease_map.read();
+        assert_eq!(lock.get("test_provider").unwrap().duration_seconds, 30);
+    }
+
+    #[tokio::test]
+    async fn endpoint_health_test() {
+        let unhealthy_endpoint_info = EndpointInfo {
+            protocol: String::from("grpc"),
+            uri: String::from("http://[::1]:40010"), // Devskim: ignore DS137138
+            context: String::from("dtmi:sdv:Vehicle:Cabin:HVAC:AmbientAirTemperature;1"),
+            operations: vec![String::from("Get")],
+            provider_id: String::from("first_provider"),
+        };
+
+        let healthy_endpoint_info = EndpointInfo {
+            protocol: String::from("grpc"),
+            uri: String::from("http://[::1]:40020"), // Devskim: ignore DS137138
+            context: String::from("dtmi:sdv:Vehicle:Cabin:HVAC:AmbientAirTemperature;1"),
+            operations: vec![String::from("Get")],
+            provider_id: String::from("second_provider"),
+        };
+
+        let entity_access_info = EntityAccessInfo {
+            name: String::from("AmbientAirTemperature"),
+            id: String::from("dtmi:sdv:Vehicle:Cabin:HVAC:AmbientAirTemperature;1"),
+            description: String::from("Ambient air temperature"),
+            endpoint_info_list: vec![
+                unhealthy_endpoint_info.clone(),
+                healthy_endpoint_info.clone(),
+            ],
+            instance_id: String::new(),
+            relationship_list: vec![],
+        };
+
+        let mut invehicle_digital_twin_impl = InvehicleDigitalTwinImpl::new();
+        let request = tonic::Request::new(RegisterRequest {
+            entity_access_info_list: vec![entity_access_info.clone()],
+            lease_duration_seconds: 0,
+        });
+        assert!(invehicle_digital_twin_impl.register(request).await.is_ok());
+
+        // This block controls the lifetime of the lock.
+        {
+            let mut lock: RwLockWriteGuard<HashMap<String, EndpointHealth>> =
+                invehicle_digital_twin_impl.endpoint_health_map.write();
+            lock.insert(unhealthy_endpoint_info.uri.clone(), EndpointHealth::Unhealthy);
+            lock.insert(healthy_endpoint_info.uri.clone(), EndpointHealth::Healthy);
+        }
+
+        // The healthy endpoint comes first.
+        let request = tonic::Request::new(FindByIdRequest {
+            id: entity_access_info.id.clone(),
+            instance_id: String::new(),
+        });
+        let result = invehicle_digital_twin_impl.find_by_id(request).await;
+        assert!(result.is_ok(), "find_by_id result is not okay: {result:?}");
+        let endpoint_info_list =
+            result.unwrap().into_inner().entity_access_info.unwrap().endpoint_info_list;
+        assert_eq!(
+            endpoint_info_list,
+            vec![healthy_endpoint_info.clone(), unhealthy_endpoint_info]
+        );
+
+        // The unhealthy endpoint is left out.
+        invehicle_digital_twin_impl.filter_unhealthy_endpoints = true;
+        let request = tonic::Request::new(FindRequest {
+            id_prefix: entity_access_info.id.clone(),
+            ..Default::default()
+        });
+        let result = invehicle_digital_twin_impl.find(request).await;
+        assert!(result.is_ok(), "find result is not okay: {result:?}");
+        let entity_access_info_list = result.unwrap().into_inner().entity_access_info_list;
+        assert_eq!(entity_access_info_list.len(), 1);
+        assert_eq!(entity_access_info_list[0].endpoint_info_list, vec![healthy_endpoint_info]);
+    }
+
+    #[tokio::test]
+    async fn register_dtdl_validation_test() {
+        let document: serde_json::Value = serde_json::from_str(
+            r#"{
+                "@context": ["dtmi:dtdl:context;3"],
+                "@type": "Interface",
+                "@id": "dtmi:sdv:hvac;1",
+                "contents": [
+                    {
+                        "@type": "Property",
+                        "@id": "dtmi:sdv:hvac:ambient_air_temperature;1",
+                        "name": "ambient_air_temperature",
+                        "schema": "integer"
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let mut dtdl_validator = DtdlValidator::new(DtdlValidationMode::Enforce);
+        dtdl_validator.add_document(&document);
+
+        let invehicle_digital_twin_impl = InvehicleDigitalTwinImpl {
+            dtdl_validator: Arc::new(dtdl_validator),
+            ..Default::default()
+        };
+
+        let create_entity = |id: &str| EntityAccessInfo {
+            name: String::from("AmbientAirTemperature"),
+            id: id.to_string(),
+            description: String::from("Ambient air temperature"),
+            endpoint_info_list: vec![EndpointInfo {
+                protocol: String::from("grpc"),
+                uri: String::from("http://[::1]:40010"), // Devskim: ignore DS137138
+                context: id.to_string(),
+                operations: vec![String::from("Get")],
+                provider_id: String::from("test_provider"),
+            }],
+            instance_id: String::new(),
+            relationship_list: vec![],
+        };
+
+        // The v0 id is not declared in the DTDL models, so nothing is registered.
+        let request = tonic::Request::new(RegisterRequest {
+            entity_access_info_list: vec![
+                create_entity("dtmi:sdv:hvac:ambient_air_temperature;1"),
+                create_entity("dtmi:sdv:Vehicle:Cabin:HVAC:AmbientAirTemperature;1"),
+            ],
+            lease_duration_seconds: 0,
+        });
+        let result = invehicle_digital_twin_impl.register(request).await;
+        assert_eq!(result.unwrap_err().code(), tonic::Code::InvalidArgument);
+        assert!(invehicle_digital_twin_impl.entity_access_info_map.read().is_empty());
+
+        let request = tonic::Request::new(RegisterRequest {
+            entity_access_info_list: vec![create_entity("dtmi:sdv:hvac:ambient_air_temperature;1")],
+            lease_duration_seconds: 0,
+        });
+        assert!(invehicle_digital_twin_impl.register(request).await.is_ok());
+        assert_eq!(invehicle_digital_twin_impl.entity_access_info_map.read().len(), 1);
+    }
+
+    #[test]
+    fn is_subset_test() {
+        assert!(InvehicleDigitalTwinImpl::is_subset(&[], &[]));
+        assert!(InvehicleDigitalTwinImpl::is_subset(&[], &["one".to_string()]));
+        assert!(InvehicleDigitalTwinImpl::is_subset(&[], &["one".to_string(), "two".to_string()]));
+        assert!(InvehicleDigitalTwinImpl::is_subset(&["one".to_string()], &["one".to_string()]));
+        assert!(InvehicleDigitalTwinImpl::is_subset(
+            &["one".to_string()],
+            &["one".to_string(), "two".to_string()]
+        ));
+        assert!(InvehicleDigitalTwinImpl::is_subset(
+            &["one".to_string(), "two".to_string()],
+            &["one".to_string(), "two".to_string()]
+        ));
+        assert!(!InvehicleDigitalTwinImpl::is_subset(
+            &["one".to_string(), "two".to_string(), "three".to_string()],
+            &["one".to_string(), "two".to_string()]
+        ));
+        assert!(!InvehicleDigitalTwinImpl::is_subset(
+            &["one".to_string(), "two".to_string(), "three".to_string()],
+            &["one".to_string()]
+        ));
+        assert!(!InvehicleDigitalTwinImpl::is_subset(
+            &["one".to_string(), "two".to_string(), "three".to_string()],
+            &[]
+        ));
+    }
+
+    #[tokio::test]
+    async fn instances_test() {
+        let model_id = "dtmi:sdv:seat_massager;1";
+
+        let create_instance = |instance_id: &str, uri: &str| EntityAccessInfo {
+            name: String::from("SeatMassager"),
+            id: model_id.to_string(),
+            description: String::from("Seat massager"),
+            endpoint_info_list: vec![EndpointInfo {
+                protocol: String::from("grpc"),
+                uri: uri.to_string(),
+                context: instance_id.to_string(),
+                operations: vec![String::from("Invoke")],
+                provider_id: String::from("test_provider"),
+            }],
+            instance_id: instance_id.to_string(),
+            relationship_list: vec![],
+        };
+
+        let invehicle_digital_twin_impl = InvehicleDigitalTwinImpl::new();
+
+        let request = tonic::Request::new(RegisterRequest {
+            entity_access_info_list: vec![
+                create_instance("dtmi:sdv:seat_massager:rear_left;1", "http://[::1]:40020"), // Devskim: ignore DS137138
+                create_instance("dtmi:sdv:seat_massager:front_left;1", "http://[::1]:40010"), // Devskim: ignore DS137138
+            ],
+            lease_duration_seconds: 0,
+        });
+        assert!(invehicle_digital_twin_impl.register(request).await.is_ok());
+        assert_eq!(invehicle_digital_twin_impl.entity_access_info_map.read().len(), 2);
+
+        // Every instance is returned for the model's id.
+        let request = tonic::Request::new(FindByIdRequest {
+            id: model_id.to_string(),
+            instance_id: String::new(),
+        });
+        let result = invehicle_digital_twin_impl.find_by_id(request).await;
+        assert!(result.is_ok(), "find_by_id result is not okay: {result:?}");
+        let response_inner = result.unwrap().into_inner();
+        let instance_ids: Vec<String> = response_inner
+            .entity_access_info_list
+            .iter()
+            .map(|entity_access_info| entity_access_info.instance_id.clone())
+            .collect();
+        assert_eq!(
+            instance_ids,
+            vec!["dtmi:sdv:seat_massager:front_left;1", "dtmi:sdv:seat_massager:rear_left;1"]
+        );
+        assert_eq!(
+            response_inner.entity_access_info.unwrap().instance_id,
+            "dtmi:sdv:seat_massager:front_left;1"
+        );
+
+        // A single instance is returned for its instance id, with or without the model's id.
+        for (id, instance_id) in [
+            (model_id, "dtmi:sdv:seat_massager:rear_left;1"),
+            ("dtmi:sdv:seat_massager:rear_left;1", ""),
+        ] {
+            let request = tonic::Request::new(FindByIdRequest {
+                id: id.to_string(),
+                instance_id: instance_id.to_string(),
+            });
+            let result = invehicle_digital_twin_impl.find_by_id(request).await;
+            assert!(result.is_ok(), "find_by_id result is not okay: {result:?}");
+            let entity_access_info_list = result.unwrap().into_inner().entity_access_info_list;
+            assert_eq!(entity_access_info_list.len(), 1);
+            assert_eq!(
+                entity_access_info_list[0].instance_id,
+                "dtmi:sdv:seat_massager:rear_left;1"
+            );
+        }
+
+        // An instance id cannot be reused by a different model.
+        let mut conflicting_instance =
+            create_instance("dtmi:sdv:seat_massager:rear_left;1", "http://[::1]:40030"); // Devskim: ignore DS137138
+        conflicting_instance.id = String::from("dtmi:sdv:hvac;1");
+        let request = tonic::Request::new(RegisterRequest {
+            entity_access_info_list: vec![conflicting_instance.clone()],
+            lease_duration_seconds: 0,
+        });
+        let result = invehicle_digital_twin_impl.register(request).await;
+        assert_eq!(result.unwrap_err().code(), tonic::Code::AlreadyExists);
+
+        // A conflict rejects the whole request, including the entities before it.
+        let request = tonic::Request::new(RegisterRequest {
+            entity_access_info_list: vec![
+                create_instance("dtmi:sdv:seat_massager:front_right;1", "http://[::1]:40040"), // Devskim: ignore DS137138
+                conflicting_instance,
+            ],
+            lease_duration_seconds: 60,
+        });
+        let result = invehicle_digital_twin_impl.register(request).await;
+        assert_eq!(result.unwrap_err().code(), tonic::Code::AlreadyExists);
+        assert_eq!(invehicle_digital_twin_impl.entity_access_info_map.read().len(), 2);
+        assert!(!invehicle_digital_twin_impl
+            .entity_access_info_map
+            .read()
+            .contains_key("dtmi:sdv:seat_massager:front_right;1"));
+        assert!(invehicle_digital_twin_impl.provider_lease_map.read().is_empty());
+
+        // Unregistering one instance leaves the other one registered.
+        let request = tonic::Request::new(UnregisterRequest {
+            id: model_id.to_string(),
+            endpoint_uri_list: vec![],
+            instance_id: String::from("dtmi:sdv:seat_massager:front_left;1"),
+        });
+        let result = invehicle_digital_twin_impl.unregister(request).await;
+        assert!(result.is_ok(), "unregister result is not okay: {result:?}");
+        assert!(result.unwrap().into_inner().entity_removed);
+
+        let lock: RwLockReadGuard<HashMap<String, EntityAccessInfo>> =
+            invehicle_digital_twin_impl.entity_access_info_map.read();
+        assert_eq!(lock.len(), 1);
+        assert!(lock.contains_key("dtmi:sdv:seat_massager:rear_left;1"));
+    }
+
+    #[tokio::test]
+    async fn relationship_test() {
+        let create_entity = |id: &str, instance_id: &str, relationships: &[(&str, &str)]| {
+            EntityAccessInfo {
+                name: String::new(),
+                id: id.to_string(),
+                description: String::new(),
+                endpoint_info_list: vec![EndpointInfo {
+                    protocol: String::from("grpc"),
+                    uri: String::from("http://[::1]:40010"), // Devskim: ignore DS137138
+                    context: String::new(),
+                    operations: vec![String::from("Get")],
+                    provider_id: String::from("test_provider"),
+                }],
+                instance_id: instance_id.to_string(),
+                relationship_list: relationships
+                    .iter()
+                    .map(|(name, target_id)| RelationshipInfo {
+                        name: name.to_string(),
+                        relationship_id: format!("{id}:{name}"),
+                        target_id: target_id.to_string(),
+                        provider_id: String::new(),
+                    })
+                    .collect(),
+            }
+        };
+
+        let invehicle_digital_twin_impl = InvehicleDigitalTwinImpl::new();
+
+        let request = tonic::Request::new(RegisterRequest {
+            entity_access_info_list: vec![
+                create_entity("dtmi:sdv:vehicle;1", "", &[("has_cabin", "dtmi:sdv:cabin;1")]),
+                create_entity(
+                    "dtmi:sdv:cabin;1",
+                    "",
+                    &[
+                        ("has_seat", "dtmi:sdv:seat:front_left;1"),
+                        ("has_seat", "dtmi:sdv:seat:front_right;1"),
+                        ("has_hvac", "dtmi:sdv:hvac;1"),
+                    ],
+                ),
+                create_entity("dtmi:sdv:seat;1", "dtmi:sdv:seat:front_left;1", &[]),
+                create_entity("dtmi:sdv:seat;1", "dtmi:sdv:seat:front_right;1", &[]),
+            ],
+            lease_duration_seconds: 0,
+        });
+        assert!(invehicle_digital_twin_impl.register(request).await.is_ok());
+
+        // The unregistered hvac is skipped.
+        let request = tonic::Request::new(GetChildrenRequest {
+            id: String::from("dtmi:sdv:cabin;1"),
+            relationship_name: String::new(),
+        });
+        let result = invehicle_digital_twin_impl.get_children(request).await;
+        assert!(result.is_ok(), "get_children result is not okay: {result:?}");
+        assert_eq!(result.unwrap().into_inner().entity_access_info_list.len(), 2);
+
+        let request = tonic::Request::new(GetChildrenRequest {
+            id: String::from("dtmi:sdv:vehicle;1"),
+            relationship_name: String::from("has_cabin"),
+        });
+        let result = invehicle_digital_twin_impl.get_children(request).await;
+        assert!(result.is_ok(), "get_children result is not okay: {result:?}");
+        let entity_access_info_list = result.unwrap().into_inner().entity_access_info_list;
+        assert_eq!(entity_access_info_list.len(), 1);
+        assert_eq!(entity_access_info_list[0].id, "dtmi:sdv:cabin;1");
+
+        let request = tonic::Request::new(GetParentRequest {
+            id: String::from("dtmi:sdv:seat:front_right;1"),
+        });
+        let result = invehicle_digital_twin_impl.get_parent(request).await;
+        assert!(result.is_ok(), "get_parent result is not okay: {result:?}");
+        let response_inner = result.unwrap().into_inner();
+        assert_eq!(response_inner.entity_access_info.unwrap().id, "dtmi:sdv:cabin;1");
+        assert_eq!(response_inner.relationship_info.unwrap().name, "has_seat");
+
+        let request =
+            tonic::Request::new(GetParentRequest { id: String::from("dtmi:sdv:vehicle;1") });
+        let result = invehicle_digital_twin_impl.get_parent(request).await;
+        assert_eq!(result.unwrap_err().code(), tonic::Code::NotFound);
+
+        // A model id returns the children of all of its instances, and each child only once.
+        let request = tonic::Request::new(RegisterRequest {
+            entity_access_info_list: vec![
+                create_entity(
+                    "dtmi:sdv:row;1",
+                    "dtmi:sdv:row:front;1",
+                    &[
+                        ("has_seat", "dtmi:sdv:seat:front_left;1"),
+                        ("has_seat", "dtmi:sdv:seat:front_right;1"),
+                    ],
+                ),
+                create_entity(
+                    "dtmi:sdv:row;1",
+                    "dtmi:sdv:row:rear;1",
+                    &[("has_seat", "dtmi:sdv:seat:front_left;1")],
+                ),
+            ],
+            lease_duration_seconds: 0,
+        });
+        assert!(invehicle_digital_twin_impl.register(request).await.is_ok());
+
+        let request = tonic::Request::new(GetChildrenRequest {
+            id: String::from("dtmi:sdv:row;1"),
+            relationship_name: String::new(),
+        });
+        let result = invehicle_digital_twin_impl.get_children(request).await;
+        assert!(result.is_ok(), "get_children result is not okay: {result:?}");
+        let entity_access_info_list = result.unwrap().into_inner().entity_access_info_list;
+        assert_eq!(entity_access_info_list.len(), 2);
+        assert_eq!(entity_access_info_list[0].instance_id, "dtmi:sdv:seat:front_left;1");
+        assert_eq!(entity_access_info_list[1].instance_id, "dtmi:sdv:seat:front_right;1");
+
+        // A provider that registers again replaces its own relationships, but keeps the
+        // relationships of other providers.
+        let mut display_cabin =
+            create_entity("dtmi:sdv:cabin;1", "", &[("has_display", "dtmi:sdv:seat:front_left;1")]);
+        display_cabin.endpoint_info_list[0].uri = String::from("http://[::1]:40020"); // Devskim: ignore DS137138
+        display_cabin.endpoint_info_list[0].provider_id = String::from("display_provider");
+        let request = tonic::Request::new(RegisterRequest {
+            entity_access_info_list: vec![display_cabin],
+            lease_duration_seconds: 0,
+        });
+        assert!(invehicle_digital_twin_impl.register(request).await.is_ok());
+
+        let request = tonic::Request::new(RegisterRequest {
+            entity_access_info_list: vec![create_entity(
+                "dtmi:sdv:cabin;1",
+                "",
+                &[("has_seat", "dtmi:sdv:seat:front_left;1")],
+            )],
+            lease_duration_seconds: 0,
+        });
+        assert!(invehicle_digital_twin_impl.register(request).await.is_ok());
+
+        for (relationship_name, expected_child_count) in
+            [("has_seat", 1), ("has_hvac", 0), ("has_display", 1)]
+        {
+            let request = tonic::Request::new(GetChildrenRequest {
+                id: String::from("dtmi:sdv:cabin;1"),
+                relationship_name: relationship_name.to_string(),
+            });
+            let result = invehicle_digital_twin_impl.get_children(request).await;
+            assert!(result.is_ok(), "get_children result is not okay: {result:?}");
+            assert_eq!(
+                result.unwrap().into_inner().entity_access_info_list.len(),
+                expected_child_count,
+                "unexpected children over {relationship_name}"
+            );
+        }
+
+        let lock: RwLockReadGuard<HashMap<String, EntityAccessInfo>> =
+            invehicle_digital_twin_impl.entity_access_info_map.read();
+        let cabin = lock.get("dtmi:sdv:cabin;1").unwrap();
+        assert_eq!(cabin.relationship_list.len(), 2);
+        assert!(cabin
+            .relationship_list
+            .iter()
+            .all(|relationship_info| !relationship_info.provider_id.is_empty()));
+    }
+
+    #[tokio::test]
+    async fn find_test() {
+        let ambient_air_temperature_endpoint_info = EndpointInfo {
+            protocol: String::from("grpc"),
+            uri: String::from("http://[::1]:40010"), // Devskim: ignore DS137138
+            context: String::from("dtmi:sdv:HVAC:AmbientAirTemperature;1"),
+            operations: vec![String::from("Subscribe"), String::from("Unsubscribe")],
+            provider_id: String::from("test_provider"),
+        };
+
+        let ambient_air_temperature_mqtt_endpoint_info = EndpointInfo {
+            protocol: String::from("mqtt"),
+            uri: String::from("tcp://[::1]:1883"),
+            context: String::from("dtmi/sdv/HVAC/AmbientAirTemperature;1"),
+            operations: vec![String::from("Subscribe")],
+            provider_id: String::from("test_provider"),
+        };
+
+        let is_air_conditioning_active_endpoint_info = EndpointInfo {
+            protocol: String::from("grpc"),
+            uri: String::from("http://[::1]:40010"), // Devskim: ignore DS137138
+            context: String::from("dtmi:sdv:HVAC:IsAirConditioningActive;1"),
+            operations: vec![String::from("Subscribe"), String::from("Set")],
+            provider_id: String::from("test_provider"),
+        };
+
+        let show_notification_endpoint_info = EndpointInfo {
+            protocol: String::from("grpc"),
+            uri: String::from("http://[::1]:40010"), // Devskim: ignore DS137138
+            context: String::from("dtmi:sdv:HMI:ShowNotification;1"),
+            operations: vec![String::from("Invoke")],
+            provider_id: String::from("test_provider"),
+        };
+
+        let entity_access_info_list = vec![
+            EntityAccessInfo {
+                name: String::from("AmbientAirTemperature"),
+                id: String::from("dtmi:sdv:HVAC:AmbientAirTemperature;1"),
+                description: String::from("Ambient air temperature"),
+                endpoint_info_list: vec![
+                    ambient_air_temperature_endpoint_info,
+                    ambient_air_temperature_mqtt_endpoint_info,
+                ],
+                instance_id: String::new(),
+                relationship_list: vec![],
+            },
+            EntityAccessInfo {
+                name: String::from("IsAirConditioningActive"),
+                id: String::from("dtmi:sdv:HVAC:IsAirConditioningActive;1"),
+                description: String::from("Is air conditioning active?"),
+                endpoint_info_list: vec![is_air_conditioning_active_endpoint_info],
+                instance_id: String::new(),
+                relationship_list: vec![],
+            },
+            EntityAccessInfo {
+                name: String::from("ShowNotification"),
+                id: String::from("dtmi:sdv:HMI:ShowNotification;1"),
+                description: String::from("Show a notification on the HMI."),
+                endpoint_info_list: vec![show_notification_endpoint_info],
+                instance_id: String::new(),
+                relationship_list: vec![],
+            },
+        ];
+
+        let invehicle_digital_twin_impl = InvehicleDigitalTwinImpl::default();
+
+        let request = tonic::Request::new(RegisterRequest {
+            entity_access_info_list,
+            lease_duration_seconds: 0,
+        });
+        let result = invehicle_digital_twin_impl.register(request).await;
+        assert!(result.is_ok(), "register result is not okay: {result:?}");
+
+        // Find by id prefix.
+        let request = tonic::Request::new(FindRequest {
+            id_prefix: String::from("dtmi:sdv:HVAC"),
+            ..Default::default()
+        });
+        let response_inner = invehicle_digital_twin_impl.find(request).await.unwrap().into_inner();
+        assert_eq!(response_inner.entity_access_info_list.len(), 2);
+        assert_eq!(
+            response_inner.entity_access_info_list[0].id,
+            "dtmi:sdv:HVAC:AmbientAirTemperature;1"
+        );
+        assert_eq!(response_inner.entity_access_info_list[0].endpoint_info_list.len(), 2);
+        assert!(response_inner.next_page_token.is_empty());
+
+        // Find by name.
+        let request = tonic::Request::new(FindRequest {
+            name: String::from("ShowNotification"),
+            ..Default::default()
+        });
+        let response_inner = invehicle_digital_twin_impl.find(request).await.unwrap().into_inner();
+        assert_eq!(response_inner.entity_access_info_list.len(), 1);
+        assert_eq!(response_inner.entity_access_info_list[0].id, "dtmi:sdv:HMI:ShowNotification;1");
+
+        // Find by protocol and operations, which also filters the endpoints.
+        let request = tonic::Request::new(FindRequest {
+            protocol: String::from("grpc"),
+            operations: vec![String::from("Subscribe")],
+            ..Default::default()
+        });
+        let response_inner = invehicle_digital_twin_impl.find(request).await.unwrap().into_inner();
+        assert_eq!(response_inner.entity_access_info_list.len(), 2);
+        assert_eq!(response_inner.entity_access_info_list[0].endpoint_info_list.len(), 1);
+        assert_eq!(
+            response_inner.entity_access_info_list[0].endpoint_info_list[0].protocol,
+            "grpc"
+        );
+
+        // Page through every entity.
+        let request = tonic::Request::new(FindRequest { page_size: 2, ..Default::default() });
+        let response_inner = invehicle_digital_twin_impl.find(request).await.unwrap().into_inner();
+        assert_eq!(response_inner.entity_access_info_list.len(), 2);
+        assert_eq!(response_inner.next_page_token, "dtmi:sdv:HVAC:AmbientAirTemperature;1");
+
+        let request = tonic::Request::new(FindRequest {
+            page_size: 2,
+            page_token: response_inner.next_page_token,
+            ..Default::default()
+        });
+        let response_inner = invehicle_digital_twin_impl.find(request).await.unwrap().into_inner();
+        assert_eq!(response_inner.entity_access_info_list.len(), 1);
+        assert_eq!(
+            response_inner.entity_access_info_list[0].id,
+            "dtmi:sdv:HVAC:IsAirConditioningActive;1"
+        );
+        assert!(response_inner.next_page_token.is_empty());
     }
 }
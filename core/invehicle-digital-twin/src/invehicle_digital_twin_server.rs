@@ -0,0 +1,377 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+use authorization::authorization_module::AuthorizationModule;
+use common::chariott_client::{
+    ChariottClient, ChariottRegistrar, ChariottRegistrationSettings, ChariottRegistrationState,
+};
+use common::grpc_metrics_layer::GrpcMetricsLayer;
+use common::grpc_module_registry::GrpcModuleRegistry;
+use common::grpc_server::GrpcServer;
+use common::metrics::MetricsRegistry;
+use common::tls_settings::TlsSettings;
+use common::trace_context::TraceContextLayer;
+use common::utils::get_entity_key;
+use core::future::Future;
+use core_protobuf_data_access::chariott::service_discovery::core::v1::ServiceMetadata;
+use core_protobuf_data_access::invehicle_digital_twin;
+use core_protobuf_data_access::invehicle_digital_twin::v1::invehicle_digital_twin_server::InvehicleDigitalTwinServer;
+use core_protobuf_data_access::invehicle_digital_twin::v1::watch_response::ChangeType;
+use core_protobuf_data_access::invehicle_digital_twin::v1::WatchResponse;
+use log::{error, info, warn};
+use managed_subscribe::managed_subscribe_module::ManagedSubscribeModule;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::watch;
+use tonic::body::BoxBody;
+use tonic::server::NamedService;
+use tonic::transport::server::TcpIncoming;
+use tonic::transport::Body;
+use tonic_health::pb::health_server::{Health, HealthServer};
+use tonic_health::server::HealthReporter;
+use tonic_health::ServingStatus;
+use tower::layer::util::Identity;
+use tower::util::option_layer;
+use tower::Service;
+
+use crate::invehicle_digital_twin_impl::InvehicleDigitalTwinImpl;
+
+const INVEHICLE_DIGITAL_TWIN_SERVICE_NAMESPACE: &str = "sdv.ibeji";
+/// The name that the service is registered with in Chariott, and that its traces are exported with.
+pub const INVEHICLE_DIGITAL_TWIN_SERVICE_NAME: &str = "invehicle_digital_twin";
+const INVEHICLE_DIGITAL_TWIN_SERVICE_VERSION: &str = "1.0";
+const INVEHICLE_DIGITAL_TWIN_SERVICE_COMMUNICATION_KIND: &str = "grpc+proto";
+const INVEHICLE_DIGITAL_TWIN_SERVICE_COMMUNICATION_REFERENCE: &str = "https://github.com/eclipse-ibeji/ibeji/blob/main/interfaces/digital_twin/v1/digital_twin.proto";
+
+/// Creates the registry of the modules that can be enabled in the settings.
+///
+/// # How to add a Module:
+/// 1. Implement `GrpcModule` for the module, including any interceptors that it creates, and the
+///    file descriptor sets and names of its services.
+/// 2. Register the module's factory under its name and priority here. Modules that authorize
+///    calls have the security priority, so that their interceptors handle calls first.
+/// 3. List the module's name in the `modules` setting to enable it.
+pub fn create_module_registry() -> GrpcModuleRegistry {
+    let mut registry = GrpcModuleRegistry::new();
+    AuthorizationModule::register(&mut registry);
+    ManagedSubscribeModule::register(&mut registry);
+    registry
+}
+
+/// Gets the full name of a gRPC service, such as "invehicle_digital_twin.InvehicleDigitalTwin".
+///
+/// # Arguments
+/// * `_service` - The service.
+fn get_service_name<T: NamedService>(_service: &T) -> &'static str {
+    T::NAME
+}
+
+/// Creates the metadata that the invehicle digital twin service is registered with in Chariott.
+///
+/// # Arguments
+/// * `invehicle_digital_twin_uri` - In-vehicle Digital Twin Service's URI.
+fn create_chariott_service_metadata(invehicle_digital_twin_uri: &str) -> ServiceMetadata {
+    ServiceMetadata {
+        namespace: INVEHICLE_DIGITAL_TWIN_SERVICE_NAMESPACE.to_string(),
+        name: INVEHICLE_DIGITAL_TWIN_SERVICE_NAME.to_string(),
+        version: INVEHICLE_DIGITAL_TWIN_SERVICE_VERSION.to_string(),
+        uri: invehicle_digital_twin_uri.to_string(),
+        communication_kind: INVEHICLE_DIGITAL_TWIN_SERVICE_COMMUNICATION_KIND.to_string(),
+        communication_reference: INVEHICLE_DIGITAL_TWIN_SERVICE_COMMUNICATION_REFERENCE.to_string(),
+    }
+}
+
+/// Sets the serving status of the in-vehicle digital twin service, and the overall status.
+///
+/// # Arguments
+/// * `health_reporter` - Reports the serving status of each hosted service.
+/// * `status` - The serving status.
+async fn set_serving_status(health_reporter: &mut HealthReporter, status: ServingStatus) {
+    health_reporter
+        .set_service_status(InvehicleDigitalTwinServer::<InvehicleDigitalTwinImpl>::NAME, status)
+        .await;
+    health_reporter.set_service_status("", status).await;
+}
+
+/// Starts a background task that reports the service as serving while it is registered with
+/// Chariott, and as not serving otherwise.
+///
+/// # Arguments
+/// * `registration_state` - Receives the changes to the registration's state.
+/// * `health_reporter` - Reports the serving status of each hosted service.
+fn start_chariott_registration_health_reporter(
+    mut registration_state: watch::Receiver<ChariottRegistrationState>,
+    mut health_reporter: HealthReporter,
+) {
+    tokio::spawn(async move {
+        loop {
+            let state = *registration_state.borrow_and_update();
+            let status = match state {
+                ChariottRegistrationState::Registered => ServingStatus::Serving,
+                _ => ServingStatus::NotServing,
+            };
+            set_serving_status(&mut health_reporter, status).await;
+
+            // The registrar has stopped when its sender has been dropped.
+            if registration_state.changed().await.is_err() {
+                break;
+            }
+        }
+    });
+}
+
+/// Notifies the modules of each entity that is removed from the registry, until the registry
+/// stops publishing its changes.
+///
+/// # Arguments
+/// * `server` - The server that hosts the modules.
+/// * `entity_change_receiver` - Receives the changes to the registry's entities.
+async fn notify_modules_of_removed_entities<L>(
+    server: &GrpcServer<L>,
+    mut entity_change_receiver: broadcast::Receiver<WatchResponse>,
+) {
+    loop {
+        match entity_change_receiver.recv().await {
+            Ok(entity_change) if entity_change.change_type() == ChangeType::Removed => {
+                if let Some(entity_access_info) = entity_change.entity_access_info {
+                    let entity_key =
+                        get_entity_key(&entity_access_info.id, &entity_access_info.instance_id);
+                    server.notify_modules_entity_removed(entity_key).await;
+                }
+            }
+            Ok(_) => {}
+            Err(RecvError::Lagged(count)) => {
+                warn!("The modules were not notified of {count} changes to the registry.")
+            }
+            Err(RecvError::Closed) => break,
+        }
+    }
+}
+
+/// Creates the enabled modules, adds them to the grpc server and serves calls on the incoming
+/// connections until the shutdown future completes.
+///
+/// # Arguments
+/// * `addr` - The address the server will be hosted on.
+/// * `base_service` - The core service that will be hosted.
+/// * `entity_change_receiver` - Receives the changes to the registry's entities, so that the
+///                              modules are notified of the removed ones.
+/// * `module_registry` - The registry that the enabled modules are created with.
+/// * `module_names` - The names of the enabled modules. Their interceptors handle requests in
+///                    this order, after those of the security modules.
+/// * `health_reporter` - Reports the serving status of each hosted service.
+/// * `health_service` - The standard gRPC health service.
+/// * `tls_settings` - The server's TLS settings, if TLS is used.
+/// * `shutdown_timeout` - How long to wait for each module to shut down.
+/// * `metrics_registry` - The registry that the metrics are collected in, if they are collected.
+/// * `incoming` - The connections that are listened on, which are bound to `addr`.
+/// * `shutdown` - Completes when the server should stop.
+#[allow(clippy::too_many_arguments)]
+async fn build_server_and_serve<S>(
+    addr: SocketAddr,
+    base_service: S,
+    entity_change_receiver: broadcast::Receiver<WatchResponse>,
+    module_registry: &GrpcModuleRegistry,
+    module_names: Vec<String>,
+    mut health_reporter: HealthReporter,
+    health_service: HealthServer<impl Health>,
+    tls_settings: Option<TlsSettings>,
+    shutdown_timeout: Duration,
+    metrics_registry: Option<Arc<MetricsRegistry>>,
+    incoming: TcpIncoming,
+    shutdown: impl Future<Output = ()>,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    S: Service<http::Request<Body>, Response = http::Response<BoxBody>, Error = Infallible>
+        + NamedService
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+{
+    let modules = module_registry.create_modules(&module_names).await.map_err(|error| {
+        error!("Unable to create the enabled modules: '{error}'");
+        error
+    })?;
+
+    let mut server: GrpcServer<Identity> = GrpcServer::new(addr);
+
+    if let Some(tls_settings) = tls_settings {
+        let tls_config = tls_settings.create_server_tls_config().map_err(|error| {
+            error!("Unable to create the TLS configuration: '{error}'");
+            error as Box<dyn std::error::Error>
+        })?;
+        server.set_tls_config(tls_config);
+    }
+
+    let mut reflection_builder = tonic_reflection::server::Builder::configure()
+        .register_encoded_file_descriptor_set(invehicle_digital_twin::v1::FILE_DESCRIPTOR_SET)
+        .register_encoded_file_descriptor_set(tonic_health::pb::FILE_DESCRIPTOR_SET);
+
+    // Make the modules' services discoverable.
+    let mut module_service_names = Vec::new();
+    for module in &modules {
+        for file_descriptor_set in module.file_descriptor_sets() {
+            reflection_builder =
+                reflection_builder.register_encoded_file_descriptor_set(file_descriptor_set);
+        }
+
+        module_service_names.extend(module.service_names());
+    }
+
+    let reflection_service = reflection_builder.build()?;
+
+    let mut hosted_service_names =
+        vec![S::NAME, get_service_name(&health_service), get_service_name(&reflection_service)];
+    hosted_service_names.extend(&module_service_names);
+
+    // Trace and collect the metrics of every call, including those that the modules' interceptors
+    // answer. The trace context layer is outermost, so that everything that handles a call is
+    // within the call's span.
+    if let Some(metrics_registry) = metrics_registry.as_ref() {
+        for module in &modules {
+            module.register_metrics(metrics_registry);
+        }
+    }
+    let metrics_layer = metrics_registry
+        .map(|metrics_registry| GrpcMetricsLayer::new(metrics_registry, &hosted_service_names));
+    let mut server =
+        server.add_layer(TraceContextLayer::new()).add_layer(option_layer(metrics_layer));
+
+    // Add the modules, with their interceptors, to the server.
+    let server = server.add_modules(modules);
+
+    // Start the modules and report their services as serving. The modules that have already
+    // started are shut down if one of them fails to start.
+    if let Err(error) = server.start_modules().await {
+        error!("Unable to start the modules: '{error}'");
+        server.shutdown_modules(shutdown_timeout).await;
+        return Err(error.into());
+    }
+    for service_name in module_service_names {
+        health_reporter.set_service_status(service_name, ServingStatus::Serving).await;
+    }
+
+    // Construct the server.
+    let builder = server
+        .construct_server()?
+        .add_service(health_service)
+        .add_service(reflection_service)
+        .add_service(base_service);
+
+    // Start the server. The modules are notified once it is listening for calls, and of the
+    // removed entities while it serves them. They are shut down once it has stopped.
+    let serve = builder.serve_with_incoming_shutdown(incoming, shutdown);
+    let notify_modules = async {
+        server.notify_modules_ready().await;
+        notify_modules_of_removed_entities(&server, entity_change_receiver).await;
+    };
+    tokio::pin!(serve, notify_modules);
+
+    let serve_result = tokio::select! {
+        serve_result = &mut serve => serve_result,
+        _ = &mut notify_modules => serve.await,
+    };
+
+    info!("The server has stopped. Shutting down the modules.");
+    server.shutdown_modules(shutdown_timeout).await;
+
+    serve_result.map_err(|error| error.into())
+}
+
+/// Serves the in-vehicle digital twin service, with its enabled modules, on the incoming
+/// connections until the shutdown future completes. When the service uses Chariott, it is kept
+/// registered with Chariott while it serves, and it reports that it is serving only while it is
+/// registered. It is unregistered once it has stopped.
+///
+/// # Arguments
+/// * `addr` - The address the server will be hosted on.
+/// * `invehicle_digital_twin_uri` - The URI that the service is registered with in Chariott.
+/// * `invehicle_digital_twin_impl` - The in-vehicle digital twin service implementation.
+/// * `module_registry` - The registry that the enabled modules are created with.
+/// * `module_names` - The names of the enabled modules. Their interceptors handle requests in
+///                    this order, after those of the security modules.
+/// * `chariott_client` - The client that the service registers with, if it uses Chariott.
+/// * `chariott_registration_settings` - How the registration is retried and re-asserted.
+/// * `tls_settings` - The server's TLS settings, if TLS is used.
+/// * `shutdown_timeout` - How long to wait for each module to shut down, and for the service
+///                        to be unregistered from Chariott.
+/// * `metrics_registry` - The registry that the metrics are collected in, if they are collected.
+/// * `incoming` - The connections that are listened on, which are bound to `addr`.
+/// * `shutdown` - Completes when the server should stop.
+#[allow(clippy::too_many_arguments)]
+pub async fn serve_invehicle_digital_twin(
+    addr: SocketAddr,
+    invehicle_digital_twin_uri: &str,
+    invehicle_digital_twin_impl: InvehicleDigitalTwinImpl,
+    module_registry: &GrpcModuleRegistry,
+    module_names: Vec<String>,
+    chariott_client: Option<ChariottClient>,
+    chariott_registration_settings: ChariottRegistrationSettings,
+    tls_settings: Option<TlsSettings>,
+    shutdown_timeout: Duration,
+    metrics_registry: Option<Arc<MetricsRegistry>>,
+    incoming: TcpIncoming,
+    shutdown: impl Future<Output = ()>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // The service reports that it is not serving until it has registered with Chariott.
+    let (mut health_reporter, health_service) = tonic_health::server::health_reporter();
+    set_serving_status(&mut health_reporter, ServingStatus::NotServing).await;
+
+    let chariott_registrar = match chariott_client {
+        Some(chariott_client) => {
+            let chariott_registrar = ChariottRegistrar::start(
+                chariott_client,
+                create_chariott_service_metadata(invehicle_digital_twin_uri),
+                chariott_registration_settings,
+            );
+            start_chariott_registration_health_reporter(
+                chariott_registrar.state(),
+                health_reporter.clone(),
+            );
+            Some(chariott_registrar)
+        }
+        None => {
+            info!("This service is not using Chariott.");
+            set_serving_status(&mut health_reporter, ServingStatus::Serving).await;
+            None
+        }
+    };
+
+    let entity_change_receiver = invehicle_digital_twin_impl.entity_change_sender.subscribe();
+    let base_service = InvehicleDigitalTwinServer::new(invehicle_digital_twin_impl);
+
+    let result = build_server_and_serve(
+        addr,
+        base_service,
+        entity_change_receiver,
+        module_registry,
+        module_names,
+        health_reporter,
+        health_service,
+        tls_settings,
+        shutdown_timeout,
+        metrics_registry,
+        incoming,
+        shutdown,
+    )
+    .await;
+
+    // Unregister the service from Chariott, so that it is not discovered once it has stopped.
+    if let Some(chariott_registrar) = chariott_registrar {
+        match tokio::time::timeout(shutdown_timeout, chariott_registrar.shutdown()).await {
+            Ok(Ok(())) => {}
+            Ok(Err(status)) => {
+                error!("Failed to unregister this service from Chariott: '{status}'")
+            }
+            Err(_) => error!("Timed out unregistering this service from Chariott."),
+        }
+    }
+
+    result
+}
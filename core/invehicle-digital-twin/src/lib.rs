@@ -0,0 +1,10 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+pub mod dtdl_validator;
+pub mod endpoint_health_prober;
+pub mod invehicle_digital_twin_config;
+pub mod invehicle_digital_twin_impl;
+pub mod invehicle_digital_twin_server;
+pub mod registry_store;
@@ -2,138 +2,156 @@
 // Licensed under the MIT license.
 // SPDX-License-Identifier: MIT
 
-// Module references behind feature flags. Add any necessary module references here.
-// Start: Module references.
-
-#[cfg(feature = "managed_subscribe")]
-use managed_subscribe::managed_subscribe_module::ManagedSubscribeModule;
-
-// End: Module references.
-
-#[allow(unused_imports)]
-use common::grpc_interceptor::GrpcInterceptorLayer;
-
-use common::grpc_server::GrpcServer;
-use core_protobuf_data_access::chariott::service_discovery::core::v1::service_registry_client::ServiceRegistryClient;
-use core_protobuf_data_access::chariott::service_discovery::core::v1::{
-    RegisterRequest, ServiceMetadata,
+use common::chariott_client::{
+    ChariottClient, ChariottRegistrationSettings, DEFAULT_REASSERT_INTERVAL,
 };
-use core_protobuf_data_access::invehicle_digital_twin::v1::invehicle_digital_twin_server::InvehicleDigitalTwinServer;
-use env_logger::{Builder, Target};
+use common::metrics::{serve_metrics, MetricsRegistry, METRICS_PATH};
+use common::tls_settings::get_uri_scheme;
+use common::tracing_settings::{init_tracing, shutdown_tracing};
+use invehicle_digital_twin::dtdl_validator::{DtdlValidationMode, DtdlValidator};
+use invehicle_digital_twin::endpoint_health_prober;
+use invehicle_digital_twin::invehicle_digital_twin_config;
+use invehicle_digital_twin::invehicle_digital_twin_impl::InvehicleDigitalTwinImpl;
+use invehicle_digital_twin::invehicle_digital_twin_server::{
+    create_module_registry, serve_invehicle_digital_twin, INVEHICLE_DIGITAL_TWIN_SERVICE_NAME,
+};
+use invehicle_digital_twin::registry_store::{JsonFileRegistryStore, RegistryStore};
 use log::{debug, error, info, LevelFilter};
-use parking_lot::RwLock;
-use std::boxed::Box;
 use std::collections::HashMap;
-use std::convert::Infallible;
 use std::env;
 use std::net::SocketAddr;
+use std::path::Path;
 use std::str::FromStr;
 use std::sync::Arc;
-use tonic::body::BoxBody;
-use tonic::server::NamedService;
-use tonic::transport::Body;
-use tonic::{Request, Status};
-use tower::layer::util::Identity;
-use tower::Service;
-
-mod invehicle_digital_twin_config;
-mod invehicle_digital_twin_impl;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast::error::TryRecvError;
+use tonic::transport::server::TcpIncoming;
 
 const DEFAULT_LOG_LEVEL: &str = "info";
-const INVEHICLE_DIGITAL_TWIN_SERVICE_NAMESPACE: &str = "sdv.ibeji";
-const INVEHICLE_DIGITAL_TWIN_SERVICE_NAME: &str = "invehicle_digital_twin";
-const INVEHICLE_DIGITAL_TWIN_SERVICE_VERSION: &str = "1.0";
-const INVEHICLE_DIGITAL_TWIN_SERVICE_COMMUNICATION_KIND: &str = "grpc+proto";
-const INVEHICLE_DIGITAL_TWIN_SERVICE_COMMUNICATION_REFERENCE: &str = "https://github.com/eclipse-ibeji/ibeji/blob/main/interfaces/digital_twin/v1/digital_twin.proto";
+const DEFAULT_LEASE_EVICTION_INTERVAL_SECS: u64 = 5;
+const DEFAULT_REGISTRY_SNAPSHOT_INTERVAL_SECS: u64 = 5;
+const DEFAULT_ENDPOINT_HEALTH_PROBE_TIMEOUT_MS: u64 = 1000;
+const DEFAULT_SHUTDOWN_TIMEOUT_SECS: u64 = 10;
 
-/// Register the invehicle digital twin service with Chariott.
+/// Starts a background task that periodically evicts the endpoints whose lease has expired.
 ///
 /// # Arguments
-/// * `chariott_uri` - Chariott's URI.
-/// * `invehicle_digital_twin_uri` - In-vehicle Digital Twin Service's URI.
-async fn register_invehicle_digital_twin_service_with_chariott(
-    chariott_uri: &str,
-    invehicle_digital_twin_uri: &str,
-) -> Result<(), Status> {
-    let mut client = ServiceRegistryClient::connect(chariott_uri.to_owned())
-        .await
-        .map_err(|e| Status::internal(e.to_string()))?;
-
-    let service = Some(ServiceMetadata {
-        namespace: INVEHICLE_DIGITAL_TWIN_SERVICE_NAMESPACE.to_string(),
-        name: INVEHICLE_DIGITAL_TWIN_SERVICE_NAME.to_string(),
-        version: INVEHICLE_DIGITAL_TWIN_SERVICE_VERSION.to_string(),
-        uri: invehicle_digital_twin_uri.to_string(),
-        communication_kind: INVEHICLE_DIGITAL_TWIN_SERVICE_COMMUNICATION_KIND.to_string(),
-        communication_reference: INVEHICLE_DIGITAL_TWIN_SERVICE_COMMUNICATION_REFERENCE.to_string(),
+/// * `invehicle_digital_twin_impl` - The in-vehicle digital twin service implementation.
+/// * `eviction_interval` - The interval at which expired endpoints are evicted.
+fn start_lease_reaper(
+    invehicle_digital_twin_impl: InvehicleDigitalTwinImpl,
+    eviction_interval: Duration,
+) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(eviction_interval);
+        loop {
+            interval.tick().await;
+            invehicle_digital_twin_impl.evict_expired_endpoints(Instant::now());
+        }
     });
+}
 
-    let request = Request::new(RegisterRequest { service });
-
-    client
-        .register(request)
-        .await
-        .map_err(|_| Status::internal("Chariott register request failed"))?;
+/// Starts a background task that periodically saves the registry to the store, when it has changed.
+///
+/// # Arguments
+/// * `invehicle_digital_twin_impl` - The in-vehicle digital twin service implementation.
+/// * `registry_store` - The store that the registry is saved to.
+/// * `snapshot_interval` - The interval at which the registry is saved.
+fn start_registry_snapshotter(
+    invehicle_digital_twin_impl: InvehicleDigitalTwinImpl,
+    registry_store: Arc<dyn RegistryStore>,
+    snapshot_interval: Duration,
+) {
+    let mut entity_change_receiver = invehicle_digital_twin_impl.entity_change_sender.subscribe();
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(snapshot_interval);
+        loop {
+            interval.tick().await;
+
+            // Drain the pending changes. A lagged receiver means that there were changes too.
+            let mut has_changed = false;
+            while let Ok(_) | Err(TryRecvError::Lagged(_)) = entity_change_receiver.try_recv() {
+                has_changed = true;
+            }
 
-    Ok(())
+            if has_changed {
+                if let Err(error) = registry_store.save(&invehicle_digital_twin_impl.snapshot()) {
+                    error!("Failed to save the registry snapshot: '{error}'");
+                }
+            }
+        }
+    });
 }
 
-/// Builds the enabled modules for the grpc server and starts the server.
+/// Starts a background task that periodically probes the registered endpoints.
 ///
 /// # Arguments
-/// * `addr` - The address the server will be hosted on.
-/// * `base_service` - The core service that will be hosted.
-///
-/// # How to add a Module to this method:
-/// 1. Add a block of code with the appropriate cfg feature flag.
-/// 2. Create the `GrpcModule` object within the block - if applicable.
-/// 3. Create the `GrpcInterceptorLayer` object(s) within the block - if applicable.
-/// 4. Add the grpc interceptors to the middleware stack with `.layer()`.
-/// 5. Call and return from the block `.add_module()` on the server with the updated middleware and
-/// module.
-#[allow(unused_assignments, unused_mut)] // Necessary when no extra modules are built.
-async fn build_server_and_serve<S>(
-    addr: SocketAddr,
-    base_service: S,
-) -> Result<(), Box<dyn std::error::Error>>
-where
-    S: Service<http::Request<Body>, Response = http::Response<BoxBody>, Error = Infallible>
-        + NamedService
-        + Clone
-        + Send
-        + 'static,
-    S::Future: Send + 'static,
-{
-    let mut server: GrpcServer<Identity> = GrpcServer::new(addr);
-
-    #[cfg(feature = "managed_subscribe")]
-    // (1) Adds the Managed Subscribe module to the service.
-    let server = {
-        // (2) Initialize the Managed Subscribe module, which implements GrpcModule.
-        let managed_subscribe_module = ManagedSubscribeModule::new().await.map_err(|error| {
-            error!("Unable to create Managed Subscribe module.");
-            error
-        })?;
-
-        // (3) Create interceptor layer to be added to the server.
-        let managed_subscribe_layer =
-            GrpcInterceptorLayer::new(Box::new(managed_subscribe_module.create_interceptor()));
+/// * `invehicle_digital_twin_impl` - The in-vehicle digital twin service implementation.
+/// * `probe_interval` - The interval at which the endpoints are probed.
+/// * `probe_timeout` - How long to wait for each endpoint to respond.
+fn start_endpoint_health_prober(
+    invehicle_digital_twin_impl: InvehicleDigitalTwinImpl,
+    probe_interval: Duration,
+    probe_timeout: Duration,
+) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(probe_interval);
+        loop {
+            interval.tick().await;
+            endpoint_health_prober::probe_registered_endpoints(
+                &invehicle_digital_twin_impl,
+                probe_timeout,
+            )
+            .await;
+        }
+    });
+}
 
-        // (4) Add the interceptor(s) to the middleware stack.
-        let current_middleware = server.middleware.clone();
-        let new_middleware = current_middleware.layer(managed_subscribe_layer);
+/// Starts a background task that serves the metrics.
+///
+/// # Arguments
+/// * `addr` - The address that the metrics are served on.
+/// * `metrics_registry` - The registry of the service's metrics.
+fn start_metrics_server(addr: SocketAddr, metrics_registry: Arc<MetricsRegistry>) {
+    info!("The metrics are served on 'http://{addr}{METRICS_PATH}'.");
+
+    tokio::spawn(async move {
+        if let Err(error) = serve_metrics(addr, metrics_registry).await {
+            error!("Unable to serve the metrics on '{addr}': '{error}'");
+        }
+    });
+}
 
-        info!("Initialized Managed Subscribe module.");
+/// Completes when the service is asked to stop, with SIGINT (Ctrl+C) or, on Unix, SIGTERM.
+async fn shutdown_signal() {
+    let interrupt = async {
+        if let Err(error) = tokio::signal::ctrl_c().await {
+            error!("Unable to listen for SIGINT: '{error}'");
+            std::future::pending::<()>().await;
+        }
+    };
 
-        // (5) Add the module with the updated middleware stack to the server.
-        server.add_module(new_middleware, Box::new(managed_subscribe_module))
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(error) => {
+                error!("Unable to listen for SIGTERM: '{error}'");
+                std::future::pending::<()>().await;
+            }
+        }
     };
 
-    // Construct the server.
-    let builder = server.construct_server().add_service(base_service);
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
 
-    // Start the server.
-    builder.serve(addr).await.map_err(|error| error.into())
+    tokio::select! {
+        _ = interrupt => info!("Received SIGINT."),
+        _ = terminate => info!("Received SIGTERM."),
+    }
 }
 
 #[tokio::main]
@@ -163,51 +181,161 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .expect("No log-level value provided");
     let log_level =
         LevelFilter::from_str(log_level_arg.as_str()).expect("Could not parse log level");
-    Builder::new().filter(None, log_level).target(Target::Stdout).init();
 
+    // Load the config.
+    let settings = invehicle_digital_twin_config::load_settings();
+
+    // Setup tracing, along with the tokio console when it is enabled.
     #[cfg(feature = "tokio_console")]
-    {
-        // enable tracing for tokio console
-        info!("Tokio console tracing is enabled");
-        tokio_console_subscriber::init();
-    }
+    let tokio_console_layer = Some(Box::new(tokio_console_subscriber::spawn()) as _);
+    #[cfg(not(feature = "tokio_console"))]
+    let tokio_console_layer = None;
+
+    init_tracing(
+        INVEHICLE_DIGITAL_TWIN_SERVICE_NAME,
+        log_level,
+        settings.tracing.as_ref(),
+        tokio_console_layer,
+    )
+    .map_err(|error| error as Box<dyn std::error::Error>)?;
+
+    #[cfg(feature = "tokio_console")]
+    info!("Tokio console tracing is enabled");
 
     info!("The In-Vehicle Digital Twin Service has started.");
 
-    // Load the config.
-    let settings = invehicle_digital_twin_config::load_settings();
     let invehicle_digital_twin_authority = settings.invehicle_digital_twin_authority;
     let chariott_uri_option = settings.chariott_uri;
+    let tls_settings = settings.tls;
 
     let addr: SocketAddr = invehicle_digital_twin_authority.parse()?;
 
-    let invehicle_digital_twin_address = format!("http://{invehicle_digital_twin_authority}"); // Devskim: ignore DS137138
+    let invehicle_digital_twin_address =
+        format!("{}://{invehicle_digital_twin_authority}", get_uri_scheme(tls_settings.as_ref()));
+    let incoming = TcpIncoming::new(addr, false, None).map_err(|error| {
+        error!("Unable to listen on '{addr}': '{error}'");
+        error as Box<dyn std::error::Error>
+    })?;
     info!("The HTTP server is listening on address '{invehicle_digital_twin_address}'");
 
-    // Register the invehicle digital twin service with Chariott if Chariott's URI was provided in the config.
-    if chariott_uri_option.is_some() {
-        let response = register_invehicle_digital_twin_service_with_chariott(
-            &chariott_uri_option.unwrap(),
-            &invehicle_digital_twin_address,
-        )
-        .await;
-        if let Err(error) = response {
-            error!("Failed to register this service with Chariott: '{error}'");
-            return Err(error)?;
+    // Load the DTDL models if registrations are to be validated against them.
+    let mut dtdl_validator = DtdlValidator::new(settings.dtdl_validation_mode.unwrap_or_default());
+    if dtdl_validator.mode != DtdlValidationMode::Off {
+        let Some(dtdl_path) = settings.dtdl_path else {
+            error!("The dtdl_path setting is required when DTDL validation is enabled.");
+            return Err("Missing the dtdl_path setting")?;
+        };
+        dtdl_validator.load_directory(Path::new(&dtdl_path)).map_err(|error| {
+            error!("Failed to load the DTDL models from '{dtdl_path}': '{error}'");
+            error as Box<dyn std::error::Error>
+        })?;
+        info!("Registrations are validated against the DTDL models in '{dtdl_path}'.");
+    }
+
+    let invehicle_digital_twin_impl = InvehicleDigitalTwinImpl {
+        filter_unhealthy_endpoints: settings.filter_unhealthy_endpoints.unwrap_or(false),
+        dtdl_validator: Arc::new(dtdl_validator),
+        ..InvehicleDigitalTwinImpl::new()
+    };
+
+    // Restore the registry and start persisting it, if a snapshot path was provided in the config.
+    if let Some(registry_snapshot_path) = settings.registry_snapshot_path {
+        let registry_store: Arc<dyn RegistryStore> =
+            Arc::new(JsonFileRegistryStore::new(registry_snapshot_path));
+
+        match registry_store.load() {
+            Ok(Some(snapshot)) => invehicle_digital_twin_impl.restore(snapshot),
+            Ok(None) => info!("There is no registry snapshot to restore."),
+            Err(error) => error!("Failed to load the registry snapshot: '{error}'"),
         }
-        info!("This service is now registered with Chariott.");
+
+        let registry_snapshot_interval = Duration::from_secs(
+            settings
+                .registry_snapshot_interval_secs
+                .unwrap_or(DEFAULT_REGISTRY_SNAPSHOT_INTERVAL_SECS),
+        );
+        start_registry_snapshotter(
+            invehicle_digital_twin_impl.clone(),
+            registry_store,
+            registry_snapshot_interval,
+        );
+    } else {
+        info!("The registry is not persisted.");
+    }
+
+    // Start evicting the endpoints whose lease has expired.
+    let lease_eviction_interval = Duration::from_secs(
+        settings.lease_eviction_interval_secs.unwrap_or(DEFAULT_LEASE_EVICTION_INTERVAL_SECS),
+    );
+    start_lease_reaper(invehicle_digital_twin_impl.clone(), lease_eviction_interval);
+
+    // Start probing the registered endpoints if a probe interval was provided in the config.
+    if let Some(endpoint_health_probe_interval_secs) = settings.endpoint_health_probe_interval_secs
+    {
+        let endpoint_health_probe_timeout = Duration::from_millis(
+            settings
+                .endpoint_health_probe_timeout_ms
+                .unwrap_or(DEFAULT_ENDPOINT_HEALTH_PROBE_TIMEOUT_MS),
+        );
+        start_endpoint_health_prober(
+            invehicle_digital_twin_impl.clone(),
+            Duration::from_secs(endpoint_health_probe_interval_secs),
+            endpoint_health_probe_timeout,
+        );
     } else {
-        info!("This service is not using Chariott.");
+        info!("The registered endpoints are not probed.");
     }
 
-    let invehicle_digital_twin_impl = invehicle_digital_twin_impl::InvehicleDigitalTwinImpl {
-        entity_access_info_map: Arc::new(RwLock::new(HashMap::new())),
+    // Collect and serve the metrics if a metrics authority was provided in the config.
+    let metrics_registry = match settings.metrics_authority {
+        Some(metrics_authority) => {
+            let metrics_addr: SocketAddr = metrics_authority.parse()?;
+            let metrics_registry = Arc::new(MetricsRegistry::new());
+            invehicle_digital_twin_impl.register_metrics(&metrics_registry);
+            start_metrics_server(metrics_addr, metrics_registry.clone());
+            Some(metrics_registry)
+        }
+        None => {
+            info!("The metrics are not collected.");
+            None
+        }
     };
 
-    let base_service = InvehicleDigitalTwinServer::new(invehicle_digital_twin_impl);
+    let shutdown_timeout = Duration::from_secs(
+        settings.shutdown_timeout_secs.unwrap_or(DEFAULT_SHUTDOWN_TIMEOUT_SECS),
+    );
+
+    // Keep the invehicle digital twin service registered with Chariott if Chariott's URI was
+    // provided in the config.
+    let chariott_client = chariott_uri_option
+        .map(|chariott_uri| ChariottClient::new(&chariott_uri, tls_settings.clone()));
+    let chariott_registration_settings = ChariottRegistrationSettings {
+        reassert_interval: settings
+            .chariott_reassert_interval_secs
+            .map_or(DEFAULT_REASSERT_INTERVAL, Duration::from_secs),
+        ..Default::default()
+    };
 
-    // Build and start the grpc server.
-    build_server_and_serve(addr, base_service).await?;
+    // Build and start the grpc server, which runs until the service is asked to stop.
+    let result = serve_invehicle_digital_twin(
+        addr,
+        &invehicle_digital_twin_address,
+        invehicle_digital_twin_impl,
+        &create_module_registry(),
+        settings.modules.unwrap_or_default(),
+        chariott_client,
+        chariott_registration_settings,
+        tls_settings,
+        shutdown_timeout,
+        metrics_registry,
+        incoming,
+        shutdown_signal(),
+    )
+    .await;
+
+    // Export the remaining spans, whether or not the service stopped because of an error.
+    shutdown_tracing();
+    result?;
 
     debug!("The Digital Twin Service has completed.");
 
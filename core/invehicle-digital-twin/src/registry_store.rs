@@ -0,0 +1,164 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+use core_protobuf_data_access::invehicle_digital_twin::v1::EntityAccessInfo;
+use log::debug;
+use parking_lot::RwLock;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// A point-in-time copy of the registry that can be persisted and later restored.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct RegistrySnapshot {
+    /// The registered entities.
+    pub entity_access_info_list: Vec<EntityAccessInfo>,
+    /// The lease duration, in seconds, for each provider that holds a lease.
+    pub provider_lease_durations: HashMap<String, u32>,
+}
+
+/// Persistent storage for the registry.
+pub trait RegistryStore: Send + Sync {
+    /// Load the most recently saved snapshot. Returns `None` when nothing has been saved yet.
+    fn load(&self) -> Result<Option<RegistrySnapshot>, Box<dyn std::error::Error + Send + Sync>>;
+
+    /// Save a snapshot, replacing any previously saved snapshot.
+    ///
+    /// # Arguments
+    /// * `snapshot` - The snapshot to save.
+    fn save(
+        &self,
+        snapshot: &RegistrySnapshot,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+}
+
+/// A registry store that keeps the snapshot in memory. Intended for tests.
+#[allow(dead_code)] // The service itself only uses the JSON file store.
+#[derive(Clone, Debug, Default)]
+pub struct InMemoryRegistryStore {
+    pub snapshot: Arc<RwLock<Option<RegistrySnapshot>>>,
+}
+
+impl RegistryStore for InMemoryRegistryStore {
+    fn load(&self) -> Result<Option<RegistrySnapshot>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.snapshot.read().clone())
+    }
+
+    fn save(
+        &self,
+        snapshot: &RegistrySnapshot,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        *self.snapshot.write() = Some(snapshot.clone());
+        Ok(())
+    }
+}
+
+/// A registry store that keeps the snapshot in a JSON file.
+#[derive(Clone, Debug)]
+pub struct JsonFileRegistryStore {
+    path: PathBuf,
+}
+
+impl JsonFileRegistryStore {
+    /// Create a new JsonFileRegistryStore.
+    ///
+    /// # Arguments
+    /// * `path` - The path of the snapshot file.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        JsonFileRegistryStore { path: path.into() }
+    }
+}
+
+impl RegistryStore for JsonFileRegistryStore {
+    fn load(&self) -> Result<Option<RegistrySnapshot>, Box<dyn std::error::Error + Send + Sync>> {
+        if !self.path.exists() {
+            debug!("There is no registry snapshot at '{}'.", self.path.display());
+            return Ok(None);
+        }
+
+        let contents = fs::read_to_string(&self.path)?;
+        let snapshot: RegistrySnapshot = serde_json::from_str(&contents)?;
+
+        Ok(Some(snapshot))
+    }
+
+    fn save(
+        &self,
+        snapshot: &RegistrySnapshot,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let contents = serde_json::to_string_pretty(snapshot)?;
+
+        // Write to a temporary file first and then rename it, so that a crash part way through
+        // the write does not leave a corrupt snapshot behind.
+        let mut temp_path = self.path.clone().into_os_string();
+        temp_path.push(".tmp");
+        fs::write(&temp_path, contents)?;
+        fs::rename(&temp_path, &self.path)?;
+
+        debug!("Saved the registry snapshot to '{}'.", self.path.display());
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod registry_store_tests {
+    use super::*;
+    use core_protobuf_data_access::invehicle_digital_twin::v1::EndpointInfo;
+
+    fn create_test_snapshot() -> RegistrySnapshot {
+        let endpoint_info = EndpointInfo {
+            protocol: String::from("grpc"),
+            uri: String::from("http://[::1]:40010"), // Devskim: ignore DS137138
+            context: String::from("dtmi:sdv:Vehicle:Cabin:HVAC:AmbientAirTemperature;1"),
+            operations: vec![String::from("Subscribe"), String::from("Unsubscribe")],
+            provider_id: String::from("test_provider"),
+        };
+
+        let entity_access_info = EntityAccessInfo {
+            name: String::from("AmbientAirTemperature"),
+            id: String::from("dtmi:sdv:Vehicle:Cabin:HVAC:AmbientAirTemperature;1"),
+            description: String::from("Ambient air temperature"),
+            endpoint_info_list: vec![endpoint_info],
+            instance_id: String::new(),
+            relationship_list: vec![],
+        };
+
+        RegistrySnapshot {
+            entity_access_info_list: vec![entity_access_info],
+            provider_lease_durations: HashMap::from([(String::from("test_provider"), 30)]),
+        }
+    }
+
+    #[test]
+    fn in_memory_registry_store_test() {
+        let store = InMemoryRegistryStore::default();
+        assert_eq!(store.load().unwrap(), None);
+
+        let snapshot = create_test_snapshot();
+        assert!(store.save(&snapshot).is_ok());
+        assert_eq!(store.load().unwrap(), Some(snapshot));
+    }
+
+    #[test]
+    fn json_file_registry_store_test() {
+        let path = std::env::temp_dir()
+            .join(format!("ibeji_registry_store_test_{}.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let store = JsonFileRegistryStore::new(&path);
+        assert_eq!(store.load().unwrap(), None);
+
+        let snapshot = create_test_snapshot();
+        assert!(store.save(&snapshot).is_ok());
+
+        // A new store on the same path sees the saved snapshot, as it would after a restart.
+        let store = JsonFileRegistryStore::new(&path);
+        assert_eq!(store.load().unwrap(), Some(snapshot));
+
+        let _ = fs::remove_file(&path);
+    }
+}
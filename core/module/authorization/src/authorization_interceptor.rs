@@ -0,0 +1,754 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+use bytes::Bytes;
+use core_protobuf_data_access::invehicle_digital_twin::v1::{
+    EntityAccessInfo, FindByIdRequest, FindByIdResponse, FindResponse, GetChildrenRequest,
+    GetChildrenResponse, GetParentRequest, GetParentResponse, RegisterRequest, UnregisterRequest,
+    WatchRequest,
+};
+use log::{debug, warn};
+use prost::Message;
+use std::sync::Arc;
+use tonic::transport::server::{TcpConnectInfo, TlsConnectInfo};
+use tonic::Status;
+use x509_parser::parse_x509_certificate;
+
+use common::grpc_interceptor::{GrpcInterceptor, InterceptedRequestParts};
+
+use crate::authorization_policy::AuthorizationPolicy;
+
+/// The entities that a call acts on.
+#[derive(Debug)]
+enum CallEntities {
+    /// The entities with these ids. It is empty for a call that does not act on particular
+    /// entities.
+    Ids(Vec<String>),
+    /// Every entity whose id starts with this prefix.
+    IdPrefix(String),
+}
+
+/// Interceptor that only lets callers make the In-Vehicle Digital Twin calls that the
+/// authorization policy allows them to. The entities that Find, GetChildren and GetParent
+/// return are not known until the service responds, so their responses only keep the entities
+/// that the caller is allowed to call the method on.
+#[derive(Clone)]
+pub struct AuthorizationInterceptor {
+    policy: Arc<AuthorizationPolicy>,
+}
+
+impl AuthorizationInterceptor {
+    const INVEHICLE_DIGITAL_TWIN_SERVICE_NAME: &str = "InvehicleDigitalTwin";
+    const FIND_BY_ID_METHOD_NAME: &str = "FindById";
+    const FIND_METHOD_NAME: &str = "Find";
+    const REGISTER_METHOD_NAME: &str = "Register";
+    const UNREGISTER_METHOD_NAME: &str = "Unregister";
+    const GET_CHILDREN_METHOD_NAME: &str = "GetChildren";
+    const GET_PARENT_METHOD_NAME: &str = "GetParent";
+    const WATCH_METHOD_NAME: &str = "Watch";
+    const AUTHORIZATION_HEADER: &str = "authorization";
+    const BEARER_PREFIX: &str = "Bearer ";
+
+    /// Create a new AuthorizationInterceptor.
+    ///
+    /// # Arguments
+    /// * `policy` - The authorization policy.
+    pub fn new(policy: Arc<AuthorizationPolicy>) -> Self {
+        AuthorizationInterceptor { policy }
+    }
+
+    /// Get the caller's identity. A caller that presents a client certificate is identified by
+    /// the certificate's common name. Otherwise, a caller that presents a bearer token is
+    /// identified by the token's identity in the policy. Returns `None` for an anonymous caller.
+    ///
+    /// # Arguments
+    /// * `request_parts` - The request's headers and extensions.
+    fn get_caller_identity(
+        &self,
+        request_parts: &http::request::Parts,
+    ) -> Result<Option<String>, Status> {
+        let peer_certs = request_parts
+            .extensions
+            .get::<TlsConnectInfo<TcpConnectInfo>>()
+            .and_then(|tls_connect_info| tls_connect_info.peer_certs());
+
+        if let Some(peer_cert) = peer_certs.as_ref().and_then(|peer_certs| peer_certs.first()) {
+            let (_, cert) = parse_x509_certificate(peer_cert.get_ref()).map_err(|error| {
+                Status::unauthenticated(format!("Unable to parse the client certificate: {error}"))
+            })?;
+
+            let common_name = cert
+                .subject()
+                .iter_common_name()
+                .next()
+                .and_then(|common_name| common_name.as_str().ok())
+                .ok_or_else(|| {
+                    Status::unauthenticated("The client certificate does not have a common name")
+                })?;
+
+            return Ok(Some(common_name.to_string()));
+        }
+
+        let Some(authorization) = request_parts.headers.get(Self::AUTHORIZATION_HEADER) else {
+            return Ok(None);
+        };
+
+        let token = authorization
+            .to_str()
+            .ok()
+            .and_then(|authorization| authorization.strip_prefix(Self::BEARER_PREFIX))
+            .ok_or_else(|| {
+                Status::unauthenticated("The authorization header is not a bearer token")
+            })?;
+
+        self.policy
+            .get_token_identity(token)
+            .map(|identity| Some(identity.to_string()))
+            .ok_or_else(|| Status::unauthenticated("The bearer token is not valid"))
+    }
+
+    /// Get the entities that a request acts on. Watch acts on every entity whose id starts with
+    /// its prefix, as a change to any of them can be sent to the caller. A FindById request that
+    /// only has an instance id does not name a model, so the models of the entities that it finds
+    /// are only checked in its response.
+    ///
+    /// # Arguments
+    /// * `method_name` - The gRPC call's method name.
+    /// * `protobuf_message_bytes` - The request's protobuf messages as bytes.
+    fn get_call_entities(
+        method_name: &str,
+        protobuf_message_bytes: &Bytes,
+    ) -> Result<CallEntities, prost::DecodeError> {
+        if method_name == Self::WATCH_METHOD_NAME {
+            let id_prefix = WatchRequest::decode(&protobuf_message_bytes[..])?.id_prefix;
+            return Ok(CallEntities::IdPrefix(id_prefix));
+        }
+
+        let entity_ids = match method_name {
+            Self::FIND_BY_ID_METHOD_NAME => {
+                let id = FindByIdRequest::decode(&protobuf_message_bytes[..])?.id;
+                if id.is_empty() {
+                    vec![]
+                } else {
+                    vec![id]
+                }
+            }
+            Self::REGISTER_METHOD_NAME => RegisterRequest::decode(&protobuf_message_bytes[..])?
+                .entity_access_info_list
+                .into_iter()
+                .map(|entity_access_info| entity_access_info.id)
+                .collect(),
+            Self::UNREGISTER_METHOD_NAME => {
+                vec![UnregisterRequest::decode(&protobuf_message_bytes[..])?.id]
+            }
+            Self::GET_CHILDREN_METHOD_NAME => {
+                vec![GetChildrenRequest::decode(&protobuf_message_bytes[..])?.id]
+            }
+            Self::GET_PARENT_METHOD_NAME => {
+                vec![GetParentRequest::decode(&protobuf_message_bytes[..])?.id]
+            }
+            _ => vec![],
+        };
+
+        Ok(CallEntities::Ids(entity_ids))
+    }
+
+    /// Check that the policy allows a caller to make a call.
+    ///
+    /// # Arguments
+    /// * `identity` - The caller's identity, or `None` for an anonymous caller.
+    /// * `method_name` - The gRPC call's method name.
+    /// * `call_entities` - The entities that the call acts on.
+    fn authorize(
+        &self,
+        identity: Option<&str>,
+        method_name: &str,
+        call_entities: &CallEntities,
+    ) -> Result<(), Status> {
+        let caller = identity.unwrap_or("anonymous");
+
+        let entity_ids = match call_entities {
+            CallEntities::Ids(entity_ids) => entity_ids,
+            CallEntities::IdPrefix(id_prefix) => {
+                if !self.policy.is_allowed_for_prefix(identity, method_name, id_prefix) {
+                    return Err(Status::permission_denied(format!(
+                        "The caller '{caller}' is not allowed to call {method_name} for every \
+                        entity whose id starts with '{id_prefix}'"
+                    )));
+                }
+
+                return Ok(());
+            }
+        };
+
+        if entity_ids.is_empty() && !self.policy.is_allowed(identity, method_name, None) {
+            return Err(Status::permission_denied(format!(
+                "The caller '{caller}' is not allowed to call {method_name}"
+            )));
+        }
+
+        for entity_id in entity_ids {
+            if !self.policy.is_allowed(identity, method_name, Some(entity_id)) {
+                return Err(Status::permission_denied(format!(
+                    "The caller '{caller}' is not allowed to call {method_name} for '{entity_id}'"
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Remove the entities that the caller is not allowed to call the method on from a FindById,
+    /// Find or GetChildren response, and replace a GetParent response whose parent the caller is
+    /// not allowed to call it on with a PERMISSION_DENIED status. The entities are checked by
+    /// their model's id, as a FindById request can name an instance rather than a model. A
+    /// FindById response that is left without entities is replaced with a PERMISSION_DENIED
+    /// status too. Returns the new response.
+    ///
+    /// # Arguments
+    /// * `identity` - The caller's identity, or `None` for an anonymous caller.
+    /// * `method_name` - The gRPC call's method name.
+    /// * `protobuf_message_bytes` - The response's protobuf messages as bytes.
+    fn filter_response(
+        &self,
+        identity: Option<&str>,
+        method_name: &str,
+        protobuf_message_bytes: Bytes,
+    ) -> Result<Bytes, Status> {
+        let decode_error = |error: prost::DecodeError| {
+            Status::internal(format!("Unable to decode the response: {error}"))
+        };
+        let is_allowed = |entity_access_info: &EntityAccessInfo| {
+            self.policy.is_allowed(identity, method_name, Some(&entity_access_info.id))
+        };
+
+        let caller = identity.unwrap_or("anonymous");
+
+        let new_protobuf_message = match method_name {
+            Self::FIND_BY_ID_METHOD_NAME => {
+                let mut response =
+                    FindByIdResponse::decode(&protobuf_message_bytes[..]).map_err(decode_error)?;
+                response.entity_access_info_list.retain(is_allowed);
+                if response.entity_access_info_list.is_empty() {
+                    return Err(Status::permission_denied(format!(
+                        "The caller '{caller}' is not allowed to call {method_name} for the \
+                        entities that were found"
+                    )));
+                }
+                // The first entity is the first of the entities that the caller may see.
+                response.entity_access_info = response.entity_access_info_list.first().cloned();
+                response.encode_to_vec()
+            }
+            Self::FIND_METHOD_NAME => {
+                let mut response =
+                    FindResponse::decode(&protobuf_message_bytes[..]).map_err(decode_error)?;
+                response.entity_access_info_list.retain(is_allowed);
+                response.encode_to_vec()
+            }
+            Self::GET_CHILDREN_METHOD_NAME => {
+                let mut response = GetChildrenResponse::decode(&protobuf_message_bytes[..])
+                    .map_err(decode_error)?;
+                response.entity_access_info_list.retain(is_allowed);
+                response.encode_to_vec()
+            }
+            Self::GET_PARENT_METHOD_NAME => {
+                let response =
+                    GetParentResponse::decode(&protobuf_message_bytes[..]).map_err(decode_error)?;
+                if !response.entity_access_info.as_ref().is_some_and(is_allowed) {
+                    return Err(Status::permission_denied(format!(
+                        "The caller '{caller}' is not allowed to call {method_name} for the parent"
+                    )));
+                }
+                return Ok(protobuf_message_bytes);
+            }
+            _ => return Ok(protobuf_message_bytes),
+        };
+
+        Ok(Bytes::from(new_protobuf_message))
+    }
+}
+
+#[tonic::async_trait]
+impl GrpcInterceptor for AuthorizationInterceptor {
+    /// Is this interceptor applicable?
+    ///
+    /// # Arguments
+    /// * `service_name` - The gRPC call's service name.
+    /// * `method_name` - The gRPC call's method name.
+    fn is_applicable(&self, service_name: &str, _method_name: &str) -> bool {
+        service_name == Self::INVEHICLE_DIGITAL_TWIN_SERVICE_NAME
+    }
+
+    /// Indicates that the request must be handled.
+    fn must_handle_request(&self) -> bool {
+        true
+    }
+
+    /// Indicates that the response must be handled.
+    fn must_handle_response(&self) -> bool {
+        true
+    }
+
+    /// Handle request. Return the request unchanged when the call is allowed, and a
+    /// PERMISSION_DENIED status when it is not.
+    ///
+    /// # Arguments
+    /// * `service_name` - The gRPC call's service name.
+    /// * `method_name` - The gRPC call's method name.
+    /// * `request_parts` - The request's headers and extensions.
+    /// * `protobuf_message_bytes` - The request's protobuf messages as bytes.
+    async fn handle_request(
+        &self,
+        _service_name: &str,
+        method_name: &str,
+        request_parts: &mut http::request::Parts,
+        protobuf_message_bytes: Bytes,
+    ) -> Result<Bytes, Status> {
+        let identity = self.get_caller_identity(request_parts)?;
+
+        let call_entities =
+            Self::get_call_entities(method_name, &protobuf_message_bytes).map_err(|error| {
+                Status::invalid_argument(format!("Unable to decode the request: {error}"))
+            })?;
+
+        if let Err(status) = self.authorize(identity.as_deref(), method_name, &call_entities) {
+            warn!("Denied a call: {}", status.message());
+            return Err(status);
+        }
+
+        debug!("Allowed the caller {identity:?} to call {method_name} for {call_entities:?}.");
+
+        Ok(protobuf_message_bytes)
+    }
+
+    /// Handle response. Return the response with only the entities that the caller is allowed
+    /// to call the method on.
+    ///
+    /// # Arguments
+    /// * `service_name` - The gRPC call's service name.
+    /// * `method_name` - The gRPC call's method name.
+    /// * `response_parts` - The response's headers and the call's request.
+    /// * `protobuf_message_bytes` - The response's protobuf messages as bytes.
+    async fn handle_response(
+        &self,
+        _service_name: &str,
+        method_name: &str,
+        response_parts: &mut http::response::Parts,
+        protobuf_message_bytes: Bytes,
+    ) -> Result<Bytes, Status> {
+        if !matches!(
+            method_name,
+            Self::FIND_BY_ID_METHOD_NAME
+                | Self::FIND_METHOD_NAME
+                | Self::GET_CHILDREN_METHOD_NAME
+                | Self::GET_PARENT_METHOD_NAME
+        ) {
+            return Ok(protobuf_message_bytes);
+        }
+
+        let Some(InterceptedRequestParts(request_parts)) =
+            response_parts.extensions.get::<InterceptedRequestParts>()
+        else {
+            return Err(Status::internal("The call's request is not known"));
+        };
+
+        let identity = self.get_caller_identity(request_parts)?;
+
+        let result = self.filter_response(identity.as_deref(), method_name, protobuf_message_bytes);
+        if let Err(status) = &result {
+            warn!("Denied a response: {}", status.message());
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod authorization_interceptor_tests {
+    use super::*;
+    use crate::authorization_policy::{AuthorizationRule, BearerToken};
+    use core_protobuf_data_access::invehicle_digital_twin::v1::{FindRequest, RelationshipInfo};
+
+    fn create_interceptor() -> AuthorizationInterceptor {
+        let policy = AuthorizationPolicy {
+            bearer_tokens: vec![
+                BearerToken {
+                    token: String::from("seat_token"),
+                    identity: String::from("seat_ecu"),
+                },
+                BearerToken {
+                    token: String::from("hvac_token"),
+                    identity: String::from("hvac_app"),
+                },
+            ],
+            rules: vec![
+                AuthorizationRule {
+                    identity: String::from("seat_ecu"),
+                    operations: vec![String::from("Register")],
+                    entity_patterns: vec![String::from("dtmi:sdv:seat*")],
+                },
+                AuthorizationRule {
+                    identity: String::from("hvac_app"),
+                    operations: vec![
+                        String::from("FindById"),
+                        String::from("Find"),
+                        String::from("Watch"),
+                        String::from("GetChildren"),
+                        String::from("GetParent"),
+                    ],
+                    entity_patterns: vec![String::from("dtmi:sdv:hvac*")],
+                },
+            ],
+        };
+
+        AuthorizationInterceptor::new(Arc::new(policy))
+    }
+
+    fn create_request_parts(authorization: Option<&str>) -> http::request::Parts {
+        let mut builder = http::Request::builder();
+        if let Some(authorization) = authorization {
+            builder = builder.header("authorization", authorization);
+        }
+        builder.body(()).unwrap().into_parts().0
+    }
+
+    /// Create the parts of a response to a call, with the call's request.
+    fn create_response_parts(authorization: Option<&str>) -> http::response::Parts {
+        let mut response_parts = http::Response::new(()).into_parts().0;
+        response_parts
+            .extensions
+            .insert(InterceptedRequestParts(Arc::new(create_request_parts(authorization))));
+        response_parts
+    }
+
+    fn create_entity_access_info(entity_id: &str) -> EntityAccessInfo {
+        EntityAccessInfo {
+            name: String::new(),
+            id: entity_id.to_string(),
+            description: String::new(),
+            endpoint_info_list: vec![],
+            instance_id: String::new(),
+            relationship_list: vec![],
+        }
+    }
+
+    fn create_register_request_bytes(entity_id: &str) -> Bytes {
+        let register_request = RegisterRequest {
+            entity_access_info_list: vec![create_entity_access_info(entity_id)],
+            lease_duration_seconds: 0,
+        };
+
+        Bytes::from(register_request.encode_to_vec())
+    }
+
+    fn get_status_code(result: Result<Bytes, Status>) -> tonic::Code {
+        result.unwrap_err().code()
+    }
+
+    #[tokio::test]
+    async fn handle_request_test() {
+        let interceptor = create_interceptor();
+        let seat_request = create_register_request_bytes("dtmi:sdv:seat_massager;1");
+        let hvac_request = create_register_request_bytes("dtmi:sdv:hvac;1");
+
+        let mut seat_ecu = create_request_parts(Some("Bearer seat_token"));
+        assert!(interceptor
+            .handle_request("InvehicleDigitalTwin", "Register", &mut seat_ecu, seat_request.clone())
+            .await
+            .is_ok());
+        assert_eq!(
+            get_status_code(
+                interceptor
+                    .handle_request("InvehicleDigitalTwin", "Register", &mut seat_ecu, hvac_request)
+                    .await
+            ),
+            tonic::Code::PermissionDenied
+        );
+
+        let mut anonymous = create_request_parts(None);
+        assert_eq!(
+            get_status_code(
+                interceptor
+                    .handle_request(
+                        "InvehicleDigitalTwin",
+                        "Register",
+                        &mut anonymous,
+                        seat_request.clone()
+                    )
+                    .await
+            ),
+            tonic::Code::PermissionDenied
+        );
+
+        let mut unknown_token = create_request_parts(Some("Bearer other_token"));
+        assert_eq!(
+            get_status_code(
+                interceptor
+                    .handle_request(
+                        "InvehicleDigitalTwin",
+                        "Register",
+                        &mut unknown_token,
+                        seat_request
+                    )
+                    .await
+            ),
+            tonic::Code::Unauthenticated
+        );
+
+        assert_eq!(
+            get_status_code(
+                interceptor
+                    .handle_request(
+                        "InvehicleDigitalTwin",
+                        "Register",
+                        &mut seat_ecu,
+                        Bytes::from_static(&[0xff, 0xff])
+                    )
+                    .await
+            ),
+            tonic::Code::InvalidArgument
+        );
+    }
+
+    #[tokio::test]
+    async fn find_outside_patterns_test() {
+        let interceptor = create_interceptor();
+        let find_request = Bytes::from(FindRequest::default().encode_to_vec());
+
+        let mut hvac_app = create_request_parts(Some("Bearer hvac_token"));
+        assert!(interceptor
+            .handle_request("InvehicleDigitalTwin", "Find", &mut hvac_app, find_request.clone())
+            .await
+            .is_ok());
+
+        let mut seat_ecu = create_request_parts(Some("Bearer seat_token"));
+        assert_eq!(
+            get_status_code(
+                interceptor
+                    .handle_request("InvehicleDigitalTwin", "Find", &mut seat_ecu, find_request)
+                    .await
+            ),
+            tonic::Code::PermissionDenied
+        );
+
+        // The seat entity is outside the caller's patterns, so it is removed from the response.
+        let find_response = FindResponse {
+            entity_access_info_list: vec![
+                create_entity_access_info("dtmi:sdv:hvac:fan;1"),
+                create_entity_access_info("dtmi:sdv:seat_massager;1"),
+            ],
+            next_page_token: String::from("next"),
+        };
+        let mut response_parts = create_response_parts(Some("Bearer hvac_token"));
+        let response_bytes = interceptor
+            .handle_response(
+                "InvehicleDigitalTwin",
+                "Find",
+                &mut response_parts,
+                Bytes::from(find_response.encode_to_vec()),
+            )
+            .await
+            .unwrap();
+        let find_response = FindResponse::decode(response_bytes).unwrap();
+        assert_eq!(find_response.entity_access_info_list.len(), 1);
+        assert_eq!(find_response.entity_access_info_list[0].id, "dtmi:sdv:hvac:fan;1");
+        assert_eq!(find_response.next_page_token, "next");
+    }
+
+    #[tokio::test]
+    async fn find_by_id_outside_patterns_test() {
+        let interceptor = create_interceptor();
+        let create_find_by_id_request = |id: &str, instance_id: &str| {
+            let find_by_id_request =
+                FindByIdRequest { id: id.to_string(), instance_id: instance_id.to_string() };
+            Bytes::from(find_by_id_request.encode_to_vec())
+        };
+
+        let mut hvac_app = create_request_parts(Some("Bearer hvac_token"));
+        assert!(interceptor
+            .handle_request(
+                "InvehicleDigitalTwin",
+                "FindById",
+                &mut hvac_app,
+                create_find_by_id_request("dtmi:sdv:hvac:fan;1", "")
+            )
+            .await
+            .is_ok());
+        assert_eq!(
+            get_status_code(
+                interceptor
+                    .handle_request(
+                        "InvehicleDigitalTwin",
+                        "FindById",
+                        &mut hvac_app,
+                        create_find_by_id_request("dtmi:sdv:seat_massager;1", "")
+                    )
+                    .await
+            ),
+            tonic::Code::PermissionDenied
+        );
+
+        // A request for an instance does not name its model, so it is checked in the response.
+        assert!(interceptor
+            .handle_request(
+                "InvehicleDigitalTwin",
+                "FindById",
+                &mut hvac_app,
+                create_find_by_id_request("", "front_left_seat")
+            )
+            .await
+            .is_ok());
+
+        let create_instance = |entity_id: &str, instance_id: &str| EntityAccessInfo {
+            instance_id: instance_id.to_string(),
+            ..create_entity_access_info(entity_id)
+        };
+
+        // An instance whose model is outside the caller's patterns is not returned.
+        let seat_instance = create_instance("dtmi:sdv:seat_massager;1", "front_left_seat");
+        let find_by_id_response = FindByIdResponse {
+            entity_access_info: Some(seat_instance.clone()),
+            entity_access_info_list: vec![seat_instance.clone()],
+        };
+        let mut response_parts = create_response_parts(Some("Bearer hvac_token"));
+        assert_eq!(
+            get_status_code(
+                interceptor
+                    .handle_response(
+                        "InvehicleDigitalTwin",
+                        "FindById",
+                        &mut response_parts,
+                        Bytes::from(find_by_id_response.encode_to_vec())
+                    )
+                    .await
+            ),
+            tonic::Code::PermissionDenied
+        );
+
+        // Nor is it among the entities that are returned with those that the caller may see.
+        let fan_instance = create_instance("dtmi:sdv:hvac:fan;1", "front_fan");
+        let find_by_id_response = FindByIdResponse {
+            entity_access_info: Some(seat_instance.clone()),
+            entity_access_info_list: vec![seat_instance, fan_instance.clone()],
+        };
+        let response_bytes = interceptor
+            .handle_response(
+                "InvehicleDigitalTwin",
+                "FindById",
+                &mut response_parts,
+                Bytes::from(find_by_id_response.encode_to_vec()),
+            )
+            .await
+            .unwrap();
+        let find_by_id_response = FindByIdResponse::decode(response_bytes).unwrap();
+        assert_eq!(find_by_id_response.entity_access_info, Some(fan_instance.clone()));
+        assert_eq!(find_by_id_response.entity_access_info_list, vec![fan_instance]);
+    }
+
+    #[tokio::test]
+    async fn watch_outside_patterns_test() {
+        let interceptor = create_interceptor();
+        let create_watch_request = |id_prefix: &str| {
+            let watch_request = WatchRequest {
+                name: String::new(),
+                id_prefix: id_prefix.to_string(),
+                include_existing: true,
+            };
+            Bytes::from(watch_request.encode_to_vec())
+        };
+
+        let mut hvac_app = create_request_parts(Some("Bearer hvac_token"));
+        assert!(interceptor
+            .handle_request(
+                "InvehicleDigitalTwin",
+                "Watch",
+                &mut hvac_app,
+                create_watch_request("dtmi:sdv:hvac")
+            )
+            .await
+            .is_ok());
+
+        // A prefix outside the caller's patterns, or no prefix, would send other entities too.
+        for id_prefix in ["dtmi:sdv:seat", "dtmi:sdv:", ""] {
+            assert_eq!(
+                get_status_code(
+                    interceptor
+                        .handle_request(
+                            "InvehicleDigitalTwin",
+                            "Watch",
+                            &mut hvac_app,
+                            create_watch_request(id_prefix)
+                        )
+                        .await
+                ),
+                tonic::Code::PermissionDenied
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn get_children_and_get_parent_outside_patterns_test() {
+        let interceptor = create_interceptor();
+
+        let get_children_response = GetChildrenResponse {
+            entity_access_info_list: vec![
+                create_entity_access_info("dtmi:sdv:hvac:fan;1"),
+                create_entity_access_info("dtmi:sdv:seat_massager;1"),
+            ],
+        };
+        let mut response_parts = create_response_parts(Some("Bearer hvac_token"));
+        let response_bytes = interceptor
+            .handle_response(
+                "InvehicleDigitalTwin",
+                "GetChildren",
+                &mut response_parts,
+                Bytes::from(get_children_response.encode_to_vec()),
+            )
+            .await
+            .unwrap();
+        let get_children_response = GetChildrenResponse::decode(response_bytes).unwrap();
+        assert_eq!(get_children_response.entity_access_info_list.len(), 1);
+        assert_eq!(get_children_response.entity_access_info_list[0].id, "dtmi:sdv:hvac:fan;1");
+
+        let create_get_parent_response = |parent_id: &str| {
+            let get_parent_response = GetParentResponse {
+                entity_access_info: Some(create_entity_access_info(parent_id)),
+                relationship_info: Some(RelationshipInfo {
+                    name: String::from("has_fan"),
+                    relationship_id: String::from("dtmi:sdv:has_fan;1"),
+                    target_id: String::from("dtmi:sdv:hvac:fan;1"),
+                    provider_id: String::new(),
+                }),
+            };
+            Bytes::from(get_parent_response.encode_to_vec())
+        };
+
+        let parent_response = create_get_parent_response("dtmi:sdv:hvac;1");
+        assert_eq!(
+            interceptor
+                .handle_response(
+                    "InvehicleDigitalTwin",
+                    "GetParent",
+                    &mut response_parts,
+                    parent_response.clone()
+                )
+                .await
+                .unwrap(),
+            parent_response
+        );
+        assert_eq!(
+            get_status_code(
+                interceptor
+                    .handle_response(
+                        "InvehicleDigitalTwin",
+                        "GetParent",
+                        &mut response_parts,
+                        create_get_parent_response("dtmi:sdv:seat;1")
+                    )
+                    .await
+            ),
+            tonic::Code::PermissionDenied
+        );
+    }
+}
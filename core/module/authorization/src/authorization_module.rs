@@ -0,0 +1,76 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+use common::grpc_interceptor::GrpcInterceptor;
+use common::grpc_module::GrpcModule;
+use common::grpc_module_registry::{GrpcModulePriority, GrpcModuleRegistry};
+use common::utils::load_settings;
+use log::info;
+use std::sync::Arc;
+use tonic::transport::server::RoutesBuilder;
+use tonic::Status;
+
+use crate::authorization_interceptor::AuthorizationInterceptor;
+use crate::authorization_policy::AuthorizationPolicy;
+
+/// The name that the module is enabled with in the service's settings.
+pub const MODULE_NAME: &str = "authorization";
+
+const CONFIG_FILENAME: &str = "authorization_policy";
+
+/// Module that authorizes the calls to the In-Vehicle Digital Twin service.
+#[derive(Clone, Debug)]
+pub struct AuthorizationModule {
+    /// The authorization policy.
+    pub policy: Arc<AuthorizationPolicy>,
+}
+
+impl AuthorizationModule {
+    /// Creates a new authorization module object.
+    pub fn new() -> Result<Self, Status> {
+        let policy = load_settings::<AuthorizationPolicy>(CONFIG_FILENAME).map_err(|error| {
+            Status::internal(format!(
+                "Unable to load the 'Authorization' policy with error: {error}."
+            ))
+        })?;
+
+        info!(
+            "Loaded an authorization policy with {} bearer tokens and {} rules.",
+            policy.bearer_tokens.len(),
+            policy.rules.len()
+        );
+
+        Ok(AuthorizationModule { policy: Arc::new(policy) })
+    }
+
+    /// Registers the factory for this module under its name. It is a security module, so its
+    /// interceptor handles calls before those of the other modules.
+    ///
+    /// # Arguments
+    /// * `registry` - The registry of the modules that can be enabled.
+    pub fn register(registry: &mut GrpcModuleRegistry) {
+        registry.register(MODULE_NAME, GrpcModulePriority::Security, || async {
+            Ok(Box::new(AuthorizationModule::new()?) as Box<dyn GrpcModule>)
+        });
+    }
+
+    /// Creates a new authorization interceptor that uses this module's policy.
+    pub fn create_interceptor(&self) -> AuthorizationInterceptor {
+        AuthorizationInterceptor::new(self.policy.clone())
+    }
+}
+
+impl GrpcModule for AuthorizationModule {
+    /// Adds the gRPC services for this module to the server builder.
+    /// This module only has an interceptor, so it does not add any services.
+    ///
+    /// # Arguments
+    /// * `builder` - A tonic::RoutesBuilder that contains the grpc services to build.
+    fn add_grpc_services(&self, _builder: &mut RoutesBuilder) {}
+
+    /// Creates the interceptor that enforces this module's policy.
+    fn create_interceptors(&self) -> Vec<Box<dyn GrpcInterceptor + Send>> {
+        vec![Box::new(self.create_interceptor())]
+    }
+}
@@ -0,0 +1,231 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+use serde_derive::Deserialize;
+
+/// The pattern that matches any identity, operation or entity id.
+const WILDCARD: &str = "*";
+
+/// Maps a bearer token to the identity of the callers that present it.
+#[derive(Clone, Debug, Deserialize)]
+pub struct BearerToken {
+    /// The token.
+    pub token: String,
+    /// The identity of the callers that present the token.
+    pub identity: String,
+}
+
+/// A rule that allows an identity to call operations on the entities that match its patterns.
+#[derive(Clone, Debug, Deserialize)]
+pub struct AuthorizationRule {
+    /// The identity that the rule applies to, or "*" for any caller, including anonymous callers.
+    pub identity: String,
+    /// The In-Vehicle Digital Twin methods that the rule allows, such as "Register", or "*" for
+    /// all of them.
+    pub operations: Vec<String>,
+    /// The patterns of the entity ids that the rule allows, in which "*" matches any characters.
+    pub entity_patterns: Vec<String>,
+}
+
+/// The authorization policy. A call is denied unless a rule allows it.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct AuthorizationPolicy {
+    /// The bearer tokens that identify callers that do not present a client certificate.
+    #[serde(default)]
+    pub bearer_tokens: Vec<BearerToken>,
+    /// The rules that allow calls.
+    #[serde(default)]
+    pub rules: Vec<AuthorizationRule>,
+}
+
+impl AuthorizationPolicy {
+    /// Get the identity for a bearer token. Returns `None` when the token is not known.
+    ///
+    /// # Arguments
+    /// * `token` - The bearer token.
+    pub fn get_token_identity(&self, token: &str) -> Option<&str> {
+        self.bearer_tokens
+            .iter()
+            .find(|bearer_token| bearer_token.token == token)
+            .map(|bearer_token| bearer_token.identity.as_str())
+    }
+
+    /// Is a caller allowed to call an operation on an entity?
+    /// An operation that does not act on a particular entity, such as Find, is allowed when any
+    /// rule for the caller allows the operation.
+    ///
+    /// # Arguments
+    /// * `identity` - The caller's identity, or `None` for an anonymous caller.
+    /// * `operation` - The method that is called.
+    /// * `entity_id` - The id of the entity that the operation acts on, if any.
+    pub fn is_allowed(
+        &self,
+        identity: Option<&str>,
+        operation: &str,
+        entity_id: Option<&str>,
+    ) -> bool {
+        self.get_rules(identity, operation).any(|rule| match entity_id {
+            Some(entity_id) => rule
+                .entity_patterns
+                .iter()
+                .any(|entity_pattern| matches_pattern(entity_pattern, entity_id)),
+            None => true,
+        })
+    }
+
+    /// Is a caller allowed to call an operation on every entity whose id starts with a prefix?
+    /// This is the case when one of the caller's patterns for the operation ends with "*" and
+    /// matches the prefix, as that pattern then matches the prefix followed by anything.
+    ///
+    /// # Arguments
+    /// * `identity` - The caller's identity, or `None` for an anonymous caller.
+    /// * `operation` - The method that is called.
+    /// * `id_prefix` - The prefix of the ids of the entities that the operation acts on.
+    pub fn is_allowed_for_prefix(
+        &self,
+        identity: Option<&str>,
+        operation: &str,
+        id_prefix: &str,
+    ) -> bool {
+        self.get_rules(identity, operation).any(|rule| {
+            rule.entity_patterns.iter().any(|entity_pattern| {
+                entity_pattern.ends_with(WILDCARD) && matches_pattern(entity_pattern, id_prefix)
+            })
+        })
+    }
+
+    /// Get the rules that allow a caller to call an operation, on some entities.
+    ///
+    /// # Arguments
+    /// * `identity` - The caller's identity, or `None` for an anonymous caller.
+    /// * `operation` - The method that is called.
+    fn get_rules<'a>(
+        &'a self,
+        identity: Option<&'a str>,
+        operation: &'a str,
+    ) -> impl Iterator<Item = &'a AuthorizationRule> {
+        self.rules.iter().filter(move |rule| {
+            (rule.identity == WILDCARD || Some(rule.identity.as_str()) == identity)
+                && rule
+                    .operations
+                    .iter()
+                    .any(|rule_operation| rule_operation == WILDCARD || rule_operation == operation)
+        })
+    }
+}
+
+/// Does a value match a pattern, in which "*" matches any sequence of characters?
+///
+/// # Arguments
+/// * `pattern` - The pattern.
+/// * `value` - The value to match.
+pub fn matches_pattern(pattern: &str, value: &str) -> bool {
+    let mut parts = pattern.split(WILDCARD);
+
+    let Some(mut remaining) = value.strip_prefix(parts.next().unwrap_or_default()) else {
+        return false;
+    };
+
+    let parts: Vec<&str> = parts.collect();
+    let Some((last_part, middle_parts)) = parts.split_last() else {
+        // The pattern does not have a wildcard, so the value must match it exactly.
+        return remaining.is_empty();
+    };
+
+    for part in middle_parts {
+        match remaining.find(part) {
+            Some(index) => remaining = &remaining[index + part.len()..],
+            None => return false,
+        }
+    }
+
+    remaining.ends_with(last_part)
+}
+
+#[cfg(test)]
+mod authorization_policy_tests {
+    use super::*;
+
+    #[test]
+    fn matches_pattern_test() {
+        assert!(matches_pattern("dtmi:sdv:seat*", "dtmi:sdv:seat_massager;1"));
+        assert!(matches_pattern("dtmi:sdv:seat*", "dtmi:sdv:seat"));
+        assert!(!matches_pattern("dtmi:sdv:seat*", "dtmi:sdv:hvac;1"));
+        assert!(matches_pattern("*", "dtmi:sdv:hvac;1"));
+        assert!(matches_pattern("dtmi:sdv:*;1", "dtmi:sdv:hvac:ambient_air_temperature;1"));
+        assert!(!matches_pattern("dtmi:sdv:*;1", "dtmi:sdv:hvac;2"));
+        assert!(matches_pattern("dtmi:*:hvac*;1", "dtmi:sdv:hvac:fan;1"));
+        assert!(matches_pattern("dtmi:sdv:hvac;1", "dtmi:sdv:hvac;1"));
+        assert!(!matches_pattern("dtmi:sdv:hvac;1", "dtmi:sdv:hvac;10"));
+        assert!(!matches_pattern("a*a", "a"));
+    }
+
+    #[test]
+    fn is_allowed_test() {
+        let policy = AuthorizationPolicy {
+            bearer_tokens: vec![BearerToken {
+                token: String::from("seat_token"),
+                identity: String::from("seat_ecu"),
+            }],
+            rules: vec![
+                AuthorizationRule {
+                    identity: String::from("seat_ecu"),
+                    operations: vec![String::from("Register"), String::from("Unregister")],
+                    entity_patterns: vec![String::from("dtmi:sdv:seat*")],
+                },
+                AuthorizationRule {
+                    identity: String::from("*"),
+                    operations: vec![String::from("FindById"), String::from("Find")],
+                    entity_patterns: vec![String::from("*")],
+                },
+            ],
+        };
+
+        assert_eq!(policy.get_token_identity("seat_token"), Some("seat_ecu"));
+        assert_eq!(policy.get_token_identity("other_token"), None);
+
+        let seat_id = Some("dtmi:sdv:seat_massager;1");
+        let hvac_id = Some("dtmi:sdv:hvac;1");
+
+        assert!(policy.is_allowed(Some("seat_ecu"), "Register", seat_id));
+        assert!(!policy.is_allowed(Some("seat_ecu"), "Register", hvac_id));
+        assert!(!policy.is_allowed(Some("hvac_ecu"), "Register", seat_id));
+        assert!(!policy.is_allowed(None, "Register", seat_id));
+        assert!(policy.is_allowed(None, "FindById", hvac_id));
+        assert!(policy.is_allowed(Some("hvac_ecu"), "Find", None));
+        assert!(!policy.is_allowed(Some("hvac_ecu"), "RenewLease", None));
+    }
+
+    #[test]
+    fn is_allowed_for_prefix_test() {
+        let policy = AuthorizationPolicy {
+            bearer_tokens: vec![],
+            rules: vec![
+                AuthorizationRule {
+                    identity: String::from("hvac_app"),
+                    operations: vec![String::from("Watch")],
+                    entity_patterns: vec![
+                        String::from("dtmi:sdv:hvac*"),
+                        String::from("dtmi:sdv:seat;1"),
+                    ],
+                },
+                AuthorizationRule {
+                    identity: String::from("diagnostics_app"),
+                    operations: vec![String::from("*")],
+                    entity_patterns: vec![String::from("*")],
+                },
+            ],
+        };
+
+        assert!(policy.is_allowed_for_prefix(Some("hvac_app"), "Watch", "dtmi:sdv:hvac"));
+        assert!(policy.is_allowed_for_prefix(Some("hvac_app"), "Watch", "dtmi:sdv:hvac:fan"));
+        assert!(!policy.is_allowed_for_prefix(Some("hvac_app"), "Watch", "dtmi:sdv:"));
+        assert!(!policy.is_allowed_for_prefix(Some("hvac_app"), "Watch", ""));
+        // A pattern without a trailing wildcard does not match the ids that extend the prefix.
+        assert!(!policy.is_allowed_for_prefix(Some("hvac_app"), "Watch", "dtmi:sdv:seat;1"));
+        assert!(!policy.is_allowed_for_prefix(Some("hvac_app"), "Find", "dtmi:sdv:hvac"));
+        assert!(policy.is_allowed_for_prefix(Some("diagnostics_app"), "Watch", ""));
+        assert!(!policy.is_allowed_for_prefix(None, "Watch", "dtmi:sdv:hvac"));
+    }
+}
@@ -0,0 +1,7 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+pub mod authorization_interceptor;
+pub mod authorization_module;
+pub mod authorization_policy;
@@ -3,42 +3,205 @@
 // SPDX-License-Identifier: MIT
 
 use bytes::Bytes;
-use core_protobuf_data_access::invehicle_digital_twin::{self, v1::RegisterRequest};
-use log::{info, warn};
-use parking_lot::RwLock;
+use core_protobuf_data_access::invehicle_digital_twin::v1::{
+    RegisterRequest, UnregisterRequest, UnregisterResponse,
+};
+use log::{error, info};
 use prost::Message;
-use std::{collections::HashMap, error::Error, sync::Arc};
+use std::collections::HashMap;
+use tonic::Status;
 
-use common::grpc_interceptor::GrpcInterceptor;
+use common::grpc_interceptor::{
+    GrpcInterceptor, InterceptedRequestMessage, InterceptedRequestParts, ReceivedRequestMessage,
+};
+use common::utils::get_entity_key;
 
-use crate::managed_subscribe_store::{CallbackInfo, EntityMetadata, ManagedSubscribeStore};
+use crate::managed_subscribe_module::ManagedSubscribeModule;
+use crate::managed_subscribe_store::{CallbackInfo, EntityMetadata};
 
-/// Interceptor for injecting a managed subscribe endpoint for providers.
+/// Interceptor for injecting a managed subscribe endpoint for providers. It handles the register
+/// and unregister responses as well as their requests, so that an entity is only added to the
+/// module once the service has registered it, and only removed from the module once the service
+/// has unregistered its managed subscribe endpoint.
 #[derive(Clone)]
 pub struct ManagedSubscribeInterceptor {
-    service_uri: String,
-    store: Arc<RwLock<ManagedSubscribeStore>>,
+    module: ManagedSubscribeModule,
 }
 
 impl ManagedSubscribeInterceptor {
     const INVEHICLE_DIGITAL_TWIN_SERVICE_NAME: &str = "InvehicleDigitalTwin";
     const REGISTER_METHOD_NAME: &str = "Register";
+    const UNREGISTER_METHOD_NAME: &str = "Unregister";
     const MANAGED_SUBSCRIBE_OPERATION: &str = "ManagedSubscribe";
 
-    pub fn new(service_uri: &str, store: Arc<RwLock<ManagedSubscribeStore>>) -> Self {
-        ManagedSubscribeInterceptor { service_uri: service_uri.to_string(), store }
+    /// Create a new ManagedSubscribeInterceptor.
+    ///
+    /// # Arguments
+    /// * `module` - The module whose store and Managed Subscribe service the interceptor uses.
+    pub fn new(module: ManagedSubscribeModule) -> Self {
+        ManagedSubscribeInterceptor { module }
+    }
+
+    /// Creates the gRPC interceptors for the register and unregister calls.
+    pub fn create_grpc_interceptors(&self) -> Vec<Box<dyn GrpcInterceptor + Send>> {
+        vec![Box::new(self.clone())]
+    }
+
+    /// Get the callbacks of the entities in a register request that support the managed
+    /// subscribe operation. The callbacks are keyed by the same key as the entities in the
+    /// registry, so that each instance of a model has its own callback.
+    ///
+    /// # Arguments
+    /// * `register_request` - The register request, as the provider sent it.
+    fn get_managed_subscribe_callbacks(
+        register_request: &RegisterRequest,
+    ) -> Vec<(String, CallbackInfo)> {
+        register_request
+            .entity_access_info_list
+            .iter()
+            .filter_map(|entity| {
+                let endpoint = entity.endpoint_info_list.iter().find(|endpoint| {
+                    endpoint.operations.contains(&Self::MANAGED_SUBSCRIBE_OPERATION.to_string())
+                })?;
+                let entity_key = get_entity_key(&entity.id, &entity.instance_id).to_string();
+                let callback =
+                    CallbackInfo { uri: endpoint.uri.clone(), protocol: endpoint.protocol.clone() };
+
+                Some((entity_key, callback))
+            })
+            .collect()
+    }
+
+    /// Handle a register request. Injects the managed subscribe endpoint for entities that
+    /// support the managed subscribe operation. The providers' callbacks are only added to the
+    /// module once the service has registered the entities.
+    ///
+    /// # Arguments
+    /// * `register_request` - The register request.
+    fn handle_register_request(
+        &self,
+        register_request: RegisterRequest,
+    ) -> Result<RegisterRequest, Status> {
+        info!("register_request = {:?}", register_request);
+
+        let mut entities = register_request.entity_access_info_list;
+
+        for entity in &mut entities {
+            let mut endpoints = entity.endpoint_info_list.clone();
+
+            for endpoint in &mut endpoints {
+                if endpoint.operations.contains(&Self::MANAGED_SUBSCRIBE_OPERATION.to_string()) {
+                    // Set endpoint information to the managed subscribe module.
+                    endpoint.uri = self.module.service_uri.clone();
+                    endpoint.protocol = "grpc".to_string();
+                    endpoint.operations = vec![Self::MANAGED_SUBSCRIBE_OPERATION.to_string()];
+                    endpoint.context = "GetSubscriptionInfo".to_string();
+
+                    break;
+                }
+            }
+
+            // Add the modified endpoint info list back to the entity access info object.
+            entity.endpoint_info_list = endpoints;
+        }
+
+        // Construct modified register request.
+        Ok(RegisterRequest { entity_access_info_list: entities, ..register_request })
+    }
+
+    /// Handle a register response. Adds the entities that support the managed subscribe
+    /// operation to the module, with their providers' callbacks. A call that failed does not have
+    /// a response message, so a rejected registration leaves the module unchanged.
+    ///
+    /// # Arguments
+    /// * `register_request` - The register request, as the provider sent it.
+    fn handle_register_response(&self, register_request: &RegisterRequest) {
+        let callbacks = Self::get_managed_subscribe_callbacks(register_request);
+
+        let mut store_lock = self.module.store.write();
+        for (entity_key, callback) in callbacks {
+            info!("add entity metadata with key: {entity_key}, callback: {}", callback.uri);
+
+            let entity_metadata = EntityMetadata { callback, topics: HashMap::new() };
+            store_lock.add_entity(&entity_key, entity_metadata);
+        }
+    }
+
+    /// Handle an unregister request. Unregisters the module's endpoint along with the provider's
+    /// callback.
+    ///
+    /// # Arguments
+    /// * `unregister_request` - The unregister request.
+    fn handle_unregister_request(
+        &self,
+        mut unregister_request: UnregisterRequest,
+    ) -> Result<UnregisterRequest, Status> {
+        info!("unregister_request = {:?}", unregister_request);
+
+        let entity_key = get_entity_key(&unregister_request.id, &unregister_request.instance_id);
+
+        let callback_uri = {
+            let store_lock = self.module.store.read();
+            store_lock
+                .get_entity_metadata(entity_key)
+                .map(|entity_metadata| entity_metadata.callback.uri.clone())
+        };
+
+        if let Some(callback_uri) = callback_uri {
+            let endpoint_uri_list = &mut unregister_request.endpoint_uri_list;
+
+            // The provider only knows its own callback uri, but the module's uri was registered in
+            // its place, so it needs to be unregistered as well.
+            if endpoint_uri_list.contains(&callback_uri)
+                && !endpoint_uri_list.contains(&self.module.service_uri)
+            {
+                endpoint_uri_list.push(self.module.service_uri.clone());
+            }
+        }
+
+        Ok(unregister_request)
+    }
+
+    /// Handle an unregister response. Removes the entity from the module, along with its topics,
+    /// when the service has removed the entity or its managed subscribe endpoint. A call that
+    /// failed does not have a response message, so it leaves the entity in the module.
+    ///
+    /// # Arguments
+    /// * `unregister_request` - The unregister request, as the service was given it.
+    /// * `unregister_response` - The unregister response.
+    async fn handle_unregister_response(
+        &self,
+        unregister_request: &UnregisterRequest,
+        unregister_response: &UnregisterResponse,
+    ) {
+        let is_managed_subscribe_endpoint_removed = unregister_response.entity_removed
+            || unregister_response
+                .endpoint_info_list
+                .iter()
+                .any(|endpoint_info| endpoint_info.uri == self.module.service_uri);
+
+        if is_managed_subscribe_endpoint_removed {
+            let entity_key =
+                get_entity_key(&unregister_request.id, &unregister_request.instance_id);
+
+            if let Err(status) = self.module.remove_entity(entity_key).await {
+                error!("Unable to remove the entity with key {entity_key}: {status}");
+            }
+        }
     }
 }
 
+#[tonic::async_trait]
 impl GrpcInterceptor for ManagedSubscribeInterceptor {
-    /// Is this interceptor applicable?
+    /// Is this interceptor applicable? It is applicable to the register and unregister calls.
     ///
     /// # Arguments
     /// * `service_name` - The gRPC call's service name.
     /// * `method_name` - The gRPC call's method name.
     fn is_applicable(&self, service_name: &str, method_name: &str) -> bool {
         service_name == Self::INVEHICLE_DIGITAL_TWIN_SERVICE_NAME
-            && method_name == Self::REGISTER_METHOD_NAME
+            && (method_name == Self::REGISTER_METHOD_NAME
+                || method_name == Self::UNREGISTER_METHOD_NAME)
     }
 
     /// Indicates that the request must be handled.
@@ -48,7 +211,7 @@ impl GrpcInterceptor for ManagedSubscribeInterceptor {
 
     /// Indicates that the response must be handled.
     fn must_handle_response(&self) -> bool {
-        false
+        true
     }
 
     /// Handle request. Return the new request.
@@ -56,82 +219,354 @@ impl GrpcInterceptor for ManagedSubscribeInterceptor {
     /// # Arguments
     /// * `service_name` - The gRPC call's service name.
     /// * `method_name` - The gRPC call's method name.
-    /// * `protobuf_message_bytes` - The request's protobuf messages as bytes.
-    fn handle_request(
+    /// * `request_parts` - The request's headers and extensions.
+    /// * `protobuf_message` - The request's protobuf messages as bytes.
+    async fn handle_request(
         &self,
         _service_name: &str,
-        _method_name: &str,
-        protobuf_message_bytes: Bytes,
-    ) -> Result<Bytes, Box<dyn Error + Send + Sync>> {
-        let register_request: invehicle_digital_twin::v1::RegisterRequest =
-            Message::decode(&protobuf_message_bytes[..]).unwrap();
+        method_name: &str,
+        _request_parts: &mut http::request::Parts,
+        protobuf_message: Bytes,
+    ) -> Result<Bytes, Status> {
+        if method_name == Self::REGISTER_METHOD_NAME {
+            let register_request = RegisterRequest::decode(protobuf_message).map_err(|error| {
+                Status::invalid_argument(format!("Unable to decode the Register request: {error}"))
+            })?;
 
-        info!("register_request = {:?}", register_request);
+            let register_request = self.handle_register_request(register_request)?;
 
-        let mut entities = register_request.entity_access_info_list;
+            return Ok(Bytes::from(register_request.encode_to_vec()));
+        }
 
-        for entity in &mut entities {
-            let entity_id = entity.id.clone();
+        let unregister_request = UnregisterRequest::decode(protobuf_message).map_err(|error| {
+            Status::invalid_argument(format!("Unable to decode the Unregister request: {error}"))
+        })?;
 
-            let mut endpoints = entity.endpoint_info_list.clone();
+        let unregister_request = self.handle_unregister_request(unregister_request)?;
 
-            for endpoint in &mut endpoints {
-                if endpoint.operations.contains(&Self::MANAGED_SUBSCRIBE_OPERATION.to_string()) {
-                    let entity_callback = endpoint.uri.clone();
-                    let callback_protocol = endpoint.protocol.clone();
+        Ok(Bytes::from(unregister_request.encode_to_vec()))
+    }
 
-                    // Set endpoint information to the managed subscribe module.
-                    endpoint.uri = self.service_uri.clone();
-                    endpoint.protocol = "grpc".to_string();
-                    endpoint.operations = vec![Self::MANAGED_SUBSCRIBE_OPERATION.to_string()];
-                    endpoint.context = "GetSubscriptionInfo".to_string();
+    /// Handle response. Return the new response, which is the service's response.
+    ///
+    /// # Arguments
+    /// * `service_name` - The gRPC call's service name.
+    /// * `method_name` - The gRPC call's method name.
+    /// * `response_parts` - The response's headers and the call's request.
+    /// * `protobuf_message` - The response's protobuf messages as bytes.
+    async fn handle_response(
+        &self,
+        _service_name: &str,
+        method_name: &str,
+        response_parts: &mut http::response::Parts,
+        protobuf_message: Bytes,
+    ) -> Result<Bytes, Status> {
+        let Some(InterceptedRequestParts(request_parts)) =
+            response_parts.extensions.get::<InterceptedRequestParts>()
+        else {
+            return Err(Status::internal("The call's request is not known"));
+        };
 
-                    // Pass the callback with relevant endpoint information to the module.
-                    let entity_metadata = EntityMetadata {
-                        callback: CallbackInfo {
-                            uri: entity_callback.clone(),
-                            protocol: callback_protocol.clone(),
-                        },
-                        topics: HashMap::new(),
-                    };
-
-                    info!("add entity metadata with id: {entity_id}, callback: {entity_callback}");
-                    {
-                        let mut store_lock = self.store.write();
-                        store_lock.add_entity(&entity_id, entity_metadata);
-                    }
+        // The register request is needed as the provider sent it, with its callbacks.
+        if method_name == Self::REGISTER_METHOD_NAME {
+            let Some(ReceivedRequestMessage(request_message)) =
+                request_parts.extensions.get::<ReceivedRequestMessage>()
+            else {
+                return Err(Status::internal("The call's request is not known"));
+            };
 
-                    break;
-                }
-            }
+            let register_request =
+                RegisterRequest::decode(request_message.clone()).map_err(|error| {
+                    Status::internal(format!("Unable to decode the request: {error}"))
+                })?;
 
-            // Add the modified endpoint info list back to the entity access info object.
-            entity.endpoint_info_list = endpoints;
+            self.handle_register_response(&register_request);
+
+            return Ok(protobuf_message);
         }
 
-        // Construct modified register request.
-        let updated_register_request = RegisterRequest { entity_access_info_list: entities };
+        // The unregister request is needed as the service was given it.
+        let Some(InterceptedRequestMessage(request_message)) =
+            request_parts.extensions.get::<InterceptedRequestMessage>()
+        else {
+            return Err(Status::internal("The call's request is not known"));
+        };
+
+        let unregister_request = UnregisterRequest::decode(request_message.clone())
+            .map_err(|error| Status::internal(format!("Unable to decode the request: {error}")))?;
+        let unregister_response = UnregisterResponse::decode(protobuf_message.clone())
+            .map_err(|error| Status::internal(format!("Unable to decode the response: {error}")))?;
+
+        self.handle_unregister_response(&unregister_request, &unregister_response).await;
+
+        Ok(protobuf_message)
+    }
+}
+
+#[cfg(test)]
+mod managed_subscribe_interceptor_tests {
+    use super::*;
+    use common::grpc_frame::{GrpcFrame, GrpcFrameDecoder};
+    use common::grpc_interceptor::GrpcInterceptorLayer;
+    use core_protobuf_data_access::invehicle_digital_twin::v1::{
+        EndpointInfo, EntityAccessInfo, RegisterResponse,
+    };
+    use http_body::Body;
+    use parking_lot::RwLock;
+    use std::sync::Arc;
+    use tonic::Code;
+    use tower::{Layer, ServiceExt};
+
+    use crate::managed_subscribe_store::ManagedSubscribeStore;
 
-        let mut new_protobuf_message_buf: Vec<u8> = Vec::new();
-        new_protobuf_message_buf.reserve(updated_register_request.encoded_len());
-        updated_register_request.encode(&mut new_protobuf_message_buf).unwrap();
-        Ok(Bytes::from(new_protobuf_message_buf))
+    const SERVICE_URI: &str = "http://127.0.0.1:5010";
+    const SEAT_MASSAGER_ID: &str = "dtmi:sdv:seat_massager;1";
+
+    /// Creates a module whose Managed Subscribe service is never called, as its entities do not
+    /// have topics.
+    fn create_module() -> ManagedSubscribeModule {
+        ManagedSubscribeModule {
+            managed_subscribe_uri: String::from("http://127.0.0.1:50051"),
+            service_uri: SERVICE_URI.to_string(),
+            service_protocol: String::from("grpc"),
+            store: Arc::new(RwLock::new(ManagedSubscribeStore::new())),
+            tls_settings: None,
+        }
+    }
+
+    fn create_seat_massager(instance_id: &str, callback_uri: &str) -> EntityAccessInfo {
+        EntityAccessInfo {
+            name: String::from("SeatMassager"),
+            id: SEAT_MASSAGER_ID.to_string(),
+            description: String::new(),
+            endpoint_info_list: vec![EndpointInfo {
+                protocol: String::from("grpc"),
+                operations: vec![String::from("ManagedSubscribe")],
+                uri: callback_uri.to_string(),
+                context: String::new(),
+                provider_id: String::new(),
+            }],
+            instance_id: instance_id.to_string(),
+            relationship_list: vec![],
+        }
     }
 
-    /// Handle response. Return the new response.
+    fn get_callback_uri(store: &Arc<RwLock<ManagedSubscribeStore>>, key: &str) -> Option<String> {
+        store.read().get_entity_metadata(key).map(|metadata| metadata.callback.uri.clone())
+    }
+
+    /// Calls an In-Vehicle Digital Twin method through the interceptor, with a service that
+    /// answers with the given response, or fails with the given code. Returns the request as the
+    /// service was given it.
     ///
     /// # Arguments
-    /// * `service_name` - The gRPC call's service name.
-    /// * `method_name` - The gRPC call's method name.
-    /// * `protobuf_message_bytes` - The response's protobuf messages as bytes.
-    fn handle_response(
-        &self,
-        _service_name: &str,
-        _method_name: &str,
-        protobuf_message_bytes: Bytes,
-    ) -> Result<Bytes, Box<dyn Error + Send + Sync>> {
-        warn!("handle_response is unimplemented");
+    /// * `interceptor` - The interceptor.
+    /// * `method_name` - The method's name.
+    /// * `request` - The request.
+    /// * `result` - The service's response, or the code that it fails with.
+    async fn call<Req, Resp>(
+        interceptor: &ManagedSubscribeInterceptor,
+        method_name: &str,
+        request: Req,
+        result: Result<Resp, Code>,
+    ) -> Req
+    where
+        Req: Message + Default,
+        Resp: Message + Clone + 'static,
+    {
+        let received_request = Arc::new(RwLock::new(None));
+        let service_received_request = received_request.clone();
+        let service = tower::service_fn(move |request: http::Request<tonic::transport::Body>| {
+            let received_request = service_received_request.clone();
+            let result = result.clone();
+            async move {
+                let (parts, body) = request.into_parts();
+                let frame = GrpcFrameDecoder::new(body).next_frame().await?.unwrap();
+                *received_request.write() = Some(frame.decode_message(&parts.headers)?);
+
+                let response = match result {
+                    Ok(response) => {
+                        let frame = GrpcFrame::encode_message(
+                            Bytes::from(response.encode_to_vec()),
+                            false,
+                        )?;
+                        let body = tonic::transport::Body::from(frame)
+                            .map_err(|e| Status::from_error(Box::new(e)))
+                            .boxed_unsync();
+                        http::Response::new(body)
+                    }
+                    Err(code) => Status::new(code, "The call failed").to_http(),
+                };
+                Ok::<_, Box<dyn std::error::Error + Send + Sync>>(response)
+            }
+        });
+
+        let frame = GrpcFrame::encode_message(Bytes::from(request.encode_to_vec()), false).unwrap();
+        let request = http::Request::builder()
+            .method(http::Method::POST)
+            .uri(format!("/invehicle_digital_twin.v1.InvehicleDigitalTwin/{method_name}"))
+            .body(tonic::transport::Body::from(frame))
+            .unwrap();
+
+        // The response's first message is handled before the response is returned.
+        GrpcInterceptorLayer::new(Box::new(interceptor.clone()))
+            .layer(service)
+            .oneshot(request)
+            .await
+            .unwrap();
+
+        let received_request = received_request.write().take();
+        Req::decode(received_request.expect("The service was not called")).unwrap()
+    }
+
+    /// Registers instances of the seat massager through the interceptor. Returns the request as
+    /// the service was given it.
+    ///
+    /// # Arguments
+    /// * `interceptor` - The interceptor.
+    /// * `instances` - The seat massager's instance ids, with their callback URIs.
+    /// * `result` - The service's response, or the code that it fails with.
+    async fn register(
+        interceptor: &ManagedSubscribeInterceptor,
+        instances: &[(&str, &str)],
+        result: Result<RegisterResponse, Code>,
+    ) -> RegisterRequest {
+        let register_request = RegisterRequest {
+            entity_access_info_list: instances
+                .iter()
+                .map(|(instance_id, callback_uri)| create_seat_massager(instance_id, callback_uri))
+                .collect(),
+            lease_duration_seconds: 0,
+        };
+
+        call(interceptor, "Register", register_request, result).await
+    }
+
+    /// Unregisters an instance of the seat massager through the interceptor.
+    ///
+    /// # Arguments
+    /// * `interceptor` - The interceptor.
+    /// * `instance_id` - The seat massager's instance id.
+    /// * `result` - The service's response, or the code that it fails with.
+    async fn unregister(
+        interceptor: &ManagedSubscribeInterceptor,
+        instance_id: &str,
+        result: Result<UnregisterResponse, Code>,
+    ) {
+        let unregister_request = UnregisterRequest {
+            id: SEAT_MASSAGER_ID.to_string(),
+            endpoint_uri_list: vec![],
+            instance_id: instance_id.to_string(),
+        };
+
+        call(interceptor, "Unregister", unregister_request, result).await;
+    }
+
+    #[tokio::test]
+    async fn multiple_instances_test() {
+        let module = create_module();
+        let interceptor = ManagedSubscribeInterceptor::new(module.clone());
+
+        let register_request = register(
+            &interceptor,
+            &[
+                ("front_left_seat", "http://127.0.0.1:4010"),
+                ("front_right_seat", "http://127.0.0.1:4020"),
+            ],
+            Ok(RegisterResponse {}),
+        )
+        .await;
+
+        // Each instance keeps its own callback, and the module's endpoint is registered for both.
+        assert_eq!(module.store.read().entity_count(), 2);
+        assert_eq!(
+            get_callback_uri(&module.store, "front_left_seat").as_deref(),
+            Some("http://127.0.0.1:4010")
+        );
+        assert_eq!(
+            get_callback_uri(&module.store, "front_right_seat").as_deref(),
+            Some("http://127.0.0.1:4020")
+        );
+        for entity_access_info in &register_request.entity_access_info_list {
+            assert_eq!(entity_access_info.endpoint_info_list[0].uri, SERVICE_URI);
+        }
+
+        // Unregistering one instance leaves the other one.
+        let unregister_response =
+            UnregisterResponse { endpoint_info_list: vec![], entity_removed: true };
+        unregister(&interceptor, "front_left_seat", Ok(unregister_response)).await;
+
+        assert!(get_callback_uri(&module.store, "front_left_seat").is_none());
+        assert_eq!(
+            get_callback_uri(&module.store, "front_right_seat").as_deref(),
+            Some("http://127.0.0.1:4020")
+        );
+    }
+
+    #[tokio::test]
+    async fn unregister_keeps_entity_test() {
+        let module = create_module();
+        let interceptor = ManagedSubscribeInterceptor::new(module.clone());
+
+        register(
+            &interceptor,
+            &[("front_left_seat", "http://127.0.0.1:4010")],
+            Ok(RegisterResponse {}),
+        )
+        .await;
+
+        // A failed unregister call leaves the entity in the module.
+        unregister(&interceptor, "front_left_seat", Err(Code::NotFound)).await;
+        assert!(module.store.read().contains_entity("front_left_seat"));
+
+        // So does one that only removed the entity's other endpoints.
+        let unregister_response = UnregisterResponse {
+            endpoint_info_list: vec![EndpointInfo {
+                uri: String::from("http://127.0.0.1:4011"),
+                ..Default::default()
+            }],
+            entity_removed: false,
+        };
+        unregister(&interceptor, "front_left_seat", Ok(unregister_response)).await;
+        assert!(module.store.read().contains_entity("front_left_seat"));
+
+        // Once the module's endpoint has been removed, so is the entity.
+        let unregister_response = UnregisterResponse {
+            endpoint_info_list: vec![EndpointInfo {
+                uri: SERVICE_URI.to_string(),
+                ..Default::default()
+            }],
+            entity_removed: false,
+        };
+        unregister(&interceptor, "front_left_seat", Ok(unregister_response)).await;
+        assert!(!module.store.read().contains_entity("front_left_seat"));
+    }
+
+    #[tokio::test]
+    async fn rejected_register_test() {
+        let module = create_module();
+        let interceptor = ManagedSubscribeInterceptor::new(module.clone());
+
+        register(
+            &interceptor,
+            &[("front_left_seat", "http://127.0.0.1:4010")],
+            Ok(RegisterResponse {}),
+        )
+        .await;
+
+        // A registration that the service rejects does not replace the registered provider's
+        // callback, and does not add the entities that it did not register.
+        register(
+            &interceptor,
+            &[("front_left_seat", "http://127.0.0.1:4030"), ("rear_seat", "http://127.0.0.1:4040")],
+            Err(Code::AlreadyExists),
+        )
+        .await;
 
-        Ok(protobuf_message_bytes)
+        assert_eq!(module.store.read().entity_count(), 1);
+        assert_eq!(
+            get_callback_uri(&module.store, "front_left_seat").as_deref(),
+            Some("http://127.0.0.1:4010")
+        );
     }
 }
@@ -10,6 +10,7 @@ use core_protobuf_data_access::agemo::pubsub::v1::pub_sub_client::PubSubClient;
 use core_protobuf_data_access::agemo::pubsub::v1::{
     CreateTopicRequest, CreateTopicResponse, DeleteTopicRequest, DeleteTopicResponse,
 };
+use core_protobuf_data_access::module;
 use core_protobuf_data_access::module::managed_subscribe::v1::managed_subscribe_callback_client::ManagedSubscribeCallbackClient;
 use core_protobuf_data_access::module::managed_subscribe::v1::managed_subscribe_server::{
     ManagedSubscribe, ManagedSubscribeServer,
@@ -19,7 +20,12 @@ use core_protobuf_data_access::module::managed_subscribe::v1::{
     TopicManagementRequest,
 };
 
+use common::grpc_interceptor::GrpcInterceptor;
 use common::grpc_module::GrpcModule;
+use common::grpc_module_registry::{GrpcModulePriority, GrpcModuleRegistry};
+use common::metrics::MetricsRegistry;
+use common::tls_settings::{connect_channel, get_uri_scheme, TlsSettings};
+use common::trace_context::traced_request;
 use common::utils::{execute_with_retry, get_service_uri, load_settings, ServiceUriSource};
 use log::{debug, error, info};
 use parking_lot::RwLock;
@@ -27,6 +33,7 @@ use serde_derive::Deserialize;
 use std::str::FromStr;
 use std::sync::Arc;
 use strum_macros::{Display, EnumString};
+use tonic::server::NamedService;
 use tonic::transport::server::RoutesBuilder;
 use tonic::{Request, Response, Status};
 
@@ -34,6 +41,9 @@ use crate::managed_subscribe_store::{CallbackInfo, ManagedSubscribeStore, TopicI
 
 use super::managed_subscribe_interceptor::ManagedSubscribeInterceptor;
 
+/// The name that the module is enabled with in the service's settings.
+pub const MODULE_NAME: &str = "managed_subscribe";
+
 const CONFIG_FILENAME: &str = "managed_subscribe_settings";
 const SERVICE_PROTOCOL: &str = "grpc";
 
@@ -68,6 +78,8 @@ pub struct ConfigSettings {
     pub base_authority: String,
     /// Where to retrieve the Managed Subscribe Service URI from.
     pub managed_subscribe_uri_source: ServiceUriSource,
+    /// The TLS settings for the module and the services that it calls, if TLS is used.
+    pub tls: Option<TlsSettings>,
 }
 
 /// Struct that handles communication with the Managed Subscribe service.
@@ -81,6 +93,8 @@ pub struct ManagedSubscribeModule {
     pub service_protocol: String,
     /// Shared store for the Managed Subscribe module.
     pub store: Arc<RwLock<ManagedSubscribeStore>>,
+    /// The TLS settings for the module and the services that it calls, if TLS is used.
+    pub tls_settings: Option<TlsSettings>,
 }
 
 impl ManagedSubscribeModule {
@@ -93,7 +107,8 @@ impl ManagedSubscribeModule {
             ))
         })?;
         let endpoint = config.base_authority;
-        let service_uri = format!("http://{endpoint}"); // Devskim: ignore DS137138
+        let tls_settings = config.tls;
+        let service_uri = format!("{}://{endpoint}", get_uri_scheme(tls_settings.as_ref()));
 
         let store = Arc::new(RwLock::new(ManagedSubscribeStore::new()));
 
@@ -104,6 +119,7 @@ impl ManagedSubscribeModule {
             config.managed_subscribe_uri_source,
             MANAGED_SUBSCRIBE_COMMUNICATION_KIND,
             MANAGED_SUBSCRIBE_COMMUNICATION_REFERENCE,
+            tls_settings.as_ref(),
         )
         .await?;
 
@@ -112,13 +128,47 @@ impl ManagedSubscribeModule {
             service_uri,
             service_protocol: SERVICE_PROTOCOL.to_string(),
             store,
+            tls_settings,
         })
     }
 
+    /// Registers the factory for this module under its name.
+    ///
+    /// # Arguments
+    /// * `registry` - The registry of the modules that can be enabled.
+    pub fn register(registry: &mut GrpcModuleRegistry) {
+        registry.register(MODULE_NAME, GrpcModulePriority::Default, || async {
+            Ok(Box::new(ManagedSubscribeModule::new().await?) as Box<dyn GrpcModule>)
+        });
+    }
+
     /// Creates a new managed subscribe interceptor that shares data with the current instance of
     /// this module.
     pub fn create_interceptor(&self) -> ManagedSubscribeInterceptor {
-        ManagedSubscribeInterceptor::new(&self.service_uri, self.store.clone())
+        ManagedSubscribeInterceptor::new(self.clone())
+    }
+
+    /// Removes an entity from the store, and deletes its topics with the Managed Subscribe
+    /// service, so that they do not outlive the entity. An entity that is not in the store is
+    /// ignored, so an entity that is removed more than once only has its topics deleted once.
+    ///
+    /// # Arguments
+    /// * `entity_key` - The entity's key in the registry.
+    pub async fn remove_entity(&self, entity_key: &str) -> Result<(), Status> {
+        let topics: Vec<String> = {
+            // This block controls the lifetime of the lock.
+            let mut store_lock = self.store.write();
+            let Some(entity_metadata) = store_lock.get_entity_metadata(entity_key) else {
+                return Ok(());
+            };
+            let topics = entity_metadata.topics.keys().cloned().collect();
+
+            info!("remove entity metadata with key: {entity_key}");
+            store_lock.remove_entity(entity_key);
+            topics
+        };
+
+        self.delete_managed_topics(topics).await
     }
 
     /// Calls the external managed subscription service to create a new topic.
@@ -130,14 +180,16 @@ impl ManagedSubscribeModule {
         entity_id: &str,
     ) -> Result<Response<CreateTopicResponse>, Status> {
         // Connect to managed subscribe service.
-        let mut ms_client =
-            PubSubClient::connect(self.managed_subscribe_uri.to_string()).await.map_err(|e| {
+        let channel = connect_channel(&self.managed_subscribe_uri, self.tls_settings.as_ref())
+            .await
+            .map_err(|e| {
                 error!("Error connecting to pub sub client: {e:?}");
-                Status::from_error(Box::new(e))
+                Status::from_error(e)
             })?;
+        let mut ms_client = PubSubClient::new(channel);
 
         // Construct request.
-        let request = Request::new(CreateTopicRequest {
+        let request = traced_request(CreateTopicRequest {
             publisher_id: entity_id.to_string(),
             management_callback: self.service_uri.clone(),
             management_protocol: self.service_protocol.clone(),
@@ -156,20 +208,50 @@ impl ManagedSubscribeModule {
         topic: &str,
     ) -> Result<Response<DeleteTopicResponse>, Status> {
         // Connect to managed subscribe service.
-        let mut ms_client =
-            PubSubClient::connect(self.managed_subscribe_uri.to_string()).await.map_err(|e| {
+        let channel = connect_channel(&self.managed_subscribe_uri, self.tls_settings.as_ref())
+            .await
+            .map_err(|e| {
                 error!("Error connecting to pub sub client: {e:?}");
-                Status::from_error(Box::new(e))
+                Status::from_error(e)
             })?;
+        let mut ms_client = PubSubClient::new(channel);
 
         // Construct request.
-        let request = Request::new(DeleteTopicRequest { topic: topic.to_string() });
+        let request = traced_request(DeleteTopicRequest { topic: topic.to_string() });
 
         // Call managed subscribe service.
         ms_client.delete_topic(request).await
     }
+
+    /// Deletes managed topics, which have been removed from the store, with the Managed Subscribe
+    /// service. A topic that cannot be deleted does not stop the others from being deleted.
+    ///
+    /// # Arguments
+    /// * `topics` - The topics to delete.
+    async fn delete_managed_topics(&self, topics: Vec<String>) -> Result<(), Status> {
+        let mut failed_topics = Vec::new();
+
+        for topic in topics {
+            match self.delete_managed_topic(&topic).await {
+                Ok(_) => info!("Deleted the managed topic '{topic}'."),
+                Err(status) => {
+                    error!("Unable to delete the managed topic '{topic}': {status}");
+                    failed_topics.push(topic);
+                }
+            }
+        }
+
+        if !failed_topics.is_empty() {
+            return Err(Status::internal(format!(
+                "Unable to delete the managed topics {failed_topics:?}."
+            )));
+        }
+
+        Ok(())
+    }
 }
 
+#[tonic::async_trait]
 impl GrpcModule for ManagedSubscribeModule {
     /// Adds the gRPC services for this module to the server builder.
     ///
@@ -184,6 +266,68 @@ impl GrpcModule for ManagedSubscribeModule {
             .add_service(managed_subscribe_service)
             .add_service(managed_subscribe_callback_service);
     }
+
+    /// Creates the interceptors that share data with this module.
+    fn create_interceptors(&self) -> Vec<Box<dyn GrpcInterceptor + Send>> {
+        self.create_interceptor().create_grpc_interceptors()
+    }
+
+    /// Gets the encoded file descriptor set of the Managed Subscribe service.
+    fn file_descriptor_sets(&self) -> Vec<&'static [u8]> {
+        vec![module::managed_subscribe::v1::FILE_DESCRIPTOR_SET]
+    }
+
+    /// Gets the name of the Managed Subscribe service.
+    fn service_names(&self) -> Vec<&'static str> {
+        vec![ManagedSubscribeServer::<ManagedSubscribeModule>::NAME]
+    }
+
+    /// Registers the gauges for the entities and topics in this module's store.
+    ///
+    /// # Arguments
+    /// * `metrics_registry` - The registry of the service's metrics.
+    fn register_metrics(&self, metrics_registry: &MetricsRegistry) {
+        let store = self.store.clone();
+        metrics_registry.register_gauge(
+            "ibeji_managed_subscribe_entities",
+            "The number of entities that support managed subscribe.",
+            move || store.read().entity_count() as f64,
+        );
+
+        let store = self.store.clone();
+        metrics_registry.register_gauge(
+            "ibeji_managed_subscribe_topics",
+            "The number of managed subscribe topics.",
+            move || store.read().topic_count() as f64,
+        );
+    }
+
+    /// Removes an entity that has been removed from the registry, along with its topics. This is
+    /// how the module learns of the entities whose provider's lease expired.
+    ///
+    /// # Arguments
+    /// * `entity_key` - The entity's key in the registry.
+    async fn on_entity_removed(&self, entity_key: &str) {
+        if let Err(status) = self.remove_entity(entity_key).await {
+            error!("Unable to remove the entity with key {entity_key}: {status}");
+        }
+    }
+
+    /// Deletes the topics that this module created with the Managed Subscribe service, so that
+    /// they do not outlive the module.
+    async fn shutdown(&self) -> Result<(), Status> {
+        let topics = {
+            // This block controls the lifetime of the lock.
+            let mut store_lock = self.store.write();
+            let topics = store_lock.get_topics();
+            for topic in &topics {
+                store_lock.remove_topic(topic);
+            }
+            topics
+        };
+
+        self.delete_managed_topics(topics).await
+    }
 }
 
 /// Calls a provider's callback endpoint with a management request.
@@ -191,22 +335,25 @@ impl GrpcModule for ManagedSubscribeModule {
 /// # Arguments
 /// * `provider_cb_uri` - The provider's callback uri.
 /// * `management_request` - The topic management request to send.
+/// * `tls_settings` - The TLS settings used to connect to the provider, if TLS is used.
 async fn call_provider_management_cb(
     provider_cb_uri: &str,
     management_request: TopicManagementRequest,
+    tls_settings: Option<&TlsSettings>,
 ) -> Result<(), Status> {
-    let mut provider_cb_client =
-        ManagedSubscribeCallbackClient::connect(provider_cb_uri.to_string()).await.map_err(
-            |e| {
-                error!("Error connecting to provider cb client: {e:?}");
-                Status::from_error(Box::new(e))
-            },
-        )?;
-
-    let _res = provider_cb_client.topic_management_cb(management_request).await.map_err(|e| {
-        error!("Error calling to provider cb client: {e:?}");
-        Status::from_error(Box::new(e))
+    let channel = connect_channel(provider_cb_uri, tls_settings).await.map_err(|e| {
+        error!("Error connecting to provider cb client: {e:?}");
+        Status::from_error(e)
     })?;
+    let mut provider_cb_client = ManagedSubscribeCallbackClient::new(channel);
+
+    let _res = provider_cb_client
+        .topic_management_cb(traced_request(management_request))
+        .await
+        .map_err(|e| {
+            error!("Error calling to provider cb client: {e:?}");
+            Status::from_error(Box::new(e))
+        })?;
 
     Ok(())
 }
@@ -357,7 +504,13 @@ impl PublisherCallback for ManagedSubscribeModule {
         execute_with_retry(
             30,
             tokio::time::Duration::from_secs(1),
-            || call_provider_management_cb(&callback_info.uri, management_request.clone()),
+            || {
+                call_provider_management_cb(
+                    &callback_info.uri,
+                    management_request.clone(),
+                    self.tls_settings.as_ref(),
+                )
+            },
             Some("call_provider_management_cb".to_string()),
         )
         .await?;
@@ -381,3 +534,214 @@ impl PublisherCallback for ManagedSubscribeModule {
         Ok(Response::new(ManageTopicResponse {}))
     }
 }
+
+#[cfg(test)]
+mod managed_subscribe_module_tests {
+    use super::*;
+
+    use core_protobuf_data_access::module::managed_subscribe::v1::managed_subscribe_client::ManagedSubscribeClient;
+    use core_protobuf_data_access::module::managed_subscribe::v1::Constraint;
+    use std::collections::HashMap;
+    use test_support::mock_agemo::{MockPubSub, TopicAction as MockTopicAction};
+    use test_support::mock_managed_subscribe_callback::MockManagedSubscribeCallback;
+    use test_support::test_server::{bind_ephemeral_port, listener_uri, serve_with_listener};
+    use tonic::transport::Server;
+    use tonic::Code;
+
+    use crate::managed_subscribe_store::EntityMetadata;
+
+    const ENTITY_ID: &str = "dtmi:sdv:Vehicle:Cabin:HVAC:AmbientAirTemperature;1";
+    const BROKER_URI: &str = "mqtt://127.0.0.1:1883";
+    const BROKER_PROTOCOL: &str = "mqtt";
+
+    /// The services that a managed subscribe test runs against, all served on localhost.
+    struct TestEnvironment {
+        module: ManagedSubscribeModule,
+        module_uri: String,
+        pub_sub: MockPubSub,
+        provider_callback: MockManagedSubscribeCallback,
+    }
+
+    /// Serves a mock Agemo, a mock provider callback and a module whose store has an entity
+    /// that is provided through that callback.
+    async fn start_test_environment() -> TestEnvironment {
+        let pub_sub = MockPubSub::new(BROKER_URI, BROKER_PROTOCOL);
+        let pub_sub_uri = pub_sub.serve().await;
+
+        let provider_callback = MockManagedSubscribeCallback::new();
+        let provider_callback_uri = provider_callback.serve().await;
+
+        // The module's URI is its management callback, so it is known before the module is
+        // created.
+        let listener = bind_ephemeral_port().await;
+        let module_uri = listener_uri(&listener);
+
+        let module = ManagedSubscribeModule {
+            managed_subscribe_uri: pub_sub_uri,
+            service_uri: module_uri.clone(),
+            service_protocol: SERVICE_PROTOCOL.to_string(),
+            store: Arc::new(RwLock::new(ManagedSubscribeStore::new())),
+            tls_settings: None,
+        };
+
+        module.store.write().add_entity(
+            ENTITY_ID,
+            EntityMetadata {
+                callback: CallbackInfo {
+                    uri: provider_callback_uri,
+                    protocol: SERVICE_PROTOCOL.to_string(),
+                },
+                topics: HashMap::new(),
+            },
+        );
+
+        let mut builder = RoutesBuilder::default();
+        module.add_grpc_services(&mut builder);
+        serve_with_listener(Server::builder().add_routes(builder.routes()), listener);
+
+        TestEnvironment { module, module_uri, pub_sub, provider_callback }
+    }
+
+    /// Gets a subscription to the entity through the module's ManagedSubscribe service.
+    ///
+    /// # Arguments
+    /// * `module_uri` - The module's URI.
+    /// * `entity_id` - The entity to subscribe to.
+    async fn get_subscription_info(
+        module_uri: &str,
+        entity_id: &str,
+    ) -> Result<SubscriptionInfoResponse, Status> {
+        let mut client = ManagedSubscribeClient::connect(module_uri.to_string()).await.unwrap();
+
+        let request = Request::new(SubscriptionInfoRequest {
+            entity_id: entity_id.to_string(),
+            constraints: vec![Constraint {
+                r#type: "frequency_ms".to_string(),
+                value: "1000".to_string(),
+            }],
+        });
+
+        client.get_subscription_info(request).await.map(|response| response.into_inner())
+    }
+
+    #[tokio::test]
+    async fn get_subscription_info_creates_topic_test() {
+        let environment = start_test_environment().await;
+
+        let subscription_info =
+            get_subscription_info(&environment.module_uri, ENTITY_ID).await.unwrap();
+        assert_eq!(subscription_info.uri, BROKER_URI);
+        assert_eq!(subscription_info.protocol, BROKER_PROTOCOL);
+
+        // The topic was created with the module as its management callback.
+        let topic = environment.pub_sub.topic(&subscription_info.context).unwrap();
+        assert_eq!(topic.publisher_id, ENTITY_ID);
+        assert_eq!(topic.management_callback, environment.module_uri);
+        assert_eq!(topic.management_protocol, SERVICE_PROTOCOL);
+
+        let store = environment.module.store.read();
+        assert_eq!(store.get_entity_id(&subscription_info.context).unwrap(), ENTITY_ID);
+    }
+
+    #[tokio::test]
+    async fn get_subscription_info_unknown_entity_test() {
+        let environment = start_test_environment().await;
+
+        let status = get_subscription_info(&environment.module_uri, "unknown").await.unwrap_err();
+        assert_eq!(status.code(), Code::NotFound);
+        assert!(environment.pub_sub.topics().is_empty());
+    }
+
+    #[tokio::test]
+    async fn manage_topic_callback_start_and_stop_test() {
+        let environment = start_test_environment().await;
+        let topic =
+            get_subscription_info(&environment.module_uri, ENTITY_ID).await.unwrap().context;
+
+        // The first subscriber asks the provider to start publishing to the topic.
+        environment.pub_sub.trigger_topic_action(&topic, MockTopicAction::Start).await.unwrap();
+
+        let requests = environment.provider_callback.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].action, PUBLISH_ACTION);
+        let payload = requests[0].payload.clone().unwrap();
+        assert_eq!(payload.entity_id, ENTITY_ID);
+        assert_eq!(payload.topic, topic);
+        assert_eq!(payload.constraints.len(), 1);
+        let subscription_info = payload.subscription_info.unwrap();
+        assert_eq!(subscription_info.uri, BROKER_URI);
+        assert_eq!(subscription_info.protocol, BROKER_PROTOCOL);
+
+        // Once there are no subscribers, the provider stops publishing and the topic is deleted.
+        environment.pub_sub.trigger_topic_action(&topic, MockTopicAction::Stop).await.unwrap();
+
+        let requests = environment.provider_callback.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[1].action, STOP_PUBLISH_ACTION);
+        assert!(requests[1].payload.as_ref().unwrap().subscription_info.is_none());
+        assert!(environment.pub_sub.topic(&topic).is_none());
+        assert!(environment.module.store.read().get_entity_id(&topic).is_none());
+    }
+
+    #[tokio::test]
+    async fn manage_topic_callback_delete_test() {
+        let environment = start_test_environment().await;
+        let topic =
+            get_subscription_info(&environment.module_uri, ENTITY_ID).await.unwrap().context;
+
+        // The provider is not told about a deleted topic.
+        environment.pub_sub.trigger_topic_action(&topic, MockTopicAction::Delete).await.unwrap();
+        assert!(environment.provider_callback.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn manage_topic_callback_unknown_topic_test() {
+        let environment = start_test_environment().await;
+
+        let status = test_support::mock_agemo::send_topic_action(
+            &environment.module_uri,
+            "unknown",
+            &MockTopicAction::Start.to_string(),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(status.code(), Code::NotFound);
+
+        let status = test_support::mock_agemo::send_topic_action(
+            &environment.module_uri,
+            "unknown",
+            "UNKNOWN_ACTION",
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(status.code(), Code::NotFound);
+        assert!(environment.provider_callback.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn remove_entity_deletes_topics_test() {
+        let environment = start_test_environment().await;
+        get_subscription_info(&environment.module_uri, ENTITY_ID).await.unwrap();
+        assert_eq!(environment.pub_sub.topics().len(), 1);
+
+        environment.module.remove_entity(ENTITY_ID).await.unwrap();
+        assert!(environment.pub_sub.topics().is_empty());
+        assert_eq!(environment.module.store.read().entity_count(), 0);
+        assert_eq!(environment.module.store.read().topic_count(), 0);
+
+        // An entity that is not in the store is ignored.
+        environment.module.remove_entity(ENTITY_ID).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn shutdown_deletes_topics_test() {
+        let environment = start_test_environment().await;
+        get_subscription_info(&environment.module_uri, ENTITY_ID).await.unwrap();
+        get_subscription_info(&environment.module_uri, ENTITY_ID).await.unwrap();
+        assert_eq!(environment.pub_sub.topics().len(), 2);
+
+        environment.module.shutdown().await.unwrap();
+        assert!(environment.pub_sub.topics().is_empty());
+        assert_eq!(environment.module.store.read().topic_count(), 0);
+    }
+}
@@ -26,6 +26,8 @@ pub struct EntityMetadata {
     pub topics: HashMap<String, TopicInfo>,
 }
 
+/// The entities that support managed subscribe and their topics. An entity is identified by its
+/// key in the registry, which is its instance id, or its id when it does not have an instance id.
 #[derive(Clone, Debug)]
 pub struct ManagedSubscribeStore {
     topic_entity_map: HashMap<String, String>,
@@ -56,6 +58,21 @@ impl ManagedSubscribeStore {
         self.entity_metadata_map.insert(entity_id.to_string(), metadata);
     }
 
+    /// Removes an entity id and its associated metadata and topics from the store.
+    ///
+    /// # Arguments
+    /// * `entity_id` - The entity to remove.
+    pub fn remove_entity(&mut self, entity_id: &str) {
+        if let Some(metadata) = self.entity_metadata_map.remove(entity_id) {
+            // Remove the map between each of the entity's topics and the entity.
+            for topic in metadata.topics.keys() {
+                self.topic_entity_map.remove(topic);
+            }
+        } else {
+            warn!("Unable to find an entry for {entity_id}.");
+        }
+    }
+
     /// Returns whether a specific entity is in the store.
     ///
     /// # Arguments
@@ -80,6 +97,21 @@ impl ManagedSubscribeStore {
         self.topic_entity_map.get(topic)
     }
 
+    /// Gets the number of entities in the store.
+    pub fn entity_count(&self) -> usize {
+        self.entity_metadata_map.len()
+    }
+
+    /// Gets the number of topics in the store.
+    pub fn topic_count(&self) -> usize {
+        self.topic_entity_map.len()
+    }
+
+    /// Gets all of the topics in the store.
+    pub fn get_topics(&self) -> Vec<String> {
+        self.topic_entity_map.keys().cloned().collect()
+    }
+
     /// Adds a topic to the store.
     ///
     /// # Arguments
@@ -2,15 +2,32 @@
 // Licensed under the MIT license.
 // SPDX-License-Identifier: MIT
 
+use std::env;
+use std::path::PathBuf;
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let out_dir = PathBuf::from(env::var("OUT_DIR")?);
+
     tonic_build::configure()
-        .message_attribute("EndpointInfo", "#[derive(serde::Deserialize, serde::Serialize)]")
-        .message_attribute("EntityAccessInfo", "#[derive(serde::Deserialize, serde::Serialize)]")
+        .file_descriptor_set_path(out_dir.join("invehicle_digital_twin_descriptor.bin"))
+        .message_attribute(
+            "EndpointInfo",
+            "#[derive(serde::Deserialize, serde::Serialize)] #[serde(default)]",
+        )
+        .message_attribute(
+            "EntityAccessInfo",
+            "#[derive(serde::Deserialize, serde::Serialize)] #[serde(default)]",
+        )
+        .message_attribute(
+            "RelationshipInfo",
+            "#[derive(serde::Deserialize, serde::Serialize)] #[serde(default)]",
+        )
         .compile(
             &["../../interfaces/invehicle_digital_twin/v1/invehicle_digital_twin.proto"],
             &["../../interfaces/invehicle_digital_twin/v1/"],
         )?;
     tonic_build::configure()
+        .file_descriptor_set_path(out_dir.join("managed_subscribe_descriptor.bin"))
         .message_attribute("Constraint", "#[derive(serde::Deserialize, serde::Serialize)]")
         .message_attribute("CallbackPayload", "#[derive(serde::Deserialize, serde::Serialize)]")
         .message_attribute("SubscriptionInfo", "#[derive(serde::Deserialize, serde::Serialize)]")
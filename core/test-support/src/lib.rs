@@ -0,0 +1,9 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+pub mod mock_agemo;
+pub mod mock_chariott;
+pub mod mock_managed_subscribe_callback;
+pub mod mock_mqtt_broker;
+pub mod test_server;
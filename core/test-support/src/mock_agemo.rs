@@ -0,0 +1,228 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+use core_protobuf_data_access::agemo::publisher::v1::publisher_callback_client::PublisherCallbackClient;
+use core_protobuf_data_access::agemo::publisher::v1::publisher_callback_server::{
+    PublisherCallback, PublisherCallbackServer,
+};
+use core_protobuf_data_access::agemo::publisher::v1::{ManageTopicRequest, ManageTopicResponse};
+use core_protobuf_data_access::agemo::pubsub::v1::pub_sub_server::{PubSub, PubSubServer};
+use core_protobuf_data_access::agemo::pubsub::v1::{
+    CreateTopicRequest, CreateTopicResponse, DeleteTopicRequest, DeleteTopicResponse,
+};
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use strum_macros::Display;
+use tonic::transport::Server;
+use tonic::{Request, Response, Status};
+
+use crate::test_server::serve_on_ephemeral_port;
+
+/// The actions that Agemo asks a topic's publisher to take.
+#[derive(Clone, Copy, Debug, Display, Eq, PartialEq)]
+pub enum TopicAction {
+    /// The topic has its first subscriber, so the publisher should start publishing.
+    #[strum(serialize = "START")]
+    Start,
+    /// The topic has no more subscribers, so the publisher should stop publishing.
+    #[strum(serialize = "STOP")]
+    Stop,
+    /// The topic has been deleted.
+    #[strum(serialize = "DELETE")]
+    Delete,
+}
+
+/// A topic that was created with the MockPubSub.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MockTopic {
+    /// The id of the topic's publisher.
+    pub publisher_id: String,
+    /// The URI that the topic's management actions are sent to.
+    pub management_callback: String,
+    /// The protocol of the management callback.
+    pub management_protocol: String,
+}
+
+/// An in-memory stand-in for Agemo's PubSub service. Clones share the same topics.
+#[derive(Clone, Debug)]
+pub struct MockPubSub {
+    broker_uri: String,
+    broker_protocol: String,
+    topics: Arc<Mutex<HashMap<String, MockTopic>>>,
+    next_topic_number: Arc<AtomicU64>,
+}
+
+impl MockPubSub {
+    /// Creates a new MockPubSub without topics.
+    ///
+    /// # Arguments
+    /// * `broker_uri` - The URI of the broker that is returned for the created topics.
+    /// * `broker_protocol` - The protocol of the broker.
+    pub fn new(broker_uri: &str, broker_protocol: &str) -> Self {
+        Self {
+            broker_uri: broker_uri.to_string(),
+            broker_protocol: broker_protocol.to_string(),
+            topics: Arc::new(Mutex::new(HashMap::new())),
+            next_topic_number: Arc::new(AtomicU64::new(1)),
+        }
+    }
+
+    /// Gets the topics that have been created and not deleted.
+    pub fn topics(&self) -> HashMap<String, MockTopic> {
+        self.topics.lock().clone()
+    }
+
+    /// Gets a topic, if it has been created and not deleted.
+    ///
+    /// # Arguments
+    /// * `topic` - The topic's name.
+    pub fn topic(&self, topic: &str) -> Option<MockTopic> {
+        self.topics.lock().get(topic).cloned()
+    }
+
+    /// Asks a topic's publisher to take an action, through the topic's management callback, as
+    /// Agemo does when the topic's subscriptions change.
+    ///
+    /// # Arguments
+    /// * `topic` - The topic's name.
+    /// * `action` - The action to take.
+    pub async fn trigger_topic_action(
+        &self,
+        topic: &str,
+        action: TopicAction,
+    ) -> Result<(), Status> {
+        let management_callback = self
+            .topic(topic)
+            .ok_or_else(|| Status::not_found(format!("No topic named '{topic}'")))?
+            .management_callback;
+
+        send_topic_action(&management_callback, topic, &action.to_string()).await
+    }
+
+    /// Serves the PubSub service on an ephemeral port on localhost. Returns the URI that it is
+    /// served on.
+    pub async fn serve(&self) -> String {
+        serve_on_ephemeral_port(Server::builder().add_service(PubSubServer::new(self.clone())))
+            .await
+    }
+}
+
+/// Sends a topic's management action to a publisher's callback.
+///
+/// # Arguments
+/// * `management_callback` - The URI of the publisher's callback.
+/// * `topic` - The topic's name.
+/// * `action` - The action to take.
+pub async fn send_topic_action(
+    management_callback: &str,
+    topic: &str,
+    action: &str,
+) -> Result<(), Status> {
+    let mut client = PublisherCallbackClient::connect(management_callback.to_string())
+        .await
+        .map_err(|error| Status::unavailable(error.to_string()))?;
+
+    let request =
+        Request::new(ManageTopicRequest { topic: topic.to_string(), action: action.to_string() });
+
+    client.manage_topic_callback(request).await?;
+
+    Ok(())
+}
+
+#[tonic::async_trait]
+impl PubSub for MockPubSub {
+    /// Creates a topic with a generated name.
+    ///
+    /// # Arguments
+    /// * `request` - The topic's publisher and its management callback.
+    async fn create_topic(
+        &self,
+        request: Request<CreateTopicRequest>,
+    ) -> Result<Response<CreateTopicResponse>, Status> {
+        let request = request.into_inner();
+
+        let topic_number = self.next_topic_number.fetch_add(1, Ordering::SeqCst);
+        let generated_topic = format!("{}-topic-{topic_number}", request.publisher_id);
+
+        self.topics.lock().insert(
+            generated_topic.clone(),
+            MockTopic {
+                publisher_id: request.publisher_id,
+                management_callback: request.management_callback,
+                management_protocol: request.management_protocol,
+            },
+        );
+
+        Ok(Response::new(CreateTopicResponse {
+            generated_topic,
+            broker_uri: self.broker_uri.clone(),
+            broker_protocol: self.broker_protocol.clone(),
+        }))
+    }
+
+    /// Deletes a topic.
+    ///
+    /// # Arguments
+    /// * `request` - The topic's name.
+    async fn delete_topic(
+        &self,
+        request: Request<DeleteTopicRequest>,
+    ) -> Result<Response<DeleteTopicResponse>, Status> {
+        let topic = request.into_inner().topic;
+
+        self.topics
+            .lock()
+            .remove(&topic)
+            .ok_or_else(|| Status::not_found(format!("No topic named '{topic}'")))?;
+
+        Ok(Response::new(DeleteTopicResponse {}))
+    }
+}
+
+/// An in-memory stand-in for a publisher's callback, which records the management actions that
+/// it is sent. Clones share the same records.
+#[derive(Clone, Debug, Default)]
+pub struct MockPublisherCallback {
+    requests: Arc<Mutex<Vec<ManageTopicRequest>>>,
+}
+
+impl MockPublisherCallback {
+    /// Creates a new MockPublisherCallback without records.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gets the management requests that have been received, oldest first.
+    pub fn requests(&self) -> Vec<ManageTopicRequest> {
+        self.requests.lock().clone()
+    }
+
+    /// Serves the callback on an ephemeral port on localhost. Returns the URI that it is served
+    /// on.
+    pub async fn serve(&self) -> String {
+        serve_on_ephemeral_port(
+            Server::builder().add_service(PublisherCallbackServer::new(self.clone())),
+        )
+        .await
+    }
+}
+
+#[tonic::async_trait]
+impl PublisherCallback for MockPublisherCallback {
+    /// Records a topic's management action.
+    ///
+    /// # Arguments
+    /// * `request` - The topic and the action to take.
+    async fn manage_topic_callback(
+        &self,
+        request: Request<ManageTopicRequest>,
+    ) -> Result<Response<ManageTopicResponse>, Status> {
+        self.requests.lock().push(request.into_inner());
+
+        Ok(Response::new(ManageTopicResponse {}))
+    }
+}
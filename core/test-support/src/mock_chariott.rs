@@ -0,0 +1,192 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+use core_protobuf_data_access::chariott::service_discovery::core::v1::service_registry_server::{
+    ServiceRegistry, ServiceRegistryServer,
+};
+use core_protobuf_data_access::chariott::service_discovery::core::v1::{
+    DiscoverByNamespaceRequest, DiscoverByNamespaceResponse, DiscoverRequest, DiscoverResponse,
+    ListServicesRequest, ListServicesResponse, RegisterRequest, RegisterResponse, ServiceMetadata,
+    UnregisterRequest, UnregisterResponse,
+};
+use parking_lot::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tonic::transport::Server;
+use tonic::{Request, Response, Status};
+
+use crate::test_server::serve_on_ephemeral_port;
+
+/// An in-memory stand-in for Chariott's service registry. Clones share the same services.
+#[derive(Clone, Debug, Default)]
+pub struct MockServiceRegistry {
+    services: Arc<Mutex<Vec<ServiceMetadata>>>,
+    is_unavailable: Arc<AtomicBool>,
+}
+
+impl MockServiceRegistry {
+    /// Creates a new empty MockServiceRegistry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a service, replacing the service with the same namespace, name and version.
+    ///
+    /// # Arguments
+    /// * `service` - The service's metadata.
+    pub fn add_service(&self, service: ServiceMetadata) {
+        let mut services = self.services.lock();
+        services.retain(|existing| !is_same_service(existing, &service));
+        services.push(service);
+    }
+
+    /// Gets the registered services.
+    pub fn services(&self) -> Vec<ServiceMetadata> {
+        self.services.lock().clone()
+    }
+
+    /// Sets whether the registry is available. While it is unavailable, it answers registrations
+    /// with unavailable, like a Chariott that has not started yet. It is available by default.
+    ///
+    /// # Arguments
+    /// * `is_available` - Whether the registry is available.
+    pub fn set_available(&self, is_available: bool) {
+        self.is_unavailable.store(!is_available, Ordering::SeqCst);
+    }
+
+    /// Serves the registry on an ephemeral port on localhost. Returns the URI that it is served
+    /// on.
+    pub async fn serve(&self) -> String {
+        serve_on_ephemeral_port(
+            Server::builder().add_service(ServiceRegistryServer::new(self.clone())),
+        )
+        .await
+    }
+}
+
+/// Whether two services have the same namespace, name and version.
+fn is_same_service(a: &ServiceMetadata, b: &ServiceMetadata) -> bool {
+    a.namespace == b.namespace && a.name == b.name && a.version == b.version
+}
+
+#[tonic::async_trait]
+impl ServiceRegistry for MockServiceRegistry {
+    /// Registers a service, replacing the service with the same namespace, name and version.
+    /// Answers with unavailable while the registry is unavailable.
+    ///
+    /// # Arguments
+    /// * `request` - The service's metadata.
+    async fn register(
+        &self,
+        request: Request<RegisterRequest>,
+    ) -> Result<Response<RegisterResponse>, Status> {
+        if self.is_unavailable.load(Ordering::SeqCst) {
+            return Err(Status::unavailable("The service registry is unavailable"));
+        }
+
+        let service = request
+            .into_inner()
+            .service
+            .ok_or_else(|| Status::invalid_argument("The service is required"))?;
+
+        self.add_service(service);
+
+        Ok(Response::new(RegisterResponse {}))
+    }
+
+    /// Unregisters a service.
+    ///
+    /// # Arguments
+    /// * `request` - The service's namespace, name and version.
+    async fn unregister(
+        &self,
+        request: Request<UnregisterRequest>,
+    ) -> Result<Response<UnregisterResponse>, Status> {
+        let request = request.into_inner();
+
+        let mut services = self.services.lock();
+        let count = services.len();
+        services.retain(|service| {
+            !(service.namespace == request.namespace
+                && service.name == request.name
+                && service.version == request.version)
+        });
+
+        if services.len() == count {
+            return Err(Status::not_found(format!(
+                "No service with namespace '{}', name '{}' and version '{}'",
+                request.namespace, request.name, request.version
+            )));
+        }
+
+        Ok(Response::new(UnregisterResponse {}))
+    }
+
+    /// Gets the services in a namespace. Like Chariott, it answers with not found when there
+    /// are none.
+    ///
+    /// # Arguments
+    /// * `request` - The namespace.
+    async fn discover_by_namespace(
+        &self,
+        request: Request<DiscoverByNamespaceRequest>,
+    ) -> Result<Response<DiscoverByNamespaceResponse>, Status> {
+        let namespace = request.into_inner().namespace;
+
+        let services: Vec<ServiceMetadata> = self
+            .services
+            .lock()
+            .iter()
+            .filter(|service| service.namespace == namespace)
+            .cloned()
+            .collect();
+
+        if services.is_empty() {
+            return Err(Status::not_found(format!("No services in namespace '{namespace}'")));
+        }
+
+        Ok(Response::new(DiscoverByNamespaceResponse { services }))
+    }
+
+    /// Gets the service with a namespace, name and version.
+    ///
+    /// # Arguments
+    /// * `request` - The service's namespace, name and version.
+    async fn discover(
+        &self,
+        request: Request<DiscoverRequest>,
+    ) -> Result<Response<DiscoverResponse>, Status> {
+        let request = request.into_inner();
+
+        let service = self
+            .services
+            .lock()
+            .iter()
+            .find(|service| {
+                service.namespace == request.namespace
+                    && service.name == request.name
+                    && service.version == request.version
+            })
+            .cloned()
+            .ok_or_else(|| {
+                Status::not_found(format!(
+                    "No service with namespace '{}', name '{}' and version '{}'",
+                    request.namespace, request.name, request.version
+                ))
+            })?;
+
+        Ok(Response::new(DiscoverResponse { service: Some(service) }))
+    }
+
+    /// Gets all of the services.
+    ///
+    /// # Arguments
+    /// * `_request` - Empty.
+    async fn list_services(
+        &self,
+        _request: Request<ListServicesRequest>,
+    ) -> Result<Response<ListServicesResponse>, Status> {
+        Ok(Response::new(ListServicesResponse { services: self.services() }))
+    }
+}
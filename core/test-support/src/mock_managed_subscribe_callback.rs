@@ -0,0 +1,60 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+use core_protobuf_data_access::module::managed_subscribe::v1::managed_subscribe_callback_server::{
+    ManagedSubscribeCallback, ManagedSubscribeCallbackServer,
+};
+use core_protobuf_data_access::module::managed_subscribe::v1::{
+    TopicManagementRequest, TopicManagementResponse,
+};
+use parking_lot::Mutex;
+use std::sync::Arc;
+use tonic::transport::Server;
+use tonic::{Request, Response, Status};
+
+use crate::test_server::serve_on_ephemeral_port;
+
+/// An in-memory stand-in for a provider's managed subscribe callback, which records the topic
+/// management requests that it is sent. Clones share the same records.
+#[derive(Clone, Debug, Default)]
+pub struct MockManagedSubscribeCallback {
+    requests: Arc<Mutex<Vec<TopicManagementRequest>>>,
+}
+
+impl MockManagedSubscribeCallback {
+    /// Creates a new MockManagedSubscribeCallback without records.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gets the topic management requests that have been received, oldest first.
+    pub fn requests(&self) -> Vec<TopicManagementRequest> {
+        self.requests.lock().clone()
+    }
+
+    /// Serves the callback on an ephemeral port on localhost. Returns the URI that it is served
+    /// on.
+    pub async fn serve(&self) -> String {
+        serve_on_ephemeral_port(
+            Server::builder().add_service(ManagedSubscribeCallbackServer::new(self.clone())),
+        )
+        .await
+    }
+}
+
+#[tonic::async_trait]
+impl ManagedSubscribeCallback for MockManagedSubscribeCallback {
+    /// Records a topic management request.
+    ///
+    /// # Arguments
+    /// * `request` - The action and the topic's details.
+    async fn topic_management_cb(
+        &self,
+        request: Request<TopicManagementRequest>,
+    ) -> Result<Response<TopicManagementResponse>, Status> {
+        self.requests.lock().push(request.into_inner());
+
+        Ok(Response::new(TopicManagementResponse {}))
+    }
+}
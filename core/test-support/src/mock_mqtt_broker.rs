@@ -0,0 +1,517 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+use log::{debug, error};
+use parking_lot::Mutex;
+use std::io::{Error, ErrorKind};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::tcp::OwnedReadHalf;
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+
+use crate::test_server::bind_ephemeral_port;
+
+// The MQTT control packet types.
+const CONNECT: u8 = 1;
+const PUBLISH: u8 = 3;
+const PUBREL: u8 = 6;
+const SUBSCRIBE: u8 = 8;
+const UNSUBSCRIBE: u8 = 10;
+const PINGREQ: u8 = 12;
+const DISCONNECT: u8 = 14;
+
+// The first bytes of the packets that the broker sends.
+const CONNACK_HEADER: u8 = 0x20;
+const PUBLISH_HEADER: u8 = 0x30;
+const PUBACK_HEADER: u8 = 0x40;
+const PUBREC_HEADER: u8 = 0x50;
+const PUBCOMP_HEADER: u8 = 0x70;
+const SUBACK_HEADER: u8 = 0x90;
+const UNSUBACK_HEADER: u8 = 0xB0;
+const PINGRESP_HEADER: u8 = 0xD0;
+
+/// The protocol level that a client sends in its CONNECT packet for MQTT v5. The packets of the
+/// earlier versions do not have properties.
+const MQTT_V5_PROTOCOL_LEVEL: u8 = 5;
+
+/// A message that was published to the broker.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MqttMessage {
+    pub topic: String,
+    pub payload: Vec<u8>,
+}
+
+/// A subscription of a connected client.
+struct Subscription {
+    connection_id: usize,
+    topic_filter: String,
+    is_mqtt_v5: bool,
+    packet_sender: mpsc::UnboundedSender<Vec<u8>>,
+}
+
+/// Minimal MQTT broker that stands in for a real broker, like Mosquitto, in tests. It supports
+/// MQTT v3.1.1 and v5 clients that connect without TLS or authentication, records the messages
+/// that are published to it and forwards them to the matching subscriptions with QoS 0.
+/// Sessions and retained messages are not supported.
+#[derive(Clone, Default)]
+pub struct MockMqttBroker {
+    messages: Arc<Mutex<Vec<MqttMessage>>>,
+    subscriptions: Arc<Mutex<Vec<Subscription>>>,
+    next_connection_id: Arc<AtomicUsize>,
+}
+
+impl MockMqttBroker {
+    /// Creates a new MockMqttBroker.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gets the messages that have been published to the broker, in the order they were received.
+    pub fn messages(&self) -> Vec<MqttMessage> {
+        self.messages.lock().clone()
+    }
+
+    /// Gets the messages that have been published to a topic, in the order they were received.
+    ///
+    /// # Arguments
+    /// * `topic` - The topic.
+    pub fn messages_for_topic(&self, topic: &str) -> Vec<MqttMessage> {
+        self.messages.lock().iter().filter(|message| message.topic == topic).cloned().collect()
+    }
+
+    /// Accepts connections on an ephemeral port on localhost in a background task.
+    /// Returns the "tcp" URI that the broker listens on.
+    pub async fn serve(&self) -> String {
+        let listener = bind_ephemeral_port().await;
+        let addr = listener.local_addr().expect("Unable to get the listener's address");
+
+        let broker = self.clone();
+        tokio::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, _)) => {
+                        let broker = broker.clone();
+                        tokio::spawn(async move {
+                            if let Err(error) = broker.handle_connection(stream).await {
+                                debug!("The MQTT connection was closed: '{error}'");
+                            }
+                        });
+                    }
+                    Err(error) => {
+                        error!("The mock MQTT broker failed to accept a connection: '{error}'");
+                        break;
+                    }
+                }
+            }
+        });
+
+        format!("tcp://{addr}")
+    }
+
+    /// Handles the packets of a client's connection until it disconnects.
+    ///
+    /// # Arguments
+    /// * `stream` - The client's connection.
+    async fn handle_connection(&self, stream: TcpStream) -> Result<(), Error> {
+        let connection_id = self.next_connection_id.fetch_add(1, Ordering::Relaxed);
+        let (mut reader, mut writer) = stream.into_split();
+
+        // The packets for the client are written in order by a single task, since the
+        // subscriptions' messages are sent from other connections.
+        let (packet_sender, mut packet_receiver) = mpsc::unbounded_channel::<Vec<u8>>();
+        tokio::spawn(async move {
+            while let Some(packet) = packet_receiver.recv().await {
+                if writer.write_all(&packet).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        let result = self.handle_packets(connection_id, &mut reader, &packet_sender).await;
+
+        self.subscriptions
+            .lock()
+            .retain(|subscription| subscription.connection_id != connection_id);
+
+        result
+    }
+
+    /// Handles the packets that a client sends.
+    ///
+    /// # Arguments
+    /// * `connection_id` - Identifies the client's connection.
+    /// * `reader` - Reads the client's packets.
+    /// * `packet_sender` - Sends packets to the client.
+    async fn handle_packets(
+        &self,
+        connection_id: usize,
+        reader: &mut OwnedReadHalf,
+        packet_sender: &mpsc::UnboundedSender<Vec<u8>>,
+    ) -> Result<(), Error> {
+        let mut is_mqtt_v5 = false;
+
+        while let Some((first_byte, body)) = read_packet(reader).await? {
+            let mut packet = PacketReader::new(&body);
+
+            let response = match first_byte >> 4 {
+                CONNECT => {
+                    let _protocol_name = packet.read_string()?;
+                    is_mqtt_v5 = packet.read_u8()? == MQTT_V5_PROTOCOL_LEVEL;
+
+                    // The session is not present and the connection is accepted.
+                    let body = if is_mqtt_v5 { vec![0, 0, 0] } else { vec![0, 0] };
+                    Some(encode_packet(CONNACK_HEADER, &body))
+                }
+                PUBLISH => {
+                    let qos = (first_byte >> 1) & 0x03;
+                    let topic = packet.read_string()?;
+                    let packet_id = if qos > 0 { Some(packet.read_u16()?) } else { None };
+                    if is_mqtt_v5 {
+                        packet.skip_properties()?;
+                    }
+
+                    self.publish(MqttMessage { topic, payload: packet.remaining().to_vec() });
+
+                    match (qos, packet_id) {
+                        (1, Some(packet_id)) => {
+                            Some(encode_packet(PUBACK_HEADER, &packet_id.to_be_bytes()))
+                        }
+                        (2, Some(packet_id)) => {
+                            Some(encode_packet(PUBREC_HEADER, &packet_id.to_be_bytes()))
+                        }
+                        _ => None,
+                    }
+                }
+                PUBREL => {
+                    let packet_id = packet.read_u16()?;
+                    Some(encode_packet(PUBCOMP_HEADER, &packet_id.to_be_bytes()))
+                }
+                SUBSCRIBE => {
+                    let packet_id = packet.read_u16()?;
+                    if is_mqtt_v5 {
+                        packet.skip_properties()?;
+                    }
+
+                    let mut body = packet_id.to_be_bytes().to_vec();
+                    if is_mqtt_v5 {
+                        body.push(0);
+                    }
+
+                    while !packet.remaining().is_empty() {
+                        let topic_filter = packet.read_string()?;
+                        let requested_qos = packet.read_u8()? & 0x03;
+
+                        self.subscriptions.lock().push(Subscription {
+                            connection_id,
+                            topic_filter,
+                            is_mqtt_v5,
+                            packet_sender: packet_sender.clone(),
+                        });
+
+                        // The messages are forwarded with QoS 0, but the requested QoS is granted
+                        // so that clients do not treat the subscription as downgraded.
+                        body.push(requested_qos);
+                    }
+
+                    Some(encode_packet(SUBACK_HEADER, &body))
+                }
+                UNSUBSCRIBE => {
+                    let packet_id = packet.read_u16()?;
+                    if is_mqtt_v5 {
+                        packet.skip_properties()?;
+                    }
+
+                    let mut body = packet_id.to_be_bytes().to_vec();
+                    if is_mqtt_v5 {
+                        body.push(0);
+                    }
+
+                    while !packet.remaining().is_empty() {
+                        let topic_filter = packet.read_string()?;
+                        self.subscriptions.lock().retain(|subscription| {
+                            subscription.connection_id != connection_id
+                                || subscription.topic_filter != topic_filter
+                        });
+
+                        if is_mqtt_v5 {
+                            body.push(0);
+                        }
+                    }
+
+                    Some(encode_packet(UNSUBACK_HEADER, &body))
+                }
+                PINGREQ => Some(encode_packet(PINGRESP_HEADER, &[])),
+                DISCONNECT => return Ok(()),
+                // The acknowledgements of the forwarded messages are not needed with QoS 0.
+                _ => None,
+            };
+
+            if let Some(response) = response {
+                packet_sender
+                    .send(response)
+                    .map_err(|_| Error::new(ErrorKind::BrokenPipe, "The connection was closed"))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Records a message and forwards it to the subscriptions whose topic filter matches its
+    /// topic. A client that has several matching subscriptions receives the message once.
+    ///
+    /// # Arguments
+    /// * `message` - The published message.
+    fn publish(&self, message: MqttMessage) {
+        debug!("The mock MQTT broker received a message for the topic '{}'", message.topic);
+
+        {
+            let subscriptions = self.subscriptions.lock();
+            let mut connection_ids = Vec::new();
+
+            for subscription in subscriptions.iter() {
+                if connection_ids.contains(&subscription.connection_id)
+                    || !topic_matches(&subscription.topic_filter, &message.topic)
+                {
+                    continue;
+                }
+                connection_ids.push(subscription.connection_id);
+
+                let mut body = encode_string(&message.topic);
+                if subscription.is_mqtt_v5 {
+                    body.push(0);
+                }
+                body.extend_from_slice(&message.payload);
+
+                // A client that has disconnected is removed once its connection has been handled.
+                let _ = subscription.packet_sender.send(encode_packet(PUBLISH_HEADER, &body));
+            }
+        }
+
+        self.messages.lock().push(message);
+    }
+}
+
+/// Reads a packet. Returns the packet's first byte and the rest of the packet after its
+/// remaining length, or None when the client has closed the connection.
+///
+/// # Arguments
+/// * `reader` - Reads the client's packets.
+async fn read_packet(reader: &mut OwnedReadHalf) -> Result<Option<(u8, Vec<u8>)>, Error> {
+    let first_byte = match reader.read_u8().await {
+        Ok(first_byte) => first_byte,
+        Err(error) if error.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(error) => return Err(error),
+    };
+
+    // The remaining length is a variable byte integer of up to four bytes.
+    let mut remaining_length = 0;
+    for shift in (0..28).step_by(7) {
+        let byte = reader.read_u8().await?;
+        remaining_length |= usize::from(byte & 0x7F) << shift;
+        if byte & 0x80 == 0 {
+            let mut body = vec![0; remaining_length];
+            reader.read_exact(&mut body).await?;
+            return Ok(Some((first_byte, body)));
+        }
+    }
+
+    Err(Error::new(ErrorKind::InvalidData, "The remaining length is malformed"))
+}
+
+/// Encodes a packet from its first byte and the rest of the packet after its remaining length.
+///
+/// # Arguments
+/// * `first_byte` - The packet's type and flags.
+/// * `body` - The packet's variable header and payload.
+fn encode_packet(first_byte: u8, body: &[u8]) -> Vec<u8> {
+    let mut packet = vec![first_byte];
+
+    let mut remaining_length = body.len();
+    loop {
+        let mut byte = (remaining_length % 128) as u8;
+        remaining_length /= 128;
+        if remaining_length > 0 {
+            byte |= 0x80;
+        }
+        packet.push(byte);
+
+        if remaining_length == 0 {
+            break;
+        }
+    }
+
+    packet.extend_from_slice(body);
+    packet
+}
+
+/// Encodes a length-prefixed UTF-8 string.
+///
+/// # Arguments
+/// * `value` - The string to encode.
+fn encode_string(value: &str) -> Vec<u8> {
+    let mut encoded = (value.len() as u16).to_be_bytes().to_vec();
+    encoded.extend_from_slice(value.as_bytes());
+    encoded
+}
+
+/// Whether a topic filter, which can contain the '+' and '#' wildcards, matches a topic.
+///
+/// # Arguments
+/// * `topic_filter` - The topic filter of a subscription.
+/// * `topic` - The topic of a published message.
+fn topic_matches(topic_filter: &str, topic: &str) -> bool {
+    let mut filter_levels = topic_filter.split('/');
+    let mut topic_levels = topic.split('/');
+
+    loop {
+        match (filter_levels.next(), topic_levels.next()) {
+            (Some("#"), _) => return true,
+            (Some("+"), Some(_)) => {}
+            (Some(filter_level), Some(topic_level)) if filter_level == topic_level => {}
+            (None, None) => return true,
+            _ => return false,
+        }
+    }
+}
+
+/// Reads the fields of a packet's variable header and payload.
+struct PacketReader<'a> {
+    body: &'a [u8],
+    position: usize,
+}
+
+impl<'a> PacketReader<'a> {
+    /// Creates a new PacketReader.
+    ///
+    /// # Arguments
+    /// * `body` - The packet's variable header and payload.
+    fn new(body: &'a [u8]) -> Self {
+        PacketReader { body, position: 0 }
+    }
+
+    /// Gets the part of the packet that has not been read.
+    fn remaining(&self) -> &'a [u8] {
+        let body: &'a [u8] = self.body;
+        &body[self.position..]
+    }
+
+    /// Reads a number of bytes.
+    ///
+    /// # Arguments
+    /// * `length` - The number of bytes to read.
+    fn read_bytes(&mut self, length: usize) -> Result<&'a [u8], Error> {
+        let bytes = self
+            .remaining()
+            .get(..length)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, "The packet is truncated"))?;
+        self.position += length;
+        Ok(bytes)
+    }
+
+    /// Reads a byte.
+    fn read_u8(&mut self) -> Result<u8, Error> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    /// Reads a big-endian two byte integer.
+    fn read_u16(&mut self) -> Result<u16, Error> {
+        let bytes = self.read_bytes(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    /// Reads a length-prefixed UTF-8 string.
+    fn read_string(&mut self) -> Result<String, Error> {
+        let length = self.read_u16()?;
+        let bytes = self.read_bytes(usize::from(length))?;
+        String::from_utf8(bytes.to_vec()).map_err(|error| Error::new(ErrorKind::InvalidData, error))
+    }
+
+    /// Skips the properties of an MQTT v5 packet, which the broker does not use.
+    fn skip_properties(&mut self) -> Result<(), Error> {
+        let mut length = 0;
+        for shift in (0..28).step_by(7) {
+            let byte = self.read_u8()?;
+            length |= usize::from(byte & 0x7F) << shift;
+            if byte & 0x80 == 0 {
+                self.read_bytes(length)?;
+                return Ok(());
+            }
+        }
+
+        Err(Error::new(ErrorKind::InvalidData, "The property length is malformed"))
+    }
+}
+
+#[cfg(test)]
+mod mock_mqtt_broker_tests {
+    use super::*;
+
+    #[test]
+    fn topic_matches_test() {
+        assert!(topic_matches("vehicle/cabin", "vehicle/cabin"));
+        assert!(topic_matches("vehicle/+", "vehicle/cabin"));
+        assert!(topic_matches("vehicle/#", "vehicle/cabin/hvac"));
+        assert!(topic_matches("#", "vehicle"));
+        assert!(!topic_matches("vehicle/+", "vehicle/cabin/hvac"));
+        assert!(!topic_matches("vehicle/cabin", "vehicle"));
+        assert!(!topic_matches("vehicle/cabin/hvac", "vehicle/cabin"));
+    }
+
+    #[test]
+    fn encode_packet_test() {
+        assert_eq!(encode_packet(PINGRESP_HEADER, &[]), vec![0xD0, 0]);
+
+        // The remaining length of 200 takes two bytes.
+        let packet = encode_packet(PUBLISH_HEADER, &[1; 200]);
+        assert_eq!(packet[..3], [PUBLISH_HEADER, 0xC8, 0x01]);
+        assert_eq!(packet.len(), 203);
+    }
+
+    #[tokio::test]
+    async fn publish_and_subscribe_test() {
+        let broker = MockMqttBroker::new();
+        let uri = broker.serve().await;
+        let addr = uri.trim_start_matches("tcp://");
+
+        let mut subscriber = TcpStream::connect(addr).await.unwrap();
+        let mut connect = encode_string("MQTT");
+        connect.extend_from_slice(&[4, 0x02, 0, 30]);
+        connect.extend_from_slice(&encode_string("subscriber"));
+        subscriber.write_all(&encode_packet(CONNECT << 4, &connect)).await.unwrap();
+        let mut connack = [0; 4];
+        subscriber.read_exact(&mut connack).await.unwrap();
+        assert_eq!(connack, [CONNACK_HEADER, 2, 0, 0]);
+
+        let mut subscribe = vec![0, 1];
+        subscribe.extend_from_slice(&encode_string("vehicle/+"));
+        subscribe.push(1);
+        subscriber.write_all(&encode_packet(0x82, &subscribe)).await.unwrap();
+        let mut suback = [0; 5];
+        subscriber.read_exact(&mut suback).await.unwrap();
+        assert_eq!(suback, [SUBACK_HEADER, 3, 0, 1, 1]);
+
+        // A QoS 1 message is acknowledged and forwarded with QoS 0.
+        let mut publisher = TcpStream::connect(addr).await.unwrap();
+        let mut publish = encode_string("vehicle/cabin");
+        publish.extend_from_slice(&[0, 7]);
+        publish.extend_from_slice(b"payload");
+        publisher.write_all(&encode_packet(PUBLISH_HEADER | 0x02, &publish)).await.unwrap();
+        let mut puback = [0; 4];
+        publisher.read_exact(&mut puback).await.unwrap();
+        assert_eq!(puback, [PUBACK_HEADER, 2, 0, 7]);
+
+        let mut forwarded = vec![0; 24];
+        subscriber.read_exact(&mut forwarded).await.unwrap();
+        let mut expected = encode_string("vehicle/cabin");
+        expected.extend_from_slice(b"payload");
+        assert_eq!(forwarded, encode_packet(PUBLISH_HEADER, &expected));
+
+        assert_eq!(
+            broker.messages_for_topic("vehicle/cabin"),
+            vec![MqttMessage { topic: "vehicle/cabin".to_string(), payload: b"payload".to_vec() }]
+        );
+    }
+}
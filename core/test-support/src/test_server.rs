@@ -0,0 +1,51 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+use log::error;
+use tokio::net::TcpListener;
+use tonic::transport::server::{Router, TcpIncoming};
+
+/// Binds a listener to an ephemeral port on localhost, so that the port is known before the
+/// service that uses it is created.
+pub async fn bind_ephemeral_port() -> TcpListener {
+    TcpListener::bind("127.0.0.1:0").await.expect("Unable to bind an ephemeral port")
+}
+
+/// Gets the "http" URI of a listener.
+///
+/// # Arguments
+/// * `listener` - The listener.
+pub fn listener_uri(listener: &TcpListener) -> String {
+    let addr = listener.local_addr().expect("Unable to get the listener's address");
+    format!("http://{addr}")
+}
+
+/// Serves a router's services on a listener in a background task. The listener is bound, so
+/// calls can be made as soon as this returns.
+///
+/// # Arguments
+/// * `router` - The router with the services to serve.
+/// * `listener` - The listener to serve on.
+pub fn serve_with_listener(router: Router, listener: TcpListener) {
+    let incoming =
+        TcpIncoming::from_listener(listener, true, None).expect("Unable to accept connections");
+
+    tokio::spawn(async move {
+        if let Err(error) = router.serve_with_incoming(incoming).await {
+            error!("The test server failed: '{error}'");
+        }
+    });
+}
+
+/// Serves a router's services on an ephemeral port on localhost in a background task.
+/// Returns the URI that the services are served on.
+///
+/// # Arguments
+/// * `router` - The router with the services to serve.
+pub async fn serve_on_ephemeral_port(router: Router) -> String {
+    let listener = bind_ephemeral_port().await;
+    let uri = listener_uri(&listener);
+    serve_with_listener(router, listener);
+    uri
+}
@@ -0,0 +1,194 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+use common::chariott_client::{ChariottClient, ChariottRegistrationSettings};
+use common::grpc_module::GrpcModule;
+use common::grpc_module_registry::{GrpcModulePriority, GrpcModuleRegistry};
+use common::tls_settings::{get_uri_scheme, TlsSettings};
+use invehicle_digital_twin::invehicle_digital_twin_impl::InvehicleDigitalTwinImpl;
+use invehicle_digital_twin::invehicle_digital_twin_server::serve_invehicle_digital_twin;
+use managed_subscribe::managed_subscribe_module::{ManagedSubscribeModule, MODULE_NAME};
+use managed_subscribe::managed_subscribe_store::ManagedSubscribeStore;
+use parking_lot::RwLock;
+use std::sync::Arc;
+use std::time::Duration;
+use test_support::mock_agemo::MockPubSub;
+use test_support::mock_mqtt_broker::MockMqttBroker;
+use test_support::test_server::bind_ephemeral_port;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tonic::transport::server::TcpIncoming;
+
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+const MANAGED_SUBSCRIBE_MODULE_PROTOCOL: &str = "grpc";
+const MQTT_BROKER_PROTOCOL: &str = "mqtt";
+const CHARIOTT_RETRY_INTERVAL: Duration = Duration::from_millis(50);
+
+/// The modules that the service is started with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ServiceConfiguration {
+    /// No modules are enabled.
+    Base,
+    /// The managed subscribe module is enabled, with stand-ins for Agemo and its MQTT broker.
+    ManagedSubscribe,
+}
+
+/// How the service is started, beyond the modules of its configuration.
+#[derive(Default)]
+pub struct ServiceOptions {
+    /// The URI of Chariott, when the service registers with it. The service reports that it is
+    /// serving once it is registered, as it does when it is deployed.
+    pub chariott_uri: Option<String>,
+    /// The registry of the other modules to enable, such as test modules.
+    pub module_registry: GrpcModuleRegistry,
+    /// The names of the other modules to enable. They are enabled after the configuration's.
+    pub module_names: Vec<String>,
+    /// The service's TLS settings, when it is served over TLS. The service's URI is then an
+    /// "https" URI.
+    pub tls_settings: Option<TlsSettings>,
+}
+
+/// The stand-ins for the services that the managed subscribe module uses.
+pub struct ManagedSubscribeServices {
+    /// Stands in for Agemo, which creates and manages the topics.
+    pub pub_sub: MockPubSub,
+    /// Stands in for the MQTT broker that the topics are published on.
+    pub mqtt_broker: MockMqttBroker,
+    /// The URI of the MQTT broker.
+    pub mqtt_broker_uri: String,
+}
+
+/// The In-Vehicle Digital Twin Service, served in-process on an ephemeral port on localhost.
+pub struct TestInvehicleDigitalTwinService {
+    /// The URI that the service is served on.
+    pub uri: String,
+    /// The stand-ins for the managed subscribe module's services, when the module is enabled.
+    pub managed_subscribe_services: Option<ManagedSubscribeServices>,
+    /// The service's implementation, which shares its registry with the served service, so that
+    /// tests can drive the registry's background work, such as evicting expired leases.
+    pub invehicle_digital_twin_impl: InvehicleDigitalTwinImpl,
+    shutdown_sender: oneshot::Sender<()>,
+    server_task: JoinHandle<Result<(), String>>,
+}
+
+impl TestInvehicleDigitalTwinService {
+    /// Starts the service, with its modules, in a background task. The service's port is bound
+    /// before this returns, so calls can be made right away.
+    ///
+    /// # Arguments
+    /// * `configuration` - The modules that the service is started with.
+    pub async fn start(configuration: ServiceConfiguration) -> Self {
+        Self::start_with_options(configuration, ServiceOptions::default()).await
+    }
+
+    /// Starts the service, with its modules and options, in a background task. The service's
+    /// port is bound before this returns, so calls can be made right away.
+    ///
+    /// # Arguments
+    /// * `configuration` - The modules that the service is started with.
+    /// * `options` - How the service is started, beyond the modules of its configuration.
+    pub async fn start_with_options(
+        configuration: ServiceConfiguration,
+        options: ServiceOptions,
+    ) -> Self {
+        let listener = bind_ephemeral_port().await;
+        let addr = listener.local_addr().expect("Unable to get the listener's address");
+        let uri = format!("{}://{addr}", get_uri_scheme(options.tls_settings.as_ref()));
+
+        let mut module_registry = options.module_registry;
+        let mut module_names = Vec::new();
+
+        let managed_subscribe_services = match configuration {
+            ServiceConfiguration::Base => None,
+            ServiceConfiguration::ManagedSubscribe => {
+                let mqtt_broker = MockMqttBroker::new();
+                let mqtt_broker_uri = mqtt_broker.serve().await;
+
+                let pub_sub = MockPubSub::new(&mqtt_broker_uri, MQTT_BROKER_PROTOCOL);
+                let pub_sub_uri = pub_sub.serve().await;
+
+                // The module is hosted by the service, so its URI is the service's URI.
+                let service_uri = uri.clone();
+                module_registry.register(MODULE_NAME, GrpcModulePriority::Default, move || {
+                    let module = ManagedSubscribeModule {
+                        managed_subscribe_uri: pub_sub_uri.clone(),
+                        service_uri: service_uri.clone(),
+                        service_protocol: MANAGED_SUBSCRIBE_MODULE_PROTOCOL.to_string(),
+                        store: Arc::new(RwLock::new(ManagedSubscribeStore::new())),
+                        tls_settings: None,
+                    };
+                    async move { Ok(Box::new(module) as Box<dyn GrpcModule>) }
+                });
+                module_names.push(MODULE_NAME.to_string());
+
+                Some(ManagedSubscribeServices { pub_sub, mqtt_broker, mqtt_broker_uri })
+            }
+        };
+        module_names.extend(options.module_names);
+
+        // The service registers with Chariott, and reports its serving status, as it does when it
+        // is deployed.
+        let chariott_client =
+            options.chariott_uri.map(|chariott_uri| ChariottClient::new(&chariott_uri, None));
+        let chariott_registration_settings = ChariottRegistrationSettings {
+            initial_retry_interval: CHARIOTT_RETRY_INTERVAL,
+            max_retry_interval: CHARIOTT_RETRY_INTERVAL,
+            ..Default::default()
+        };
+
+        let invehicle_digital_twin_impl = InvehicleDigitalTwinImpl::new();
+        let incoming =
+            TcpIncoming::from_listener(listener, true, None).expect("Unable to accept connections");
+        let (shutdown_sender, shutdown_receiver) = oneshot::channel::<()>();
+        let tls_settings = options.tls_settings;
+
+        let service_uri = uri.clone();
+        let service_impl = invehicle_digital_twin_impl.clone();
+        let server_task = tokio::spawn(async move {
+            serve_invehicle_digital_twin(
+                addr,
+                &service_uri,
+                service_impl,
+                &module_registry,
+                module_names,
+                chariott_client,
+                chariott_registration_settings,
+                tls_settings,
+                SHUTDOWN_TIMEOUT,
+                None,
+                incoming,
+                async {
+                    // The service also stops if the test drops it without shutting it down.
+                    let _ = shutdown_receiver.await;
+                },
+            )
+            .await
+            .map_err(|error| error.to_string())
+        });
+
+        TestInvehicleDigitalTwinService {
+            uri,
+            managed_subscribe_services,
+            invehicle_digital_twin_impl,
+            shutdown_sender,
+            server_task,
+        }
+    }
+
+    /// Gets the stand-ins for the managed subscribe module's services. Panics if the module is
+    /// not enabled.
+    pub fn managed_subscribe_services(&self) -> &ManagedSubscribeServices {
+        self.managed_subscribe_services
+            .as_ref()
+            .expect("The managed subscribe module is not enabled")
+    }
+
+    /// Stops the service, and waits for it and its modules to shut down. The service is then
+    /// unregistered from Chariott, when it registers with it. Panics if the service failed.
+    pub async fn shutdown(self) {
+        let _ = self.shutdown_sender.send(());
+
+        self.server_task.await.expect("The service's task panicked").expect("The service failed");
+    }
+}
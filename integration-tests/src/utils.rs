@@ -0,0 +1,30 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+use tokio::time::{sleep, Duration, Instant};
+
+/// How long to wait for a flow to complete before the test fails.
+const WAIT_TIMEOUT: Duration = Duration::from_secs(10);
+/// How often to check whether a flow has completed.
+const WAIT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Waits until a condition produces a value, and returns that value. Panics if the condition
+/// does not produce a value within the timeout.
+///
+/// # Arguments
+/// * `description` - Describes what is being waited for, for the panic message.
+/// * `condition` - Produces a value once the condition is met.
+pub async fn wait_for<T>(description: &str, mut condition: impl FnMut() -> Option<T>) -> T {
+    let deadline = Instant::now() + WAIT_TIMEOUT;
+
+    loop {
+        if let Some(value) = condition() {
+            return value;
+        }
+
+        assert!(Instant::now() < deadline, "Timed out waiting for {description}.");
+
+        sleep(WAIT_POLL_INTERVAL).await;
+    }
+}
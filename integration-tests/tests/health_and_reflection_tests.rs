@@ -0,0 +1,215 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+use common::grpc_module::GrpcModule;
+use common::grpc_module_registry::{GrpcModulePriority, GrpcModuleRegistry};
+use integration_tests::test_invehicle_digital_twin_service::{
+    ServiceConfiguration, ServiceOptions, TestInvehicleDigitalTwinService,
+};
+use std::sync::Arc;
+use test_support::mock_chariott::MockServiceRegistry;
+use tokio::sync::Notify;
+use tokio::time::{sleep, timeout, Duration, Instant};
+use tonic::transport::server::RoutesBuilder;
+use tonic::transport::Channel;
+use tonic::{Code, Status};
+use tonic_health::pb::health_check_response::ServingStatus;
+use tonic_health::pb::health_client::HealthClient;
+use tonic_health::pb::HealthCheckRequest;
+use tonic_reflection::pb::server_reflection_client::ServerReflectionClient;
+use tonic_reflection::pb::server_reflection_request::MessageRequest;
+use tonic_reflection::pb::server_reflection_response::MessageResponse;
+use tonic_reflection::pb::ServerReflectionRequest;
+
+const INVEHICLE_DIGITAL_TWIN_SERVICE_NAME: &str = "invehicle_digital_twin.InvehicleDigitalTwin";
+const MANAGED_SUBSCRIBE_SERVICE_NAME: &str = "managed_subscribe.ManagedSubscribe";
+const HEALTH_SERVICE_NAME: &str = "grpc.health.v1.Health";
+const REFLECTION_SERVICE_NAME: &str = "grpc.reflection.v1alpha.ServerReflection";
+const GATED_MODULE_NAME: &str = "gated";
+/// How long to wait for the service to report a serving status before the test fails.
+const WAIT_TIMEOUT: Duration = Duration::from_secs(10);
+/// How often to check the service's serving status.
+const WAIT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Module that does not finish starting until the test opens its gate.
+struct GatedModule {
+    start_gate: Arc<Notify>,
+}
+
+#[tonic::async_trait]
+impl GrpcModule for GatedModule {
+    fn add_grpc_services(&self, _builder: &mut RoutesBuilder) {}
+
+    async fn start(&self) -> Result<(), Status> {
+        self.start_gate.notified().await;
+        Ok(())
+    }
+}
+
+/// Connects to the service.
+///
+/// # Arguments
+/// * `uri` - The service's URI.
+async fn connect(uri: &str) -> Channel {
+    Channel::from_shared(uri.to_string()).unwrap().connect().await.unwrap()
+}
+
+/// Checks the serving status of a service with the standard health service.
+///
+/// # Arguments
+/// * `uri` - The URI of the service that hosts the health service.
+/// * `service_name` - The service's full name, or an empty string for the overall status.
+async fn check(uri: &str, service_name: &str) -> Result<ServingStatus, Status> {
+    let request = HealthCheckRequest { service: service_name.to_string() };
+    let response = HealthClient::new(connect(uri).await).check(request).await?;
+
+    Ok(response.into_inner().status())
+}
+
+/// Waits until the health service reports a serving status for a service. Panics if it does not
+/// report it within the timeout.
+///
+/// # Arguments
+/// * `uri` - The URI of the service that hosts the health service.
+/// * `service_name` - The service's full name, or an empty string for the overall status.
+/// * `expected_status` - The serving status that is waited for.
+async fn wait_for_status(uri: &str, service_name: &str, expected_status: ServingStatus) {
+    let deadline = Instant::now() + WAIT_TIMEOUT;
+
+    while check(uri, service_name).await.unwrap() != expected_status {
+        assert!(
+            Instant::now() < deadline,
+            "Timed out waiting for '{service_name}' to be {expected_status:?}."
+        );
+
+        sleep(WAIT_POLL_INTERVAL).await;
+    }
+}
+
+/// Lists the services that are advertised by server reflection.
+///
+/// # Arguments
+/// * `uri` - The URI of the service that hosts the reflection service.
+async fn list_services(uri: &str) -> Vec<String> {
+    let request = ServerReflectionRequest {
+        host: String::new(),
+        message_request: Some(MessageRequest::ListServices(String::new())),
+    };
+    let mut responses = ServerReflectionClient::new(connect(uri).await)
+        .server_reflection_info(tokio_stream::once(request))
+        .await
+        .unwrap()
+        .into_inner();
+
+    match responses.message().await.unwrap().and_then(|response| response.message_response) {
+        Some(MessageResponse::ListServicesResponse(list_services_response)) => {
+            list_services_response.service.into_iter().map(|service| service.name).collect()
+        }
+        other => panic!("Unexpected reflection response: {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn health_check_test() {
+    // Chariott is not available yet, so the service cannot register with it.
+    let chariott = MockServiceRegistry::new();
+    chariott.set_available(false);
+    let chariott_uri = chariott.serve().await;
+
+    let start_gate = Arc::new(Notify::new());
+    let module_start_gate = start_gate.clone();
+    let mut module_registry = GrpcModuleRegistry::new();
+    module_registry.register(GATED_MODULE_NAME, GrpcModulePriority::Default, move || {
+        let module = GatedModule { start_gate: module_start_gate.clone() };
+        async move { Ok(Box::new(module) as Box<dyn GrpcModule>) }
+    });
+
+    let service = TestInvehicleDigitalTwinService::start_with_options(
+        ServiceConfiguration::ManagedSubscribe,
+        ServiceOptions {
+            chariott_uri: Some(chariott_uri),
+            module_registry,
+            module_names: vec![GATED_MODULE_NAME.to_string()],
+        },
+    )
+    .await;
+
+    // The health check is not answered while the modules are starting.
+    let uri = service.uri.clone();
+    let check_during_start =
+        tokio::spawn(async move { check(&uri, MANAGED_SUBSCRIBE_SERVICE_NAME).await });
+    sleep(Duration::from_millis(200)).await;
+    assert!(!check_during_start.is_finished());
+
+    // Once the modules have started, their services are serving.
+    start_gate.notify_one();
+    let status = timeout(WAIT_TIMEOUT, check_during_start).await.unwrap().unwrap();
+    assert_eq!(status.unwrap(), ServingStatus::Serving);
+
+    // The service itself is not serving until it has registered with Chariott.
+    assert_eq!(check(&service.uri, "").await.unwrap(), ServingStatus::NotServing);
+    assert_eq!(
+        check(&service.uri, INVEHICLE_DIGITAL_TWIN_SERVICE_NAME).await.unwrap(),
+        ServingStatus::NotServing
+    );
+
+    chariott.set_available(true);
+    wait_for_status(&service.uri, "", ServingStatus::Serving).await;
+    assert_eq!(
+        check(&service.uri, INVEHICLE_DIGITAL_TWIN_SERVICE_NAME).await.unwrap(),
+        ServingStatus::Serving
+    );
+    assert_eq!(chariott.services().len(), 1);
+    assert_eq!(chariott.services()[0].uri, service.uri);
+
+    // A service that is not hosted is unknown.
+    let result = check(&service.uri, "unknown.Unknown").await;
+    assert_eq!(result.err().unwrap().code(), Code::NotFound);
+
+    // The service is unregistered from Chariott once it has stopped.
+    service.shutdown().await;
+    assert!(chariott.services().is_empty());
+}
+
+#[tokio::test]
+async fn health_check_without_modules_test() {
+    let service = TestInvehicleDigitalTwinService::start(ServiceConfiguration::Base).await;
+
+    // The service is serving right away when it does not use Chariott.
+    assert_eq!(check(&service.uri, "").await.unwrap(), ServingStatus::Serving);
+    assert_eq!(
+        check(&service.uri, INVEHICLE_DIGITAL_TWIN_SERVICE_NAME).await.unwrap(),
+        ServingStatus::Serving
+    );
+
+    // The managed subscribe service is only hosted when its module is enabled.
+    let result = check(&service.uri, MANAGED_SUBSCRIBE_SERVICE_NAME).await;
+    assert_eq!(result.err().unwrap().code(), Code::NotFound);
+
+    service.shutdown().await;
+}
+
+#[tokio::test]
+async fn reflection_test() {
+    let service = TestInvehicleDigitalTwinService::start(ServiceConfiguration::Base).await;
+
+    let service_names = list_services(&service.uri).await;
+    for service_name in
+        [INVEHICLE_DIGITAL_TWIN_SERVICE_NAME, HEALTH_SERVICE_NAME, REFLECTION_SERVICE_NAME]
+    {
+        assert!(service_names.contains(&service_name.to_string()), "{service_name} is not listed");
+    }
+    assert!(!service_names.contains(&MANAGED_SUBSCRIBE_SERVICE_NAME.to_string()));
+
+    service.shutdown().await;
+
+    // The modules' services are listed when the modules are enabled.
+    let service =
+        TestInvehicleDigitalTwinService::start(ServiceConfiguration::ManagedSubscribe).await;
+
+    let service_names = list_services(&service.uri).await;
+    assert!(service_names.contains(&MANAGED_SUBSCRIBE_SERVICE_NAME.to_string()));
+
+    service.shutdown().await;
+}
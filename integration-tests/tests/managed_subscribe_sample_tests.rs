@@ -0,0 +1,197 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+use digital_twin_model::sdv_v0 as sdv;
+use integration_tests::test_invehicle_digital_twin_service::{
+    ServiceConfiguration, TestInvehicleDigitalTwinService,
+};
+use integration_tests::utils::wait_for;
+use samples_common::constants::{constraint_type, digital_twin_operation, digital_twin_protocol};
+use samples_common::utils::discover_digital_twin_provider_using_ibeji;
+use samples_managed_subscribe::provider::{
+    register_ambient_air_temperature, start_ambient_air_temperature_data_stream,
+};
+use samples_managed_subscribe::provider_impl::ProviderImpl;
+use samples_protobuf_data_access::invehicle_digital_twin::v1::invehicle_digital_twin_client::InvehicleDigitalTwinClient;
+use samples_protobuf_data_access::invehicle_digital_twin::v1::{
+    EndpointInfo, EntityAccessInfo, RegisterRequest,
+};
+use samples_protobuf_data_access::module::managed_subscribe::v1::managed_subscribe_callback_server::ManagedSubscribeCallbackServer;
+use samples_protobuf_data_access::module::managed_subscribe::v1::managed_subscribe_client::ManagedSubscribeClient;
+use samples_protobuf_data_access::module::managed_subscribe::v1::{
+    Constraint, SubscriptionInfoRequest, SubscriptionInfoResponse,
+};
+use test_support::mock_agemo::TopicAction;
+use test_support::test_server::serve_on_ephemeral_port;
+use std::time::{Duration, Instant};
+use tonic::transport::Server;
+use tonic::{Code, Status};
+
+const MIN_INTERVAL_MS: u64 = 100;
+const MANAGED_SUBSCRIBE_CONTEXT: &str = "GetSubscriptionInfo";
+const LEASED_PROVIDER_ID: &str = "leased-ambient-air-temperature-provider";
+const LEASE_DURATION_SECONDS: u32 = 1;
+
+/// Serves the managed subscribe sample's provider. Returns the URI of the provider's callback.
+async fn serve_managed_subscribe_provider() -> String {
+    let data_stream = start_ambient_air_temperature_data_stream(MIN_INTERVAL_MS);
+    let provider_impl = ProviderImpl::new(data_stream, MIN_INTERVAL_MS);
+    serve_on_ephemeral_port(
+        Server::builder().add_service(ManagedSubscribeCallbackServer::new(provider_impl)),
+    )
+    .await
+}
+
+/// Serves the managed subscribe sample's provider and registers its entity with the service.
+/// Returns the URI of the provider's callback.
+///
+/// # Arguments
+/// * `invehicle_digital_twin_uri` - The In-Vehicle Digital Twin Service's URI.
+async fn start_managed_subscribe_provider(invehicle_digital_twin_uri: &str) -> String {
+    let uri = serve_managed_subscribe_provider().await;
+
+    register_ambient_air_temperature(invehicle_digital_twin_uri, &uri).await.unwrap();
+
+    uri
+}
+
+/// Subscribes to the ambient air temperature through the module's ManagedSubscribe service.
+///
+/// # Arguments
+/// * `managed_subscribe_uri` - The URI of the module's ManagedSubscribe service.
+async fn get_subscription_info(
+    managed_subscribe_uri: &str,
+) -> Result<SubscriptionInfoResponse, Status> {
+    let mut client =
+        ManagedSubscribeClient::connect(managed_subscribe_uri.to_string()).await.unwrap();
+    client
+        .get_subscription_info(SubscriptionInfoRequest {
+            entity_id: sdv::hvac::ambient_air_temperature::ID.to_string(),
+            constraints: vec![Constraint {
+                r#type: constraint_type::FREQUENCY_MS.to_string(),
+                value: MIN_INTERVAL_MS.to_string(),
+            }],
+        })
+        .await
+        .map(|response| response.into_inner())
+}
+
+/// Finds the managed subscribe endpoint of the ambient air temperature through the service.
+///
+/// # Arguments
+/// * `invehicle_digital_twin_uri` - The In-Vehicle Digital Twin Service's URI.
+async fn find_managed_subscribe_endpoint(invehicle_digital_twin_uri: &str) -> EndpointInfo {
+    discover_digital_twin_provider_using_ibeji(
+        invehicle_digital_twin_uri,
+        sdv::hvac::ambient_air_temperature::ID,
+        digital_twin_protocol::GRPC,
+        &[digital_twin_operation::MANAGEDSUBSCRIBE.to_string()],
+    )
+    .await
+    .unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn managed_subscribe_sample_test() {
+    let service =
+        TestInvehicleDigitalTwinService::start(ServiceConfiguration::ManagedSubscribe).await;
+    let managed_subscribe_services = service.managed_subscribe_services();
+    let pub_sub = managed_subscribe_services.pub_sub.clone();
+    let mqtt_broker = managed_subscribe_services.mqtt_broker.clone();
+    let mqtt_broker_uri = managed_subscribe_services.mqtt_broker_uri.clone();
+
+    let _provider_uri = start_managed_subscribe_provider(&service.uri).await;
+
+    // Find. The module's interceptor registered its own endpoint in place of the provider's
+    // callback.
+    let endpoint_info = find_managed_subscribe_endpoint(&service.uri).await;
+    assert_eq!(endpoint_info.uri, service.uri);
+    assert_eq!(endpoint_info.context, MANAGED_SUBSCRIBE_CONTEXT);
+
+    // Subscribe. The module creates a topic on the MQTT broker.
+    let subscription_info = get_subscription_info(&endpoint_info.uri).await.unwrap();
+    assert_eq!(subscription_info.uri, mqtt_broker_uri);
+    let topic = subscription_info.context;
+    assert!(pub_sub.topic(&topic).is_some());
+
+    // Publish. Agemo starts the topic once it has a subscriber, and the provider publishes to it
+    // through the broker.
+    pub_sub.trigger_topic_action(&topic, TopicAction::Start).await.unwrap();
+
+    let message = wait_for("the ambient air temperature to be published", || {
+        mqtt_broker.messages_for_topic(&topic).into_iter().next()
+    })
+    .await;
+    let value: serde_json::Value = serde_json::from_slice(&message.payload).unwrap();
+    assert!(value[sdv::hvac::ambient_air_temperature::NAME].is_i64());
+    assert_eq!(value["$metadata"]["model"], sdv::hvac::ambient_air_temperature::ID);
+
+    pub_sub.trigger_topic_action(&topic, TopicAction::Stop).await.unwrap();
+
+    // The module deletes its topics when the service shuts down.
+    service.shutdown().await;
+    assert!(pub_sub.topics().is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn managed_subscribe_sample_without_module_test() {
+    let service = TestInvehicleDigitalTwinService::start(ServiceConfiguration::Base).await;
+
+    let provider_uri = start_managed_subscribe_provider(&service.uri).await;
+
+    // Without the module, the provider's callback is registered unchanged.
+    let endpoint_info = find_managed_subscribe_endpoint(&service.uri).await;
+    assert_eq!(endpoint_info.uri, provider_uri);
+    assert_eq!(endpoint_info.context, MANAGED_SUBSCRIBE_CONTEXT);
+
+    service.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn managed_subscribe_eviction_test() {
+    let service =
+        TestInvehicleDigitalTwinService::start(ServiceConfiguration::ManagedSubscribe).await;
+    let pub_sub = service.managed_subscribe_services().pub_sub.clone();
+
+    // Register the provider's entity with a lease that it does not renew.
+    let provider_uri = serve_managed_subscribe_provider().await;
+    let mut client = InvehicleDigitalTwinClient::connect(service.uri.clone()).await.unwrap();
+    client
+        .register(RegisterRequest {
+            entity_access_info_list: vec![EntityAccessInfo {
+                name: sdv::hvac::ambient_air_temperature::NAME.to_string(),
+                id: sdv::hvac::ambient_air_temperature::ID.to_string(),
+                description: sdv::hvac::ambient_air_temperature::DESCRIPTION.to_string(),
+                endpoint_info_list: vec![EndpointInfo {
+                    protocol: digital_twin_protocol::GRPC.to_string(),
+                    operations: vec![digital_twin_operation::MANAGEDSUBSCRIBE.to_string()],
+                    uri: provider_uri,
+                    context: MANAGED_SUBSCRIBE_CONTEXT.to_string(),
+                    provider_id: LEASED_PROVIDER_ID.to_string(),
+                }],
+                instance_id: String::new(),
+                relationship_list: vec![],
+            }],
+            lease_duration_seconds: LEASE_DURATION_SECONDS,
+        })
+        .await
+        .unwrap();
+
+    let topic = get_subscription_info(&service.uri).await.unwrap().context;
+    assert!(pub_sub.topic(&topic).is_some());
+
+    // Once the lease has expired, the entity is evicted without an unregister call, and the module
+    // removes it along with its topic.
+    let lease_expiry = Instant::now() + Duration::from_secs(LEASE_DURATION_SECONDS.into());
+    service.invehicle_digital_twin_impl.evict_expired_endpoints(lease_expiry);
+
+    wait_for("the evicted entity's topic to be deleted", || {
+        pub_sub.topics().is_empty().then_some(())
+    })
+    .await;
+    let status = get_subscription_info(&service.uri).await.unwrap_err();
+    assert_eq!(status.code(), Code::NotFound);
+
+    service.shutdown().await;
+}
@@ -0,0 +1,137 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+use digital_twin_model::sdv_v0 as sdv;
+use integration_tests::test_invehicle_digital_twin_service::{
+    ServiceConfiguration, TestInvehicleDigitalTwinService,
+};
+use integration_tests::utils::wait_for;
+use parking_lot::Mutex;
+use samples_common::constants::{digital_twin_operation, digital_twin_protocol};
+use samples_common::utils::discover_digital_twin_provider_using_ibeji;
+use samples_mixed::consumer::{
+    discover_providers, send_invoke_request, send_set_request, subscribe_to_properties,
+};
+use samples_mixed::consumer_impl::ConsumerImpl;
+use samples_mixed::provider::{register_entities, start_vehicle_simulator};
+use samples_mixed::provider_impl::{ProviderImpl, SubscriptionMap};
+use samples_mixed::vehicle::Vehicle;
+use samples_protobuf_data_access::sample_grpc::v1::digital_twin_consumer::digital_twin_consumer_server::DigitalTwinConsumerServer;
+use samples_protobuf_data_access::sample_grpc::v1::digital_twin_provider::digital_twin_provider_server::DigitalTwinProviderServer;
+use std::sync::Arc;
+use test_support::test_server::serve_on_ephemeral_port;
+use tokio::time::Duration;
+use tonic::transport::Server;
+
+const PUBLISH_INTERVAL: Duration = Duration::from_millis(100);
+const RESPONSE_ID: &str = "mixed-sample-test-response";
+
+/// The mixed sample's provider, served in-process.
+struct MixedProvider {
+    uri: String,
+    vehicle: Arc<Mutex<Vehicle>>,
+}
+
+/// Serves the mixed sample's provider, registers its entities with the service and starts its
+/// vehicle simulator.
+///
+/// # Arguments
+/// * `invehicle_digital_twin_uri` - The In-Vehicle Digital Twin Service's URI.
+async fn start_mixed_provider(invehicle_digital_twin_uri: &str) -> MixedProvider {
+    let subscription_map = Arc::new(Mutex::new(SubscriptionMap::new()));
+    let vehicle = Arc::new(Mutex::new(Vehicle::new()));
+    let provider_impl =
+        ProviderImpl { subscription_map: subscription_map.clone(), vehicle: vehicle.clone() };
+    let uri = serve_on_ephemeral_port(
+        Server::builder().add_service(DigitalTwinProviderServer::new(provider_impl)),
+    )
+    .await;
+
+    register_entities(invehicle_digital_twin_uri, &uri).await.unwrap();
+
+    start_vehicle_simulator(subscription_map, vehicle.clone(), PUBLISH_INTERVAL).await;
+
+    MixedProvider { uri, vehicle }
+}
+
+/// Runs the mixed sample's flows against the service with the sample's consumer: register,
+/// find, subscribe, publish, set and invoke.
+///
+/// # Arguments
+/// * `configuration` - The modules that the service is started with.
+async fn mixed_sample_flows(configuration: ServiceConfiguration) {
+    let service = TestInvehicleDigitalTwinService::start(configuration).await;
+    let provider = start_mixed_provider(&service.uri).await;
+    let consumer_impl = ConsumerImpl::default();
+    let consumer_uri = serve_on_ephemeral_port(
+        Server::builder().add_service(DigitalTwinConsumerServer::new(consumer_impl.clone())),
+    )
+    .await;
+
+    // Find. Every entity is found with the provider's endpoint for the requested operations.
+    let provider_uris = discover_providers(&service.uri).await.unwrap();
+    for provider_uri in [
+        &provider_uris.show_notification,
+        &provider_uris.ambient_air_temperature,
+        &provider_uris.is_air_conditioning_active,
+        &provider_uris.hybrid_battery_remaining,
+    ] {
+        assert_eq!(provider_uri, &provider.uri);
+    }
+    let result = discover_digital_twin_provider_using_ibeji(
+        &service.uri,
+        sdv::hvac::ambient_air_temperature::ID,
+        digital_twin_protocol::GRPC,
+        &[digital_twin_operation::INVOKE.to_string()],
+    )
+    .await;
+    assert!(result.is_err());
+
+    // Subscribe and publish.
+    subscribe_to_properties(&provider_uris, &consumer_uri).await.unwrap();
+
+    let value = wait_for("the ambient air temperature to be published", || {
+        consumer_impl.latest_value(sdv::hvac::ambient_air_temperature::ID)
+    })
+    .await;
+    let value: serde_json::Value = serde_json::from_str(&value).unwrap();
+    assert!(value[sdv::hvac::ambient_air_temperature::NAME].is_i64());
+    assert_eq!(value["$metadata"]["model"], sdv::hvac::ambient_air_temperature::ID);
+
+    wait_for("the hybrid battery remaining to be published", || {
+        consumer_impl.latest_value(sdv::obd::hybrid_battery_remaining::ID)
+    })
+    .await;
+
+    // Set.
+    send_set_request(&provider_uris.is_air_conditioning_active, true).await.unwrap();
+
+    wait_for("the air conditioning to be activated", || {
+        provider.vehicle.lock().is_air_conditioning_active.then_some(())
+    })
+    .await;
+
+    // Invoke. The provider responds to the consumer.
+    send_invoke_request(&provider_uris.show_notification, &consumer_uri, RESPONSE_ID)
+        .await
+        .unwrap();
+
+    let payload =
+        wait_for("the show-notification response", || consumer_impl.response(RESPONSE_ID)).await;
+    assert_eq!(payload, format!("Successfully invoked {}", sdv::hmi::show_notification::ID));
+
+    service.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn mixed_sample_test() {
+    mixed_sample_flows(ServiceConfiguration::Base).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn mixed_sample_with_managed_subscribe_test() {
+    // The managed subscribe module's interceptors decode and re-encode every register request,
+    // so the entities must still be registered unchanged.
+    mixed_sample_flows(ServiceConfiguration::ManagedSubscribe).await;
+}
@@ -0,0 +1,81 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+use digital_twin_model::sdv_v0 as sdv;
+use integration_tests::test_invehicle_digital_twin_service::{
+    ServiceConfiguration, TestInvehicleDigitalTwinService,
+};
+use samples_protobuf_data_access::sample_grpc::v1::digital_twin_provider::digital_twin_provider_client::DigitalTwinProviderClient;
+use samples_protobuf_data_access::sample_grpc::v1::digital_twin_provider::digital_twin_provider_server::DigitalTwinProviderServer;
+use samples_streaming::consumer::{discover_camera_feed_provider_uri, receive_images};
+use samples_streaming::provider::register_entities;
+use samples_streaming::provider_impl::ProviderImpl;
+use std::fs;
+use std::path::PathBuf;
+use test_support::test_server::serve_on_ephemeral_port;
+use tonic::transport::Server;
+
+const IMAGE_FILENAME: &str = "camera-feed.jpg";
+const IMAGE_CONTENT: &[u8] = b"not really a jpeg";
+
+/// Creates a directory with an image for the streaming sample's provider to stream.
+///
+/// # Arguments
+/// * `name` - Distinguishes the directory from those of the other tests.
+fn create_image_directory(name: &str) -> PathBuf {
+    let image_directory = std::env::temp_dir().join(format!("ibeji-{name}-{}", std::process::id()));
+    fs::create_dir_all(&image_directory).unwrap();
+    fs::write(image_directory.join(IMAGE_FILENAME), IMAGE_CONTENT).unwrap();
+    image_directory
+}
+
+/// Runs the streaming sample's flows against the service: register, find and stream.
+///
+/// # Arguments
+/// * `configuration` - The modules that the service is started with.
+/// * `name` - Distinguishes the test's image directory from those of the other tests.
+async fn streaming_sample_flows(configuration: ServiceConfiguration, name: &str) {
+    let service = TestInvehicleDigitalTwinService::start(configuration).await;
+
+    let image_directory = create_image_directory(name);
+    let provider_impl = ProviderImpl::new(image_directory.to_str().unwrap());
+    let provider_uri = serve_on_ephemeral_port(
+        Server::builder().add_service(DigitalTwinProviderServer::new(provider_impl)),
+    )
+    .await;
+    register_entities(&service.uri, &provider_uri).await.unwrap();
+
+    // Find.
+    let camera_feed_provider_uri = discover_camera_feed_provider_uri(&service.uri).await.unwrap();
+    assert_eq!(camera_feed_provider_uri, provider_uri);
+
+    // Stream. The first image is streamed right away, and the sample's consumer disconnects once it
+    // has received it.
+    let mut client = DigitalTwinProviderClient::connect(camera_feed_provider_uri).await.unwrap();
+    let mut images = Vec::new();
+    receive_images(&mut client, sdv::camera::feed::ID, 1, |media_content| {
+        images.push(media_content);
+        Ok(())
+    })
+    .await
+    .unwrap();
+    assert_eq!(images, vec![IMAGE_CONTENT.to_vec()]);
+
+    service.shutdown().await;
+    fs::remove_dir_all(image_directory).unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn streaming_sample_test() {
+    streaming_sample_flows(ServiceConfiguration::Base, "streaming-sample-test").await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn streaming_sample_with_managed_subscribe_test() {
+    streaming_sample_flows(
+        ServiceConfiguration::ManagedSubscribe,
+        "streaming-sample-with-managed-subscribe-test",
+    )
+    .await;
+}
@@ -0,0 +1,346 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+use authorization::authorization_module::{AuthorizationModule, MODULE_NAME};
+use authorization::authorization_policy::{AuthorizationPolicy, AuthorizationRule};
+use common::grpc_module::GrpcModule;
+use common::grpc_module_registry::{GrpcModulePriority, GrpcModuleRegistry};
+use common::tls_settings::{connect_channel, TlsSettings};
+use core_protobuf_data_access::invehicle_digital_twin::v1::invehicle_digital_twin_client::InvehicleDigitalTwinClient;
+use core_protobuf_data_access::invehicle_digital_twin::v1::{
+    EndpointInfo, EntityAccessInfo, FindByIdRequest, RegisterRequest,
+};
+use integration_tests::test_invehicle_digital_twin_service::{
+    ServiceConfiguration, ServiceOptions, TestInvehicleDigitalTwinService,
+};
+use rcgen::{BasicConstraints, Certificate, CertificateParams, DistinguishedName, DnType, IsCa};
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tonic::Code;
+
+/// The domain name in the service's certificate. The service is served on 127.0.0.1, so the
+/// clients verify its certificate against this name instead of the URI's host.
+const SERVER_DOMAIN_NAME: &str = "localhost";
+const SEAT_ECU_IDENTITY: &str = "seat_ecu";
+const HVAC_ECU_IDENTITY: &str = "hvac_ecu";
+const SEAT_ENTITY_ID: &str = "dtmi:sdv:seat:massager;1";
+
+/// The certificates that a test uses, which are generated for the test and written to a
+/// directory of its own, since the TLS settings read them from files. The directory is removed
+/// when they are dropped.
+struct TestCertificates {
+    directory: PathBuf,
+}
+
+impl TestCertificates {
+    /// Generates a CA, a certificate for the service and certificates for the seat and HVAC ECUs
+    /// that are signed by the CA. It also generates a certificate for the seat ECU that is signed
+    /// by another CA, which the service does not trust.
+    ///
+    /// # Arguments
+    /// * `name` - Distinguishes the certificates' directory from those of the other tests.
+    fn generate(name: &str) -> Self {
+        let directory = std::env::temp_dir().join(format!("ibeji-{name}-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let certificates = TestCertificates { directory };
+
+        let ca = create_ca_certificate("Test CA");
+        certificates.write("ca_cert.pem", &ca.serialize_pem().unwrap());
+
+        let server = create_certificate("Test server", vec![SERVER_DOMAIN_NAME.to_string()]);
+        certificates.write_identity("server", &server, &ca);
+
+        for identity in [SEAT_ECU_IDENTITY, HVAC_ECU_IDENTITY] {
+            certificates.write_identity(identity, &create_certificate(identity, vec![]), &ca);
+        }
+
+        let untrusted_ca = create_ca_certificate("Untrusted CA");
+        certificates.write("untrusted_ca_cert.pem", &untrusted_ca.serialize_pem().unwrap());
+        let untrusted_seat_ecu = create_certificate(SEAT_ECU_IDENTITY, vec![]);
+        certificates.write_identity("untrusted_seat_ecu", &untrusted_seat_ecu, &untrusted_ca);
+
+        certificates
+    }
+
+    /// Gets the path of a file in the certificates' directory.
+    ///
+    /// # Arguments
+    /// * `filename` - The file's name.
+    fn path(&self, filename: &str) -> String {
+        self.directory.join(filename).to_str().unwrap().to_string()
+    }
+
+    /// Writes a file to the certificates' directory.
+    ///
+    /// # Arguments
+    /// * `filename` - The file's name.
+    /// * `contents` - The file's contents.
+    fn write(&self, filename: &str, contents: &str) {
+        fs::write(self.path(filename), contents).unwrap();
+    }
+
+    /// Writes a certificate, signed by a CA, and its private key to the certificates' directory.
+    ///
+    /// # Arguments
+    /// * `name` - The name that the files are written with.
+    /// * `cert` - The certificate.
+    /// * `ca` - The CA that signs the certificate.
+    fn write_identity(&self, name: &str, cert: &Certificate, ca: &Certificate) {
+        self.write(&format!("{name}_cert.pem"), &cert.serialize_pem_with_signer(ca).unwrap());
+        self.write(&format!("{name}_key.pem"), &cert.serialize_private_key_pem());
+    }
+
+    /// Gets the service's TLS settings.
+    ///
+    /// # Arguments
+    /// * `verify_client_certificates` - Whether client certificates are verified against the CA.
+    /// * `require_client_certificate` - Whether clients must present a certificate.
+    fn server_tls_settings(
+        &self,
+        verify_client_certificates: bool,
+        require_client_certificate: bool,
+    ) -> TlsSettings {
+        TlsSettings {
+            cert_path: Some(self.path("server_cert.pem")),
+            key_path: Some(self.path("server_key.pem")),
+            ca_cert_path: verify_client_certificates.then(|| self.path("ca_cert.pem")),
+            require_client_certificate: Some(require_client_certificate),
+            domain_name: None,
+        }
+    }
+
+    /// Gets a client's TLS settings.
+    ///
+    /// # Arguments
+    /// * `ca_cert_filename` - The file with the CA certificate that the service is verified with.
+    /// * `identity` - The name of the certificate that the client presents, if it presents one.
+    fn client_tls_settings(&self, ca_cert_filename: &str, identity: Option<&str>) -> TlsSettings {
+        TlsSettings {
+            cert_path: identity.map(|identity| self.path(&format!("{identity}_cert.pem"))),
+            key_path: identity.map(|identity| self.path(&format!("{identity}_key.pem"))),
+            ca_cert_path: Some(self.path(ca_cert_filename)),
+            require_client_certificate: None,
+            domain_name: Some(SERVER_DOMAIN_NAME.to_string()),
+        }
+    }
+}
+
+impl Drop for TestCertificates {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.directory);
+    }
+}
+
+/// Creates a self-signed CA certificate.
+///
+/// # Arguments
+/// * `common_name` - The certificate's common name.
+fn create_ca_certificate(common_name: &str) -> Certificate {
+    let mut params = CertificateParams::new(vec![]);
+    params.distinguished_name = DistinguishedName::new();
+    params.distinguished_name.push(DnType::CommonName, common_name);
+    params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    Certificate::from_params(params).unwrap()
+}
+
+/// Creates a certificate that is to be signed by a CA.
+///
+/// # Arguments
+/// * `common_name` - The certificate's common name.
+/// * `subject_alt_names` - The certificate's DNS names.
+fn create_certificate(common_name: &str, subject_alt_names: Vec<String>) -> Certificate {
+    let mut params = CertificateParams::new(subject_alt_names);
+    params.distinguished_name = DistinguishedName::new();
+    params.distinguished_name.push(DnType::CommonName, common_name);
+    Certificate::from_params(params).unwrap()
+}
+
+/// Starts the service over TLS.
+///
+/// # Arguments
+/// * `tls_settings` - The service's TLS settings.
+/// * `policy` - The authorization policy, when the authorization module is enabled.
+async fn start_service(
+    tls_settings: TlsSettings,
+    policy: Option<AuthorizationPolicy>,
+) -> TestInvehicleDigitalTwinService {
+    let mut module_registry = GrpcModuleRegistry::new();
+    let mut module_names = Vec::new();
+    if let Some(policy) = policy {
+        let policy = Arc::new(policy);
+        module_registry.register(MODULE_NAME, GrpcModulePriority::Security, move || {
+            let module = AuthorizationModule { policy: policy.clone() };
+            async move { Ok(Box::new(module) as Box<dyn GrpcModule>) }
+        });
+        module_names.push(MODULE_NAME.to_string());
+    }
+
+    TestInvehicleDigitalTwinService::start_with_options(
+        ServiceConfiguration::Base,
+        ServiceOptions {
+            module_registry,
+            module_names,
+            tls_settings: Some(tls_settings),
+            ..Default::default()
+        },
+    )
+    .await
+}
+
+/// Connects to the service with TLS, and registers the seat entity.
+///
+/// # Arguments
+/// * `uri` - The service's URI.
+/// * `tls_settings` - The client's TLS settings.
+async fn register_seat(
+    uri: &str,
+    tls_settings: &TlsSettings,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut client =
+        InvehicleDigitalTwinClient::new(connect_channel(uri, Some(tls_settings)).await?);
+    let request = RegisterRequest {
+        entity_access_info_list: vec![EntityAccessInfo {
+            name: String::from("Massager"),
+            id: SEAT_ENTITY_ID.to_string(),
+            description: String::from("The seat's massager."),
+            endpoint_info_list: vec![EndpointInfo {
+                protocol: String::from("grpc"),
+                operations: vec![String::from("Invoke")],
+                uri: String::from("http://[::1]:40010"),
+                context: SEAT_ENTITY_ID.to_string(),
+                provider_id: String::new(),
+            }],
+            instance_id: String::new(),
+            relationship_list: vec![],
+        }],
+        lease_duration_seconds: 0,
+    };
+    client.register(request).await?;
+
+    Ok(())
+}
+
+/// Connects to the service with TLS, and finds the seat entity.
+///
+/// # Arguments
+/// * `uri` - The service's URI.
+/// * `tls_settings` - The client's TLS settings.
+async fn find_seat(
+    uri: &str,
+    tls_settings: &TlsSettings,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut client =
+        InvehicleDigitalTwinClient::new(connect_channel(uri, Some(tls_settings)).await?);
+    let request = FindByIdRequest { id: SEAT_ENTITY_ID.to_string() };
+    let response = client.find_by_id(request).await?.into_inner();
+    assert_eq!(response.entity_access_info.unwrap().id, SEAT_ENTITY_ID);
+
+    Ok(())
+}
+
+/// Gets the status code of a failed call.
+///
+/// # Arguments
+/// * `error` - The call's error.
+fn get_status_code(error: Box<dyn Error + Send + Sync>) -> Code {
+    error.downcast_ref::<tonic::Status>().expect("The call did not fail with a status").code()
+}
+
+#[tokio::test]
+async fn server_tls_test() {
+    let certificates = TestCertificates::generate("server-tls-test");
+    let service = start_service(certificates.server_tls_settings(false, false), None).await;
+    assert!(service.uri.starts_with("https://"));
+
+    // A client that trusts the service's CA can call it over TLS.
+    let client_tls_settings = certificates.client_tls_settings("ca_cert.pem", None);
+    register_seat(&service.uri, &client_tls_settings).await.unwrap();
+    find_seat(&service.uri, &client_tls_settings).await.unwrap();
+
+    // A client that does not trust the service's CA cannot.
+    let untrusting_tls_settings = certificates.client_tls_settings("untrusted_ca_cert.pem", None);
+    assert!(find_seat(&service.uri, &untrusting_tls_settings).await.is_err());
+
+    // A client that does not use TLS cannot call the service either.
+    let plaintext_uri = service.uri.replacen("https://", "http://", 1);
+    let result = async {
+        let channel = connect_channel(&plaintext_uri, None).await?;
+        InvehicleDigitalTwinClient::new(channel)
+            .find_by_id(FindByIdRequest { id: SEAT_ENTITY_ID.to_string() })
+            .await?;
+        Ok::<(), Box<dyn Error + Send + Sync>>(())
+    }
+    .await;
+    assert!(result.is_err());
+
+    service.shutdown().await;
+}
+
+#[tokio::test]
+async fn mutual_tls_test() {
+    let certificates = TestCertificates::generate("mutual-tls-test");
+    let service = start_service(certificates.server_tls_settings(true, true), None).await;
+
+    // A client that presents a certificate signed by the service's CA is accepted.
+    let trusted_tls_settings =
+        certificates.client_tls_settings("ca_cert.pem", Some(SEAT_ECU_IDENTITY));
+    register_seat(&service.uri, &trusted_tls_settings).await.unwrap();
+    find_seat(&service.uri, &trusted_tls_settings).await.unwrap();
+
+    // A client that does not present a certificate is rejected.
+    let anonymous_tls_settings = certificates.client_tls_settings("ca_cert.pem", None);
+    assert!(find_seat(&service.uri, &anonymous_tls_settings).await.is_err());
+
+    // So is a client that presents a certificate that is signed by another CA.
+    let untrusted_tls_settings =
+        certificates.client_tls_settings("ca_cert.pem", Some("untrusted_seat_ecu"));
+    assert!(find_seat(&service.uri, &untrusted_tls_settings).await.is_err());
+
+    service.shutdown().await;
+}
+
+#[tokio::test]
+async fn client_certificate_identity_test() {
+    // Only the seat ECU may register seat entities, and anyone may find them.
+    let policy = AuthorizationPolicy {
+        bearer_tokens: vec![],
+        rules: vec![
+            AuthorizationRule {
+                identity: SEAT_ECU_IDENTITY.to_string(),
+                operations: vec![String::from("Register")],
+                entity_patterns: vec![String::from("dtmi:sdv:seat*")],
+            },
+            AuthorizationRule {
+                identity: String::from("*"),
+                operations: vec![String::from("FindById")],
+                entity_patterns: vec![String::from("*")],
+            },
+        ],
+    };
+
+    // Client certificates are optional, so that anonymous callers can connect.
+    let certificates = TestCertificates::generate("client-certificate-identity-test");
+    let service = start_service(certificates.server_tls_settings(true, false), Some(policy)).await;
+
+    // Callers are identified by their certificate's common name.
+    let anonymous_tls_settings = certificates.client_tls_settings("ca_cert.pem", None);
+    let error = register_seat(&service.uri, &anonymous_tls_settings).await.unwrap_err();
+    assert_eq!(get_status_code(error), Code::PermissionDenied);
+
+    let hvac_ecu_tls_settings =
+        certificates.client_tls_settings("ca_cert.pem", Some(HVAC_ECU_IDENTITY));
+    let error = register_seat(&service.uri, &hvac_ecu_tls_settings).await.unwrap_err();
+    assert_eq!(get_status_code(error), Code::PermissionDenied);
+
+    let seat_ecu_tls_settings =
+        certificates.client_tls_settings("ca_cert.pem", Some(SEAT_ECU_IDENTITY));
+    register_seat(&service.uri, &seat_ecu_tls_settings).await.unwrap();
+
+    find_seat(&service.uri, &anonymous_tls_settings).await.unwrap();
+    find_seat(&service.uri, &hvac_ecu_tls_settings).await.unwrap();
+
+    service.shutdown().await;
+}
@@ -4,8 +4,9 @@
 
 mod consumer_impl;
 
+use common::trace_context::{traced_request, TraceContextLayer};
+use common::tracing_settings::{init_tracing, shutdown_tracing};
 use digital_twin_model::{sdv_v0 as sdv, Metadata};
-use env_logger::{Builder, Target};
 use log::{debug, info, warn, LevelFilter};
 use samples_common::constants::{digital_twin_operation, digital_twin_protocol};
 use samples_common::consumer_config;
@@ -17,8 +18,11 @@ use serde_derive::{Deserialize, Serialize};
 use std::net::SocketAddr;
 use tokio::time::{sleep, Duration};
 use tonic::transport::Server;
+use tracing::{info_span, Instrument};
 use uuid::Uuid;
 
+const SERVICE_NAME: &str = "command-consumer";
+
 #[derive(Debug, Serialize, Deserialize)]
 struct ShowNotificationRequestPayload {
     #[serde(rename = "Notification")]
@@ -57,14 +61,18 @@ fn start_show_notification_repeater(provider_uri: String, consumer_uri: String)
 
             let response_id = Uuid::new_v4().to_string();
 
-            let request = tonic::Request::new(InvokeRequest {
-                entity_id: sdv::hmi::show_notification::ID.to_string(),
-                consumer_uri: consumer_uri.clone(),
-                response_id,
-                payload: request_payload_json.to_string(),
+            // Each invoke request starts a trace, which the provider's response is correlated with.
+            let span = info_span!("invoke_show_notification");
+            let request = span.in_scope(|| {
+                traced_request(InvokeRequest {
+                    entity_id: sdv::hmi::show_notification::ID.to_string(),
+                    consumer_uri: consumer_uri.clone(),
+                    response_id,
+                    payload: request_payload_json.to_string(),
+                })
             });
 
-            let response = client.invoke(request).await;
+            let response = client.invoke(request).instrument(span).await;
             match response {
                 Ok(_) => (),
                 Err(status) => warn!("{status:?}"),
@@ -79,12 +87,13 @@ fn start_show_notification_repeater(provider_uri: String, consumer_uri: String)
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Setup logging.
-    Builder::new().filter(None, LevelFilter::Info).target(Target::Stdout).init();
+    let settings = consumer_config::load_settings();
 
-    info!("The Consumer has started.");
+    // Setup tracing.
+    init_tracing(SERVICE_NAME, LevelFilter::Info, settings.tracing.as_ref(), None)
+        .map_err(|error| error as Box<dyn std::error::Error>)?;
 
-    let settings = consumer_config::load_settings();
+    info!("The Consumer has started.");
 
     let invehicle_digital_twin_uri = retrieve_invehicle_digital_twin_uri(
         settings.invehicle_digital_twin_uri,
@@ -99,8 +108,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Setup the HTTP server.
     let addr: SocketAddr = consumer_authority.parse()?;
     let consumer_impl = consumer_impl::ConsumerImpl::default();
-    let server_future =
-        Server::builder().add_service(DigitalTwinConsumerServer::new(consumer_impl)).serve(addr);
+    let server_future = Server::builder()
+        .layer(TraceContextLayer::new())
+        .add_service(DigitalTwinConsumerServer::new(consumer_impl))
+        .serve(addr);
     info!("The HTTP server is listening on address '{consumer_authority}'");
 
     let provider_endpoint_info = discover_digital_twin_provider_using_ibeji(
@@ -123,5 +134,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     debug!("The Consumer has completed.");
 
+    shutdown_tracing();
+
     Ok(())
 }
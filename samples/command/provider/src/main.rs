@@ -4,8 +4,9 @@
 
 mod provider_impl;
 
+use common::trace_context::{traced_request, TraceContextLayer};
+use common::tracing_settings::{init_tracing, shutdown_tracing};
 use digital_twin_model::sdv_v0 as sdv;
-use env_logger::{Builder, Target};
 use log::{debug, info, LevelFilter};
 use samples_common::constants::{digital_twin_operation, digital_twin_protocol};
 use samples_common::utils::{retrieve_invehicle_digital_twin_uri, retry_async_based_on_status};
@@ -19,6 +20,10 @@ use tonic::{Status, transport::Server};
 
 use crate::provider_impl::ProviderImpl;
 
+const PROVIDER_ID: &str = "command-provider";
+
+const SERVICE_NAME: &str = "command-provider";
+
 /// Register the show notification command's endpoint.
 ///
 /// # Arguments
@@ -33,6 +38,7 @@ async fn register_show_notification(
         operations: vec![digital_twin_operation::INVOKE.to_string()],
         uri: provider_uri.to_string(),
         context: sdv::hmi::show_notification::ID.to_string(),
+        provider_id: PROVIDER_ID.to_string(),
     };
 
     let entity_access_info = EntityAccessInfo {
@@ -40,13 +46,17 @@ async fn register_show_notification(
         id: sdv::hmi::show_notification::ID.to_string(),
         description: sdv::hmi::show_notification::DESCRIPTION.to_string(),
         endpoint_info_list: vec![endpoint_info],
+        instance_id: String::new(),
+        relationship_list: vec![],
     };
 
     let mut client = InvehicleDigitalTwinClient::connect(invehicle_digital_twin_uri.to_string())
         .await
         .map_err(|e| Status::internal(e.to_string()))?;
-    let request =
-        tonic::Request::new(RegisterRequest { entity_access_info_list: vec![entity_access_info] });
+    let request = traced_request(RegisterRequest {
+        entity_access_info_list: vec![entity_access_info],
+        lease_duration_seconds: 0,
+    });
     let _response = client.register(request).await?;
 
     Ok(())
@@ -54,12 +64,13 @@ async fn register_show_notification(
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Setup logging.
-    Builder::new().filter(None, LevelFilter::Info).target(Target::Stdout).init();
+    let settings = provider_config::load_settings();
 
-    info!("The Provider has started.");
+    // Setup tracing.
+    init_tracing(SERVICE_NAME, LevelFilter::Info, settings.tracing.as_ref(), None)
+        .map_err(|error| error as Box<dyn std::error::Error>)?;
 
-    let settings = provider_config::load_settings();
+    info!("The Provider has started.");
 
     let provider_authority = settings.provider_authority;
 
@@ -75,8 +86,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Setup the HTTP server.
     let addr: SocketAddr = provider_authority.parse()?;
     let provider_impl = ProviderImpl {};
-    let server_future =
-        Server::builder().add_service(DigitalTwinProviderServer::new(provider_impl)).serve(addr);
+    let server_future = Server::builder()
+        .layer(TraceContextLayer::new())
+        .add_service(DigitalTwinProviderServer::new(provider_impl))
+        .serve(addr);
     info!("The HTTP server is listening on address '{provider_authority}'");
 
     info!("Sending a register request to the In-Vehicle Digital Twin Service URI {invehicle_digital_twin_uri}");
@@ -89,5 +102,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     debug!("The Provider has completed.");
 
+    shutdown_tracing();
+
     Ok(())
 }
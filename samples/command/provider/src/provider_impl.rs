@@ -2,6 +2,7 @@
 // Licensed under the MIT license.
 // SPDX-License-Identifier: MIT
 
+use common::trace_context::traced_request;
 use digital_twin_model::sdv_v0 as sdv;
 use log::{debug, info, warn};
 use samples_protobuf_data_access::sample_grpc::v1::digital_twin_consumer::digital_twin_consumer_client::DigitalTwinConsumerClient;
@@ -16,6 +17,7 @@ use serde_derive::{Deserialize, Serialize};
 use std::pin::Pin;
 use tokio_stream::Stream;
 use tonic::{Request, Response, Status};
+use tracing::{Instrument, Span};
 
 /// The reponse payload is empty.
 #[derive(Debug, Serialize, Deserialize)]
@@ -98,7 +100,7 @@ impl DigitalTwinProvider for ProviderImpl {
 
         info!("Notification: '{notification}'");
 
-        tokio::spawn(async move {
+        let send_response = async move {
             let mut client = DigitalTwinConsumerClient::connect(consumer_uri.clone())
                 .await
                 .map_err(|error| Status::internal(error.to_string()))?;
@@ -106,7 +108,7 @@ impl DigitalTwinProvider for ProviderImpl {
             let response_payload = ResponsePayload {};
             let response_payload_json = serde_json::to_string(&response_payload).unwrap();
 
-            let respond_request = tonic::Request::new(RespondRequest {
+            let respond_request = traced_request(RespondRequest {
                 entity_id: sdv::hmi::show_notification::response::ID.to_string(),
                 response_id,
                 payload: response_payload_json,
@@ -120,7 +122,10 @@ impl DigitalTwinProvider for ProviderImpl {
             );
 
             response_future
-        });
+        };
+
+        // The response is sent within the invoke request's span, so that it is correlated with it.
+        tokio::spawn(send_response.instrument(Span::current()));
 
         let response = InvokeResponse {};
 
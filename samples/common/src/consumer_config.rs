@@ -4,6 +4,7 @@
 
 use crate::utils;
 
+use common::tracing_settings::TracingSettings;
 use serde_derive::Deserialize;
 
 const CONFIG_FILENAME: &str = "consumer_settings";
@@ -13,6 +14,7 @@ pub struct Settings {
     pub consumer_authority: Option<String>,
     pub chariott_uri: Option<String>,
     pub invehicle_digital_twin_uri: Option<String>,
+    pub tracing: Option<TracingSettings>,
 }
 
 /// Load the settings.
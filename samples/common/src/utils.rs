@@ -4,6 +4,9 @@
 
 use crate::constants;
 
+use common::chariott_client::{ChariottClient, DiscoveryPolicy, ServiceQuery};
+use common::trace_context::traced_request;
+use common::utils::execute_with_retry;
 use config::{Config, ConfigError, File, FileFormat};
 use constants::chariott::{
     INVEHICLE_DIGITAL_TWIN_SERVICE_COMMUNICATION_KIND,
@@ -11,13 +14,11 @@ use constants::chariott::{
     INVEHICLE_DIGITAL_TWIN_SERVICE_NAMESPACE, INVEHICLE_DIGITAL_TWIN_SERVICE_VERSION,
 };
 use log::{debug, info};
-use samples_protobuf_data_access::chariott::service_discovery::core::v1::service_registry_client::ServiceRegistryClient;
-use samples_protobuf_data_access::chariott::service_discovery::core::v1::DiscoverRequest;
 use samples_protobuf_data_access::invehicle_digital_twin::v1::invehicle_digital_twin_client::InvehicleDigitalTwinClient;
-use samples_protobuf_data_access::invehicle_digital_twin::v1::{EndpointInfo, FindByIdRequest};
+use samples_protobuf_data_access::invehicle_digital_twin::v1::{EndpointInfo, FindRequest};
 use std::future::Future;
 use tokio::time::{sleep, Duration};
-use tonic::{Code, Request, Status};
+use tonic::{Code, Status};
 
 const IBEJI_HOME_VAR_NAME: &str = "IBEJI_HOME";
 
@@ -41,17 +42,6 @@ where
     config.try_deserialize()
 }
 
-/// Is the provided subset a subset of the provided superset?
-///
-/// # Arguments
-/// * `subset` - The provided subset.
-/// * `superset` - The provided superset.
-fn is_subset(subset: &[String], superset: &[String]) -> bool {
-    subset.iter().all(|subset_member| {
-        superset.iter().any(|supserset_member| subset_member == supserset_member)
-    })
-}
-
 ///
 /// Retry an async function that uses tonic::Status in for its error result.
 ///
@@ -106,28 +96,29 @@ pub async fn discover_digital_twin_provider_using_ibeji(
     protocol: &str,
     operations: &[String],
 ) -> Result<EndpointInfo, String> {
-    info!("Sending a find_by_id request for entity id {entity_id} to the In-Vehicle Digital Twin Service URI {invehicle_digitial_twin_service_uri}");
+    info!("Sending a find request for entity id {entity_id} to the In-Vehicle Digital Twin Service URI {invehicle_digitial_twin_service_uri}");
 
     let mut client =
         InvehicleDigitalTwinClient::connect(invehicle_digitial_twin_service_uri.to_string())
             .await
             .map_err(|error| format!("{error}"))?;
-    let request = tonic::Request::new(FindByIdRequest { id: entity_id.to_string() });
-    let response = client.find_by_id(request).await.map_err(|error| error.to_string())?;
+    let request = traced_request(FindRequest {
+        id_prefix: entity_id.to_string(),
+        protocol: protocol.to_string(),
+        operations: operations.to_vec(),
+        ..Default::default()
+    });
+    let response = client.find(request).await.map_err(|error| error.to_string())?;
     let response_inner = response.into_inner();
-    debug!("Received the response for the find_by_id request");
-    info!("response_payload: {:?}", response_inner.entity_access_info);
+    debug!("Received the response for the find request");
+    info!("response_payload: {:?}", response_inner.entity_access_info_list);
 
+    // The service only returns the endpoints that meet our requirements.
     match response_inner
-        .entity_access_info
-        .ok_or_else(|| "Did not find the entity".to_string())?
-        .endpoint_info_list
-        .iter()
-        .find(|endpoint_info| {
-            endpoint_info.protocol == protocol
-                && is_subset(operations, endpoint_info.operations.as_slice())
-        })
-        .cloned()
+        .entity_access_info_list
+        .into_iter()
+        .find(|entity_access_info| entity_access_info.id == entity_id)
+        .and_then(|entity_access_info| entity_access_info.endpoint_info_list.into_iter().next())
     {
         Some(result) => {
             info!(
@@ -141,49 +132,6 @@ pub async fn discover_digital_twin_provider_using_ibeji(
     }
 }
 
-/// Use Chariott to discover a service.
-///
-/// # Arguments
-/// * `chariott_uri` - Chariott's URI.
-/// * `namespace` - The service's namespace.
-/// * `name` - The service's name.
-/// * `version` - The service's version.
-/// # `communication_kind` - The service's communication kind.
-/// # `communication_reference` - The service's communication reference.
-pub async fn discover_service_using_chariott(
-    chariott_uri: &str,
-    namespace: &str,
-    name: &str,
-    version: &str,
-    communication_kind: &str,
-    communication_reference: &str,
-) -> Result<String, Status> {
-    let mut client = ServiceRegistryClient::connect(chariott_uri.to_owned())
-        .await
-        .map_err(|e| Status::internal(e.to_string()))?;
-
-    let request = Request::new(DiscoverRequest {
-        namespace: namespace.to_string(),
-        name: name.to_string(),
-        version: version.to_string(),
-    });
-
-    let response =
-        client.discover(request).await.map_err(|error| Status::internal(error.to_string()))?;
-
-    let service = response.into_inner().service.ok_or_else(|| Status::not_found("Did not find a service in Chariott with namespace '{namespace}', name '{name}' and version {version}"))?;
-
-    if service.communication_kind != communication_kind
-        && service.communication_reference != communication_reference
-    {
-        return Err(Status::not_found(
-            "Did not find a service in Chariott with namespace '{namespace}', name '{name}' and version {version} that has communication kind '{communication_kind} and communication_reference '{communication_reference}''",
-        ));
-    }
-
-    Ok(service.uri)
-}
-
 /// Retrieve the In-Vehicle Digital Twin URI.
 /// If invehicle_digital_twin_uri is provided, then its value is returned.
 /// Otherwise, chariott_uri is used to retrieve it from Chariott.
@@ -207,16 +155,20 @@ pub async fn retrieve_invehicle_digital_twin_uri(
             match chariott_uri {
                 Some(value) => {
                     info!("The URI for the in-vehicle digital twin service will be retrieved from Chariott.");
-                    match retry_async_based_on_status(
+                    let chariott_client = ChariottClient::new(&value, None);
+                    let query = ServiceQuery {
+                        namespace: INVEHICLE_DIGITAL_TWIN_SERVICE_NAMESPACE.to_string(),
+                        name: INVEHICLE_DIGITAL_TWIN_SERVICE_NAME.to_string(),
+                        version: INVEHICLE_DIGITAL_TWIN_SERVICE_VERSION.to_string(),
+                        communication_kind: INVEHICLE_DIGITAL_TWIN_SERVICE_COMMUNICATION_KIND.to_string(),
+                        communication_reference: INVEHICLE_DIGITAL_TWIN_SERVICE_COMMUNICATION_REFERENCE.to_string(),
+                        policy: DiscoveryPolicy::Exact,
+                    };
+                    match execute_with_retry(
                         30,
                         Duration::from_secs(1),
-                        || discover_service_using_chariott(
-                            &value,
-                            INVEHICLE_DIGITAL_TWIN_SERVICE_NAMESPACE,
-                            INVEHICLE_DIGITAL_TWIN_SERVICE_NAME,
-                            INVEHICLE_DIGITAL_TWIN_SERVICE_VERSION,
-                            INVEHICLE_DIGITAL_TWIN_SERVICE_COMMUNICATION_KIND,
-                            INVEHICLE_DIGITAL_TWIN_SERVICE_COMMUNICATION_REFERENCE)
+                        || chariott_client.discover(&query),
+                        Some(String::from("Attempting to discover the in-vehicle digital twin service with Chariott."))
                     ).await {
                         Ok(value) => value,
                         Err(error) => Err(format!("Failed to discover the in-vehicle digital twin service's URI due to error: {error}"))?
@@ -231,30 +183,3 @@ pub async fn retrieve_invehicle_digital_twin_uri(
 
     Ok(result)
 }
-
-#[cfg(test)]
-mod ibeji_common_utils_tests {
-    use super::*;
-
-    #[test]
-    fn is_subset_test() {
-        assert!(is_subset(&[], &[]));
-        assert!(is_subset(&[], &["one".to_string()]));
-        assert!(is_subset(&[], &["one".to_string(), "two".to_string()]));
-        assert!(is_subset(&["one".to_string()], &["one".to_string()]));
-        assert!(is_subset(&["one".to_string()], &["one".to_string(), "two".to_string()]));
-        assert!(is_subset(
-            &["one".to_string(), "two".to_string()],
-            &["one".to_string(), "two".to_string()]
-        ));
-        assert!(!is_subset(
-            &["one".to_string(), "two".to_string(), "three".to_string()],
-            &["one".to_string(), "two".to_string()]
-        ));
-        assert!(!is_subset(
-            &["one".to_string(), "two".to_string(), "three".to_string()],
-            &["one".to_string()]
-        ));
-        assert!(!is_subset(&["one".to_string(), "two".to_string(), "three".to_string()], &[]));
-    }
-}
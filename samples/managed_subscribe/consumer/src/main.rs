@@ -4,8 +4,9 @@
 
 use std::env;
 
+use common::trace_context::traced_request;
+use common::tracing_settings::{init_tracing, shutdown_tracing};
 use digital_twin_model::sdv_v0 as sdv;
-use env_logger::{Builder, Target};
 use log::{debug, info, LevelFilter};
 use paho_mqtt as mqtt;
 use samples_common::constants::{constraint_type, digital_twin_operation, digital_twin_protocol};
@@ -20,17 +21,20 @@ use samples_protobuf_data_access::module::managed_subscribe::v1::{
 use tokio::signal;
 use tokio::task::JoinHandle;
 use tokio::time::Duration;
-use tonic::{Request, Status};
+use tonic::Status;
+use tracing::instrument;
 use uuid::Uuid;
 
 const FREQUENCY_MS_FLAG: &str = "freq_ms=";
 const MQTT_CLIENT_ID: &str = "managed-subscribe-consumer";
+const SERVICE_NAME: &str = "managed-subscribe-consumer";
 
 /// Get subscription information from managed subscribe endpoint.
 ///
 /// # Arguments
 /// * `managed_subscribe_uri` - The managed subscribe URI.
 /// * `constraints` - Constraints for the managed topic.
+#[instrument(skip_all)]
 async fn get_ambient_air_temperature_subscription_info(
     managed_subscribe_uri: &str,
     constraints: Vec<Constraint>,
@@ -40,7 +44,7 @@ async fn get_ambient_air_temperature_subscription_info(
         .await
         .map_err(|err| Status::from_error(err.into()))?;
 
-    let request = Request::new(SubscriptionInfoRequest {
+    let request = traced_request(SubscriptionInfoRequest {
         entity_id: sdv::hvac::ambient_air_temperature::ID.to_string(),
         constraints,
     });
@@ -128,12 +132,13 @@ async fn receive_ambient_air_temperature_updates(
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Setup logging.
-    Builder::new().filter(None, LevelFilter::Info).target(Target::Stdout).init();
+    let settings = consumer_config::load_settings();
 
-    info!("The Consumer has started.");
+    // Setup tracing.
+    init_tracing(SERVICE_NAME, LevelFilter::Info, settings.tracing.as_ref(), None)
+        .map_err(|error| error as Box<dyn std::error::Error>)?;
 
-    let settings = consumer_config::load_settings();
+    info!("The Consumer has started.");
 
     let invehicle_digital_twin_uri = retrieve_invehicle_digital_twin_uri(
         settings.invehicle_digital_twin_uri,
@@ -196,5 +201,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Wait for subscriber task to cleanly shutdown.
     _ = sub_handle.await;
 
+    shutdown_tracing();
+
     Ok(())
 }
@@ -0,0 +1,101 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+use common::trace_context::traced_request;
+use digital_twin_model::sdv_v0 as sdv;
+use log::{debug, warn};
+use samples_common::constants::{digital_twin_operation, digital_twin_protocol};
+use samples_protobuf_data_access::invehicle_digital_twin::v1::invehicle_digital_twin_client::InvehicleDigitalTwinClient;
+use samples_protobuf_data_access::invehicle_digital_twin::v1::{
+    EndpointInfo, EntityAccessInfo, RegisterRequest,
+};
+use tokio::sync::watch;
+use tokio::time::{sleep, Duration};
+use tonic::Status;
+
+const PROVIDER_ID: &str = "managed-subscribe-provider";
+
+/// Register the ambient air temperature property's endpoint.
+///
+/// # Arguments
+/// * `invehicle_digital_twin_uri` - The In-Vehicle Digital Twin URI.
+/// * `provider_uri` - The provider's URI.
+pub async fn register_ambient_air_temperature(
+    invehicle_digital_twin_uri: &str,
+    provider_uri: &str,
+) -> Result<(), Status> {
+    let endpoint_info = EndpointInfo {
+        protocol: digital_twin_protocol::GRPC.to_string(),
+        operations: vec![digital_twin_operation::MANAGEDSUBSCRIBE.to_string()],
+        uri: provider_uri.to_string(),
+        context: "GetSubscriptionInfo".to_string(),
+        provider_id: PROVIDER_ID.to_string(),
+    };
+
+    let entity_access_info = EntityAccessInfo {
+        name: sdv::hvac::ambient_air_temperature::NAME.to_string(),
+        id: sdv::hvac::ambient_air_temperature::ID.to_string(),
+        description: sdv::hvac::ambient_air_temperature::DESCRIPTION.to_string(),
+        endpoint_info_list: vec![endpoint_info],
+        instance_id: String::new(),
+        relationship_list: vec![],
+    };
+
+    let mut client = InvehicleDigitalTwinClient::connect(invehicle_digital_twin_uri.to_string())
+        .await
+        .map_err(|e| Status::internal(e.to_string()))?;
+    let request = traced_request(RegisterRequest {
+        entity_access_info_list: vec![entity_access_info],
+        lease_duration_seconds: 0,
+    });
+    let _response = client.register(request).await?;
+
+    Ok(())
+}
+
+/// Start the ambient air temperature data stream.
+///
+/// # Arguments
+/// `min_interval_ms` - minimum frequency for data stream.
+pub fn start_ambient_air_temperature_data_stream(min_interval_ms: u64) -> watch::Receiver<i32> {
+    debug!("Starting the Provider's ambient air temperature data stream.");
+    let mut temperature: i32 = 75;
+    let (sender, reciever) = watch::channel(temperature);
+    tokio::spawn(async move {
+        let mut is_temperature_increasing: bool = true;
+        loop {
+            debug!(
+                "Recording new value for {} of {temperature}",
+                sdv::hvac::ambient_air_temperature::ID
+            );
+
+            if let Err(err) = sender.send(temperature) {
+                warn!("Failed to get new value due to '{err:?}'");
+                break;
+            }
+
+            debug!("Completed the publish request");
+
+            // Calculate the new temperature.
+            // It bounces back and forth between 65 and 85 degrees.
+            if is_temperature_increasing {
+                if temperature == 85 {
+                    is_temperature_increasing = false;
+                    temperature -= 1;
+                } else {
+                    temperature += 1;
+                }
+            } else if temperature == 65 {
+                is_temperature_increasing = true;
+                temperature += 1;
+            } else {
+                temperature -= 1;
+            }
+
+            sleep(Duration::from_millis(min_interval_ms)).await;
+        }
+    });
+
+    reciever
+}
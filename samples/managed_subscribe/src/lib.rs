@@ -0,0 +1,8 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+#[path = "../provider/src/provider.rs"]
+pub mod provider;
+#[path = "../provider/src/provider_impl.rs"]
+pub mod provider_impl;
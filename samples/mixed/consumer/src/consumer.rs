@@ -0,0 +1,287 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+use common::trace_context::traced_request;
+use digital_twin_model::{sdv_v0 as sdv, Metadata};
+use log::{debug, info, warn};
+use samples_common::constants::{digital_twin_operation, digital_twin_protocol};
+use samples_common::utils::{discover_digital_twin_provider_using_ibeji, retry_async_based_on_status};
+use samples_protobuf_data_access::sample_grpc::v1::digital_twin_provider::digital_twin_provider_client::DigitalTwinProviderClient;
+use samples_protobuf_data_access::sample_grpc::v1::digital_twin_provider::{
+    InvokeRequest, SetRequest, SubscribeRequest,
+};
+use serde_derive::{Deserialize, Serialize};
+use tokio::time::{sleep, Duration};
+use tonic::Status;
+use tracing::instrument;
+use uuid::Uuid;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct IsAirConditioningActiveProperty {
+    #[serde(rename = "IsAirConditioningActive")]
+    is_air_conditioning_active: sdv::hvac::is_air_conditioning_active::TYPE,
+    #[serde(rename = "$metadata")]
+    metadata: Metadata,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ShowNotificationRequestPayload {
+    #[serde(rename = "Notification")]
+    notification: sdv::hmi::show_notification::request::TYPE,
+    #[serde(rename = "$metadata")]
+    metadata: Metadata,
+}
+
+/// The URIs of the providers of the entities that the consumer uses.
+#[derive(Clone, Debug)]
+pub struct ProviderUris {
+    pub show_notification: String,
+    pub ambient_air_temperature: String,
+    pub is_air_conditioning_active: String,
+    pub hybrid_battery_remaining: String,
+}
+
+/// Discover the provider of an entity that supports the operations using the In-Vehicle Digital
+/// Twin Service, and return its URI.
+///
+/// # Arguments
+/// `invehicle_digital_twin_uri` - The In-Vehicle Digital Twin URI.
+/// `entity_id` - The entity id.
+/// `operations` - The operations that the provider must support.
+async fn discover_provider_uri(
+    invehicle_digital_twin_uri: &str,
+    entity_id: &str,
+    operations: &[&str],
+) -> Result<String, String> {
+    let operations: Vec<String> =
+        operations.iter().map(|operation| operation.to_string()).collect();
+    let endpoint_info = discover_digital_twin_provider_using_ibeji(
+        invehicle_digital_twin_uri,
+        entity_id,
+        digital_twin_protocol::GRPC,
+        &operations,
+    )
+    .await?;
+
+    Ok(endpoint_info.uri)
+}
+
+/// Discover the providers of the entities that the consumer uses.
+///
+/// # Arguments
+/// `invehicle_digital_twin_uri` - The In-Vehicle Digital Twin URI.
+pub async fn discover_providers(invehicle_digital_twin_uri: &str) -> Result<ProviderUris, String> {
+    Ok(ProviderUris {
+        show_notification: discover_provider_uri(
+            invehicle_digital_twin_uri,
+            sdv::hmi::show_notification::ID,
+            &[digital_twin_operation::INVOKE],
+        )
+        .await?,
+        ambient_air_temperature: discover_provider_uri(
+            invehicle_digital_twin_uri,
+            sdv::hvac::ambient_air_temperature::ID,
+            &[digital_twin_operation::SUBSCRIBE],
+        )
+        .await?,
+        is_air_conditioning_active: discover_provider_uri(
+            invehicle_digital_twin_uri,
+            sdv::hvac::is_air_conditioning_active::ID,
+            &[digital_twin_operation::SUBSCRIBE, digital_twin_operation::SET],
+        )
+        .await?,
+        hybrid_battery_remaining: discover_provider_uri(
+            invehicle_digital_twin_uri,
+            sdv::obd::hybrid_battery_remaining::ID,
+            &[digital_twin_operation::SUBSCRIBE],
+        )
+        .await?,
+    })
+}
+
+/// Send a subscribe request.
+///
+/// # Arguments
+/// `provider_uri` - The provider's URI.
+/// `entity_id` - The entity id.
+/// `consumer_uri` - The consumer's URI.
+#[instrument(skip_all)]
+pub async fn send_subscribe_request(
+    provider_uri: &str,
+    entity_id: &str,
+    consumer_uri: &str,
+) -> Result<(), Status> {
+    info!("Sending a subscribe request for entity id {entity_id} to provider URI {provider_uri}");
+    let mut client = DigitalTwinProviderClient::connect(provider_uri.to_string())
+        .await
+        .map_err(|e| Status::internal(e.to_string()))?;
+    let request = traced_request(SubscribeRequest {
+        entity_id: entity_id.to_string(),
+        consumer_uri: consumer_uri.to_string(),
+    });
+    let _response = client.subscribe(request).await?;
+
+    Ok(())
+}
+
+/// Subscribe to the properties that the consumer uses.
+///
+/// # Arguments
+/// `provider_uris` - The URIs of the providers.
+/// `consumer_uri` - The consumer's URI.
+pub async fn subscribe_to_properties(
+    provider_uris: &ProviderUris,
+    consumer_uri: &str,
+) -> Result<(), Status> {
+    for (provider_uri, entity_id) in [
+        (&provider_uris.ambient_air_temperature, sdv::hvac::ambient_air_temperature::ID),
+        (&provider_uris.is_air_conditioning_active, sdv::hvac::is_air_conditioning_active::ID),
+        (&provider_uris.hybrid_battery_remaining, sdv::obd::hybrid_battery_remaining::ID),
+    ] {
+        retry_async_based_on_status(30, Duration::from_secs(1), || {
+            send_subscribe_request(provider_uri, entity_id, consumer_uri)
+        })
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Send a set request that activates or deactivates the air conditioning.
+///
+/// # Arguments
+/// `provider_uri` - The provider's URI.
+/// `is_active` - Whether the air conditioning should be active.
+#[instrument(skip_all)]
+pub async fn send_set_request(provider_uri: &str, is_active: bool) -> Result<(), Status> {
+    info!("Sending a set request for entity id {} to the value '{is_active}' to provider URI {provider_uri}",
+        sdv::hvac::is_air_conditioning_active::ID);
+
+    let metadata: Metadata =
+        Metadata { model: sdv::hvac::is_air_conditioning_active::ID.to_string() };
+    let property: IsAirConditioningActiveProperty =
+        IsAirConditioningActiveProperty { is_air_conditioning_active: is_active, metadata };
+
+    let value = serde_json::to_string_pretty(&property).unwrap();
+
+    let mut client = DigitalTwinProviderClient::connect(provider_uri.to_string())
+        .await
+        .map_err(|e| Status::unavailable(e.to_string()))?;
+
+    let request = traced_request(SetRequest {
+        entity_id: sdv::hvac::is_air_conditioning_active::ID.to_string(),
+        value,
+    });
+
+    let _response = client.set(request).await?;
+
+    debug!("Completed the set request.");
+
+    Ok(())
+}
+
+/// Send an invoke request for the show-notification command. The provider responds to the
+/// consumer with the response id.
+///
+/// # Arguments
+/// `provider_uri` - The provider's URI.
+/// `consumer_uri` - The consumer's URI.
+/// `response_id` - The id that the provider responds with.
+#[instrument(skip_all)]
+pub async fn send_invoke_request(
+    provider_uri: &str,
+    consumer_uri: &str,
+    response_id: &str,
+) -> Result<(), Status> {
+    let metadata = Metadata { model: sdv::hmi::show_notification::request::ID.to_string() };
+
+    let request_payload: ShowNotificationRequestPayload = ShowNotificationRequestPayload {
+        notification: "The show-notification request.".to_string(),
+        metadata,
+    };
+
+    let request_payload_json = serde_json::to_string(&request_payload).unwrap();
+
+    info!(
+        "Sending an invoke request on entity {} with payload '{}' to provider URI {provider_uri}",
+        sdv::hmi::show_notification::ID,
+        &request_payload_json
+    );
+
+    let mut client = DigitalTwinProviderClient::connect(provider_uri.to_string())
+        .await
+        .map_err(|e| Status::unavailable(e.to_string()))?;
+
+    let request = traced_request(InvokeRequest {
+        entity_id: sdv::hmi::show_notification::ID.to_string(),
+        consumer_uri: consumer_uri.to_string(),
+        response_id: response_id.to_string(),
+        payload: request_payload_json,
+    });
+
+    let _response = client.invoke(request).await?;
+
+    debug!("Completed the invoke request");
+
+    Ok(())
+}
+
+/// Start the show-notification repeater.
+///
+/// # Arguments
+/// `provider_uri` - The provider_uri.
+/// `consumer_uri` - The consumer_uri.
+/// `interval` - How long to wait between the invoke requests.
+pub fn start_show_notification_repeater(
+    provider_uri: String,
+    consumer_uri: String,
+    interval: Duration,
+) {
+    debug!("Starting the Consumer's show-notification repeater.");
+
+    tokio::spawn(async move {
+        loop {
+            let response_id = Uuid::new_v4().to_string();
+
+            match send_invoke_request(&provider_uri, &consumer_uri, &response_id).await {
+                Ok(_) => (),
+                Err(status) if status.code() == tonic::Code::Unavailable => {
+                    warn!("Unable to connect. We will retry in a moment.");
+                    sleep(Duration::from_secs(1)).await;
+                    continue;
+                }
+                Err(status) => warn!("{status:?}"),
+            }
+
+            sleep(interval).await;
+        }
+    });
+}
+
+/// Start the activate-air-conditioing repeater.
+///
+/// # Arguments
+/// `provider_uri` - The provider_uri.
+/// `interval` - How long to wait between the set requests.
+pub fn start_activate_air_conditioning_repeater(provider_uri: String, interval: Duration) {
+    debug!("Starting the Consumer's activate-air-conditioning repeater.");
+
+    tokio::spawn(async move {
+        let mut is_active = true;
+
+        loop {
+            match send_set_request(&provider_uri, is_active).await {
+                Ok(_) => is_active = !is_active,
+                Err(status) if status.code() == tonic::Code::Unavailable => {
+                    warn!("Unable to connect. We will retry in a moment.");
+                    sleep(Duration::from_secs(1)).await;
+                    continue;
+                }
+                Err(status) => warn!("{status:?}"),
+            }
+
+            sleep(interval).await;
+        }
+    });
+}
@@ -3,14 +3,40 @@
 // SPDX-License-Identifier: MIT
 
 use log::info;
+use parking_lot::Mutex;
 use samples_protobuf_data_access::sample_grpc::v1::digital_twin_consumer::digital_twin_consumer_server::DigitalTwinConsumer;
 use samples_protobuf_data_access::sample_grpc::v1::digital_twin_consumer::{
     PublishRequest, PublishResponse, RespondRequest, RespondResponse,
 };
+use std::collections::HashMap;
+use std::sync::Arc;
 use tonic::{Request, Response, Status};
 
-#[derive(Debug, Default)]
-pub struct ConsumerImpl {}
+#[derive(Clone, Debug, Default)]
+pub struct ConsumerImpl {
+    /// The latest value that has been published for each entity id.
+    latest_values: Arc<Mutex<HashMap<String, String>>>,
+    /// The payload of each response that has been received, by response id.
+    responses: Arc<Mutex<HashMap<String, String>>>,
+}
+
+impl ConsumerImpl {
+    /// Get the latest value that has been published for an entity.
+    ///
+    /// # Arguments
+    /// * `entity_id` - The entity id.
+    pub fn latest_value(&self, entity_id: &str) -> Option<String> {
+        self.latest_values.lock().get(entity_id).cloned()
+    }
+
+    /// Get the payload of a response that has been received.
+    ///
+    /// # Arguments
+    /// * `response_id` - The response id.
+    pub fn response(&self, response_id: &str) -> Option<String> {
+        self.responses.lock().get(response_id).cloned()
+    }
+}
 
 #[tonic::async_trait]
 impl DigitalTwinConsumer for ConsumerImpl {
@@ -26,6 +52,8 @@ impl DigitalTwinConsumer for ConsumerImpl {
 
         info!("Received a publish request for entity id {entity_id} with the value {value}");
 
+        self.latest_values.lock().insert(entity_id, value);
+
         let response = PublishResponse {};
 
         Ok(Response::new(response))
@@ -43,6 +71,8 @@ impl DigitalTwinConsumer for ConsumerImpl {
 
         info!("Received a respond request for entity id {entity_id} with the response id {response_id} and the payload '{payload}'");
 
+        self.responses.lock().insert(response_id, payload);
+
         let response = RespondResponse {};
 
         Ok(Response::new(response))
@@ -56,7 +86,7 @@ mod consumer_impl_tests {
 
     #[tokio::test]
     async fn publish_test() {
-        let consumer_impl = ConsumerImpl {};
+        let consumer_impl = ConsumerImpl::default();
 
         let entity_id = String::from("some-id");
         let value = String::from("some-value");
@@ -64,18 +94,24 @@ mod consumer_impl_tests {
         let request = tonic::Request::new(PublishRequest { entity_id, value });
         let result = consumer_impl.publish(request).await;
         assert!(result.is_ok());
+        assert_eq!(consumer_impl.latest_value("some-id").as_deref(), Some("some-value"));
     }
 
     #[tokio::test]
     async fn respond_test() {
-        let consumer_impl = ConsumerImpl {};
+        let consumer_impl = ConsumerImpl::default();
 
         let entity_id = String::from("some-id");
         let response_id = Uuid::new_v4().to_string();
         let payload = String::from("some-payload");
 
-        let request = tonic::Request::new(RespondRequest { entity_id, response_id, payload });
+        let request = tonic::Request::new(RespondRequest {
+            entity_id,
+            response_id: response_id.clone(),
+            payload,
+        });
         let result = consumer_impl.respond(request).await;
         assert!(result.is_ok());
+        assert_eq!(consumer_impl.response(&response_id).as_deref(), Some("some-payload"));
     }
 }
@@ -2,6 +2,7 @@
 // Licensed under the MIT license.
 // SPDX-License-Identifier: MIT
 
+use common::trace_context::traced_request;
 use digital_twin_model::sdv_v0 as sdv;
 use log::{debug, info, warn};
 use parking_lot::{Mutex, MutexGuard};
@@ -18,6 +19,7 @@ use std::pin::Pin;
 use std::sync::Arc;
 use tokio_stream::Stream;
 use tonic::{Request, Response, Status};
+use tracing::{Instrument, Span};
 
 use crate::vehicle::Vehicle;
 
@@ -174,7 +176,7 @@ impl DigitalTwinProvider for ProviderImpl {
             "Received an invoke request from for entity id {entity_id} with payload '{payload}' from consumer URI {consumer_uri}"
         );
 
-        tokio::spawn(async move {
+        let send_response = async move {
             let mut response_payload: String = format!("Successfully invoked {entity_id}");
 
             if entity_id == sdv::hmi::show_notification::ID {
@@ -193,14 +195,17 @@ impl DigitalTwinProvider for ProviderImpl {
             }
             let mut client = client_result.unwrap();
 
-            let respond_request = tonic::Request::new(RespondRequest {
+            let respond_request = traced_request(RespondRequest {
                 entity_id,
                 response_id,
                 payload: response_payload,
             });
 
             client.respond(respond_request).await
-        });
+        };
+
+        // The response is sent within the invoke request's span, so that it is correlated with it.
+        tokio::spawn(send_response.instrument(Span::current()));
 
         let response = InvokeResponse {};
 
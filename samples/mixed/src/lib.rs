@@ -0,0 +1,14 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+#[path = "../consumer/src/consumer.rs"]
+pub mod consumer;
+#[path = "../consumer/src/consumer_impl.rs"]
+pub mod consumer_impl;
+#[path = "../provider/src/provider.rs"]
+pub mod provider;
+#[path = "../provider/src/provider_impl.rs"]
+pub mod provider_impl;
+#[path = "../provider/src/vehicle.rs"]
+pub mod vehicle;
@@ -2,8 +2,8 @@
 // Licensed under the MIT license.
 // SPDX-License-Identifier: MIT
 
+use common::tracing_settings::{init_tracing, shutdown_tracing};
 use digital_twin_model::sdv_v0 as sdv;
-use env_logger::{Builder, Target};
 use log::{debug, info, LevelFilter};
 use paho_mqtt as mqtt;
 use samples_common::constants::{digital_twin_operation, digital_twin_protocol};
@@ -15,6 +15,7 @@ use tokio::time::Duration;
 use tonic::Status;
 
 const MQTT_CLIENT_ID: &str = "property-consumer";
+const SERVICE_NAME: &str = "property-consumer";
 
 /// Receive Ambient Air Temperature updates.
 ///
@@ -74,12 +75,13 @@ fn receive_ambient_air_temperature_updates(broker_uri: &str, topic: &str) -> Res
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Setup logging.
-    Builder::new().filter(None, LevelFilter::Info).target(Target::Stdout).init();
+    let settings = consumer_config::load_settings();
 
-    info!("The Consumer has started.");
+    // Setup tracing.
+    init_tracing(SERVICE_NAME, LevelFilter::Info, settings.tracing.as_ref(), None)
+        .map_err(|error| error as Box<dyn std::error::Error>)?;
 
-    let settings = consumer_config::load_settings();
+    info!("The Consumer has started.");
 
     let invehicle_digital_twin_uri = retrieve_invehicle_digital_twin_uri(
         settings.invehicle_digital_twin_uri,
@@ -106,5 +108,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     info!("The Consumer has completed.");
 
+    shutdown_tracing();
+
     Ok(())
 }
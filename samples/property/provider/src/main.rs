@@ -2,8 +2,9 @@
 // Licensed under the MIT license.
 // SPDX-License-Identifier: MIT
 
+use common::trace_context::traced_request;
+use common::tracing_settings::{init_tracing, shutdown_tracing};
 use digital_twin_model::{sdv_v0 as sdv, Metadata};
-use env_logger::{Builder, Target};
 use log::{debug, info, warn, LevelFilter};
 use paho_mqtt as mqtt;
 use samples_common::constants::{digital_twin_operation, digital_twin_protocol};
@@ -18,7 +19,9 @@ use tokio::signal;
 use tokio::time::{sleep, Duration};
 use tonic::Status;
 
+const PROVIDER_ID: &str = "property-provider";
 const MQTT_CLIENT_ID: &str = "property-subscriber";
+const SERVICE_NAME: &str = "property-provider";
 
 #[derive(Debug, Serialize, Deserialize)]
 struct Property {
@@ -44,6 +47,7 @@ async fn register_ambient_air_temperature(
         operations: vec![digital_twin_operation::SUBSCRIBE.to_string()],
         uri: broker_uri.to_string(),
         context: topic.to_string(),
+        provider_id: PROVIDER_ID.to_string(),
     };
 
     let entity_access_info = EntityAccessInfo {
@@ -51,13 +55,17 @@ async fn register_ambient_air_temperature(
         id: sdv::hvac::ambient_air_temperature::ID.to_string(),
         description: sdv::hvac::ambient_air_temperature::DESCRIPTION.to_string(),
         endpoint_info_list: vec![endpoint_info],
+        instance_id: String::new(),
+        relationship_list: vec![],
     };
 
     let mut client = InvehicleDigitalTwinClient::connect(invehicle_digital_twin_uri.to_string())
         .await
         .map_err(|e| Status::internal(e.to_string()))?;
-    let request =
-        tonic::Request::new(RegisterRequest { entity_access_info_list: vec![entity_access_info] });
+    let request = traced_request(RegisterRequest {
+        entity_access_info_list: vec![entity_access_info],
+        lease_duration_seconds: 0,
+    });
     let _response = client.register(request).await?;
 
     Ok(())
@@ -180,12 +188,13 @@ fn convert_dtmi_to_topic(dtmi: &str) -> Result<String, String> {
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Setup logging.
-    Builder::new().filter(None, LevelFilter::Info).target(Target::Stdout).init();
+    let settings = provider_config::load_settings();
 
-    info!("The Provider has started.");
+    // Setup tracing.
+    init_tracing(SERVICE_NAME, LevelFilter::Info, settings.tracing.as_ref(), None)
+        .map_err(|error| error as Box<dyn std::error::Error>)?;
 
-    let settings = provider_config::load_settings();
+    info!("The Provider has started.");
 
     let provider_authority = settings.provider_authority;
 
@@ -213,5 +222,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     info!("The Provider has completed.");
 
+    shutdown_tracing();
+
     Ok(())
 }
@@ -8,8 +8,18 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     tonic_build::compile_protos("../interfaces/async_rpc/v1/respond.proto")?;
     tonic_build::compile_protos("../interfaces/async_rpc/v1/request.proto")?;
     tonic_build::configure()
-        .message_attribute("EndpointInfo", "#[derive(serde::Deserialize, serde::Serialize)]")
-        .message_attribute("EntityAccessInfo", "#[derive(serde::Deserialize, serde::Serialize)]")
+        .message_attribute(
+            "EndpointInfo",
+            "#[derive(serde::Deserialize, serde::Serialize)] #[serde(default)]",
+        )
+        .message_attribute(
+            "EntityAccessInfo",
+            "#[derive(serde::Deserialize, serde::Serialize)] #[serde(default)]",
+        )
+        .message_attribute(
+            "RelationshipInfo",
+            "#[derive(serde::Deserialize, serde::Serialize)] #[serde(default)]",
+        )
         .compile(
             &["../../interfaces/invehicle_digital_twin/v1/invehicle_digital_twin.proto"],
             &["../../interfaces/invehicle_digital_twin/v1/"],
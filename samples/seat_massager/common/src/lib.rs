@@ -5,11 +5,11 @@
 use serde_derive::{Deserialize, Serialize};
 
 /// A targeted payload.
-/// The targeting details helps on the receiver's side to dispatch the request.
+/// The targeting details helps on the receiver's side to dispatch the request. The target entity is
+/// the instance whose provider receives the request, which is found by its instance id in the
+/// In-Vehicle Digital Twin Service.
 #[derive(Serialize, Deserialize, Debug)]
 pub struct TargetedPayload {
-    /// The instance id for the target entity.
-    pub instance_id: String,
     /// The path within the target entity to member that we are targeting.
     pub member_path: String,
     /// The operation to be performed on the target entity's member.
@@ -4,39 +4,80 @@
 
 mod respond_impl;
 
+use common::trace_context::{traced_request, TraceContextLayer};
+use common::tracing_settings::{init_tracing, shutdown_tracing};
 use digital_twin_model::sdv_v1 as sdv;
-use env_logger::{Builder, Target};
 use log::{debug, error, info, warn, LevelFilter};
 use rand::rngs::StdRng;
 use rand::Rng;
 use rand::SeedableRng; // trait needed to initialize StdRng
 use samples_common::constants::{digital_twin_operation, digital_twin_protocol};
 use samples_common::consumer_config;
-use samples_common::utils::{
-    discover_digital_twin_provider_using_ibeji, retrieve_invehicle_digital_twin_uri,
-};
+use samples_common::utils::retrieve_invehicle_digital_twin_uri;
 use samples_protobuf_data_access::async_rpc::v1::request::request_client::RequestClient;
 use samples_protobuf_data_access::async_rpc::v1::request::AskRequest;
 use samples_protobuf_data_access::async_rpc::v1::respond::respond_server::RespondServer;
 use samples_protobuf_data_access::async_rpc::v1::respond::AnswerRequest;
+use samples_protobuf_data_access::invehicle_digital_twin::v1::invehicle_digital_twin_client::InvehicleDigitalTwinClient;
+use samples_protobuf_data_access::invehicle_digital_twin::v1::FindByIdRequest;
 use std::net::SocketAddr;
 use tokio::sync::mpsc;
 use tokio::time::{sleep, timeout, Duration};
 use tonic::transport::Server;
+use tracing::{info_span, Instrument};
 use uuid::Uuid;
 
 use seat_massager_common::TargetedPayload;
 
+const SERVICE_NAME: &str = "seat-massager-consumer";
+
+/// Find an instance of the premium airbag seat massager that can be invoked over gRPC.
+/// Returns the instance's id and its provider's URI.
+///
+/// # Arguments
+/// `invehicle_digital_twin_uri` - The In-Vehicle Digital Twin URI.
+async fn find_seat_massager_instance(
+    invehicle_digital_twin_uri: &str,
+) -> Result<(String, String), String> {
+    info!("Sending a find_by_id request for entity id {} to the In-Vehicle Digital Twin Service URI {invehicle_digital_twin_uri}", sdv::premium_airbag_seat_massager::ID);
+
+    let mut client = InvehicleDigitalTwinClient::connect(invehicle_digital_twin_uri.to_string())
+        .await
+        .map_err(|error| error.to_string())?;
+    let request = traced_request(FindByIdRequest {
+        id: sdv::premium_airbag_seat_massager::ID.to_string(),
+        instance_id: String::new(),
+    });
+    let response = client.find_by_id(request).await.map_err(|error| error.to_string())?;
+
+    // The response lists every instance of the model, each with its own instance id.
+    response
+        .into_inner()
+        .entity_access_info_list
+        .into_iter()
+        .find_map(|entity_access_info| {
+            entity_access_info
+                .endpoint_info_list
+                .into_iter()
+                .find(|endpoint_info| {
+                    endpoint_info.protocol == digital_twin_protocol::GRPC
+                        && endpoint_info
+                            .operations
+                            .contains(&digital_twin_operation::INVOKE.to_string())
+                })
+                .map(|endpoint_info| (entity_access_info.instance_id, endpoint_info.uri))
+        })
+        .ok_or_else(|| "Did not find an instance that met our requirements".to_string())
+}
+
 /// Start the seat massage steps.
 ///
 /// # Arguments
 /// `consumer_uri` - The consumer uri.
-/// `instance_id` - The instance id.
-/// `provider_uri` - The provider uri.
+/// `provider_uri` - The uri of the targeted instance's provider.
 /// `rx` - The receiver for the asynchronous channel for AnswerRequest's.
 fn start_seat_massage_steps(
     consumer_uri: String,
-    instance_id: String,
     provider_uri: String,
     mut rx: mpsc::Receiver<AnswerRequest>,
 ) {
@@ -76,7 +117,6 @@ fn start_seat_massage_steps(
                 serde_json::to_string_pretty(&request_payload).unwrap();
 
             let targeted_payload = TargetedPayload {
-                instance_id: instance_id.clone(),
                 member_path: sdv::airbag_seat_massager::perform_step::NAME.to_string(),
                 operation: digital_twin_operation::INVOKE.to_string(),
                 payload: request_payload_json,
@@ -85,14 +125,18 @@ fn start_seat_massage_steps(
             // Serialize the targeted payload.
             let targeted_payload_json = serde_json::to_string_pretty(&targeted_payload).unwrap();
 
-            let request = tonic::Request::new(AskRequest {
-                respond_uri: consumer_uri.clone(),
-                ask_id: ask_id.clone(),
-                payload: targeted_payload_json.clone(),
+            // Each ask starts a trace, which the provider's answer is correlated with.
+            let span = info_span!("ask_perform_step");
+            let request = span.in_scope(|| {
+                traced_request(AskRequest {
+                    respond_uri: consumer_uri.clone(),
+                    ask_id: ask_id.clone(),
+                    payload: targeted_payload_json.clone(),
+                })
             });
 
             // Send the ask.
-            let response = client.ask(request).await;
+            let response = client.ask(request).instrument(span).await;
             if let Err(status) = response {
                 warn!("Unable to call ask, due to {status:?}\nWe will retry in a moment.");
                 sleep(Duration::from_secs(1)).await;
@@ -145,12 +189,13 @@ fn start_seat_massage_steps(
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Setup logging.
-    Builder::new().filter(None, LevelFilter::Info).target(Target::Stdout).init();
+    let settings = consumer_config::load_settings();
 
-    info!("The Consumer has started.");
+    // Setup tracing.
+    init_tracing(SERVICE_NAME, LevelFilter::Info, settings.tracing.as_ref(), None)
+        .map_err(|error| error as Box<dyn std::error::Error>)?;
 
-    let settings = consumer_config::load_settings();
+    info!("The Consumer has started.");
 
     let invehicle_digital_twin_uri = retrieve_invehicle_digital_twin_uri(
         settings.invehicle_digital_twin_uri,
@@ -169,29 +214,28 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Setup the HTTP server.
     let addr: SocketAddr = consumer_authority.parse().unwrap();
-    let server_future = Server::builder().add_service(RespondServer::new(respond_impl)).serve(addr);
+    let server_future = Server::builder()
+        .layer(TraceContextLayer::new())
+        .add_service(RespondServer::new(respond_impl))
+        .serve(addr);
     info!("The HTTP server is listening on address '{consumer_authority}'");
 
-    // Retrieve the provider URI.
-    let provider_endpoint_info = discover_digital_twin_provider_using_ibeji(
-        &invehicle_digital_twin_uri,
-        sdv::premium_airbag_seat_massager::ID,
-        digital_twin_protocol::GRPC,
-        &[digital_twin_operation::INVOKE.to_string()],
-    )
-    .await
-    .unwrap();
-    let provider_uri = provider_endpoint_info.uri;
-    let instance_id = provider_endpoint_info.context;
-    info!("The URI for the premium seat massager's provider is {provider_uri}");
+    // Retrieve the instance and its provider URI.
+    let (instance_id, provider_uri) =
+        find_seat_massager_instance(&invehicle_digital_twin_uri).await.unwrap();
+    info!(
+        "The URI for the premium seat massager instance {instance_id}'s provider is {provider_uri}"
+    );
 
     let consumer_uri = format!("http://{consumer_authority}"); // Devskim: ignore DS137138
 
-    start_seat_massage_steps(consumer_uri.clone(), instance_id, provider_uri.clone(), rx);
+    start_seat_massage_steps(consumer_uri.clone(), provider_uri.clone(), rx);
 
     server_future.await?;
 
     debug!("The Consumer has completed.");
 
+    shutdown_tracing();
+
     Ok(())
 }
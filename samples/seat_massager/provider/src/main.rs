@@ -4,8 +4,9 @@
 
 mod request_impl;
 
+use common::trace_context::{traced_request, TraceContextLayer};
+use common::tracing_settings::{init_tracing, shutdown_tracing};
 use digital_twin_model::sdv_v1 as sdv;
-use env_logger::{Builder, Target};
 use log::{debug, info, LevelFilter};
 use parking_lot::Mutex;
 use samples_common::constants::{digital_twin_operation, digital_twin_protocol};
@@ -23,6 +24,9 @@ use tonic::{transport::Server, Status};
 
 use crate::request_impl::{RequestImpl, RequestState};
 
+const PROVIDER_ID: &str = "seat-massager-provider";
+const SERVICE_NAME: &str = "seat-massager-provider";
+
 /// Register the airbag seat massager's massage airbags property.
 ///
 /// # Arguments
@@ -41,7 +45,8 @@ async fn register_airbag_massager(
             digital_twin_operation::INVOKE.to_string(),
         ],
         uri: provider_uri.to_string(),
-        context: instance_id.to_string(),
+        context: String::new(),
+        provider_id: PROVIDER_ID.to_string(),
     };
 
     let entity_access_info = EntityAccessInfo {
@@ -49,13 +54,17 @@ async fn register_airbag_massager(
         id: sdv::premium_airbag_seat_massager::ID.to_string(),
         description: sdv::premium_airbag_seat_massager::DESCRIPTION.to_string(),
         endpoint_info_list: vec![endpoint_info],
+        instance_id: instance_id.to_string(),
+        relationship_list: vec![],
     };
 
     let mut client = InvehicleDigitalTwinClient::connect(invehicle_digital_twin_uri.to_string())
         .await
         .map_err(|e| Status::internal(e.to_string()))?;
-    let request =
-        tonic::Request::new(RegisterRequest { entity_access_info_list: vec![entity_access_info] });
+    let request = traced_request(RegisterRequest {
+        entity_access_info_list: vec![entity_access_info],
+        lease_duration_seconds: 0,
+    });
     let _response = client.register(request).await?;
 
     Ok(())
@@ -63,12 +72,13 @@ async fn register_airbag_massager(
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Setup logging.
-    Builder::new().filter(None, LevelFilter::Info).target(Target::Stdout).init();
+    let settings = provider_config::load_settings();
 
-    info!("The Provider has started.");
+    // Setup tracing.
+    init_tracing(SERVICE_NAME, LevelFilter::Info, settings.tracing.as_ref(), None)
+        .map_err(|error| error as Box<dyn std::error::Error>)?;
 
-    let settings = provider_config::load_settings();
+    info!("The Provider has started.");
 
     let provider_authority = settings.provider_authority;
 
@@ -86,8 +96,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Setup the HTTP server.
     let addr: SocketAddr = provider_authority.parse()?;
     let state = Arc::new(Mutex::new(RequestState {}));
-    let request_impl = RequestImpl { state };
-    let server_future = Server::builder().add_service(RequestServer::new(request_impl)).serve(addr);
+    let request_impl = RequestImpl { state, instance_id: instance_id.clone() };
+    let server_future = Server::builder()
+        .layer(TraceContextLayer::new())
+        .add_service(RequestServer::new(request_impl))
+        .serve(addr);
     info!("The HTTP server is listening on address '{provider_authority}'");
 
     info!("Sending a register request to the In-Vehicle Digital Twin Service URI {invehicle_digital_twin_uri}");
@@ -100,5 +113,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     debug!("The Provider has completed.");
 
+    shutdown_tracing();
+
     Ok(())
 }
@@ -2,6 +2,7 @@
 // Licensed under the MIT license.
 // SPDX-License-Identifier: MIT
 
+use common::trace_context::traced_request;
 use digital_twin_model::sdv_v1 as sdv;
 use log::{debug, error, info, warn};
 use parking_lot::Mutex;
@@ -13,6 +14,7 @@ use samples_protobuf_data_access::async_rpc::v1::respond::{
 };
 use seat_massager_common::{status, TargetedPayload};
 use std::sync::Arc;
+use tracing::{Instrument, Span};
 
 #[derive(Debug, Default)]
 pub struct RequestState {}
@@ -20,6 +22,8 @@ pub struct RequestState {}
 #[derive(Debug, Default)]
 pub struct RequestImpl {
     pub state: Arc<Mutex<RequestState>>,
+    /// The instance id that the seat massager is registered with.
+    pub instance_id: String,
 }
 
 #[tonic::async_trait]
@@ -44,7 +48,7 @@ impl Request for RequestImpl {
         // Deserialize the targeted payload.
         let targeted_payload_json: TargetedPayload = serde_json::from_str(&payload).unwrap();
 
-        info!("  instance_id: {}", targeted_payload_json.instance_id);
+        info!("  instance_id: {}", self.instance_id);
         info!("  member_path: {}", targeted_payload_json.member_path);
         info!("  operation: {}", targeted_payload_json.operation);
 
@@ -63,7 +67,7 @@ impl Request for RequestImpl {
         }
 
         // Asynchronously perform the step.
-        tokio::spawn(async move {
+        let perform_step = async move {
             let client_result = RespondClient::connect(respond_uri).await;
             if let Err(error_message) = client_result {
                 error!("Unable to connect due to {error_message}");
@@ -99,14 +103,17 @@ impl Request for RequestImpl {
                 serde_json::to_string_pretty(&response_payload).unwrap();
 
             let answer_request =
-                tonic::Request::new(AnswerRequest { ask_id, payload: response_payload_json });
+                traced_request(AnswerRequest { ask_id, payload: response_payload_json });
 
             // Send the answer.
             let response = client.answer(answer_request).await;
             if let Err(status) = response {
                 error!("Answer failed: {status:?}");
             }
-        });
+        };
+
+        // The answer is sent within the ask request's span, so that it is correlated with it.
+        tokio::spawn(perform_step.instrument(Span::current()));
 
         debug!("Completed the ask request.");
 
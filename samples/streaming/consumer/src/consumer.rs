@@ -0,0 +1,69 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+use common::trace_context::traced_request;
+use digital_twin_model::sdv_v0 as sdv;
+use log::{info, warn};
+use samples_common::constants::{digital_twin_operation, digital_twin_protocol};
+use samples_common::utils::discover_digital_twin_provider_using_ibeji;
+use samples_protobuf_data_access::sample_grpc::v1::digital_twin_provider::digital_twin_provider_client::DigitalTwinProviderClient;
+use samples_protobuf_data_access::sample_grpc::v1::digital_twin_provider::StreamRequest;
+use std::error::Error;
+use tokio_stream::StreamExt;
+use tonic::transport::Channel;
+use tracing::instrument;
+
+/// Discover the provider of the Cabin Camera Feed property using the In-Vehicle Digital Twin
+/// Service, and return its URI.
+///
+/// # Arguments
+/// * `invehicle_digital_twin_uri` - The In-Vehicle Digital Twin URI.
+pub async fn discover_camera_feed_provider_uri(
+    invehicle_digital_twin_uri: &str,
+) -> Result<String, String> {
+    let provider_endpoint_info = discover_digital_twin_provider_using_ibeji(
+        invehicle_digital_twin_uri,
+        sdv::camera::feed::ID,
+        digital_twin_protocol::GRPC,
+        &[digital_twin_operation::STREAM.to_string()],
+    )
+    .await?;
+    let provider_uri = provider_endpoint_info.uri;
+    info!("The provider URI for the Cabin Camera Feed property's provider is {provider_uri}");
+
+    Ok(provider_uri)
+}
+
+/// Stream images from the server and hand the content of each one to the image handler.
+///
+/// # Arguments
+/// * `client` - The client connection to the service that will transfer the stream.
+/// * `entity_id` - The entity id that is to be streamed.
+/// * `number_of_images` - The number of images that we will stream.
+/// * `handle_image` - Handles the content of each image that is streamed.
+#[instrument(skip_all)]
+pub async fn receive_images(
+    client: &mut DigitalTwinProviderClient<Channel>,
+    entity_id: &str,
+    number_of_images: usize,
+    mut handle_image: impl FnMut(Vec<u8>) -> Result<(), Box<dyn Error>>,
+) -> Result<(), Box<dyn Error>> {
+    let request = traced_request(StreamRequest { entity_id: entity_id.to_string() });
+    let stream = client.stream(request).await?.into_inner();
+
+    // The stream is infinite, so we will just take number_of_images elements and then disconnect.
+    let mut stream = stream.take(number_of_images);
+    while let Some(item) = stream.next().await {
+        let opt_media = item?.media;
+        if opt_media.is_none() {
+            warn!("No media value present, so ignoring this item.");
+            continue;
+        }
+        handle_image(opt_media.unwrap().media_content)?;
+    }
+
+    // The stream is dropped when we exit the function and the disconnect info is sent to the server.
+
+    Ok(())
+}
@@ -4,22 +4,20 @@
 
 mod streaming_consumer_config;
 
+use common::tracing_settings::{init_tracing, shutdown_tracing};
 use digital_twin_model::sdv_v0 as sdv;
-use env_logger::{Builder, Target};
 use image::{DynamicImage, io::Reader as ImageReader};
 use log::{info, LevelFilter, warn};
-use samples_common::constants::{digital_twin_operation, digital_twin_protocol};
 use samples_common::image_rendering::{create_canvas, render_image_to_canvas, resize_image_to_fit_in_canvas};
-use samples_common::utils::{
-    discover_digital_twin_provider_using_ibeji, retrieve_invehicle_digital_twin_uri,
-};
-use samples_protobuf_data_access::sample_grpc::v1::digital_twin_provider::StreamRequest;
+use samples_common::utils::retrieve_invehicle_digital_twin_uri;
 use samples_protobuf_data_access::sample_grpc::v1::digital_twin_provider::digital_twin_provider_client::DigitalTwinProviderClient;
+use samples_streaming::consumer::{discover_camera_feed_provider_uri, receive_images};
 use std::error::Error;
 use std::io::Cursor;
-use tokio_stream::StreamExt;
 use tonic::transport::Channel;
 
+const SERVICE_NAME: &str = "streaming-consumer";
+
 /// Stream images from the server and display them in the provided window.
 ///
 /// # Arguments
@@ -35,18 +33,7 @@ async fn stream_images(
 
     let mut canvas = create_canvas(&mut sdl_context, "Streamed Image", 800, 500)?;
 
-    let stream =
-        client.stream(StreamRequest { entity_id: entity_id.to_string() }).await?.into_inner();
-
-    // The stream is infinite, so we will just take number_of_images elements and then disconnect.
-    let mut stream = stream.take(number_of_images);
-    while let Some(item) = stream.next().await {
-        let opt_media = item.unwrap().media;
-        if opt_media.is_none() {
-            warn!("No media value present, so ignoring this item.");
-            continue;
-        }
-        let media_content = opt_media.unwrap().media_content;
+    receive_images(client, entity_id, number_of_images, |media_content| {
         let image_reader = ImageReader::new(Cursor::new(media_content)).with_guessed_format()?;
         let image: DynamicImage = image_reader.decode()?;
 
@@ -55,28 +42,28 @@ async fn stream_images(
             Err(err) => {
                 warn!("Failed to resize the image due to: {err}");
                 // Skip this image.
-                continue;
+                return Ok(());
             }
         };
 
         if let Err(err) = render_image_to_canvas(&resized_image, &mut canvas) {
             warn!("Failed to render the image due to: {err}");
         }
-    }
-
-    // The stream is dropped when we exit the function and the disconnect info is sent to the server.
 
-    Ok(())
+        Ok(())
+    })
+    .await
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
-    // Setup logging.
-    Builder::new().filter(None, LevelFilter::Info).target(Target::Stdout).init();
+    let settings = crate::streaming_consumer_config::load_settings();
 
-    info!("The Consumer has started.");
+    // Setup tracing.
+    init_tracing(SERVICE_NAME, LevelFilter::Info, settings.tracing.as_ref(), None)
+        .map_err(|error| error as Box<dyn std::error::Error>)?;
 
-    let settings = crate::streaming_consumer_config::load_settings();
+    info!("The Consumer has started.");
 
     let invehicle_digital_twin_uri = retrieve_invehicle_digital_twin_uri(
         settings.invehicle_digital_twin_uri,
@@ -86,21 +73,14 @@ async fn main() -> Result<(), Box<dyn Error>> {
     .unwrap();
 
     // Retrieve the provider URI.
-    let provider_endpoint_info = discover_digital_twin_provider_using_ibeji(
-        &invehicle_digital_twin_uri,
-        sdv::camera::feed::ID,
-        digital_twin_protocol::GRPC,
-        &[digital_twin_operation::STREAM.to_string()],
-    )
-    .await
-    .unwrap();
-    let provider_uri = provider_endpoint_info.uri;
-    info!("The provider URI for the Cabin Camera Feed property's provider is {provider_uri}");
+    let provider_uri = discover_camera_feed_provider_uri(&invehicle_digital_twin_uri).await.unwrap();
 
     let mut client = DigitalTwinProviderClient::connect(provider_uri.clone()).await.unwrap();
     stream_images(&mut client, sdv::camera::feed::ID, settings.number_of_images.into()).await?;
 
     info!("The Consumer has completed.");
 
+    shutdown_tracing();
+
     Ok(())
 }
@@ -4,6 +4,7 @@
 
 #![cfg(feature = "yaml")]
 
+use common::tracing_settings::TracingSettings;
 use config::{Config, File, FileFormat};
 use serde_derive::Deserialize;
 
@@ -14,6 +15,7 @@ pub struct Settings {
     pub chariott_uri: Option<String>,
     pub invehicle_digital_twin_uri: Option<String>,
     pub number_of_images: u16,
+    pub tracing: Option<TracingSettings>,
 }
 
 /// Load the settings.
@@ -2,67 +2,30 @@
 // Licensed under the MIT license.
 // SPDX-License-Identifier: MIT
 
-mod provider_impl;
 mod streaming_provider_config;
 
-use digital_twin_model::sdv_v0 as sdv;
-use env_logger::{Builder, Target};
+use common::trace_context::TraceContextLayer;
+use common::tracing_settings::{init_tracing, shutdown_tracing};
 use log::{info, LevelFilter};
-use samples_common::constants::{digital_twin_operation, digital_twin_protocol};
 use samples_common::utils::{retrieve_invehicle_digital_twin_uri, retry_async_based_on_status};
-use samples_protobuf_data_access::invehicle_digital_twin::v1::invehicle_digital_twin_client::InvehicleDigitalTwinClient;
-use samples_protobuf_data_access::invehicle_digital_twin::v1::{EndpointInfo, EntityAccessInfo, RegisterRequest};
 use samples_protobuf_data_access::sample_grpc::v1::digital_twin_provider::digital_twin_provider_server::DigitalTwinProviderServer;
+use samples_streaming::provider::register_entities;
+use samples_streaming::provider_impl::ProviderImpl;
 use std::net::SocketAddr;
 use tokio::time::Duration;
-use tonic::{Status, transport::Server};
+use tonic::transport::Server;
 
-use crate::provider_impl::ProviderImpl;
-
-/// Register the entities endpoints.
-///
-/// # Arguments
-/// * `invehicle_digital_twin_uri` - The In-Vehicle Digital Twin URI.
-/// * `provider_uri` - The provider's URI.
-async fn register_entities(
-    invehicle_digital_twin_uri: &str,
-    provider_uri: &str,
-) -> Result<(), Status> {
-    // Camera Feed
-    let camera_feed_endpoint_info = EndpointInfo {
-        protocol: digital_twin_protocol::GRPC.to_string(),
-        operations: vec![digital_twin_operation::STREAM.to_string()],
-        uri: provider_uri.to_string(),
-        context: sdv::camera::feed::ID.to_string(),
-    };
-    let camera_feed_access_info = EntityAccessInfo {
-        name: sdv::camera::feed::NAME.to_string(),
-        id: sdv::camera::feed::ID.to_string(),
-        description: sdv::camera::feed::DESCRIPTION.to_string(),
-        endpoint_info_list: vec![camera_feed_endpoint_info],
-    };
-
-    let entity_access_info_list = vec![camera_feed_access_info];
-
-    info!("Registering the list {:?}", entity_access_info_list);
-
-    let mut client = InvehicleDigitalTwinClient::connect(invehicle_digital_twin_uri.to_string())
-        .await
-        .map_err(|e| Status::internal(e.to_string()))?;
-    let request = tonic::Request::new(RegisterRequest { entity_access_info_list });
-    let _response = client.register(request).await?;
-
-    Ok(())
-}
+const SERVICE_NAME: &str = "streaming-provider";
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Setup logging.
-    Builder::new().filter(None, LevelFilter::Info).target(Target::Stdout).init();
+    let settings = crate::streaming_provider_config::load_settings();
 
-    info!("The Provider has started.");
+    // Setup tracing.
+    init_tracing(SERVICE_NAME, LevelFilter::Info, settings.tracing.as_ref(), None)
+        .map_err(|error| error as Box<dyn std::error::Error>)?;
 
-    let settings = crate::streaming_provider_config::load_settings();
+    info!("The Provider has started.");
 
     let provider_authority = settings.provider_authority;
 
@@ -78,8 +41,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Setup the HTTP server.
     let addr: SocketAddr = provider_authority.parse()?;
     let provider_impl = ProviderImpl::new(&settings.image_directory);
-    let server_future =
-        Server::builder().add_service(DigitalTwinProviderServer::new(provider_impl)).serve(addr);
+    let server_future = Server::builder()
+        .layer(TraceContextLayer::new())
+        .add_service(DigitalTwinProviderServer::new(provider_impl))
+        .serve(addr);
     info!("The HTTP server is listening on address '{provider_authority}'");
 
     info!("Sending a register request to the In-Vehicle Digital Twin Service URI {invehicle_digital_twin_uri}");
@@ -92,5 +57,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     info!("The Provider has completed.");
 
+    shutdown_tracing();
+
     Ok(())
 }
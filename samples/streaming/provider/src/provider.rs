@@ -0,0 +1,55 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+// SPDX-License-Identifier: MIT
+
+use common::trace_context::traced_request;
+use digital_twin_model::sdv_v0 as sdv;
+use log::info;
+use samples_common::constants::{digital_twin_operation, digital_twin_protocol};
+use samples_protobuf_data_access::invehicle_digital_twin::v1::invehicle_digital_twin_client::InvehicleDigitalTwinClient;
+use samples_protobuf_data_access::invehicle_digital_twin::v1::{
+    EndpointInfo, EntityAccessInfo, RegisterRequest,
+};
+use tonic::Status;
+
+const PROVIDER_ID: &str = "streaming-provider";
+
+/// Register the entities endpoints.
+///
+/// # Arguments
+/// * `invehicle_digital_twin_uri` - The In-Vehicle Digital Twin URI.
+/// * `provider_uri` - The provider's URI.
+pub async fn register_entities(
+    invehicle_digital_twin_uri: &str,
+    provider_uri: &str,
+) -> Result<(), Status> {
+    // Camera Feed
+    let camera_feed_endpoint_info = EndpointInfo {
+        protocol: digital_twin_protocol::GRPC.to_string(),
+        operations: vec![digital_twin_operation::STREAM.to_string()],
+        uri: provider_uri.to_string(),
+        context: sdv::camera::feed::ID.to_string(),
+        provider_id: PROVIDER_ID.to_string(),
+    };
+    let camera_feed_access_info = EntityAccessInfo {
+        name: sdv::camera::feed::NAME.to_string(),
+        id: sdv::camera::feed::ID.to_string(),
+        description: sdv::camera::feed::DESCRIPTION.to_string(),
+        endpoint_info_list: vec![camera_feed_endpoint_info],
+        instance_id: String::new(),
+        relationship_list: vec![],
+    };
+
+    let entity_access_info_list = vec![camera_feed_access_info];
+
+    info!("Registering the list {:?}", entity_access_info_list);
+
+    let mut client = InvehicleDigitalTwinClient::connect(invehicle_digital_twin_uri.to_string())
+        .await
+        .map_err(|e| Status::internal(e.to_string()))?;
+    let request =
+        traced_request(RegisterRequest { entity_access_info_list, lease_duration_seconds: 0 });
+    let _response = client.register(request).await?;
+
+    Ok(())
+}
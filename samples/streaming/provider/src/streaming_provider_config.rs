@@ -4,6 +4,7 @@
 
 #![cfg(feature = "yaml")]
 
+use common::tracing_settings::TracingSettings;
 use config::{Config, File, FileFormat};
 use serde_derive::Deserialize;
 
@@ -15,6 +16,7 @@ pub struct Settings {
     pub chariott_uri: Option<String>,
     pub invehicle_digital_twin_uri: Option<String>,
     pub image_directory: String,
+    pub tracing: Option<TracingSettings>,
 }
 
 /// Load the settings.
@@ -2,8 +2,9 @@
 // Licensed under the MIT license.
 // SPDX-License-Identifier: MIT
 
+use common::trace_context::traced_request;
+use common::tracing_settings::{init_tracing, shutdown_tracing};
 use digital_twin_model::{sdv_v0 as sdv, Metadata};
-use env_logger::{Builder, Target};
 use log::{debug, info, warn, LevelFilter};
 use samples_common::constants::{digital_twin_operation, digital_twin_protocol};
 use samples_common::consumer_config;
@@ -17,6 +18,9 @@ use serde_derive::{Deserialize, Serialize};
 use std::collections::HashMap;
 use tokio::time::{sleep, Duration};
 use tonic::Status;
+use tracing::{info_span, instrument, Instrument};
+
+const SERVICE_NAME: &str = "tutorial-consumer";
 
 #[derive(Debug, Serialize, Deserialize)]
 struct ShowNotificationRequestPayload {
@@ -80,12 +84,15 @@ async fn start_show_notification_repeater(provider_uri: String) -> Result<(), St
         }
         let mut client = client_result.unwrap();
 
-        let request = tonic::Request::new(InvokeRequest {
-            entity_id: sdv::hmi::show_notification::ID.to_string(),
-            payload: request_payload_json.to_string(),
+        let span = info_span!("invoke_show_notification");
+        let request = span.in_scope(|| {
+            traced_request(InvokeRequest {
+                entity_id: sdv::hmi::show_notification::ID.to_string(),
+                payload: request_payload_json.to_string(),
+            })
         });
 
-        let response = client.invoke(request).await?;
+        let response = client.invoke(request).instrument(span).await?;
 
         info!("Show notification response: {}", response.into_inner().response);
 
@@ -99,12 +106,13 @@ async fn start_show_notification_repeater(provider_uri: String) -> Result<(), St
 /// # Arguments
 /// `provider_uri` - The provider's URI.
 /// `entity_id` - The entity id.
+#[instrument(skip_all)]
 async fn send_get_request(provider_uri: &str, entity_id: &str) -> Result<String, Status> {
     info!("Sending a get request to provider URI {provider_uri} for the value of {entity_id}");
     let mut client = DigitalTwinProviderTutorialClient::connect(provider_uri.to_string())
         .await
         .map_err(|e| Status::internal(e.to_string()))?;
-    let request = tonic::Request::new(GetRequest { entity_id: entity_id.to_string() });
+    let request = traced_request(GetRequest { entity_id: entity_id.to_string() });
     let response = client.get(request).await?;
 
     Ok(response.into_inner().property_value)
@@ -112,12 +120,13 @@ async fn send_get_request(provider_uri: &str, entity_id: &str) -> Result<String,
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Setup logging.
-    Builder::new().filter(None, LevelFilter::Info).target(Target::Stdout).init();
+    let settings = consumer_config::load_settings();
 
-    info!("The digital twin consumer has started.");
+    // Setup tracing.
+    init_tracing(SERVICE_NAME, LevelFilter::Info, settings.tracing.as_ref(), None)
+        .map_err(|error| error as Box<dyn std::error::Error>)?;
 
-    let settings = consumer_config::load_settings();
+    info!("The digital twin consumer has started.");
 
     let invehicle_digital_twin_uri = retrieve_invehicle_digital_twin_uri(
         settings.invehicle_digital_twin_uri,
@@ -176,5 +185,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     debug!("The Consumer has completed.");
 
+    shutdown_tracing();
+
     Ok(())
 }
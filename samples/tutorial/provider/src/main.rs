@@ -5,8 +5,9 @@
 mod provider_impl;
 
 use crate::provider_impl::ProviderImpl;
+use common::trace_context::{traced_request, TraceContextLayer};
+use common::tracing_settings::{init_tracing, shutdown_tracing};
 use digital_twin_model::sdv_v0 as sdv;
-use env_logger::{Builder, Target};
 use log::{info, LevelFilter};
 use samples_common::constants::{digital_twin_operation, digital_twin_protocol};
 use samples_common::provider_config;
@@ -20,6 +21,9 @@ use std::net::SocketAddr;
 use tokio::time::Duration;
 use tonic::{transport::Server, Status};
 
+const PROVIDER_ID: &str = "tutorial-provider";
+const SERVICE_NAME: &str = "tutorial-provider";
+
 /// Register the entities' endpoints with the In-Vehicle Digital Twin Service.
 ///
 /// # Arguments
@@ -35,12 +39,15 @@ async fn register_entities(
         operations: vec![digital_twin_operation::GET.to_string()],
         uri: provider_uri.to_string(),
         context: sdv::hvac::ambient_air_temperature::ID.to_string(),
+        provider_id: PROVIDER_ID.to_string(),
     };
     let ambient_air_temperature_access_info = EntityAccessInfo {
         name: sdv::hvac::ambient_air_temperature::NAME.to_string(),
         id: sdv::hvac::ambient_air_temperature::ID.to_string(),
         description: sdv::hvac::ambient_air_temperature::DESCRIPTION.to_string(),
         endpoint_info_list: vec![ambient_air_temperature_endpoint_info],
+        instance_id: String::new(),
+        relationship_list: vec![],
     };
 
     // IsAirConditioningActive
@@ -49,12 +56,15 @@ async fn register_entities(
         operations: vec![digital_twin_operation::GET.to_string()],
         uri: provider_uri.to_string(),
         context: sdv::hvac::is_air_conditioning_active::ID.to_string(),
+        provider_id: PROVIDER_ID.to_string(),
     };
     let is_air_conditioning_active_access_info = EntityAccessInfo {
         name: sdv::hvac::is_air_conditioning_active::NAME.to_string(),
         id: sdv::hvac::is_air_conditioning_active::ID.to_string(),
         description: sdv::hvac::is_air_conditioning_active::DESCRIPTION.to_string(),
         endpoint_info_list: vec![is_air_conditioning_active_endpoint_info],
+        instance_id: String::new(),
+        relationship_list: vec![],
     };
 
     // ShowNotification
@@ -63,12 +73,15 @@ async fn register_entities(
         operations: vec![digital_twin_operation::INVOKE.to_string()],
         uri: provider_uri.to_string(),
         context: sdv::hmi::show_notification::ID.to_string(),
+        provider_id: PROVIDER_ID.to_string(),
     };
     let show_notification_access_info = EntityAccessInfo {
         name: sdv::hmi::show_notification::NAME.to_string(),
         id: sdv::hmi::show_notification::ID.to_string(),
         description: sdv::hmi::show_notification::DESCRIPTION.to_string(),
         endpoint_info_list: vec![show_notification_endpoint_info],
+        instance_id: String::new(),
+        relationship_list: vec![],
     };
 
     let entity_access_info_list = vec![
@@ -80,19 +93,21 @@ async fn register_entities(
     let mut client = InvehicleDigitalTwinClient::connect(invehicle_digital_twin_uri.to_string())
         .await
         .map_err(|e| Status::internal(e.to_string()))?;
-    let request = tonic::Request::new(RegisterRequest { entity_access_info_list });
+    let request =
+        traced_request(RegisterRequest { entity_access_info_list, lease_duration_seconds: 0 });
     let _response = client.register(request).await?;
 
     Ok(())
 }
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Setup logging.
-    Builder::new().filter(None, LevelFilter::Info).target(Target::Stdout).init();
+    let settings = provider_config::load_settings();
 
-    info!("The Digital Twin Provider has started.");
+    // Setup tracing.
+    init_tracing(SERVICE_NAME, LevelFilter::Info, settings.tracing.as_ref(), None)
+        .map_err(|error| error as Box<dyn std::error::Error>)?;
 
-    let settings = provider_config::load_settings();
+    info!("The Digital Twin Provider has started.");
 
     let provider_authority = settings.provider_authority;
 
@@ -109,6 +124,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let addr: SocketAddr = provider_authority.parse()?;
     let provider_impl = ProviderImpl {};
     let server_future = Server::builder()
+        .layer(TraceContextLayer::new())
         .add_service(DigitalTwinProviderTutorialServer::new(provider_impl))
         .serve(addr);
     info!("The HTTP server is listening on address '{provider_authority}'");
@@ -122,5 +138,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     info!("The Provider has completed.");
 
+    shutdown_tracing();
+
     Ok(())
 }